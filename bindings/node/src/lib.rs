@@ -0,0 +1,129 @@
+// Node.js bindings, so that pentest automation written in JavaScript and Electron UIs can run the
+// GPU search directly: `findSeeds(constraints, options, onProgress)` returns a Promise.
+
+use std::str::FromStr;
+
+use napi::bindgen_prelude::{AsyncTask, Env, Error, Result, Task};
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use php_mt_seed::engine::{prepare_engine, EngineKind};
+use php_mt_seed::flavor::Flavor;
+use php_mt_seed::stop::Stop;
+use php_mt_seed::{lint_arguments, normalize_arguments, search, Options};
+
+/// The options of `findSeeds`, named after their command line counterparts.
+#[napi(object)]
+pub struct SearchOptions {
+    /// `"7.1"` (default), `"5.2.1"`, or `"auto"` for both.
+    pub php: Option<String>,
+    /// `"wgpu"` (default), `"reference"`, or the engines the library was built with.
+    pub engine: Option<String>,
+    pub allow_software: Option<bool>,
+}
+
+/// A matching seed, and the `mt_rand()` it matches with.
+#[napi(object)]
+pub struct FoundSeed {
+    pub seed: u32,
+    pub php: String,
+}
+
+/// Sent to `onProgress` after each step, `steps` per flavor.
+#[napi(object)]
+pub struct Progress {
+    pub php: String,
+    pub step: u32,
+    pub steps: u32,
+    pub found: u32,
+}
+
+pub struct FindSeeds {
+    options: Options,
+    arguments: Vec<u32>,
+    on_progress: Option<ThreadsafeFunction<Progress, ErrorStrategy::Fatal>>,
+}
+
+impl Task for FindSeeds {
+    type Output = Vec<(u32, Flavor)>;
+    type JsValue = Vec<FoundSeed>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let mut found = Vec::new();
+        let mut engine = prepare_engine(&self.options).map_err(Error::from_reason)?;
+        let stop = Stop::new(self.options.max_results);
+        for &flavor in &self.options.flavors {
+            let on_seed = |seed| found.push((seed, flavor));
+            let on_step = |step, found| {
+                if let Some(on_progress) = &self.on_progress {
+                    let progress = Progress {
+                        php: flavor.versions().to_string(),
+                        step,
+                        steps: self.options.partitions,
+                        found: found as u32,
+                    };
+                    on_progress.call(progress, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            };
+            engine = search(
+                &self.options,
+                &stop,
+                engine,
+                flavor,
+                &self.arguments,
+                0..self.options.partitions,
+                on_seed,
+                on_step,
+            )
+            .map_err(Error::from_reason)?;
+        }
+        Ok(found)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output
+            .into_iter()
+            .map(|(seed, flavor)| FoundSeed {
+                seed,
+                php: flavor.versions().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Search the seeds matching `constraints`, the numbers given on the command line, on a worker
+/// thread. Throws right away when the constraints or options are invalid.
+#[napi(
+    ts_args_type = "constraints: number[], options?: SearchOptions, onProgress?: (progress: Progress) => void",
+    ts_return_type = "Promise<FoundSeed[]>"
+)]
+pub fn find_seeds(
+    constraints: Vec<u32>,
+    options: Option<SearchOptions>,
+    on_progress: Option<ThreadsafeFunction<Progress, ErrorStrategy::Fatal>>,
+) -> Result<AsyncTask<FindSeeds>> {
+    let mut arguments = constraints;
+    normalize_arguments(&mut arguments);
+    if !lint_arguments(&arguments) {
+        return Err(Error::from_reason("invalid constraints"));
+    }
+
+    let mut search_options = Options::default();
+    if let Some(options) = options {
+        if let Some(php) = options.php {
+            search_options.flavors = match php.as_str() {
+                "auto" => Flavor::ALL.to_vec(),
+                _ => vec![Flavor::from_str(&php).map_err(Error::from_reason)?],
+            };
+        }
+        if let Some(engine) = options.engine {
+            search_options.engine = EngineKind::from_str(&engine).map_err(Error::from_reason)?;
+        }
+        search_options.allow_software = options.allow_software.unwrap_or(false);
+    }
+
+    Ok(AsyncTask::new(FindSeeds {
+        options: search_options,
+        arguments,
+        on_progress,
+    }))
+}
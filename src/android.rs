@@ -0,0 +1,59 @@
+// JNI entry point, so that an Android app can run the search on the phone's GPU through the
+// Vulkan backend of wgpu. The library is built as a `cdylib` with cargo-ndk, see the README.
+
+use std::ptr;
+
+use jni_sys::{jclass, jint, jintArray, JNIEnv};
+
+use crate::context::GpuContext;
+use crate::flavor::Flavor;
+use crate::query::Query;
+use crate::seed_search::SeedSearch;
+
+/// `static native int[] findSeeds(int[] constraints, int flavor)` of the Java class
+/// `io.github.max1truc.phpmtseed.PhpMtSeed`.
+///
+/// `constraints` are the numbers given on the command line, and `flavor` is an index in
+/// `Flavor::ALL` (0 for PHP 7.1.0+). Java has no unsigned int, seeds above 0x7fffffff come back
+/// negative: read them with `Integer.toUnsignedLong()`. Returns null when the constraints or the
+/// flavor are invalid, or when the GPU cannot search them.
+///
+/// The search blocks for minutes: call it from a background thread.
+///
+/// # Safety
+///
+/// Must only be called by the JVM, with a valid `env` and int array.
+#[no_mangle]
+pub unsafe extern "system" fn Java_io_github_max1truc_phpmtseed_PhpMtSeed_findSeeds(
+    env: *mut JNIEnv,
+    _class: jclass,
+    constraints: jintArray,
+    flavor: jint,
+) -> jintArray {
+    let functions = &**env;
+
+    let len = functions.GetArrayLength.unwrap()(env, constraints);
+    let mut values = vec![0; len as usize];
+    functions.GetIntArrayRegion.unwrap()(env, constraints, 0, len, values.as_mut_ptr());
+    // Negative values become too large to pass validation
+    let arguments: Vec<u32> = values.into_iter().map(|value| value as u32).collect();
+    let Some(&flavor) = Flavor::ALL.get(flavor as usize) else {
+        return ptr::null_mut();
+    };
+    let Ok(query) = Query::from_arguments(&arguments, vec![flavor]) else {
+        return ptr::null_mut();
+    };
+    let Ok(ctx) = GpuContext::global() else {
+        return ptr::null_mut();
+    };
+    let Ok(results) = SeedSearch::from(query).run(ctx) else {
+        return ptr::null_mut();
+    };
+    let seeds: Vec<jint> = results.iter().map(|result| result.seed as jint).collect();
+
+    let result = functions.NewIntArray.unwrap()(env, seeds.len() as jint);
+    if !result.is_null() {
+        functions.SetIntArrayRegion.unwrap()(env, result, 0, seeds.len() as jint, seeds.as_ptr());
+    }
+    result
+}
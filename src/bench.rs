@@ -0,0 +1,98 @@
+// The comparison printed by the bench subcommand: how the engine benchmarked fares against the
+// CPU, to tell whether a small GPU is worth using over a CPU cracker.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::flavor::Flavor;
+use crate::reference;
+
+/// Seeds of a whole search, for one flavor.
+const SEEDS: f64 = 4294967296.0;
+
+/// How long the CPU is measured for: a whole step would take minutes, a sample is enough.
+pub const CPU_BENCH_TIME: Duration = Duration::from_secs(1);
+
+/// Seeds per second the CPU checks against the normalized `arguments`, on all its cores like
+/// `--only-seeds` does, measured on the first seeds for about `budget`, in chunks growing until it
+/// is spent.
+pub fn cpu_rate(flavor: Flavor, arguments: &[u32], budget: Duration) -> f64 {
+    let start_time = Instant::now();
+    let mut tested = 0u32;
+    let mut chunk = 4096;
+    loop {
+        let candidates: Vec<u32> = (tested..tested + chunk).collect();
+        reference::verify_candidates(flavor, arguments, &candidates);
+        tested += chunk;
+        if start_time.elapsed() >= budget || tested.checked_add(chunk * 2).is_none() {
+            break;
+        }
+        chunk *= 2;
+    }
+    f64::from(tested) / start_time.elapsed().as_secs_f64()
+}
+
+/// A duration in the largest unit that keeps it above 1, for estimates spanning seconds to days.
+pub fn format_duration(seconds: f64) -> String {
+    match seconds {
+        s if s < 60.0 => format!("{s:.1} s"),
+        s if s < 3600.0 => format!("{:.1} min", s / 60.0),
+        s if s < 86400.0 => format!("{:.1} h", s / 3600.0),
+        s => format!("{:.1} days", s / 86400.0),
+    }
+}
+
+/// The backends side by side, given with their seeds per second, the first one being the
+/// reference of the speedups: rate, speedup and time to search every seed of a flavor.
+pub fn comparison_table(rows: &[(String, f64)]) -> String {
+    let Some(&(_, baseline)) = rows.first() else {
+        return String::new();
+    };
+    let mut table = format!(
+        "{:<24} {:>14} {:>9} {:>12}\n",
+        "backend", "seeds/s", "speedup", "full search"
+    );
+    for (name, rate) in rows {
+        writeln!(
+            table,
+            "{name:<24} {rate:>14.0} {:>8.3}x {:>12}",
+            rate / baseline,
+            format_duration(SEEDS / rate)
+        )
+        .unwrap();
+    }
+    table
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(3.25), "3.2 s");
+    assert_eq!(format_duration(90.0), "1.5 min");
+    assert_eq!(format_duration(5400.0), "1.5 h");
+    assert_eq!(format_duration(172800.0), "2.0 days");
+}
+
+#[test]
+fn test_comparison_table() {
+    let rows = [
+        ("wgpu".to_string(), 4294967296.0 / 8.0),
+        ("CPU, 4 threads".to_string(), 4294967296.0 / 3600.0),
+    ];
+    let table = comparison_table(&rows);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("wgpu "));
+    assert!(lines[1].ends_with("1.000x        8.0 s"));
+    assert!(lines[2].ends_with("0.002x        1.0 h"));
+    assert_eq!(comparison_table(&[]), "");
+}
+
+#[test]
+fn test_cpu_rate() {
+    let rate = cpu_rate(
+        Flavor::Php71,
+        &[0, 0x7fffffff, 0, 0x7fffffff],
+        Duration::ZERO,
+    );
+    assert!(rate > 0.0);
+}
@@ -0,0 +1,287 @@
+// The device a host application searches with, prepared once.
+
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{Arc, Condvar, Mutex, OnceLock},
+};
+
+use crate::engine::{prepare_engine, Engine};
+use crate::flavor::Flavor;
+use crate::query::{QueryKey, SeedResult};
+use crate::stop::Stop;
+use crate::{search, search_queries, Options};
+
+/// An engine prepared with its options, to run any number of searches on.
+///
+/// Clones share the same engine, and threads may search on it at the same time: their dispatches
+/// are interleaved on the device.
+#[derive(Clone)]
+pub struct GpuContext {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    options: Options,
+    /// Replaced by the searches when the device is lost, hence the lock. It is only held to get
+    /// or replace the engine, not while searching.
+    engine: Mutex<Arc<dyn Engine>>,
+    running: Running,
+}
+
+/// The searches running on a context, so that identical ones submitted meanwhile wait for their
+/// results instead of searching again.
+#[derive(Default)]
+struct Running {
+    searches: Mutex<HashMap<QueryKey, Arc<Execution>>>,
+}
+
+#[derive(Default)]
+struct Execution {
+    outcome: Mutex<Outcome>,
+    finished: Condvar,
+}
+
+#[derive(Default, Clone)]
+enum Outcome {
+    #[default]
+    Pending,
+    Done(Vec<SeedResult>),
+    /// The search failed or panicked, the searches waiting for it run on their own.
+    Abandoned,
+}
+
+/// Publishes the outcome of an execution, even when its search fails or panics.
+struct Publish<'a> {
+    running: &'a Running,
+    key: &'a QueryKey,
+    execution: &'a Execution,
+    results: Option<Vec<SeedResult>>,
+}
+
+impl Drop for Publish<'_> {
+    fn drop(&mut self) {
+        self.running.searches.lock().unwrap().remove(self.key);
+        *self.execution.outcome.lock().unwrap() = match self.results.take() {
+            Some(results) => Outcome::Done(results),
+            None => Outcome::Abandoned,
+        };
+        self.execution.finished.notify_all();
+    }
+}
+
+impl Running {
+    /// The results of `search`, or of the identical search already running.
+    fn deduplicate(
+        &self,
+        key: &QueryKey,
+        search: impl FnOnce() -> Result<Vec<SeedResult>, String>,
+    ) -> Result<Vec<SeedResult>, String> {
+        let (execution, leader) = {
+            let mut searches = self.searches.lock().unwrap();
+            match searches.get(key) {
+                Some(execution) => (execution.clone(), false),
+                None => {
+                    let execution = Arc::new(Execution::default());
+                    searches.insert(key.clone(), execution.clone());
+                    (execution, true)
+                }
+            }
+        };
+
+        if !leader {
+            let mut outcome = execution.outcome.lock().unwrap();
+            while let Outcome::Pending = *outcome {
+                outcome = execution.finished.wait(outcome).unwrap();
+            }
+            match outcome.clone() {
+                Outcome::Done(results) => return Ok(results),
+                _ => {
+                    drop(outcome);
+                    return search();
+                }
+            }
+        }
+
+        let mut publish = Publish {
+            running: self,
+            key,
+            execution: &execution,
+            results: None,
+        };
+        let results = search()?;
+        publish.results = Some(results.clone());
+        Ok(results)
+    }
+}
+
+impl GpuContext {
+    /// Prepare the engine `options.engine` (wgpu by default) on the first adapter.
+    pub fn new(options: Options) -> Result<Self, String> {
+        let engine = prepare_engine(&options)?;
+        Ok(GpuContext {
+            shared: Arc::new(Shared {
+                options,
+                engine: Mutex::new(engine),
+                running: Running::default(),
+            }),
+        })
+    }
+
+    /// The context of the process, prepared with the default options on first use. When it
+    /// cannot be, every call gets the error.
+    pub fn global() -> Result<&'static GpuContext, String> {
+        static GLOBAL: OnceLock<Result<GpuContext, String>> = OnceLock::new();
+        GLOBAL
+            .get_or_init(|| GpuContext::new(Options::default()))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.shared.options
+    }
+
+    /// Search the seeds of `steps` against normalized `arguments`, calling `on_seed` for each
+    /// match.
+    pub(crate) fn search_steps(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        steps: Range<u32>,
+        on_seed: impl FnMut(u32),
+    ) -> Result<(), String> {
+        self.with_engine(|engine| {
+            search(
+                self.options(),
+                &Stop::new(self.options().max_results),
+                engine,
+                flavor,
+                arguments,
+                steps,
+                on_seed,
+                |_, _| {},
+            )
+        })
+    }
+
+    /// Search the seeds of `steps` against several normalized queries at once, calling `on_seed`
+    /// with the index of the query for each match.
+    pub(crate) fn search_queries(
+        &self,
+        flavor: Flavor,
+        queries: &[Vec<u32>],
+        steps: Range<u32>,
+        on_seed: impl FnMut(usize, u32),
+    ) -> Result<(), String> {
+        self.with_engine(|engine| {
+            search_queries(
+                self.options(),
+                &Stop::new(self.options().max_results),
+                engine,
+                flavor,
+                queries,
+                steps,
+                on_seed,
+                |_, _| {},
+            )
+        })
+    }
+
+    /// The results of `search` for the query `key`, shared with the identical searches submitted
+    /// while it runs.
+    pub(crate) fn deduplicate(
+        &self,
+        key: &QueryKey,
+        search: impl FnOnce() -> Result<Vec<SeedResult>, String>,
+    ) -> Result<Vec<SeedResult>, String> {
+        self.shared.running.deduplicate(key, search)
+    }
+
+    /// Run a search, given the shared engine, and share the one it returns.
+    fn with_engine(
+        &self,
+        search: impl FnOnce(Arc<dyn Engine>) -> Result<Arc<dyn Engine>, String>,
+    ) -> Result<(), String> {
+        let engine = self.shared.engine.lock().unwrap().clone();
+        let searched = search(engine.clone())?;
+        if !Arc::ptr_eq(&searched, &engine) {
+            // The device was lost and the engine prepared again, later searches can use it.
+            *self.shared.engine.lock().unwrap() = searched;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_deduplicate() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    let running = Running::default();
+    let key = crate::query::Query::from_arguments(&[1178568022], vec![Flavor::Php71])
+        .unwrap()
+        .cache_key();
+    let executions = AtomicUsize::new(0);
+    let search = || {
+        executions.fetch_add(1, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(200));
+        Ok(vec![SeedResult {
+            seed: 0,
+            flavor: Flavor::Php71,
+        }])
+    };
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|_| scope.spawn(|| running.deduplicate(&key, search)))
+            .collect();
+        threads
+            .into_iter()
+            .map(|t| t.join().unwrap().unwrap())
+            .collect()
+    });
+    assert_eq!(executions.load(Ordering::SeqCst), 1);
+    assert!(results.iter().all(|seeds| seeds == &results[0]));
+    // Finished searches are not cached, only shared while they run.
+    running.deduplicate(&key, search).unwrap();
+    assert_eq!(executions.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_concurrent_searches() {
+    let ctx = GpuContext::new(Options {
+        allow_software: true,
+        ..Default::default()
+    })
+    .unwrap();
+    // mt_srand(0); mt_rand(); on each flavor, searched from two threads at once
+    let searches = [(Flavor::Php71, 1178568022), (Flavor::Php52, 963932192)];
+    let found: Vec<Vec<u32>> = std::thread::scope(|scope| {
+        let threads: Vec<_> = searches
+            .iter()
+            .map(|&(flavor, value)| {
+                let ctx = ctx.clone();
+                scope.spawn(move || {
+                    let mut seeds = Vec::new();
+                    ctx.search_steps(flavor, &[value, value, 0, 0x7fffffff], 0..1, |seed| {
+                        seeds.push(seed)
+                    })
+                    .unwrap();
+                    seeds
+                })
+            })
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    assert!(found.iter().all(|seeds| seeds.contains(&0)));
+}
+
+#[test]
+fn test_context_errors() {
+    // Preparing the engine fails, instead of exiting the process of the host application.
+    let options = Options {
+        gpu_mem_limit: Some(1),
+        ..Options::default()
+    };
+    assert!(GpuContext::new(options).is_err());
+}
@@ -0,0 +1,177 @@
+use std::{ops::Range, sync::Arc};
+
+use cudarc::{
+    driver::{CudaContext, CudaFunction, CudaStream, LaunchConfig, PushKernelArg},
+    nvrtc::{compile_ptx_with_opts, CompileOptions},
+};
+
+use crate::engine::{
+    extract_results, is_exact_output, pack_small_ranges, Engine, ExecuteError, MAX_RESULTS,
+    WORKGROUPS_PER_STEP,
+};
+use crate::flavor::Flavor;
+use crate::Options;
+
+/// mt19937.cl is valid CUDA C++ once the few OpenCL-specific names are defined, and once every
+/// function defaults to `__device__` (see `--device-as-default-execution-space` below).
+const CUDA_PRELUDE: &str = r#"
+typedef unsigned int uint;
+typedef unsigned long long ulong;
+#define clz(x) __clzll(x)
+#define kernel extern "C" __global__
+#define global
+#define get_global_id(dim) (blockIdx.x * blockDim.x + threadIdx.x)
+#define atomic_inc(p) atomicAdd(p, 1u)
+"#;
+
+/// The CUDA counterpart of `GpuPrepared`: everything needed to run `mt19937.cl` repeatedly.
+pub struct CudaPrepared {
+    stream: Arc<CudaStream>,
+    function: CudaFunction,
+    // `exact_search`, for a single exact output, and `small_search`, for small ranges.
+    exact_function: CudaFunction,
+    small_function: CudaFunction,
+}
+
+/// Prepare the first CUDA device once: context, stream, and the kernel compiled with NVRTC.
+///
+/// Returns `None` when the CUDA driver, NVRTC or a CUDA device is missing, so that the caller
+/// can fall back to another engine.
+pub fn prepare_cuda(_options: &Options) -> Option<CudaPrepared> {
+    // The CUDA libraries are loaded at runtime, check that they are there before using them as
+    // cudarc panics otherwise.
+    let present = unsafe {
+        cudarc::driver::sys::is_culib_present() && cudarc::nvrtc::sys::is_culib_present()
+    };
+    if !present {
+        log::info!("the CUDA driver or NVRTC library was not found");
+        return None;
+    }
+    let context = match CudaContext::new(0) {
+        Ok(context) => context,
+        Err(error) => {
+            log::info!("failed to create a CUDA context: {error:?}");
+            return None;
+        }
+    };
+
+    println!(
+        "\rRunning on CUDA device: {}",
+        context.name().unwrap_or_default()
+    );
+    log::info!("CUDA device: {}", context.name().unwrap_or_default());
+
+    let ptx = compile_ptx_with_opts(
+        format!("{CUDA_PRELUDE}{}", include_str!("mt19937.cl")),
+        CompileOptions {
+            options: vec!["--device-as-default-execution-space".to_string()],
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|error| panic!("Failed to build mt19937.cl for CUDA: {error:?}"));
+    let module = context
+        .load_module(ptx)
+        .expect("Failed to load the CUDA module");
+    let function = module
+        .load_function("search")
+        .expect("Failed to load the CUDA kernel");
+    let exact_function = module
+        .load_function("exact_search")
+        .expect("Failed to load the CUDA kernel");
+    let small_function = module
+        .load_function("small_search")
+        .expect("Failed to load the CUDA kernel");
+
+    Some(CudaPrepared {
+        stream: context.default_stream(),
+        function,
+        exact_function,
+        small_function,
+    })
+}
+
+impl CudaPrepared {
+    /// Execute the given workgroups of a step for `kernel_flavor`, the `Flavor::kernel_id` of the
+    /// flavor searched or `Flavor::ALL_KERNEL_ID`.
+    fn execute_kernel(
+        &self,
+        kernel_flavor: u32,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        assert!(step < 256);
+        assert!(!workgroups.is_empty() && workgroups.end <= WORKGROUPS_PER_STEP);
+
+        // CUDA errors while running usually mean the device went away (Xid errors, ...), which
+        // the caller handles by preparing the engine again.
+        let lost = |error| {
+            log::warn!("CUDA error: {error:?}");
+            ExecuteError::DeviceLost
+        };
+
+        // Single exact outputs and small ranges have leaner kernels, the latter with packed slots.
+        let packed = pack_small_ranges(arguments);
+        let (function, arguments) = match (is_exact_output(arguments), &packed) {
+            (true, _) => (&self.exact_function, arguments),
+            (false, Some(packed)) => (&self.small_function, packed.as_slice()),
+            (false, None) => (&self.function, arguments),
+        };
+
+        let mut input_data = Vec::new();
+        input_data.push(step);
+        input_data.push(workgroups.start * 256);
+        input_data.push(kernel_flavor);
+        input_data.extend_from_slice(arguments);
+
+        let input = self.stream.clone_htod(&input_data).map_err(lost)?;
+        let mut output = self.stream.alloc_zeros::<u32>(MAX_RESULTS).map_err(lost)?;
+        let input_len = input_data.len() as u32;
+        let output_len = MAX_RESULTS as u32;
+
+        let config = LaunchConfig {
+            grid_dim: (workgroups.len() as u32, 1, 1),
+            block_dim: (256, 1, 1),
+            shared_mem_bytes: 0,
+        };
+        let mut launch = self.stream.launch_builder(function);
+        launch
+            .arg(&input)
+            .arg(&input_len)
+            .arg(&mut output)
+            .arg(&output_len);
+        unsafe { launch.launch(config) }.map_err(lost)?;
+
+        let result = self.stream.clone_dtoh(&output).map_err(lost)?;
+        extract_results(&result)
+    }
+}
+
+impl Engine for CudaPrepared {
+    fn execute_workgroups(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        self.execute_kernel(flavor.kernel_id(), arguments, step, workgroups)
+    }
+
+    fn execute_batch_all_flavors(
+        &self,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        batch
+            .iter()
+            .map(|(step, workgroups)| {
+                self.execute_kernel(Flavor::ALL_KERNEL_ID, arguments, *step, workgroups.clone())
+            })
+            .collect()
+    }
+
+    fn single_pass_flavors(&self) -> bool {
+        true
+    }
+}
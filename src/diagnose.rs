@@ -0,0 +1,123 @@
+// The diagnostic printed when no seed matched: which constraint most likely went wrong, and what
+// to try next.
+
+use std::fmt::Write;
+
+use crate::engine::is_any_value;
+use crate::explain::selectivity;
+use crate::flavor::Flavor;
+
+/// `mt_getrandmax()`, the largest value of `mt_rand()` without a range, in every flavor.
+pub const MT_GETRANDMAX: u32 = 0x7fffffff;
+
+/// The `@RANGE_MIN-RANGE_MAX` of a slot on the command line, empty for the range of `mt_rand()`.
+fn range_suffix(slot: &[u32]) -> String {
+    match slot[2..] == [0, MT_GETRANDMAX] {
+        true => String::new(),
+        false => format!("@{}-{}", slot[2], slot[3]),
+    }
+}
+
+/// A slot written as the shorthand of the command line, such as `5-9@0-61`.
+fn shorthand(slot: &[u32]) -> String {
+    match slot[0] == slot[1] {
+        true => format!("{}{}", slot[0], range_suffix(slot)),
+        false => format!("{}-{}{}", slot[0], slot[1], range_suffix(slot)),
+    }
+}
+
+/// The slots of the `arguments` with values `mt_rand()` never returns, one line each. Such
+/// constraints are refused before searching, this tells why. Wider ranges are given explicitly.
+pub fn beyond_getrandmax(arguments: &[u32]) -> String {
+    let mut lines = String::new();
+    for (i, slot) in arguments.chunks(4).enumerate() {
+        if slot.len() == 4 && slot[1] > MT_GETRANDMAX && slot[3] <= MT_GETRANDMAX {
+            writeln!(
+                lines,
+                "slot {} ({}) exceeds mt_getrandmax() = {MT_GETRANDMAX}, mt_rand() never returns \
+                 it: the value may come from another generator, such as random_int()",
+                i + 1,
+                shorthand(slot)
+            )
+            .unwrap();
+        }
+    }
+    lines
+}
+
+/// Why the normalized `arguments` matched no seed in `flavors`, and what to try instead, one line
+/// each.
+pub fn no_results(arguments: &[u32], flavors: &[Flavor]) -> String {
+    let mut lines = String::from("no seed matches the constraints\n");
+    lines.push_str(&beyond_getrandmax(arguments));
+    let slots: Vec<(usize, &[u32])> = arguments
+        .chunks(4)
+        .enumerate()
+        .filter(|(_, slot)| !is_any_value(slot))
+        .collect();
+    let most_restrictive = slots
+        .iter()
+        .min_by(|(_, a), (_, b)| selectivity(a).total_cmp(&selectivity(b)));
+    if let Some((i, slot)) = most_restrictive {
+        writeln!(
+            lines,
+            "slot {} ({}) is the most restrictive, 1 seed in {:.0} passes it: if it is wrong, no \
+             seed can match",
+            i + 1,
+            shorthand(slot),
+            1.0 / selectivity(slot)
+        )
+        .unwrap();
+    }
+
+    for flavor in Flavor::ALL.iter().filter(|f| !flavors.contains(f)) {
+        writeln!(
+            lines,
+            "- the target may run PHP {}: try --php {} or --php auto",
+            flavor.versions(),
+            flavor.name()
+        )
+        .unwrap();
+    }
+    if let Some((i, slot)) = slots.iter().find(|(_, slot)| slot[0] == slot[1]) {
+        writeln!(
+            lines,
+            "- values read off a page may be off or rounded: widen them with VALUE~DELTA, such as \
+             {}~1{} for slot {}",
+            slot[0],
+            range_suffix(slot),
+            i + 1
+        )
+        .unwrap();
+    }
+    writeln!(
+        lines,
+        "- the target may call mt_rand() before the first value observed: add skipped outputs \
+         (0-{MT_GETRANDMAX}, or 0-61@0-61 for mt_rand(0, 61)) first, or try --skip-range 0..16"
+    )
+    .unwrap();
+    lines
+}
+
+#[test]
+fn test_shorthand() {
+    assert_eq!(shorthand(&[5, 9, 0, 61]), "5-9@0-61");
+    assert_eq!(shorthand(&[44, 44, 0, MT_GETRANDMAX]), "44");
+}
+
+#[test]
+fn test_no_results() {
+    let arguments = [697823703, 697823703, 0, MT_GETRANDMAX, 44, 44, 0, 61];
+    let diagnostic = no_results(&arguments, &[Flavor::Php71]);
+    assert!(diagnostic.contains("slot 1 (697823703) is the most restrictive"));
+    assert!(diagnostic.contains("try --php 5.2.1 or --php auto"));
+    assert!(diagnostic.contains("such as 697823703~1 for slot 1"));
+    assert!(!diagnostic.contains("mt_getrandmax() ="));
+    assert!(!no_results(&arguments, &Flavor::ALL).contains("--php"));
+
+    assert_eq!(
+        beyond_getrandmax(&[3000000000, 3000000000, 0, MT_GETRANDMAX]),
+        "slot 1 (3000000000) exceeds mt_getrandmax() = 2147483647, mt_rand() never returns it: \
+         the value may come from another generator, such as random_int()\n"
+    );
+}
@@ -0,0 +1,399 @@
+// The doctor subcommand: what keeps the search from running, or from running well, on this
+// machine, with what to do about it. Most problems come from the drivers, not from the search.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::context::GpuContext;
+use crate::flavor::Flavor;
+use crate::gpu::is_software_adapter;
+use crate::gpu_info::AdapterReport;
+use crate::reference::Mt19937;
+
+/// The seed the kernels are checked with, among those of the first step.
+const TEST_SEED: u32 = 424242;
+
+/// The watchdog of Windows, without a `TdrDelay` in the registry.
+const DEFAULT_TDR_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Problem,
+}
+
+/// The outcome of a check, and what to do about it unless it passed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &str, detail: String) -> Self {
+        Check {
+            name: name.to_string(),
+            status: Status::Ok,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn failed(name: &str, status: Status, detail: String, hint: String) -> Self {
+        Check {
+            name: name.to_string(),
+            status,
+            detail,
+            hint: Some(hint),
+        }
+    }
+
+    /// The check in a line for people, and its hint on the next one.
+    pub fn text(&self) -> String {
+        let status = match self.status {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Problem => "PROBLEM",
+        };
+        let mut text = format!("{status:<8} {}: {}\n", self.name, self.detail);
+        if let Some(hint) = &self.hint {
+            writeln!(text, "{:<8} -> {hint}", "").unwrap();
+        }
+        text
+    }
+}
+
+/// Whether there is an adapter to search on, and what is wrong with the others. Unusable adapters
+/// are only a problem when none is usable.
+pub fn adapter_checks(reports: &[AdapterReport]) -> Vec<Check> {
+    if reports.is_empty() {
+        return vec![Check::failed(
+            "adapters",
+            Status::Problem,
+            "none found".to_string(),
+            no_adapter_hint().to_string(),
+        )];
+    }
+    let usable = reports.iter().filter(|r| r.problems().is_empty()).count();
+    let mut checks = vec![Check::ok(
+        "adapters",
+        format!("{} found, {usable} usable", reports.len()),
+    )];
+    for report in reports {
+        let info = &report.info;
+        let name = format!("{} ({})", info.name, info.backend.to_str());
+        let problems = report.problems();
+        if problems.is_empty() {
+            let detail = format!("usable, driver {} {}", info.driver, info.driver_info);
+            checks.push(Check::ok(&name, detail));
+            continue;
+        }
+        let status = match usable {
+            0 => Status::Problem,
+            _ => Status::Warning,
+        };
+        checks.push(Check::failed(
+            &name,
+            status,
+            problems.join(", "),
+            adapter_hint(report),
+        ));
+    }
+    checks
+}
+
+/// Whether one of the adapters can run the kernels, software rasterizers included with
+/// `allow_software`.
+pub fn can_run_kernels(reports: &[AdapterReport], allow_software: bool) -> bool {
+    reports.iter().any(|report| match report.problems().len() {
+        0 => true,
+        // Being software is then its only problem.
+        1 => allow_software && is_software_adapter(&report.info),
+        _ => false,
+    })
+}
+
+/// How to get an adapter at all, on this system.
+fn no_adapter_hint() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "install the driver of the GPU from its vendor, for DirectX 12 or Vulkan"
+    } else if cfg!(target_os = "macos") {
+        "Metal needs macOS 10.13 or later, on a Mac whose GPU supports it"
+    } else {
+        "install the Vulkan driver of the GPU (mesa-vulkan-drivers for AMD and Intel, the Vulkan \
+         ICD of the NVIDIA driver), `vulkaninfo --summary` should list it; in a container, pass \
+         the GPU through (`--device /dev/dri`, or `--gpus all` with the NVIDIA Container Toolkit)"
+    }
+}
+
+/// What to do about an adapter the search cannot use.
+fn adapter_hint(report: &AdapterReport) -> String {
+    if is_software_adapter(&report.info) {
+        return "it emulates a GPU on the CPU: install the driver of the GPU, or search anyway \
+                with --allow-software or --engine reference"
+            .to_string();
+    }
+    if !report
+        .downlevel
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        return format!(
+            "its {} driver has no compute shaders (OpenGL before 4.3, OpenGL ES before 3.1): \
+             install a Vulkan, Metal or DirectX 12 driver",
+            report.info.backend.to_str()
+        );
+    }
+    "its limits are too small for the kernels: try --engine opencl, or --engine reference on the \
+     CPU"
+    .to_string()
+}
+
+/// The constraints of `TEST_SEED` in `flavor` for each kernel: a single exact output, a shuffle
+/// of small ranges, and ranges of every size.
+fn kernel_arguments(flavor: Flavor) -> [Vec<u32>; 3] {
+    let draws = |ranges: &[(u32, u32)]| {
+        let mut mt = Mt19937::new(TEST_SEED, flavor);
+        let slots = ranges.iter().flat_map(|&(range_min, range_max)| {
+            let output = mt.next_in_range(range_min, range_max);
+            [output, output, range_min, range_max]
+        });
+        slots.collect()
+    };
+    let shuffle: Vec<(u32, u32)> = (1..9).rev().map(|n_left| (0, n_left)).collect();
+    [
+        draws(&[(0, 0x7fffffff)]),
+        draws(&shuffle),
+        draws(&[(1000, 10000), (0, 0x7fffffff), (0, 61), (0, u32::MAX)]),
+    ]
+}
+
+/// Search the first step for `TEST_SEED` with each kernel in each flavor, which catches drivers
+/// miscompiling them. Returns the check along with the longest of the searches, a whole step.
+pub fn kernel_check(ctx: &GpuContext) -> (Check, Duration) {
+    let mut longest = Duration::ZERO;
+    let mut missed = Vec::new();
+    for flavor in Flavor::ALL {
+        let kernels = ["exact output", "small ranges", "ranges"];
+        for (kernel, arguments) in kernels.iter().zip(kernel_arguments(flavor)) {
+            let start = Instant::now();
+            let mut found = false;
+            let searched =
+                ctx.search_steps(flavor, &arguments, 0..1, |seed| found |= seed == TEST_SEED);
+            longest = longest.max(start.elapsed());
+            match searched {
+                Err(e) => missed.push(format!("{kernel} (PHP {}, {e})", flavor.versions())),
+                Ok(()) if !found => missed.push(format!("{kernel} (PHP {})", flavor.versions())),
+                Ok(()) => {}
+            }
+        }
+    }
+    let check = match missed.is_empty() {
+        true => Check::ok("kernels", "found the seed they were given".to_string()),
+        false => Check::failed(
+            "kernels",
+            Status::Problem,
+            format!("missed the seed they were given: {}", missed.join(", ")),
+            "the driver miscompiles them: update it, and search with --engine opencl or \
+             --engine reference meanwhile"
+                .to_string(),
+        ),
+    };
+    (check, longest)
+}
+
+/// The check of the kernels when the engine cannot even be prepared on an adapter that looked
+/// usable, with the `error` preparing it.
+pub fn engine_check(error: String) -> Check {
+    Check::failed(
+        "kernels",
+        Status::Problem,
+        format!("cannot prepare the engine: {error}"),
+        "search with --engine opencl or --engine reference meanwhile".to_string(),
+    )
+}
+
+/// The delay of `reg query` for `TdrDelay`, printed in hexadecimal.
+fn parse_tdr_delay(output: &str) -> Option<Duration> {
+    let line = output.lines().find(|line| line.contains("TdrDelay"))?;
+    let value = line.split_whitespace().last()?.strip_prefix("0x")?;
+    u64::from_str_radix(value, 16).ok().map(Duration::from_secs)
+}
+
+/// How long the driver watchdog lets a dispatch run, when the system says so: Windows resets the
+/// GPU after its TDR delay.
+fn watchdog_timeout() -> Option<Duration> {
+    if !cfg!(target_os = "windows") {
+        return None;
+    }
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SYSTEM\CurrentControlSet\Control\GraphicsDrivers",
+            "/v",
+            "TdrDelay",
+        ])
+        .output()
+        .ok()?;
+    Some(parse_tdr_delay(&String::from_utf8_lossy(&output.stdout)).unwrap_or(DEFAULT_TDR_DELAY))
+}
+
+/// Whether a `step_time` long dispatch, a whole step by default, stays well under the watchdog of
+/// `timeout`. Without a timeout to read, that of Windows is assumed: desktop drivers reset GPUs
+/// driving a display after a few seconds.
+pub fn watchdog_check(step_time: Duration, timeout: Option<Duration>) -> Check {
+    let (limit, known) = match timeout {
+        Some(timeout) => (
+            timeout,
+            format!("the watchdog resets the GPU after {timeout:?}"),
+        ),
+        None => (DEFAULT_TDR_DELAY, "no watchdog setting to read".to_string()),
+    };
+    let detail = format!("a step takes {step_time:.0?}, {known}");
+    if step_time * 2 < limit {
+        return Check::ok("watchdog", detail);
+    }
+    let mut hint = "dispatch less at once with --target-dispatch-ms 500, or search in smaller \
+                    steps with --partitions 1024"
+        .to_string();
+    if timeout.is_some() {
+        hint.push_str(
+            ", or raise TdrDelay under HKLM\\SYSTEM\\CurrentControlSet\\Control\\GraphicsDrivers",
+        );
+    }
+    Check::failed("watchdog", Status::Warning, detail, hint)
+}
+
+/// The watchdog check of this system.
+pub fn system_watchdog_check(step_time: Duration) -> Check {
+    watchdog_check(step_time, watchdog_timeout())
+}
+
+/// Whether curl, giving its `version` line, is there to post `--webhook` and `--notify`. Only
+/// these need it, so that it is missing is only worth a warning.
+#[cfg(feature = "webhook")]
+pub fn curl_check(version: Option<&str>) -> Check {
+    match version {
+        Some(version) => Check::ok("curl", version.to_string()),
+        None => Check::failed(
+            "curl",
+            Status::Warning,
+            "not found, --webhook and --notify cannot post".to_string(),
+            "install curl, or leave --webhook and --notify out".to_string(),
+        ),
+    }
+}
+
+/// The curl check of this system, with the version of `curl --version`, such as `curl 8.5.0`.
+#[cfg(feature = "webhook")]
+pub fn system_curl_check() -> Check {
+    let output = std::process::Command::new("curl")
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success());
+    let stdout = output.map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+    let version = stdout.map(|stdout| {
+        let words: Vec<&str> = stdout.split_whitespace().take(2).collect();
+        words.join(" ")
+    });
+    curl_check(version.as_deref())
+}
+
+/// Browsers are out of reach: there is no WebAssembly build of the search yet.
+pub fn webgpu_check() -> Check {
+    Check::failed(
+        "webgpu",
+        Status::Warning,
+        "no WebAssembly build, the search cannot run in browsers".to_string(),
+        "run this binary, or the Node.js bindings of bindings/node from JavaScript".to_string(),
+    )
+}
+
+#[test]
+fn test_adapter_checks() {
+    use crate::gpu_info::test_report;
+
+    let checks = adapter_checks(&[]);
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].status, Status::Problem);
+
+    let software = [test_report(
+        "llvmpipe (LLVM 15.0.7, 256 bits)",
+        wgpu::DeviceType::Cpu,
+    )];
+    let checks = adapter_checks(&software);
+    assert_eq!(checks[1].status, Status::Problem);
+    assert!(checks[1]
+        .hint
+        .as_ref()
+        .unwrap()
+        .contains("--allow-software"));
+    assert!(!can_run_kernels(&software, false));
+    assert!(can_run_kernels(&software, true));
+
+    // Another adapter is usable, the software one is only worth a warning.
+    let gpu = test_report("GPU", wgpu::DeviceType::DiscreteGpu);
+    let mut gl = test_report("GPU", wgpu::DeviceType::DiscreteGpu);
+    gl.info.backend = wgpu::Backend::Gl;
+    gl.downlevel.flags = wgpu::DownlevelFlags::empty();
+    let checks = adapter_checks(&[gpu, gl]);
+    assert_eq!(checks[0].detail, "2 found, 1 usable");
+    assert_eq!(checks[1].status, Status::Ok);
+    assert_eq!(checks[2].name, "GPU (gl)");
+    assert_eq!(checks[2].status, Status::Warning);
+    assert!(checks[2].hint.as_ref().unwrap().contains("compute shaders"));
+}
+
+#[test]
+fn test_kernel_arguments() {
+    use crate::replay::{first_divergence, outputs};
+
+    for flavor in Flavor::ALL {
+        let [exact, small, ranges] = kernel_arguments(flavor);
+        assert!(crate::engine::is_exact_output(&exact));
+        assert!(crate::engine::pack_small_ranges(&small).is_some());
+        for arguments in [exact, small, ranges] {
+            let outputs = outputs(flavor, &arguments, TEST_SEED);
+            assert_eq!(first_divergence(&arguments, &outputs), None);
+        }
+    }
+    // In the first of the 256 steps of the default partitions.
+    const { assert!(TEST_SEED < 1 << 24) };
+}
+
+#[test]
+fn test_watchdog_check() {
+    let output = "\r\nHKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Control\\GraphicsDrivers\r\n\
+                  \x20   TdrDelay    REG_DWORD    0xa\r\n";
+    assert_eq!(parse_tdr_delay(output), Some(Duration::from_secs(10)));
+    assert_eq!(parse_tdr_delay(""), None);
+
+    let check = watchdog_check(Duration::from_millis(200), None);
+    assert_eq!(check.status, Status::Ok);
+    let check = watchdog_check(Duration::from_millis(1500), None);
+    assert_eq!(check.status, Status::Warning);
+    assert!(!check.hint.unwrap().contains("TdrDelay"));
+    let check = watchdog_check(Duration::from_millis(1500), Some(Duration::from_secs(10)));
+    assert_eq!(check.status, Status::Ok);
+    let check = watchdog_check(Duration::from_secs(3), Some(Duration::from_secs(2)));
+    assert!(check.hint.unwrap().contains("TdrDelay"));
+    assert_eq!(
+        Check::ok("kernels", "fine".to_string()).text(),
+        "ok       kernels: fine\n"
+    );
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn test_curl_check() {
+    let check = curl_check(Some("curl 8.5.0"));
+    assert_eq!(check.status, Status::Ok);
+    assert_eq!(check.detail, "curl 8.5.0");
+    let check = curl_check(None);
+    assert_eq!(check.status, Status::Warning);
+    assert!(check.hint.unwrap().contains("install curl"));
+}
@@ -0,0 +1,407 @@
+// `--emit`: a script replaying the `mt_rand()` calls of the constraints for the seeds found, so
+// that they can be confirmed on the exact PHP version of the target, or without PHP at all by a
+// reimplementation of its generator.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::flavor::Flavor;
+
+/// The languages scripts can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Php,
+    Rust,
+    Python,
+}
+
+impl std::str::FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "php" => Ok(Language::Php),
+            "rust" => Ok(Language::Rust),
+            "python" => Ok(Language::Python),
+            _ => Err(format!(
+                "unknown language {s:?}, expected php, rust or python"
+            )),
+        }
+    }
+}
+
+impl Language {
+    /// How to run the script written at `path`.
+    pub fn command(self, path: &Path) -> String {
+        let path = path.display();
+        match self {
+            Language::Php => format!("php {path}"),
+            Language::Rust => format!("rustc -O {path} -o verify && ./verify"),
+            Language::Python => format!("python3 {path}"),
+        }
+    }
+}
+
+/// The script replaying the seeds `found` for the normalized `arguments` in `language`.
+pub fn script(language: Language, arguments: &[u32], found: &[(Flavor, Vec<u32>)]) -> String {
+    match language {
+        Language::Php => php_script(arguments, found),
+        Language::Rust => rust_script(arguments, found),
+        Language::Python => python_script(arguments, found),
+    }
+}
+
+/// The slots of the normalized `arguments`, without ranges for `mt_rand()` itself.
+fn calls(arguments: &[u32]) -> impl Iterator<Item = (u32, u32, Option<(u32, u32)>)> + '_ {
+    arguments.chunks(4).map(|slot| {
+        let &[match_min, match_max, range_min, range_max] = slot else {
+            panic!("arguments must be normalized");
+        };
+        let range = (range_min, range_max);
+        (
+            match_min,
+            match_max,
+            (range != (0, 0x7fffffff)).then_some(range),
+        )
+    })
+}
+
+/// The seeds found, each with whether PHP 5.2.1 to 7.0.x seeded it, and its flavor.
+fn seeds(found: &[(Flavor, Vec<u32>)]) -> impl Iterator<Item = (u32, bool, Flavor)> + '_ {
+    found.iter().flat_map(|(flavor, seeds)| {
+        seeds
+            .iter()
+            .map(move |&seed| (seed, *flavor == Flavor::Php52, *flavor))
+    })
+}
+
+/// A PHP script seeding `mt_srand()` with each seed found and printing the outputs of the calls the
+/// normalized `arguments` constrain, along with whether they match. It exits with status 1 when a
+/// seed does not, and only uses syntax PHP 5.2 understands, as targets may be that old.
+pub fn php_script(arguments: &[u32], found: &[(Flavor, Vec<u32>)]) -> String {
+    let mut script = String::from(
+        "<?php\n\
+         // Replays the mt_rand() calls of the constraints for the seeds found by php_mt_seed.rs.\n\
+         // Run it on the PHP version of the target: php verify.php\n\
+         // array(MATCH_MIN, MATCH_MAX, RANGE_MIN, RANGE_MAX), null ranges for mt_rand() itself\n\
+         $calls = array(\n",
+    );
+    for (match_min, match_max, range) in calls(arguments) {
+        match range {
+            None => writeln!(
+                script,
+                "    array({match_min}, {match_max}, null, null), // mt_rand()"
+            ),
+            Some((range_min, range_max)) => writeln!(
+                script,
+                "    array({match_min}, {match_max}, {range_min}, {range_max}), \
+                 // mt_rand({range_min}, {range_max})"
+            ),
+        }
+        .unwrap();
+    }
+    script.push_str(
+        ");\n\
+         // array(SEED, whether PHP 5.2.1 to 7.0.x seeded it, MT_RAND_PHP since 7.1.0)\n\
+         $seeds = array(\n",
+    );
+    for (seed, legacy, flavor) in seeds(found) {
+        writeln!(
+            script,
+            "    array({seed}, {legacy}), // PHP {}",
+            flavor.versions()
+        )
+        .unwrap();
+    }
+    script.push_str(
+        ");\n\
+         \n\
+         $modern = version_compare(PHP_VERSION, '7.1.0', '>=');\n\
+         $all = true;\n\
+         foreach ($seeds as $entry) {\n\
+         \x20   list($seed, $legacy) = $entry;\n\
+         \x20   if ($legacy && $modern) {\n\
+         \x20       mt_srand($seed, MT_RAND_PHP);\n\
+         \x20   } else {\n\
+         \x20       mt_srand($seed);\n\
+         \x20   }\n\
+         \x20   // Older versions cannot give the outputs of PHP 7.1.0+.\n\
+         \x20   $matches = $legacy || $modern;\n\
+         \x20   echo \"seed $seed:\";\n\
+         \x20   foreach ($calls as $call) {\n\
+         \x20       list($min, $max, $range_min, $range_max) = $call;\n\
+         \x20       $value = $range_min === null ? mt_rand() : mt_rand($range_min, $range_max);\n\
+         \x20       $matches = $matches && $min <= $value && $value <= $max;\n\
+         \x20       echo \" $value\";\n\
+         \x20   }\n\
+         \x20   echo $matches ? \" (matches)\\n\" : \" (does not match)\\n\";\n\
+         \x20   $all = $all && $matches;\n\
+         }\n\
+         exit($all ? 0 : 1);\n",
+    );
+    script
+}
+
+/// PHP's `mt_rand()`, in Python: MT19937 as `mt_srand()` seeds it, the twist of PHP 5.2.1 to
+/// 7.0.x, and both ways of drawing in a range, with the draws PHP 7.1.0+ rejects.
+const PYTHON_MT_RAND: &str = r#"
+N, M = 624, 397
+
+
+class MtRand:
+    def __init__(self, seed, legacy):
+        self.legacy = legacy
+        self.state = [seed]
+        for i in range(1, N):
+            prev = self.state[i - 1]
+            self.state.append((1812433253 * (prev ^ (prev >> 30)) + i) & 0xFFFFFFFF)
+        self.index = N
+
+    def twist(self):
+        s = self.state
+        for i in range(N):
+            u, v = s[i], s[(i + 1) % N]
+            y = s[(i + M) % N] ^ (((u & 0x80000000) | (v & 0x7FFFFFFF)) >> 1)
+            # PHP before 7.1.0 took the low bit of the wrong word.
+            if (u if self.legacy else v) & 1:
+                y ^= 0x9908B0DF
+            s[i] = y
+        self.index = 0
+
+    def next32(self):
+        if self.index >= N:
+            self.twist()
+        y = self.state[self.index]
+        self.index += 1
+        y ^= y >> 11
+        y ^= (y << 7) & 0x9D2C5680
+        y ^= (y << 15) & 0xEFC60000
+        return y ^ (y >> 18)
+
+    def mt_rand(self, range_min=None, range_max=None):
+        if range_min is None:
+            return self.next32() >> 1
+        if self.legacy:
+            n = self.next32() >> 1
+            return range_min + int((range_max - range_min + 1.0) * (n / 2147483648.0))
+        umax = range_max - range_min + 1
+        n = self.next32()
+        if umax & (umax - 1) == 0:
+            return range_min + (n & (umax - 1))
+        limit = 0xFFFFFFFF - (0xFFFFFFFF % umax) - 1
+        while n > limit:
+            n = self.next32()
+        return range_min + n % umax
+
+
+all_match = True
+for seed, legacy in SEEDS:
+    mt = MtRand(seed, legacy)
+    values = [mt.mt_rand(range_min, range_max) for _, _, range_min, range_max in CALLS]
+    matches = all(low <= value <= high for value, (low, high, _, _) in zip(values, CALLS))
+    print("seed %d: %s (%s)" % (seed, " ".join(map(str, values)),
+                                "matches" if matches else "does not match"))
+    all_match = all_match and matches
+sys.exit(0 if all_match else 1)
+"#;
+
+/// A Python 3 script replaying the seeds like the PHP one, for machines without PHP.
+pub fn python_script(arguments: &[u32], found: &[(Flavor, Vec<u32>)]) -> String {
+    let mut script = String::from(
+        "#!/usr/bin/env python3\n\
+         # Replays the mt_rand() calls of the constraints for the seeds found by php_mt_seed.rs,\n\
+         # with a reimplementation of PHP's mt_rand(): python3 verify.py\n\
+         import sys\n\
+         \n\
+         # (MATCH_MIN, MATCH_MAX, RANGE_MIN, RANGE_MAX), None ranges for mt_rand() itself\n\
+         CALLS = [\n",
+    );
+    for (match_min, match_max, range) in calls(arguments) {
+        match range {
+            None => writeln!(
+                script,
+                "    ({match_min}, {match_max}, None, None),  # mt_rand()"
+            ),
+            Some((range_min, range_max)) => writeln!(
+                script,
+                "    ({match_min}, {match_max}, {range_min}, {range_max}),  \
+                 # mt_rand({range_min}, {range_max})"
+            ),
+        }
+        .unwrap();
+    }
+    script.push_str("]\n# (SEED, whether PHP 5.2.1 to 7.0.x seeded it)\nSEEDS = [\n");
+    for (seed, legacy, flavor) in seeds(found) {
+        let legacy = if legacy { "True" } else { "False" };
+        writeln!(
+            script,
+            "    ({seed}, {legacy}),  # PHP {}",
+            flavor.versions()
+        )
+        .unwrap();
+    }
+    script.push_str("]\n");
+    script.push_str(PYTHON_MT_RAND);
+    script
+}
+
+/// PHP's `mt_rand()`, in Rust, like `PYTHON_MT_RAND`.
+const RUST_MT_RAND: &str = r#"
+const N: usize = 624;
+const M: usize = 397;
+
+struct MtRand {
+    legacy: bool,
+    state: [u32; N],
+    index: usize,
+}
+
+impl MtRand {
+    fn new(seed: u32, legacy: bool) -> Self {
+        let mut state = [seed; N];
+        for i in 1..N {
+            let prev = state[i - 1];
+            state[i] = 1812433253u32
+                .wrapping_mul(prev ^ (prev >> 30))
+                .wrapping_add(i as u32);
+        }
+        MtRand { legacy, state, index: N }
+    }
+
+    fn twist(&mut self) {
+        let s = &mut self.state;
+        for i in 0..N {
+            let (u, v) = (s[i], s[(i + 1) % N]);
+            let mut y = s[(i + M) % N] ^ (((u & 0x80000000) | (v & 0x7fffffff)) >> 1);
+            // PHP before 7.1.0 took the low bit of the wrong word.
+            if (if self.legacy { u } else { v }) & 1 != 0 {
+                y ^= 0x9908b0df;
+            }
+            s[i] = y;
+        }
+        self.index = 0;
+    }
+
+    fn next32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c5680;
+        y ^= (y << 15) & 0xefc60000;
+        y ^ (y >> 18)
+    }
+
+    fn mt_rand(&mut self, range: Option<(u32, u32)>) -> u32 {
+        let Some((range_min, range_max)) = range else {
+            return self.next32() >> 1;
+        };
+        if self.legacy {
+            let n = self.next32() >> 1;
+            let scaled = (f64::from(range_max - range_min) + 1.0) * (f64::from(n) / 2147483648.0);
+            return range_min + scaled as u32;
+        }
+        let mut n = self.next32();
+        if range_max - range_min == u32::MAX {
+            return n;
+        }
+        let umax = range_max - range_min + 1;
+        if umax.is_power_of_two() {
+            return range_min + (n & (umax - 1));
+        }
+        let limit = u32::MAX - (u32::MAX % umax) - 1;
+        while n > limit {
+            n = self.next32();
+        }
+        range_min + n % umax
+    }
+}
+
+fn main() -> std::process::ExitCode {
+    let mut all_match = true;
+    for &(seed, legacy) in SEEDS {
+        let mut mt = MtRand::new(seed, legacy);
+        let values: Vec<u32> = CALLS.iter().map(|&(_, _, range)| mt.mt_rand(range)).collect();
+        let matches = values
+            .iter()
+            .zip(CALLS)
+            .all(|(value, (low, high, _))| (low..=high).contains(&value));
+        let values: Vec<String> = values.iter().map(u32::to_string).collect();
+        let verdict = if matches { "matches" } else { "does not match" };
+        println!("seed {seed}: {} ({verdict})", values.join(" "));
+        all_match &= matches;
+    }
+    if all_match {
+        std::process::ExitCode::SUCCESS
+    } else {
+        std::process::ExitCode::FAILURE
+    }
+}
+"#;
+
+/// A Rust program replaying the seeds like the PHP script, built with nothing but `rustc`.
+pub fn rust_script(arguments: &[u32], found: &[(Flavor, Vec<u32>)]) -> String {
+    let mut script = String::from(
+        "// Replays the mt_rand() calls of the constraints for the seeds found by php_mt_seed.rs,\n\
+         // with a reimplementation of PHP's mt_rand(): rustc -O verify.rs && ./verify\n\
+         \n\
+         // (MATCH_MIN, MATCH_MAX, RANGE), no range for mt_rand() itself\n\
+         const CALLS: &[(u32, u32, Option<(u32, u32)>)] = &[\n",
+    );
+    for (match_min, match_max, range) in calls(arguments) {
+        match range {
+            None => writeln!(script, "    ({match_min}, {match_max}, None), // mt_rand()"),
+            Some((range_min, range_max)) => writeln!(
+                script,
+                "    ({match_min}, {match_max}, Some(({range_min}, {range_max}))), \
+                 // mt_rand({range_min}, {range_max})"
+            ),
+        }
+        .unwrap();
+    }
+    script.push_str(
+        "];\n// (SEED, whether PHP 5.2.1 to 7.0.x seeded it)\nconst SEEDS: &[(u32, bool)] = &[\n",
+    );
+    for (seed, legacy, flavor) in seeds(found) {
+        writeln!(
+            script,
+            "    ({seed}, {legacy}), // PHP {}",
+            flavor.versions()
+        )
+        .unwrap();
+    }
+    script.push_str("];\n");
+    script.push_str(RUST_MT_RAND);
+    script
+}
+
+#[test]
+fn test_php_script() {
+    let arguments = [1178568022, 1178568022, 0, 0x7fffffff, 5, 9, 0, 61];
+    let found = [(Flavor::Php71, vec![0]), (Flavor::Php52, vec![4242])];
+    let script = php_script(&arguments, &found);
+    assert!(script.starts_with("<?php\n"));
+    assert!(script.contains("    array(1178568022, 1178568022, null, null), // mt_rand()\n"));
+    assert!(script.contains("    array(5, 9, 0, 61), // mt_rand(0, 61)\n"));
+    assert!(script.contains("    array(0, false), // PHP 7.1.0+\n"));
+    assert!(script.contains("    array(4242, true), // PHP 5.2.1 to 7.0.x; HHVM\n"));
+    assert!(script.ends_with("exit($all ? 0 : 1);\n"));
+}
+
+#[test]
+fn test_scripts() {
+    let arguments = [5, 9, 0, 61];
+    let found = [(Flavor::Php52, vec![4242])];
+    let python = script(Language::Python, &arguments, &found);
+    assert!(python.contains("    (5, 9, 0, 61),  # mt_rand(0, 61)\n"));
+    assert!(python.contains("    (4242, True),  # PHP 5.2.1 to 7.0.x; HHVM\n"));
+    let rust = script(Language::Rust, &arguments, &found);
+    assert!(rust.contains("    (5, 9, Some((0, 61))), // mt_rand(0, 61)\n"));
+    assert!(rust.contains("    (4242, true), // PHP 5.2.1 to 7.0.x; HHVM\n"));
+    assert!(script(Language::Php, &arguments, &found).starts_with("<?php\n"));
+
+    assert_eq!("python".parse(), Ok(Language::Python));
+    assert!("perl".parse::<Language>().is_err());
+}
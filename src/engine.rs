@@ -0,0 +1,444 @@
+use std::{ops::Range, sync::Arc, time::Duration};
+
+use crate::flavor::Flavor;
+use crate::Options;
+
+/// Why a step could not be completed on the GPU.
+#[derive(Debug, PartialEq)]
+pub enum ExecuteError {
+    /// More seeds matched than the output buffer can hold.
+    Overflow,
+    /// The device was lost (driver reset, power event, ...), the GPU has to be prepared again.
+    DeviceLost,
+}
+
+/// Each step covers 2^24 seeds, that is 2^16 workgroups of 256 invocations.
+pub const WORKGROUPS_PER_STEP: u32 = 65536;
+
+/// How many slots the kernels can check: the shaders only keep the MT19937 state they need, in a
+/// fixed-size array. Further slots are verified on the CPU, on the seeds the kernels found.
+pub const KERNEL_SLOTS: usize = 8;
+
+/// Whether any output matches a normalized slot, as when an output is skipped.
+pub fn is_any_value(slot: &[u32]) -> bool {
+    let &[match_min, match_max, range_min, range_max] = slot else {
+        panic!("arguments must be normalized");
+    };
+    match_min == range_min && match_max == range_max
+}
+
+/// Whether the slots the kernels check are a single exact output of `mt_rand()` without arguments,
+/// the most common query, which the kernels search with a leaner entry point.
+pub fn is_exact_output(arguments: &[u32]) -> bool {
+    matches!(arguments, &[match_min, match_max, 0, 0x7fffffff] if match_min == match_max)
+}
+
+/// Largest `MAX` of the slots `pack_small_ranges` packs, for `mt_rand(0, MAX)`.
+pub const SMALL_RANGE_MAX: u32 = 1023;
+
+/// The slots the kernels check packed into a word each, `MATCH_MIN | MATCH_MAX << 10 | MAX << 20`,
+/// when they are all small ranges `mt_rand(0, MAX)`, such as those of shuffles: the kernels search
+/// them with a leaner entry point, which stops at the first mismatching output.
+pub fn pack_small_ranges(arguments: &[u32]) -> Option<Vec<u32>> {
+    if arguments.is_empty() {
+        return None;
+    }
+    arguments
+        .chunks(4)
+        .map(|slot| match *slot {
+            [match_min, match_max, 0, range_max] if range_max <= SMALL_RANGE_MAX => {
+                Some(match_min | match_max.min(range_max) << 10 | range_max << 20)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// How many slots of the normalized `arguments` the kernels check, from the first: at most
+/// `kernel_slots`, without the slots ending them that any output matches, which would only make
+/// the kernels compute more state. The other slots are verified on the CPU, for the seeds the
+/// kernels found. At least one slot is checked, if there is any.
+pub fn kernel_slot_count(arguments: &[u32], kernel_slots: usize) -> usize {
+    let slots = (arguments.len() / 4).min(kernel_slots);
+    arguments[..4 * slots]
+        .chunks(4)
+        .rposition(|slot| !is_any_value(slot))
+        .map_or(slots.min(1), |last| last + 1)
+}
+
+/// Size, in u32, of the output buffers: a length prefix, then the matching seeds.
+pub const MAX_RESULTS: usize = 1_000;
+
+/// Extract the seeds from an output buffer read back from the device.
+pub fn extract_results(result: &[u32]) -> Result<Vec<u32>, ExecuteError> {
+    // Extract results (length prefix + data)
+    let subslice_start = 1;
+    let subslice_end = 1 + result[0] as usize;
+    if subslice_end > result.len() {
+        log::warn!(
+            "{} seeds found in a dispatch, only {} could be read back",
+            result[0],
+            result.len() - 1
+        );
+        return Err(ExecuteError::Overflow);
+    }
+    let useful_results = &result[subslice_start..subslice_end];
+
+    Ok(Vec::from(useful_results))
+}
+
+/// A compute backend able to test seeds against the constraints.
+///
+/// Engines only know how to run a slice of a step: the chunking, retries and result printing are
+/// shared and done by the caller. Engines are shared between the threads of a host application,
+/// and may run several searches at once.
+pub trait Engine: Send + Sync {
+    /// Execute only the given workgroups of a step, and return the matching seeds.
+    fn execute_workgroups(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError>;
+
+    /// Execute several `(step, workgroups)` slices, and return the matching seeds of each.
+    ///
+    /// Engines that can keep several slices in flight override this, by default slices are
+    /// executed one after the other.
+    fn execute_batch(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        batch
+            .iter()
+            .map(|(step, workgroups)| {
+                self.execute_workgroups(flavor, arguments, *step, workgroups.clone())
+            })
+            .collect()
+    }
+
+    /// Execute several slices like `execute_batch`, each of them skipping the rest of its work
+    /// once it has found `limit` seeds: the search then needs no more.
+    ///
+    /// Engines whose kernels can stop a dispatch midway override this, by default slices are
+    /// executed whole and the caller drops the seeds past the limit. Each slice only stops on
+    /// its own seeds: those found by the others, or by the CPU stage, do not stop it.
+    fn execute_batch_limited(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+        _limit: u32,
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        self.execute_batch(flavor, arguments, batch)
+    }
+
+    /// Execute several `(step, workgroups)` slices for every flavor at once, and return the seeds
+    /// of each matching the `arguments` in any flavor, which the caller tells apart on the CPU.
+    ///
+    /// Engines whose kernels check every flavor in a single invocation override this, along with
+    /// `single_pass_flavors`. By default flavors are executed one after the other.
+    fn execute_batch_all_flavors(
+        &self,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        execute_each_flavor(self, arguments, batch)
+    }
+
+    /// Whether `execute_batch_all_flavors` checks every flavor in a single invocation: the state
+    /// of each seed is then initialized once, its most expensive part, instead of once per flavor.
+    fn single_pass_flavors(&self) -> bool {
+        false
+    }
+
+    /// Execute the given workgroups of a step for several independent queries, and return the
+    /// matching seeds of each.
+    ///
+    /// Engines that can check several queries in a single dispatch override this, so that the
+    /// MT19937 state of each seed is computed once for all of them. By default queries are
+    /// executed one after the other.
+    fn execute_queries(
+        &self,
+        flavor: Flavor,
+        queries: &[&[u32]],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        queries
+            .iter()
+            .map(|arguments| self.execute_workgroups(flavor, arguments, step, workgroups.clone()))
+            .collect()
+    }
+
+    /// Total time spent in the kernel so far, when the engine can measure it.
+    fn kernel_time(&self) -> Option<Duration> {
+        None
+    }
+
+    /// How many slots the engine checks, at most `KERNEL_SLOTS`. Devices with small buffers may
+    /// check fewer, the other slots are then verified on the CPU.
+    fn kernel_slots(&self) -> usize {
+        KERNEL_SLOTS
+    }
+}
+
+/// Execute several `(step, workgroups)` slices for each flavor, one after the other, and return
+/// the seeds of each slice matching in any flavor, in ascending order.
+pub fn execute_each_flavor<E: Engine + ?Sized>(
+    engine: &E,
+    arguments: &[u32],
+    batch: &[(u32, Range<u32>)],
+) -> Result<Vec<Vec<u32>>, ExecuteError> {
+    let mut merged = vec![Vec::new(); batch.len()];
+    for flavor in Flavor::ALL {
+        let results = engine.execute_batch(flavor, arguments, batch)?;
+        for (seeds, results) in merged.iter_mut().zip(results) {
+            seeds.extend(results);
+        }
+    }
+    for seeds in &mut merged {
+        seeds.sort_unstable();
+        seeds.dedup();
+    }
+    Ok(merged)
+}
+
+/// Execute the given workgroups of a step like `Engine::execute_workgroups`, halving them for as
+/// long as their seeds overflow the output buffer, so that the results are complete however weak
+/// the constraints are. A single workgroup still overflowing, which only small output buffers
+/// allow, is searched on the CPU instead, unless `cpu_fallback` is false: the CPU cannot run
+/// predicates.
+pub fn execute_subdivided(
+    engine: &dyn Engine,
+    flavor: Flavor,
+    arguments: &[u32],
+    step: u32,
+    workgroups: Range<u32>,
+    cpu_fallback: bool,
+) -> Result<Vec<u32>, ExecuteError> {
+    match engine.execute_workgroups(flavor, arguments, step, workgroups.clone()) {
+        Err(ExecuteError::Overflow) if workgroups.len() > 1 => {
+            let middle = workgroups.start + workgroups.len() as u32 / 2;
+            log::warn!(
+                "workgroups {workgroups:?} of step {step} overflowed, splitting them at {middle}"
+            );
+            let mut results = execute_subdivided(
+                engine,
+                flavor,
+                arguments,
+                step,
+                workgroups.start..middle,
+                cpu_fallback,
+            )?;
+            results.extend(execute_subdivided(
+                engine,
+                flavor,
+                arguments,
+                step,
+                middle..workgroups.end,
+                cpu_fallback,
+            )?);
+            Ok(results)
+        }
+        Err(ExecuteError::Overflow) if cpu_fallback => {
+            log::warn!(
+                "workgroup {} of step {step} overflowed, searching it on the CPU",
+                workgroups.start
+            );
+            Ok(crate::reference::search_workgroups(
+                flavor, arguments, step, workgroups,
+            ))
+        }
+        result => result,
+    }
+}
+
+/// The engines that can be selected with `--engine`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineKind {
+    /// Vulkan/Metal/D3D12/OpenGL through wgpu.
+    Wgpu,
+    /// OpenCL, for older GPUs and accelerators wgpu cannot target.
+    #[cfg(feature = "opencl")]
+    OpenCl,
+    /// CUDA, usually faster than wgpu on NVIDIA cards. Falls back to wgpu without CUDA.
+    #[cfg(feature = "cuda")]
+    Cuda,
+    /// The CPU reference implementation, to check GPU results or run without any GPU.
+    Reference,
+}
+
+impl Default for EngineKind {
+    /// CUDA when it is built in, as it falls back to wgpu anyway, wgpu otherwise.
+    fn default() -> Self {
+        #[cfg(feature = "cuda")]
+        return EngineKind::Cuda;
+        #[cfg(not(feature = "cuda"))]
+        return EngineKind::Wgpu;
+    }
+}
+
+impl std::str::FromStr for EngineKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wgpu" => Ok(EngineKind::Wgpu),
+            #[cfg(feature = "opencl")]
+            "opencl" => Ok(EngineKind::OpenCl),
+            #[cfg(not(feature = "opencl"))]
+            "opencl" => Err("this build does not include the OpenCL engine, \
+                             rebuild with `--features opencl`"
+                .to_string()),
+            #[cfg(feature = "cuda")]
+            "cuda" => Ok(EngineKind::Cuda),
+            #[cfg(not(feature = "cuda"))]
+            "cuda" => Err("this build does not include the CUDA engine, \
+                           rebuild with `--features cuda`"
+                .to_string()),
+            "reference" => Ok(EngineKind::Reference),
+            _ => Err(format!("unknown engine {s:?}")),
+        }
+    }
+}
+
+/// Prepare the engine selected in `options`, or tell why it cannot run the search.
+pub fn prepare_engine(options: &Options) -> Result<Arc<dyn Engine>, String> {
+    let _span = tracing::info_span!("prepare", engine = ?options.engine).entered();
+    // Predicates and the generators of `--rand` are WGSL, CUDA falls back to wgpu for them as it
+    // does without a device.
+    #[cfg(feature = "cuda")]
+    let fallback = options.engine == EngineKind::Cuda;
+    #[cfg(not(feature = "cuda"))]
+    let fallback = false;
+    if options.predicate.is_some() && options.engine != EngineKind::Wgpu && !fallback {
+        return Err("predicates only run on the wgpu engine".to_string());
+    }
+    if options.generator.is_some() && options.engine != EngineKind::Wgpu && !fallback {
+        return Err("--rand only runs on the wgpu engine".to_string());
+    }
+    Ok(match options.engine {
+        EngineKind::Wgpu => Arc::new(crate::gpu::prepare_gpu(options)?),
+        #[cfg(feature = "opencl")]
+        EngineKind::OpenCl => Arc::new(crate::opencl::prepare_opencl(options)),
+        #[cfg(feature = "cuda")]
+        EngineKind::Cuda if options.predicate.is_some() => {
+            eprintln!("\rpredicates only run on wgpu, using it instead of CUDA");
+            Arc::new(crate::gpu::prepare_gpu(options)?)
+        }
+        #[cfg(feature = "cuda")]
+        EngineKind::Cuda if options.generator.is_some() => {
+            eprintln!("\r--rand only runs on wgpu, using it instead of CUDA");
+            Arc::new(crate::gpu::prepare_gpu(options)?)
+        }
+        #[cfg(feature = "cuda")]
+        EngineKind::Cuda => match crate::cuda::prepare_cuda(options) {
+            Some(prepared) => Arc::new(prepared),
+            None => {
+                eprintln!("\rCUDA is not available, falling back to wgpu");
+                Arc::new(crate::gpu::prepare_gpu(options)?)
+            }
+        },
+        EngineKind::Reference => Arc::new(crate::reference::prepare_reference()),
+    })
+}
+
+#[test]
+fn test_kernel_slot_count() {
+    let exact = [5, 5, 0, 9];
+    let any = [0, 61, 0, 61];
+    assert_eq!(kernel_slot_count(&exact, KERNEL_SLOTS), 1);
+    assert_eq!(
+        kernel_slot_count(&[exact, any, exact].concat(), KERNEL_SLOTS),
+        3
+    );
+    assert_eq!(
+        kernel_slot_count(&[exact, any, any].concat(), KERNEL_SLOTS),
+        1
+    );
+    assert_eq!(kernel_slot_count(&any.repeat(3), KERNEL_SLOTS), 1);
+    assert_eq!(
+        kernel_slot_count(&exact.repeat(KERNEL_SLOTS + 2), KERNEL_SLOTS),
+        KERNEL_SLOTS
+    );
+    assert_eq!(kernel_slot_count(&exact.repeat(4), 2), 2);
+    assert_eq!(kernel_slot_count(&[], KERNEL_SLOTS), 0);
+    assert!(is_any_value(&[0, 0x7fffffff, 0, 0x7fffffff]));
+    assert!(!is_any_value(&[0, 60, 0, 61]));
+}
+
+#[test]
+fn test_is_exact_output() {
+    assert!(is_exact_output(&[1178568022, 1178568022, 0, 0x7fffffff]));
+    assert!(!is_exact_output(&[1178568022, 1178568023, 0, 0x7fffffff]));
+    assert!(!is_exact_output(&[7, 7, 0, 61]));
+    assert!(!is_exact_output(&[7, 7, 0, 0x7fffffff].repeat(2)));
+    assert!(!is_exact_output(&[]));
+}
+
+#[test]
+fn test_pack_small_ranges() {
+    // A shuffle of 4 elements, then a match past the range.
+    let arguments = [2, 2, 0, 3, 0, 0, 0, 2, 1, 1, 0, 1, 0, 70, 0, 61];
+    assert_eq!(
+        pack_small_ranges(&arguments),
+        Some(vec![
+            2 | 2 << 10 | 3 << 20,
+            2 << 20,
+            1 | 1 << 10 | 1 << 20,
+            61 << 10 | 61 << 20
+        ])
+    );
+    assert_eq!(pack_small_ranges(&[7, 7, 1, 61]), None);
+    assert_eq!(pack_small_ranges(&[7, 7, 0, 1024]), None);
+    assert_eq!(pack_small_ranges(&[7, 7, 0, 61, 5, 5, 0, 0x7fffffff]), None);
+    assert_eq!(pack_small_ranges(&[]), None);
+}
+
+#[cfg(test)]
+struct SmallBufferEngine(usize);
+
+#[cfg(test)]
+impl Engine for SmallBufferEngine {
+    fn execute_workgroups(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        let results = crate::reference::search_workgroups(flavor, arguments, step, workgroups);
+        match results.len() > self.0 {
+            true => Err(ExecuteError::Overflow),
+            false => Ok(results),
+        }
+    }
+}
+
+#[test]
+fn test_execute_subdivided() {
+    // About one seed out of 8 matches, 32 per workgroup.
+    let arguments = [0, 0x0fffffff, 0, 0x7fffffff];
+    let expected = crate::reference::search_workgroups(Flavor::Php71, &arguments, 3, 0..4);
+    for engine in [SmallBufferEngine(40), SmallBufferEngine(1)] {
+        assert_eq!(
+            execute_subdivided(&engine, Flavor::Php71, &arguments, 3, 0..4, true),
+            Ok(expected.clone())
+        );
+    }
+    assert_eq!(
+        execute_subdivided(
+            &SmallBufferEngine(1),
+            Flavor::Php71,
+            &arguments,
+            3,
+            0..4,
+            false
+        ),
+        Err(ExecuteError::Overflow)
+    );
+}
@@ -0,0 +1,66 @@
+// `--quick-epoch-pass`: the seeds derived from the time, checked on the CPU before the search, as
+// many targets seed with `mt_srand(time())` or `mt_srand((double) microtime() * 1000000)`.
+
+use std::ops::RangeInclusive;
+
+/// Seeds of `(double) microtime() * 1000000`: the microseconds of the current second.
+const MICROSECONDS: u32 = 1_000_000;
+
+/// The window of `--quick-epoch-pass`: `FROM:TO` in Unix timestamps, or a duration back from
+/// `now` with an `s`, `m`, `h` or `d` suffix.
+pub fn parse_window(s: &str, now: u64) -> Result<RangeInclusive<u32>, String> {
+    let timestamp = |s: &str| {
+        s.parse::<u32>()
+            .map_err(|_| format!("{s:?} is not a Unix timestamp"))
+    };
+    if let Some((from, to)) = s.split_once(':') {
+        let (from, to) = (timestamp(from)?, timestamp(to)?);
+        if from > to {
+            return Err(format!("the window {s:?} ends before it starts"));
+        }
+        return Ok(from..=to);
+    }
+
+    let unit = match s.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        _ => return Err(format!("{s:?} is neither FROM:TO nor a duration like 12h")),
+    };
+    let count: u64 = s[..s.len() - 1]
+        .parse()
+        .map_err(|_| format!("{s:?} is neither FROM:TO nor a duration like 12h"))?;
+    let now = now.min(u64::from(u32::MAX));
+    let from = now.saturating_sub(count.saturating_mul(unit));
+    Ok(from as u32..=now as u32)
+}
+
+/// The seeds to check for `window`: its timestamps, and the microsecond seeds, in order.
+pub fn candidates(window: RangeInclusive<u32>) -> Vec<u32> {
+    let mut seeds: Vec<u32> = (0..MICROSECONDS).chain(window).collect();
+    seeds.sort_unstable();
+    seeds.dedup();
+    seeds
+}
+
+#[test]
+fn test_parse_window() {
+    let now = 1_700_000_000;
+    assert_eq!(
+        parse_window("1600000000:1600000100", now),
+        Ok(1_600_000_000..=1_600_000_100)
+    );
+    assert_eq!(parse_window("2h", now), Ok(now as u32 - 7200..=now as u32));
+    assert_eq!(parse_window("30d", 1000), Ok(0..=1000));
+    assert!(parse_window("5:4", now).is_err());
+    assert!(parse_window("12x", now).is_err());
+    assert!(parse_window("d", now).is_err());
+}
+
+#[test]
+fn test_candidates() {
+    let seeds = candidates(999_990..=1_000_009);
+    assert_eq!(seeds.len(), 1_000_010);
+    assert!(seeds.is_sorted());
+}
@@ -0,0 +1,224 @@
+// The query plan printed by `--explain`: how the constraints are checked, and how selective they
+// are, to understand why a query finds nothing or far too much.
+
+use std::fmt::Write;
+
+use crate::engine::{is_any_value, kernel_slot_count, KERNEL_SLOTS};
+use crate::flavor::Flavor;
+use crate::predicate::Predicate;
+
+/// Seeds searched by a whole run.
+const SEEDS: f64 = 4294967296.0;
+
+/// How `mt_rand()` turns the MT19937 output `mt` into the value of a slot.
+fn scaling(flavor: Flavor, range_min: u32, range_max: u32) -> String {
+    if range_min == 0 && range_max == 0x7fffffff {
+        return "mt >> 1".to_string();
+    }
+    let range = u64::from(range_max - range_min) + 1;
+    match flavor {
+        Flavor::Php71 => format!("mt % {range} + {range_min}"),
+        Flavor::Php52 => format!("{range_min} + (long)({range}.0 * ((mt >> 1) / 2147483648.0))"),
+    }
+}
+
+/// Odds that a random seed passes a slot.
+pub fn selectivity(slot: &[u32]) -> f64 {
+    let &[match_min, match_max, range_min, range_max] = slot else {
+        panic!("arguments must be normalized");
+    };
+    (f64::from(match_max - match_min) + 1.0) / (f64::from(range_max - range_min) + 1.0)
+}
+
+/// The normalized constraints, one slot per line.
+pub fn constraint_table(arguments: &[u32]) -> String {
+    let mut table = format!(
+        "{:>6} {:>11} {:>11} {:>11} {:>11}\n",
+        "slot", "match_min", "match_max", "range_min", "range_max"
+    );
+    for (i, slot) in arguments.chunks(4).enumerate() {
+        write!(table, "{:>6}", i + 1).unwrap();
+        for value in slot {
+            write!(table, " {value:>11}").unwrap();
+        }
+        table.push('\n');
+    }
+    table
+}
+
+/// Describe the evaluation of the normalized `arguments` for each of the `flavors`.
+pub fn explain(arguments: &[u32], flavors: &[Flavor]) -> String {
+    let mut plan = String::from("query plan:\n");
+    if arguments.is_empty() {
+        plan.push_str("  no constraints, every seed is left to the predicate\n");
+        return plan;
+    }
+    let slots: Vec<&[u32]> = arguments.chunks(4).collect();
+    let kernel_slots = kernel_slot_count(arguments, KERNEL_SLOTS);
+    for (i, slot) in slots.iter().enumerate() {
+        let &[match_min, match_max, range_min, range_max] = *slot else {
+            panic!("arguments must be normalized");
+        };
+        let checked_on = if i < kernel_slots { "GPU" } else { "CPU" };
+        let selectivity = selectivity(slot);
+        let kind = if selectivity == 1.0 {
+            "skipped, any value matches"
+        } else if match_min == match_max {
+            "exact"
+        } else {
+            "range"
+        };
+        writeln!(
+            plan,
+            "  slot {}: output #{} of MT19937 must be in {match_min}..={match_max} \
+             (mt_rand({range_min}, {range_max})), {kind}, selectivity {selectivity:.3e}, \
+             on the {checked_on}",
+            i + 1,
+            i + 1,
+        )
+        .unwrap();
+        for &flavor in flavors {
+            let formula = scaling(flavor, range_min, range_max);
+            writeln!(plan, "    PHP {}: {formula}", flavor.versions()).unwrap();
+        }
+    }
+
+    writeln!(
+        plan,
+        "evaluation order: slots 1 to {kernel_slots} in order on the GPU, each seed stops at its \
+         first mismatch"
+    )
+    .unwrap();
+    if slots[kernel_slots..].iter().any(|slot| !is_any_value(slot)) {
+        writeln!(
+            plan,
+            "  slots {} to {} are verified on the CPU, only for the seeds the GPU found, while \
+             the GPU goes on",
+            kernel_slots + 1,
+            slots.len()
+        )
+        .unwrap();
+    }
+
+    let kernel_selectivity: f64 = slots[..kernel_slots]
+        .iter()
+        .map(|slot| selectivity(slot))
+        .product();
+    let total_selectivity: f64 = slots.iter().map(|slot| selectivity(slot)).product();
+    writeln!(
+        plan,
+        "expected seeds found by the GPU by chance: {:.3e} per flavor",
+        kernel_selectivity * SEEDS
+    )
+    .unwrap();
+    writeln!(
+        plan,
+        "expected seeds matching every slot by chance: {:.3e} per flavor",
+        total_selectivity * SEEDS
+    )
+    .unwrap();
+    plan
+}
+
+/// How many seeds are expected to match the normalized `arguments` by chance, per flavor.
+pub fn expected_seeds(arguments: &[u32]) -> f64 {
+    SEEDS * arguments.chunks(4).map(selectivity).product::<f64>()
+}
+
+/// The filtering entropy of the normalized `arguments` against the 32 bits of the seeds, and how
+/// many seeds to expect from it, on one line.
+pub fn entropy(arguments: &[u32]) -> String {
+    let bits: f64 = arguments
+        .chunks(4)
+        .map(|slot| -selectivity(slot).log2())
+        .sum();
+    let chance = expected_seeds(arguments);
+    let expect = if chance < 0.01 {
+        "a unique seed".to_string()
+    } else if chance < 1.0 {
+        format!(
+            "a unique seed, with {:.0}% odds of another one matching by chance",
+            chance * 100.0
+        )
+    } else if chance <= 1000.0 {
+        format!("a handful of seeds, about {chance:.0} matching by chance")
+    } else {
+        format!(
+            "about {chance:.1e} seeds matching by chance, add observations to narrow them \
+             down"
+        )
+    };
+    format!(
+        "constraints: {bits:.1} bits of filtering for 32 bits of seeds, expect {expect} per \
+         flavor\n"
+    )
+}
+
+/// How the predicate of `--predicate` fits in the plan.
+pub fn explain_predicate(predicate: &Predicate) -> String {
+    format!(
+        "predicate: `matches` is called on the GPU with the first {} outputs of MT19937, for the \
+         seeds passing the slots the GPU checks\n",
+        predicate.outputs()
+    )
+}
+
+#[test]
+fn test_explain() {
+    let arguments = [
+        1178568022, 1178568022, 0, 0x7fffffff, 0, 61, 0, 61, 7505, 7505, 1000, 10000,
+    ];
+    let plan = explain(&arguments, &Flavor::ALL);
+    assert!(plan.contains("slot 1: output #1 of MT19937 must be in 1178568022..=1178568022"));
+    assert!(
+        plan.contains("slot 2: output #2 of MT19937 must be in 0..=61 (mt_rand(0, 61)), skipped")
+    );
+    assert!(plan.contains("PHP 7.1.0+: mt % 9001 + 1000"));
+    assert!(plan
+        .contains("PHP 5.2.1 to 7.0.x; HHVM: 1000 + (long)(9001.0 * ((mt >> 1) / 2147483648.0))"));
+    assert!(plan.contains("slots 1 to 3 in order on the GPU"));
+    assert!(!plan.contains("on the CPU, only"));
+}
+
+#[test]
+fn test_explain_cpu_slots() {
+    let arguments = [5, 5, 0, 9].repeat(KERNEL_SLOTS + 2);
+    let plan = explain(&arguments, &[Flavor::Php71]);
+    assert!(plan.contains("slot 9: output #9 of MT19937 must be in 5..=5 (mt_rand(0, 9)), exact"));
+    assert!(plan.contains("selectivity 1.000e-1, on the CPU"));
+    assert!(plan.contains("slots 9 to 10 are verified on the CPU"));
+}
+
+#[test]
+fn test_entropy() {
+    let exact = [1178568022, 1178568022, 0, 0x7fffffff];
+    assert_eq!(
+        entropy(&exact.repeat(2)),
+        "constraints: 62.0 bits of filtering for 32 bits of seeds, expect a unique seed per \
+         flavor\n"
+    );
+    assert!(entropy(&exact).contains("31.0 bits"));
+    assert!(entropy(&exact).contains("a handful of seeds, about 2 matching by chance"));
+    assert!(entropy(&[5, 5, 0, 61].repeat(6)).contains("with 8% odds"));
+    assert!(entropy(&[5, 5, 0, 61].repeat(2)).contains("about 1.1e6 seeds"));
+    assert_eq!(expected_seeds(&exact), 2.0);
+}
+
+#[test]
+fn test_constraint_table() {
+    assert_eq!(
+        constraint_table(&[1178568022, 1178568022, 0, 0x7fffffff, 5, 9, 0, 61]),
+        "  slot   match_min   match_max   range_min   range_max\n\
+         \x20    1  1178568022  1178568022           0  2147483647\n\
+         \x20    2           5           9           0          61\n"
+    );
+}
+
+#[test]
+fn test_explain_trailing_skipped_slots() {
+    let arguments = [[5, 5, 0, 9], [0, 61, 0, 61], [0, 61, 0, 61]].concat();
+    let plan = explain(&arguments, &[Flavor::Php71]);
+    assert!(plan.contains("slots 1 to 1 in order on the GPU"));
+    assert!(plan.contains("skipped, any value matches, selectivity 1.000e0, on the CPU"));
+    assert!(!plan.contains("verified on the CPU"));
+}
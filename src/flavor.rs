@@ -0,0 +1,156 @@
+/// The variants of `mt_rand()` that can be searched, selected with `--php`.
+///
+/// Serialized as the main value `--php` accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Flavor {
+    /// PHP 7.1.0+: the correct MT19937, and ranges reduced with a modulo.
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "7.1"))]
+    Php71,
+    /// PHP 5.2.1 to 7.0.x, HHVM, and `MT_RAND_PHP` since 7.1.0: a typo in the twist of MT19937,
+    /// and ranges scaled with floating point math.
+    #[cfg_attr(feature = "serde", serde(rename = "5.2.1"))]
+    Php52,
+}
+
+impl Flavor {
+    /// Every flavor, searched by `--php auto`.
+    pub const ALL: [Flavor; 2] = [Flavor::Php71, Flavor::Php52];
+
+    /// How every flavor at once is passed to the kernels, which then find the seeds matching in
+    /// either of them.
+    pub const ALL_KERNEL_ID: u32 = 2;
+
+    /// The main value `--php` accepts for this flavor, as it is serialized.
+    pub fn name(self) -> &'static str {
+        match self {
+            Flavor::Php71 => "7.1",
+            Flavor::Php52 => "5.2.1",
+        }
+    }
+
+    /// The versions attributed to a seed found in this flavor, as openwall's php_mt_seed puts it.
+    pub fn versions(self) -> &'static str {
+        match self {
+            Flavor::Php71 => "7.1.0+",
+            Flavor::Php52 => "5.2.1 to 7.0.x; HHVM",
+        }
+    }
+
+    /// How the flavor is passed to the kernels.
+    pub fn kernel_id(self) -> u32 {
+        match self {
+            Flavor::Php71 => 0,
+            Flavor::Php52 => 1,
+        }
+    }
+}
+
+impl std::str::FromStr for Flavor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "7.1" | "7.1.0" | "7.1+" | "mt19937" => Ok(Flavor::Php71),
+            "5.2.1" | "5" | "7.0" | "hhvm" | "php" => Ok(Flavor::Php52),
+            _ => Err(format!("unknown PHP flavor {s:?}, expected 7.1 or 5.2.1")),
+        }
+    }
+}
+
+/// What the seeds found per flavor say about the target, when several flavors were searched.
+///
+/// Only one flavor finding seeds is a good hint of the engine the target runs: the constraints
+/// usually leave no chance for random seeds to match.
+pub fn conclusion(found: &[(Flavor, usize)]) -> Option<String> {
+    if found.len() < 2 {
+        return None;
+    }
+    let matching: Vec<Flavor> = found
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(flavor, _)| *flavor)
+        .collect();
+    match matching[..] {
+        [] => None,
+        [flavor] => Some(format!(
+            "target appears to run the PHP {} engine",
+            flavor.versions()
+        )),
+        _ => Some(
+            "seeds were found for several flavors, the constraints are too weak to tell them apart"
+                .to_string(),
+        ),
+    }
+}
+
+/// A result line, `seed = 0x2a = 42 (PHP 7.1.0+)`.
+pub fn format_seed(seed: u32, flavor: Flavor) -> String {
+    format_merged_seed(seed, &[flavor])
+}
+
+/// A result line for a seed found in each of `flavors`, labeled with all of them.
+pub fn format_merged_seed(seed: u32, flavors: &[Flavor]) -> String {
+    let versions: Vec<&str> = flavors.iter().map(|flavor| flavor.versions()).collect();
+    format!("seed = {seed:#x} = {seed} (PHP {})", versions.join(" and "))
+}
+
+/// The seeds found per flavor, each once with every flavor it was found in, in the order they
+/// were found.
+pub fn merge(found: &[(Flavor, Vec<u32>)]) -> Vec<(u32, Vec<Flavor>)> {
+    let mut merged: Vec<(u32, Vec<Flavor>)> = Vec::new();
+    for (flavor, seeds) in found {
+        for &seed in seeds {
+            match merged
+                .iter_mut()
+                .find(|(merged_seed, _)| *merged_seed == seed)
+            {
+                Some((_, flavors)) if !flavors.contains(flavor) => flavors.push(*flavor),
+                Some(_) => {}
+                None => merged.push((seed, vec![*flavor])),
+            }
+        }
+    }
+    merged
+}
+
+#[test]
+fn test_format_seed() {
+    assert_eq!(
+        format_seed(0x12345678, Flavor::Php52),
+        "seed = 0x12345678 = 305419896 (PHP 5.2.1 to 7.0.x; HHVM)"
+    );
+    assert_eq!(format_seed(0, Flavor::Php71), "seed = 0x0 = 0 (PHP 7.1.0+)");
+}
+
+#[test]
+fn test_merge() {
+    let found = [
+        (Flavor::Php71, vec![7, 42, 7]),
+        (Flavor::Php52, vec![42, 1234]),
+    ];
+    assert_eq!(
+        merge(&found),
+        [
+            (7, vec![Flavor::Php71]),
+            (42, vec![Flavor::Php71, Flavor::Php52]),
+            (1234, vec![Flavor::Php52])
+        ]
+    );
+    assert_eq!(
+        format_merged_seed(42, &Flavor::ALL),
+        "seed = 0x2a = 42 (PHP 7.1.0+ and 5.2.1 to 7.0.x; HHVM)"
+    );
+}
+
+#[test]
+fn test_conclusion() {
+    assert_eq!(conclusion(&[(Flavor::Php71, 1)]), None);
+    assert_eq!(conclusion(&[(Flavor::Php71, 0), (Flavor::Php52, 0)]), None);
+    assert_eq!(
+        conclusion(&[(Flavor::Php71, 0), (Flavor::Php52, 2)]).as_deref(),
+        Some("target appears to run the PHP 5.2.1 to 7.0.x; HHVM engine")
+    );
+    assert!(conclusion(&[(Flavor::Php71, 1), (Flavor::Php52, 1)]).is_some());
+}
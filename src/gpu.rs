@@ -0,0 +1,1591 @@
+use std::{
+    num::NonZeroU64,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::engine::{
+    execute_each_flavor, extract_results, is_exact_output, pack_small_ranges, Engine, ExecuteError,
+    KERNEL_SLOTS, MAX_RESULTS, WORKGROUPS_PER_STEP,
+};
+use crate::flavor::Flavor;
+use crate::legacy_rand::Generator;
+use crate::Options;
+
+/// Size in bytes of the two timestamps (beginning and end of the main dispatch) of a submission.
+const TIMESTAMPS_SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+/// In how many sub-ranges a dispatch is re-run when its output buffer overflows.
+const OVERFLOW_SPLITS: u32 = 16;
+
+/// The most queries a batch dispatch checks at once, larger batches are split.
+const MAX_BATCH_QUERIES: usize = 64;
+
+/// The smallest output buffers `--gpu-mem-limit` may shrink them to, in u32.
+const MIN_RESULTS: usize = 16;
+
+/// The `limit` of a dispatch of `submit_workgroups` searching every seed of its workgroups.
+pub const NO_LIMIT: u32 = u32::MAX;
+
+/// Bytes a dispatch allocates besides its outputs and their download copies: inputs and abort
+/// flags, overflow plan, indirect arguments and timestamps.
+const DISPATCH_OVERHEAD: u64 = (1 + OVERFLOW_SPLITS as u64) * (3 + 4 * KERNEL_SLOTS as u64 + 2) * 4
+    + (1 + 2 * 3 * OVERFLOW_SPLITS as u64) * 4
+    + 2 * TIMESTAMPS_SIZE;
+
+/// Bytes a dispatch allocates, with output buffers of `max_results` u32.
+///
+/// Each dispatch has one output buffer per overflow re-run on top of its own, and copies all of
+/// them into a buffer the CPU can read.
+fn dispatch_size(max_results: usize) -> u64 {
+    DISPATCH_OVERHEAD + 2 * (1 + u64::from(OVERFLOW_SPLITS)) * 4 * max_results as u64
+}
+
+/// The output buffer size, in u32, and the number of slots fitting storage buffer bindings of
+/// `max_binding_size` bytes, given the output buffer size wanted.
+///
+/// Downlevel adapters (old mobile GPUs, GLES) may not bind the buffers the kernel would like:
+/// outputs are shrunk, and slots that do not fit the input buffer are left to the CPU.
+pub(crate) fn fit_binding_size(
+    max_binding_size: u64,
+    max_results: usize,
+) -> Option<(usize, usize)> {
+    let words = (max_binding_size / 4).min(usize::MAX as u64) as usize;
+    let kernel_slots = (words.checked_sub(3)? / 4).min(KERNEL_SLOTS);
+    let max_results = max_results.min(words);
+    // Room for the length prefix and at least one seed
+    (kernel_slots > 0 && max_results > 1).then_some((max_results, kernel_slots))
+}
+
+/// The size, in u32, of the output buffers fitting `in_flight` dispatches in `limit` bytes.
+fn max_results_within(limit: u64, in_flight: usize) -> Option<usize> {
+    let per_dispatch = (limit / in_flight as u64).checked_sub(DISPATCH_OVERHEAD)?;
+    let per_output = per_dispatch / (2 * (1 + u64::from(OVERFLOW_SPLITS)));
+    let max_results = (per_output / 4).min(MAX_RESULTS as u64) as usize;
+    (max_results >= MIN_RESULTS).then_some(max_results)
+}
+
+/// How many queries a batch dispatch can check, with storage buffer bindings of `max_binding_size`
+/// bytes and output buffers of `max_results` u32: each query has an output region of its own, and
+/// its slots in the input. Under `--gpu-mem-limit`, the regions and their download copy must fit
+/// `limit` bytes too.
+fn max_batch_queries(
+    max_binding_size: u64,
+    max_results: usize,
+    kernel_slots: usize,
+    limit: Option<u64>,
+) -> usize {
+    let words = (max_binding_size / 4).min(usize::MAX as u64) as usize;
+    let by_outputs = words / max_results;
+    let by_inputs = words.saturating_sub(5) / (1 + 4 * kernel_slots);
+    let by_limit = limit.map_or(usize::MAX, |limit| {
+        (limit / (2 * 4 * max_results as u64)).min(usize::MAX as u64) as usize
+    });
+    by_outputs
+        .min(by_inputs)
+        .min(by_limit)
+        .clamp(1, MAX_BATCH_QUERIES)
+}
+
+// A small struct holding the prepared GPU resources to reuse across multiple workloads.
+pub struct GpuPrepared {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    // The `batch_main` entry point, checking several queries in a dispatch.
+    batch_pipeline: wgpu::ComputePipeline,
+    // The `exact_main` entry point, for a single exact output, and `small_main`, for small ranges.
+    // Not with a predicate or a generator of `--rand`, which they do not check.
+    exact_pipeline: Option<wgpu::ComputePipeline>,
+    small_pipeline: Option<wgpu::ComputePipeline>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    // Set from wgpu callbacks when the device is lost or reports an error we did not capture.
+    lost: Arc<AtomicBool>,
+    failed: Arc<AtomicBool>,
+    // Only available when the adapter supports indirect dispatches.
+    overflow: Option<OverflowPipeline>,
+    // Only set in bench mode, when the adapter supports timestamp queries.
+    timestamps: bool,
+    kernel_time: Mutex<Duration>,
+    /// Size, in u32, of each output buffer, smaller than `MAX_RESULTS` under `--gpu-mem-limit` or
+    /// on adapters with small storage buffers.
+    max_results: usize,
+    /// Slots checked by the kernel, fewer than `KERNEL_SLOTS` on adapters with small storage
+    /// buffers.
+    kernel_slots: usize,
+    /// Queries checked by a batch dispatch at most.
+    max_batch_queries: usize,
+    /// Whether `pipeline` checks the predicate of `--predicate` or searches the generator of
+    /// `--rand`, which `batch_main` does not.
+    custom_main: bool,
+    /// Only set on the GL backend. It keeps its single context locked while waiting for the
+    /// device, so other threads would time out getting it: their batches run one at a time.
+    serialized: Option<Mutex<()>>,
+}
+
+impl GpuPrepared {
+    /// Size in bytes of the output buffer of a dispatch.
+    fn output_buffer_size(&self) -> u64 {
+        (self.max_results * std::mem::size_of::<u32>()) as u64
+    }
+}
+
+/// The shader deciding, on the GPU, whether the sub-ranges of a dispatch must be re-run.
+struct OverflowPipeline {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// Whether the adapter is a software implementation running on the CPU.
+///
+/// Headless machines and VMs without a GPU silently get one of those (llvmpipe, lavapipe,
+/// SwiftShader, WARP), and they are orders of magnitude slower than any real GPU.
+pub(crate) fn is_software_adapter(info: &wgpu::AdapterInfo) -> bool {
+    let name = info.name.to_lowercase();
+    info.device_type == wgpu::DeviceType::Cpu
+        || [
+            "llvmpipe",
+            "lavapipe",
+            "softpipe",
+            "swiftshader",
+            "basic render driver",
+        ]
+        .iter()
+        .any(|software| name.contains(software))
+}
+
+/// Prepare the GPU once: instance, adapter, device, queue, shader module, pipeline, bind group layout.
+/// Prints adapter info, and fails if the adapter is a software rasterizer unless `options.allow_software` is set.
+///
+/// This function is intended to be called once and its result reused across many `execute_with_prepared_gpu` calls.
+pub fn prepare_gpu(options: &Options) -> Result<GpuPrepared, String> {
+    let max_results = match options.gpu_mem_limit {
+        None => MAX_RESULTS,
+        Some(limit) => max_results_within(limit, options.in_flight).ok_or_else(|| {
+            let needed = dispatch_size(MIN_RESULTS) * options.in_flight as u64;
+            format!(
+                "--gpu-mem-limit {limit} is too small for {} dispatches in flight, {needed} bytes \
+                 are needed (or lower --in-flight)",
+                options.in_flight
+            )
+        })?,
+    };
+
+    // We first initialize an wgpu `Instance`, which contains any "global" state wgpu needs.
+    //
+    // This is what loads the vulkan/dx12/metal/opengl libraries.
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+
+    // We then create an `Adapter` which represents a physical gpu in the system. It allows
+    // us to query information about it and create a `Device` from it.
+    //
+    // This function is asynchronous in WebGPU, so request_adapter returns a future. On native/webgl
+    // the future resolves immediately, so we can block on it without harm.
+    let adapter =
+        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .map_err(|e| format!("cannot find a GPU adapter: {e}"))?;
+
+    // Print out some basic information about the adapter.
+    let info = adapter.get_info();
+    println!("\rRunning on Adapter: {:#?}", info);
+    log::info!(
+        "adapter: {} ({:?}, {:?}, driver {} {})",
+        info.name,
+        info.backend,
+        info.device_type,
+        info.driver,
+        info.driver_info
+    );
+
+    if is_software_adapter(&info) {
+        if !options.allow_software {
+            return Err(format!(
+                "{:?} is a software rasterizer, the search would be terribly slow.\n\
+                 Check your GPU drivers, or pass --allow-software to run anyway.",
+                info.name
+            ));
+        }
+        eprintln!(
+            "WARNING: {:?} is a software rasterizer, performance will be terrible",
+            info.name
+        );
+    }
+
+    // Check to see if the adapter supports compute shaders. While WebGPU guarantees support for
+    // compute shaders, wgpu supports a wider range of devices through the use of "downlevel" devices.
+    let downlevel_capabilities = adapter.get_downlevel_capabilities();
+    if !downlevel_capabilities
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        return Err(format!("{:?} does not support compute shaders", info.name));
+    }
+
+    // Storage buffers bound by downlevel adapters may be smaller than what the defaults ask for.
+    let adapter_limits = adapter.limits();
+    let max_binding_size = u64::from(adapter_limits.max_storage_buffer_binding_size);
+    let wanted_results = max_results;
+    let Some((max_results, kernel_slots)) = fit_binding_size(max_binding_size, wanted_results)
+    else {
+        return Err(format!(
+            "{:?} cannot bind storage buffers of more than {max_binding_size} bytes, which is too \
+             small to hold a single constraint",
+            info.name
+        ));
+    };
+    if kernel_slots < KERNEL_SLOTS || max_results < wanted_results {
+        log::warn!(
+            "storage buffers are limited to {max_binding_size} bytes: {kernel_slots} slots are \
+             checked on the GPU, with room for {} seeds per dispatch",
+            max_results - 1
+        );
+    }
+    let mut required_limits = wgpu::Limits::downlevel_defaults();
+    required_limits.max_storage_buffer_binding_size = required_limits
+        .max_storage_buffer_binding_size
+        .min(adapter_limits.max_storage_buffer_binding_size);
+    required_limits.max_buffer_size = required_limits
+        .max_buffer_size
+        .min(adapter_limits.max_buffer_size);
+    let max_batch_queries = max_batch_queries(
+        u64::from(required_limits.max_storage_buffer_binding_size),
+        max_results,
+        kernel_slots,
+        options.gpu_mem_limit,
+    );
+
+    // Timestamp queries are only worth their overhead when benchmarking, or to tell how busy the
+    // kernels keep the GPU in verbose mode.
+    let timestamps = (options.bench || options.verbose)
+        && adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+
+    // We then create a `Device` and a `Queue` from the `Adapter`.
+    //
+    // The `Device` is used to create and manage GPU resources.
+    // The `Queue` is a queue used to submit work for the GPU to process.
+    let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+        label: None,
+        required_features: if timestamps {
+            wgpu::Features::TIMESTAMP_QUERY
+        } else {
+            wgpu::Features::empty()
+        },
+        required_limits,
+        memory_hints: wgpu::MemoryHints::MemoryUsage,
+        trace: wgpu::Trace::Off,
+    }))
+    .map_err(|e| format!("cannot create a device on {:?}: {e}", info.name))?;
+
+    // By default wgpu panics on device loss and on errors that were not captured. We would rather
+    // record them so that the caller can prepare the GPU again and resume from the last step.
+    let lost = Arc::new(AtomicBool::new(false));
+    let failed = Arc::new(AtomicBool::new(false));
+    {
+        let lost = lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            log::warn!("device lost ({reason:?}): {message}");
+            lost.store(true, Ordering::SeqCst);
+        });
+    }
+    {
+        let failed = failed.clone();
+        device.on_uncaptured_error(Box::new(move |error| {
+            log::error!("uncaptured wgpu error: {error}");
+            failed.store(true, Ordering::SeqCst);
+        }));
+    }
+
+    // Create a shader module from our shader code. This will parse and validate the shader.
+    //
+    // `include_wgsl` is a macro provided by wgpu like `include_str` which constructs a ShaderModuleDescriptor.
+    // If you want to load shaders differently, you can construct the ShaderModuleDescriptor manually.
+    //
+    // The predicate of `--predicate` comes with its own entry point, spliced into the shader.
+    // Errors in it are reported instead of going to the uncaptured error handler. The generators
+    // of `--rand` have theirs in legacy_rand.wgsl, appended to the shader.
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let (module, entry_point) = match (&options.predicate, options.generator) {
+        (None, None) => (
+            device.create_shader_module(wgpu::include_wgsl!("mt19937.wgsl")),
+            "main",
+        ),
+        (None, Some(generator)) => (
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("legacy_rand"),
+                source: wgpu::ShaderSource::Wgsl(Generator::shader_source().into()),
+            }),
+            generator.entry_point(),
+        ),
+        (Some(predicate), _) => (
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("predicate"),
+                source: wgpu::ShaderSource::Wgsl(predicate.shader_source().into()),
+            }),
+            "predicate_main",
+        ),
+    };
+
+    // A bind group layout describes the types of resources that a bind group can contain.
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            // Input buffer
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    // This is the size of a single element in the buffer.
+                    min_binding_size: Some(NonZeroU64::new(4).unwrap()),
+                    has_dynamic_offset: false,
+                },
+                count: None,
+            },
+            // Output buffer
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    // This is the size of a single element in the buffer.
+                    min_binding_size: Some(NonZeroU64::new(8).unwrap()),
+                    has_dynamic_offset: false,
+                },
+                count: None,
+            },
+            // Abort flag, along with the number of seeds that raises it
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    min_binding_size: Some(NonZeroU64::new(8).unwrap()),
+                    has_dynamic_offset: false,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    // The pipeline layout describes the bind groups that a pipeline expects
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    // The pipeline is the ready-to-go program state for the GPU. It contains the shader modules,
+    // the interfaces (bind group layouts) and the shader entry point.
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        module: &module,
+        entry_point: Some(entry_point),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    let batch_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        module: &module,
+        entry_point: Some("batch_main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    let custom_main = options.predicate.is_some() || options.generator.is_some();
+    let lean_pipeline = |entry_point| {
+        (!custom_main).then(|| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        })
+    };
+    let exact_pipeline = lean_pipeline("exact_main");
+    let small_pipeline = lean_pipeline("small_main");
+    if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+        return Err(format!(
+            "the shader does not compile, check the predicate:\n{error}"
+        ));
+    }
+
+    // The overflow shader binds the plan of the re-runs in a single buffer.
+    let plan_size = (1 + 3 * u64::from(OVERFLOW_SPLITS)) * 4;
+    let overflow = if downlevel_capabilities
+        .flags
+        .contains(wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+        && plan_size <= max_binding_size
+    {
+        Some(prepare_overflow_pipeline(&device))
+    } else {
+        None
+    };
+
+    Ok(GpuPrepared {
+        device,
+        queue,
+        pipeline,
+        batch_pipeline,
+        exact_pipeline,
+        small_pipeline,
+        bind_group_layout,
+        lost,
+        failed,
+        overflow,
+        timestamps,
+        kernel_time: Mutex::new(Duration::ZERO),
+        max_results,
+        kernel_slots,
+        max_batch_queries,
+        custom_main,
+        serialized: (info.backend == wgpu::Backend::Gl).then(|| Mutex::new(())),
+    })
+}
+
+fn prepare_overflow_pipeline(device: &wgpu::Device) -> OverflowPipeline {
+    let module = device.create_shader_module(wgpu::include_wgsl!("overflow.wgsl"));
+
+    let storage = |binding, read_only, min_binding_size| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            min_binding_size: NonZeroU64::new(min_binding_size),
+            has_dynamic_offset: false,
+        },
+        count: None,
+    };
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            // Output buffer of the main dispatch
+            storage(0, true, 4),
+            // Planned indirect dispatch arguments
+            storage(1, true, 8),
+            // Indirect dispatch arguments
+            storage(2, false, 4),
+        ],
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+
+    OverflowPipeline {
+        pipeline,
+        bind_group_layout,
+    }
+}
+
+/// Execute the workload using an already prepared GPU context.
+///
+/// This function mirrors the original `find_mersenne_seed` implementation but assumes the device,
+/// queue, pipeline, etc. are already available in `prepared`. It returns the matching seeds on success.
+#[cfg(test)]
+pub fn execute_with_prepared_gpu(
+    prepared: &GpuPrepared,
+    arguments: &[u32],
+    step: u32,
+) -> Result<Vec<u32>, ExecuteError> {
+    execute_workgroups(
+        prepared,
+        Flavor::Php71,
+        arguments,
+        step,
+        0..WORKGROUPS_PER_STEP,
+    )
+}
+
+/// Execute only the given workgroups of a step, in a single dispatch.
+pub fn execute_workgroups(
+    prepared: &GpuPrepared,
+    flavor: Flavor,
+    arguments: &[u32],
+    step: u32,
+    workgroups: Range<u32>,
+) -> Result<Vec<u32>, ExecuteError> {
+    let kernel_flavor = flavor.kernel_id();
+    let submitted = submit_workgroups(
+        prepared,
+        kernel_flavor,
+        arguments,
+        step,
+        workgroups,
+        NO_LIMIT,
+    )?;
+    read_submitted(prepared, submitted)
+}
+
+/// Work that was submitted to the queue, but whose results have not been read back yet.
+pub struct Submitted {
+    index: wgpu::SubmissionIndex,
+    download_buffer: wgpu::Buffer,
+    // Number of sub-range re-runs whose outputs follow the main output in `download_buffer`.
+    splits: usize,
+    // Whether the timestamps of the main dispatch follow the outputs in `download_buffer`.
+    timestamps: bool,
+}
+
+/// Fill an input buffer for the search shader, and bind it along with a fresh output buffer and
+/// an abort flag raised at `limit` seeds.
+fn create_search_bind_group(
+    prepared: &GpuPrepared,
+    kernel_flavor: u32,
+    arguments: &[u32],
+    step: u32,
+    first_workgroup: u32,
+    limit: u32,
+) -> (wgpu::BindGroup, wgpu::Buffer) {
+    let mut input_data = Vec::new();
+    input_data.push(step);
+    input_data.push(first_workgroup * 256);
+    input_data.push(kernel_flavor);
+    input_data.extend_from_slice(arguments);
+
+    create_bind_group(prepared, &input_data, prepared.output_buffer_size(), limit)
+}
+
+/// Bind a buffer filled with `input_data` along with a fresh output buffer of `output_size` bytes,
+/// and a fresh abort flag raised once `limit` seeds are in the output.
+///
+/// Only the kernel raises the flag: writes of the queue run after the dispatches submitted before
+/// them, so that the host cannot abort a dispatch it has already submitted.
+fn create_bind_group(
+    prepared: &GpuPrepared,
+    input_data: &[u32],
+    output_size: u64,
+    limit: u32,
+) -> (wgpu::BindGroup, wgpu::Buffer) {
+    let device = &prepared.device;
+
+    // Create a buffer with the data we want to process on the GPU.
+    //
+    // The buffer is filled through the queue rather than mapped at creation: mapping panics
+    // instead of reporting an error when the device has been lost.
+    let input_data_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: std::mem::size_of_val(input_data) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    prepared
+        .queue
+        .write_buffer(&input_data_buffer, 0, bytemuck::cast_slice(input_data));
+
+    // Now we create a buffer to store the output data.
+    let output_data_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: output_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    // The limit, then the flag, lowered.
+    let abort_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 8,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    prepared
+        .queue
+        .write_buffer(&abort_buffer, 0, bytemuck::cast_slice(&[limit, 0]));
+
+    // The bind group contains the actual resources to bind to the pipeline.
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &prepared.bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: input_data_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: output_data_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: abort_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    (bind_group, output_data_buffer)
+}
+
+/// Split `workgroups` into at most `OVERFLOW_SPLITS` contiguous sub-ranges.
+fn split_workgroups(workgroups: Range<u32>) -> Vec<Range<u32>> {
+    let count = workgroups.len() as u32;
+    let splits = OVERFLOW_SPLITS.min(count);
+    (0..splits)
+        .map(|i| workgroups.start + count * i / splits..workgroups.start + count * (i + 1) / splits)
+        .collect()
+}
+
+/// Submit the given workgroups of a step without waiting for the GPU.
+///
+/// When the adapter supports indirect dispatches, re-runs of sub-ranges are chained after the
+/// main dispatch in the same submission. They only do work if the main dispatch overflowed its
+/// output buffer, which saves a round trip to the CPU in that case.
+///
+/// `kernel_flavor` is the `Flavor::kernel_id` of the flavor searched, or `Flavor::ALL_KERNEL_ID`.
+/// Once the dispatch has found `limit` seeds, the invocations that did not start yet skip their
+/// work, and so do those of each re-run: `NO_LIMIT` searches every seed of the workgroups.
+pub fn submit_workgroups(
+    prepared: &GpuPrepared,
+    kernel_flavor: u32,
+    arguments: &[u32],
+    step: u32,
+    workgroups: Range<u32>,
+    limit: u32,
+) -> Result<Submitted, ExecuteError> {
+    assert!(step < 256);
+    assert!(!workgroups.is_empty() && workgroups.end <= WORKGROUPS_PER_STEP);
+
+    if prepared.lost.load(Ordering::SeqCst) {
+        return Err(ExecuteError::DeviceLost);
+    }
+
+    let device = &prepared.device;
+    let queue = &prepared.queue;
+    // Single exact outputs and small ranges have leaner entry points, the latter with packed slots.
+    let small = prepared
+        .small_pipeline
+        .as_ref()
+        .zip(pack_small_ranges(arguments));
+    let (pipeline, arguments) = match (&prepared.exact_pipeline, &small) {
+        (Some(exact_pipeline), _) if is_exact_output(arguments) => (exact_pipeline, arguments),
+        (_, Some((small_pipeline, packed))) => (*small_pipeline, packed.as_slice()),
+        _ => (&prepared.pipeline, arguments),
+    };
+
+    let (bind_group, output_data_buffer) = create_search_bind_group(
+        prepared,
+        kernel_flavor,
+        arguments,
+        step,
+        workgroups.start,
+        limit,
+    );
+
+    // The sub-ranges re-run on overflow, with their own output buffers.
+    let sub_ranges = match prepared.overflow {
+        Some(_) if workgroups.len() > 1 => split_workgroups(workgroups.clone()),
+        _ => Vec::new(),
+    };
+    let sub_searches: Vec<_> = sub_ranges
+        .iter()
+        .map(|sub_range| {
+            create_search_bind_group(
+                prepared,
+                kernel_flavor,
+                arguments,
+                step,
+                sub_range.start,
+                limit,
+            )
+        })
+        .collect();
+
+    let output_buffer_size = prepared.output_buffer_size();
+    let outputs_size = output_buffer_size * (1 + sub_searches.len() as u64);
+    let query_set = prepared.timestamps.then(|| {
+        device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: None,
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        })
+    });
+
+    // Finally we create a buffer which can be read by the CPU.
+    let download_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: outputs_size
+            + if query_set.is_some() {
+                TIMESTAMPS_SIZE
+            } else {
+                0
+            },
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    // The command encoder allows us to record commands that we will later submit to the GPU.
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+    // A compute pass is a single series of compute operations.
+    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: None,
+        timestamp_writes: query_set
+            .as_ref()
+            .map(|query_set| wgpu::ComputePassTimestampWrites {
+                query_set,
+                beginning_of_pass_write_index: Some(0),
+                end_of_pass_write_index: Some(1),
+            }),
+    });
+
+    // Set the pipeline and bind group
+    compute_pass.set_pipeline(pipeline);
+    compute_pass.set_bind_group(0, &bind_group, &[]);
+
+    // Now we dispatch a series of workgroups. A dimension is limited to 65535 workgroups, so a
+    // whole step is dispatched as two rows.
+    let count = workgroups.len() as u32;
+    if count > 65535 {
+        compute_pass.dispatch_workgroups(count / 2, 2, 1);
+    } else {
+        compute_pass.dispatch_workgroups(count, 1, 1);
+    }
+
+    // End compute pass
+    drop(compute_pass);
+
+    if let (Some(overflow), false) = (&prepared.overflow, sub_searches.is_empty()) {
+        // The arguments of the indirect dispatches, as they would be without any overflow. The
+        // overflow shader zeroes them unless the main dispatch overflowed.
+        let mut plan = vec![(prepared.max_results - 1) as u32];
+        for sub_range in &sub_ranges {
+            plan.extend_from_slice(&[sub_range.len() as u32, 1, 1]);
+        }
+        let plan_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of_val(plan.as_slice()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&plan_buffer, 0, bytemuck::cast_slice(&plan));
+        let indirect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (3 * sub_ranges.len() * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+            mapped_at_creation: false,
+        });
+        let overflow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &overflow.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: output_data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: plan_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: indirect_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&overflow.pipeline);
+        compute_pass.set_bind_group(0, &overflow_bind_group, &[]);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+        drop(compute_pass);
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: None,
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(pipeline);
+        for (i, (sub_bind_group, _)) in sub_searches.iter().enumerate() {
+            compute_pass.set_bind_group(0, sub_bind_group, &[]);
+            compute_pass.dispatch_workgroups_indirect(&indirect_buffer, (i * 12) as u64);
+        }
+        drop(compute_pass);
+    }
+
+    // Copy the GPU outputs to the CPU-readable buffer.
+    encoder.copy_buffer_to_buffer(
+        &output_data_buffer,
+        0,
+        &download_buffer,
+        0,
+        output_buffer_size,
+    );
+    for (i, (_, sub_output_buffer)) in sub_searches.iter().enumerate() {
+        encoder.copy_buffer_to_buffer(
+            sub_output_buffer,
+            0,
+            &download_buffer,
+            output_buffer_size * (1 + i as u64),
+            output_buffer_size,
+        );
+    }
+
+    if let Some(query_set) = &query_set {
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: TIMESTAMPS_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        encoder.resolve_query_set(query_set, 0..2, &resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &resolve_buffer,
+            0,
+            &download_buffer,
+            outputs_size,
+            TIMESTAMPS_SIZE,
+        );
+    }
+
+    // Finish and submit
+    let command_buffer = encoder.finish();
+    let index = queue.submit([command_buffer]);
+
+    Ok(Submitted {
+        index,
+        download_buffer,
+        splits: sub_searches.len(),
+        timestamps: query_set.is_some(),
+    })
+}
+
+/// Wait for submitted work to complete, and read its results back.
+pub fn read_submitted(
+    prepared: &GpuPrepared,
+    submitted: Submitted,
+) -> Result<Vec<u32>, ExecuteError> {
+    let data = map_download(prepared, submitted.index, &submitted.download_buffer)?;
+    let outputs_size = (prepared.output_buffer_size() * (1 + submitted.splits as u64)) as usize;
+    let result: &[u32] = bytemuck::cast_slice(&data[..outputs_size]);
+
+    if submitted.timestamps {
+        // Only the main dispatch is timed, re-runs after an overflow are rare enough to ignore.
+        let [begin, end]: [u64; 2] = bytemuck::pod_read_unaligned(&data[outputs_size..]);
+        let nanoseconds =
+            end.wrapping_sub(begin) as f64 * f64::from(prepared.queue.get_timestamp_period());
+        *prepared.kernel_time.lock().unwrap() += Duration::from_nanos(nanoseconds as u64);
+    }
+
+    let mut outputs = result.chunks(prepared.max_results);
+    let main_output = outputs.next().unwrap();
+    if submitted.splits == 0 || (main_output[0] as usize) < prepared.max_results {
+        return extract_results(main_output);
+    }
+
+    // The main output overflowed, the results come from the sub-range re-runs instead.
+    log::warn!(
+        "more than {} seeds in a dispatch, using its {} sub-range re-runs",
+        prepared.max_results - 1,
+        submitted.splits
+    );
+    let mut seeds = Vec::new();
+    for output in outputs {
+        seeds.extend(extract_results(output)?);
+    }
+    Ok(seeds)
+}
+
+/// Wait for the submission `index` to complete, and map its download buffer.
+fn map_download<'a>(
+    prepared: &GpuPrepared,
+    index: wgpu::SubmissionIndex,
+    download_buffer: &'a wgpu::Buffer,
+) -> Result<wgpu::BufferView<'a>, ExecuteError> {
+    let _span = tracing::info_span!("readback", bytes = download_buffer.size()).entered();
+    let buffer_slice = download_buffer.slice(..);
+    let mapped = Arc::new(AtomicBool::new(false));
+    {
+        let mapped = mapped.clone();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            mapped.store(result.is_ok(), Ordering::SeqCst);
+        });
+    }
+    // A submission that failed (on a lost device for instance) has no index we could wait for.
+    let poll_type = if prepared.failed.load(Ordering::SeqCst) {
+        wgpu::PollType::Wait
+    } else {
+        wgpu::PollType::WaitForSubmissionIndex(index)
+    };
+    let polled = prepared.device.poll(poll_type);
+    if prepared.lost.load(Ordering::SeqCst) || !mapped.load(Ordering::SeqCst) {
+        return Err(ExecuteError::DeviceLost);
+    }
+    polled.expect("Failed to wait for the GPU");
+    if prepared.failed.load(Ordering::SeqCst) {
+        panic!("the GPU reported an error, see the log above");
+    }
+    Ok(buffer_slice.get_mapped_range())
+}
+
+/// Execute the given workgroups of a step for all `queries` in a single dispatch of `batch_main`,
+/// and return the matching seeds of each query.
+///
+/// Queries whose output region overflowed are executed again on their own, with the re-runs in
+/// sub-ranges of `execute_workgroups`.
+pub fn execute_query_batch(
+    prepared: &GpuPrepared,
+    flavor: Flavor,
+    queries: &[&[u32]],
+    step: u32,
+    workgroups: Range<u32>,
+) -> Result<Vec<Vec<u32>>, ExecuteError> {
+    assert!(step < 256);
+    assert!(!workgroups.is_empty() && workgroups.end <= WORKGROUPS_PER_STEP);
+    assert!(!queries.is_empty() && queries.len() <= prepared.max_batch_queries);
+
+    if prepared.lost.load(Ordering::SeqCst) {
+        return Err(ExecuteError::DeviceLost);
+    }
+
+    let device = &prepared.device;
+    let args_len = queries.iter().map(|query| query.len() / 4).max().unwrap();
+    assert!(args_len <= prepared.kernel_slots);
+    let mut input_data = vec![
+        step,
+        workgroups.start * 256,
+        flavor.kernel_id(),
+        queries.len() as u32,
+        args_len as u32,
+    ];
+    for query in queries {
+        input_data.push((query.len() / 4) as u32);
+        input_data.extend_from_slice(query);
+        input_data.resize(input_data.len() + 4 * args_len - query.len(), 0);
+    }
+    let outputs_size = prepared.output_buffer_size() * queries.len() as u64;
+    let (bind_group, output_data_buffer) =
+        create_bind_group(prepared, &input_data, outputs_size, NO_LIMIT);
+    let download_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: outputs_size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: None,
+        timestamp_writes: None,
+    });
+    compute_pass.set_pipeline(&prepared.batch_pipeline);
+    compute_pass.set_bind_group(0, &bind_group, &[]);
+    let count = workgroups.len() as u32;
+    if count > 65535 {
+        compute_pass.dispatch_workgroups(count / 2, 2, 1);
+    } else {
+        compute_pass.dispatch_workgroups(count, 1, 1);
+    }
+    drop(compute_pass);
+    encoder.copy_buffer_to_buffer(&output_data_buffer, 0, &download_buffer, 0, outputs_size);
+    let index = prepared.queue.submit([encoder.finish()]);
+
+    let data = map_download(prepared, index, &download_buffer)?;
+    let regions: &[u32] = bytemuck::cast_slice(&data);
+    queries
+        .iter()
+        .zip(regions.chunks(prepared.max_results))
+        .map(
+            |(query, region)| match (region[0] as usize) < prepared.max_results {
+                true => extract_results(region),
+                false => {
+                    log::warn!(
+                        "step {step}: more than {} seeds for a query of the batch, running it alone",
+                        prepared.max_results - 1
+                    );
+                    execute_workgroups(prepared, flavor, query, step, workgroups.clone())
+                }
+            },
+        )
+        .collect()
+}
+
+/// Submit every `(step, workgroups)` slice of `batch`, then read their results back, for the
+/// `kernel_flavor` and the `limit` of `submit_workgroups`.
+fn execute_batch(
+    prepared: &GpuPrepared,
+    kernel_flavor: u32,
+    arguments: &[u32],
+    batch: &[(u32, Range<u32>)],
+    limit: u32,
+) -> Result<Vec<Vec<u32>>, ExecuteError> {
+    let _serialized = prepared
+        .serialized
+        .as_ref()
+        .map(|lock| lock.lock().unwrap());
+    // Queue everything first, so the GPU never waits for us to read results back.
+    let submitted = batch
+        .iter()
+        .map(|(step, workgroups)| {
+            submit_workgroups(
+                prepared,
+                kernel_flavor,
+                arguments,
+                *step,
+                workgroups.clone(),
+                limit,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    submitted
+        .into_iter()
+        .map(|submitted| read_submitted(prepared, submitted))
+        .collect()
+}
+
+impl Engine for GpuPrepared {
+    fn execute_workgroups(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        let _serialized = self.serialized.as_ref().map(|lock| lock.lock().unwrap());
+        execute_workgroups(self, flavor, arguments, step, workgroups)
+    }
+
+    fn execute_batch(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        execute_batch(self, flavor.kernel_id(), arguments, batch, NO_LIMIT)
+    }
+
+    fn execute_batch_limited(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+        limit: u32,
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        execute_batch(self, flavor.kernel_id(), arguments, batch, limit)
+    }
+
+    fn execute_batch_all_flavors(
+        &self,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        // The entry points of predicates and generators search a single flavor.
+        match self.custom_main {
+            true => execute_each_flavor(self, arguments, batch),
+            false => execute_batch(self, Flavor::ALL_KERNEL_ID, arguments, batch, NO_LIMIT),
+        }
+    }
+
+    fn single_pass_flavors(&self) -> bool {
+        !self.custom_main
+    }
+
+    fn execute_queries(
+        &self,
+        flavor: Flavor,
+        queries: &[&[u32]],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        let _serialized = self.serialized.as_ref().map(|lock| lock.lock().unwrap());
+        if self.custom_main {
+            return queries
+                .iter()
+                .map(|arguments| {
+                    execute_workgroups(self, flavor, arguments, step, workgroups.clone())
+                })
+                .collect();
+        }
+        let mut results = Vec::new();
+        for chunk in queries.chunks(self.max_batch_queries) {
+            results.extend(execute_query_batch(
+                self,
+                flavor,
+                chunk,
+                step,
+                workgroups.clone(),
+            )?);
+        }
+        Ok(results)
+    }
+
+    fn kernel_time(&self) -> Option<Duration> {
+        self.timestamps.then(|| *self.kernel_time.lock().unwrap())
+    }
+
+    fn kernel_slots(&self) -> usize {
+        self.kernel_slots
+    }
+}
+
+/// Tests also run on CI machines without a GPU, where a software rasterizer is all we get.
+#[cfg(test)]
+fn test_gpu() -> GpuPrepared {
+    let _ = env_logger::builder().is_test(true).try_init();
+    prepare_gpu(&Options {
+        allow_software: true,
+        ..Options::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_find_seed_0() {
+    let mut arguments = vec![1178568022];
+    let expected_seed = 0;
+    crate::normalize_arguments(&mut arguments);
+    let step = expected_seed % 256;
+    let prepared = test_gpu();
+    let result = execute_with_prepared_gpu(&prepared, &arguments, step);
+    assert_eq!(result, Ok(vec![expected_seed]));
+}
+
+#[test]
+fn test_find_seed_0_short_range() {
+    let mut arguments = vec![16378811, 16378811, 0, 21474836];
+    let expected_seed = 0;
+    crate::normalize_arguments(&mut arguments);
+    let step = expected_seed % 256;
+    let prepared = test_gpu();
+    let result = execute_with_prepared_gpu(&prepared, &arguments, step).unwrap();
+    assert!(
+        result.contains(&expected_seed),
+        "expected that the results contain the seed {expected_seed} : {result:?}"
+    );
+}
+
+#[test]
+fn test_find_seed_with_multiple_outputs_default_range() {
+    let arguments = vec![
+        697823703, 697823703, 0, 0x7fffffff, 1736388855, 1736388855, 0, 0x7fffffff, 2019524934,
+        2019524934, 0, 0x7fffffff,
+    ];
+    let expected_seed = 4242;
+    let step = expected_seed % 256;
+    let prepared = test_gpu();
+    let result = execute_with_prepared_gpu(&prepared, &arguments, step);
+    assert_eq!(result, Ok(vec![expected_seed]));
+}
+
+#[test]
+fn test_find_seed_with_multiple_outputs_shorter_ranges() {
+    let arguments = vec![
+        7505, 7505, 1000, 10000, 2986, 2986, 1000, 10000, 1457, 1457, 1000, 10000,
+    ];
+    let expected_seed = 424242;
+    let step = expected_seed % 256;
+    let prepared = test_gpu();
+    let result = execute_with_prepared_gpu(&prepared, &arguments, step);
+    assert_eq!(result, Ok(vec![expected_seed]));
+}
+
+#[test]
+fn test_find_exact_output_of_each_flavor() {
+    // mt_srand(0); mt_rand(); in each flavor, and in both at once.
+    let prepared = test_gpu();
+    let workgroups = 0..WORKGROUPS_PER_STEP;
+    for (kernel_flavor, value) in [
+        (Flavor::Php71.kernel_id(), 1178568022),
+        (Flavor::Php52.kernel_id(), 963932192),
+        (Flavor::ALL_KERNEL_ID, 963932192),
+    ] {
+        let arguments = [value, value, 0, 0x7fffffff];
+        let submitted = submit_workgroups(
+            &prepared,
+            kernel_flavor,
+            &arguments,
+            0,
+            workgroups.clone(),
+            NO_LIMIT,
+        );
+        assert_eq!(read_submitted(&prepared, submitted.unwrap()), Ok(vec![0]));
+    }
+}
+
+#[test]
+fn test_abort_flag() {
+    // About one seed in 2^17 matches: a dispatch limited to one of them may stop before the
+    // others, how many it finds depending on how many workgroups the GPU runs at once.
+    let prepared = test_gpu();
+    let arguments = [0, 0x3fff, 0, 0x7fffffff];
+    let kernel_flavor = Flavor::Php71.kernel_id();
+    let workgroups = 0..WORKGROUPS_PER_STEP;
+    let search = |limit| {
+        let submitted = submit_workgroups(
+            &prepared,
+            kernel_flavor,
+            &arguments,
+            0,
+            workgroups.clone(),
+            limit,
+        );
+        read_submitted(&prepared, submitted.unwrap()).unwrap()
+    };
+    let all = search(NO_LIMIT);
+    let limited = search(1);
+    assert!(!limited.is_empty());
+    assert!(limited.iter().all(|seed| all.contains(seed)));
+}
+
+#[test]
+fn test_find_shuffle_with_small_ranges() {
+    // The draws of a shuffle of 9 elements after mt_srand(seed), which only PHP 7.1.0+ makes with
+    // mt_rand(), searched by the entry point of small ranges like by the reference.
+    let prepared = test_gpu();
+    let flavor = Flavor::Php71;
+    let seed = 424242;
+    let mut mt = crate::reference::Mt19937::new(seed, flavor);
+    let mut arguments = Vec::new();
+    for n_left in (1..9).rev() {
+        let draw = mt.next_in_range(0, n_left);
+        arguments.extend_from_slice(&[draw, draw, 0, n_left]);
+    }
+    assert!(pack_small_ranges(&arguments).is_some());
+    let workgroups = seed >> 16..(seed >> 16) + 1;
+    let step = seed % 256;
+    let mut result =
+        execute_workgroups(&prepared, flavor, &arguments, step, workgroups.clone()).unwrap();
+    result.sort();
+    let expected = crate::reference::search_workgroups(flavor, &arguments, step, workgroups);
+    assert!(expected.contains(&seed));
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn test_device_loss_is_reported() {
+    let arguments = vec![1178568022, 1178568022, 0, 0x7fffffff];
+    let prepared = test_gpu();
+    prepared.device.destroy();
+    let result = execute_with_prepared_gpu(&prepared, &arguments, 0);
+    assert_eq!(result, Err(ExecuteError::DeviceLost));
+}
+
+#[test]
+fn test_find_seed_in_split_step() {
+    let arguments = vec![1178568022, 1178568022, 0, 0x7fffffff];
+    let prepared = test_gpu();
+    let mut results = Vec::new();
+    for start in (0..WORKGROUPS_PER_STEP).step_by(16384) {
+        results.extend(
+            execute_workgroups(
+                &prepared,
+                Flavor::Php71,
+                &arguments,
+                0,
+                start..start + 16384,
+            )
+            .unwrap(),
+        );
+    }
+    assert_eq!(results, vec![0]);
+}
+
+#[test]
+fn test_is_software_adapter() {
+    let mut info = wgpu::AdapterInfo {
+        name: "llvmpipe (LLVM 15.0.7, 256 bits)".to_string(),
+        vendor: 0x10005,
+        device: 0,
+        device_type: wgpu::DeviceType::Cpu,
+        driver: "llvmpipe".to_string(),
+        driver_info: String::new(),
+        backend: wgpu::Backend::Vulkan,
+    };
+    assert!(is_software_adapter(&info));
+    info.device_type = wgpu::DeviceType::Other;
+    assert!(is_software_adapter(&info));
+    info.name = "NVIDIA GeForce GTX 1660 Ti".to_string();
+    info.device_type = wgpu::DeviceType::DiscreteGpu;
+    assert!(!is_software_adapter(&info));
+}
+
+#[test]
+fn test_find_seed_with_batch() {
+    let arguments = vec![1178568022, 1178568022, 0, 0x7fffffff];
+    let prepared = test_gpu();
+    let batch = [
+        (1, 0..WORKGROUPS_PER_STEP),
+        (0, 0..WORKGROUPS_PER_STEP / 2),
+        (0, WORKGROUPS_PER_STEP / 2..WORKGROUPS_PER_STEP),
+    ];
+    let results = prepared.execute_batch(Flavor::Php71, &arguments, &batch);
+    assert_eq!(results, Ok(vec![vec![], vec![0], vec![]]));
+}
+
+#[test]
+fn test_find_seeds_for_all_flavors() {
+    // Weak enough for each flavor to match seeds of its own in the workgroups searched.
+    let arguments = vec![0, 0x0fffffff, 0, 0x7fffffff, 0, 30, 0, 61];
+    let prepared = test_gpu();
+    let batch = [(7, 0..4), (7, 4..8)];
+    assert!(prepared.single_pass_flavors());
+    let mut results = prepared
+        .execute_batch_all_flavors(&arguments, &batch)
+        .unwrap();
+    for seeds in &mut results {
+        seeds.sort_unstable();
+    }
+    assert_eq!(
+        Ok(results),
+        execute_each_flavor(&prepared, &arguments, &batch)
+    );
+}
+
+#[test]
+fn test_split_workgroups() {
+    assert_eq!(split_workgroups(0..3), vec![0..1, 1..2, 2..3]);
+    let splits = split_workgroups(100..WORKGROUPS_PER_STEP);
+    assert_eq!(splits.len(), OVERFLOW_SPLITS as usize);
+    assert_eq!(splits[0].start, 100);
+    assert_eq!(splits[splits.len() - 1].end, WORKGROUPS_PER_STEP);
+    assert!(splits.windows(2).all(|pair| pair[0].end == pair[1].start));
+}
+
+#[test]
+fn test_overflow_is_rerun_in_sub_ranges() {
+    // About 2^24 / 2^13 = 2048 seeds per step match, which overflows a single output buffer.
+    let arguments = vec![0, 0x3ffff, 0, 0x7fffffff];
+    let prepared = test_gpu();
+    let mut results = execute_with_prepared_gpu(&prepared, &arguments, 0).unwrap();
+    assert!(results.len() >= MAX_RESULTS);
+
+    let mut expected = Vec::new();
+    for sub_range in split_workgroups(0..WORKGROUPS_PER_STEP) {
+        let (start, end) = (sub_range.start, sub_range.end);
+        let middle = (start + end) / 2;
+        expected.extend(
+            execute_workgroups(&prepared, Flavor::Php71, &arguments, 0, start..middle).unwrap(),
+        );
+        expected.extend(
+            execute_workgroups(&prepared, Flavor::Php71, &arguments, 0, middle..end).unwrap(),
+        );
+    }
+    results.sort();
+    expected.sort();
+    assert_eq!(results, expected);
+}
+
+#[test]
+fn test_kernel_time_in_bench_mode() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let prepared = prepare_gpu(&Options {
+        allow_software: true,
+        bench: true,
+        ..Options::default()
+    })
+    .unwrap();
+    let result = execute_with_prepared_gpu(&prepared, &[1178568022, 1178568022, 0, 0x7fffffff], 0);
+    assert_eq!(result, Ok(vec![0]));
+    // Not every adapter supports timestamp queries, but when one does the kernel took some time.
+    if let Some(kernel_time) = prepared.kernel_time() {
+        assert!(kernel_time > Duration::ZERO);
+    }
+}
+
+#[test]
+fn test_no_kernel_time_outside_bench_mode() {
+    assert_eq!(test_gpu().kernel_time(), None);
+}
+
+/// Pick random flavors, seeds and ranges, and check that the shader finds exactly the seeds the CPU
+/// reference finds around them.
+#[test]
+fn test_matches_reference() {
+    use proptest::prelude::*;
+    use proptest::test_runner::{Config, TestRunner};
+
+    let range = prop_oneof![
+        Just((0, 0x7fffffff)),
+        Just((0, u32::MAX)),
+        (0..=0x7fffffffu32, 0..1000u32).prop_map(|(min, span)| (min, min.saturating_add(span))),
+        (0..=0x7fffffffu32, 0..=0x7fffffffu32).prop_map(|(a, b)| (a.min(b), a.max(b))),
+        any::<(u32, u32)>().prop_map(|(a, b)| (a.min(b), a.max(b))),
+    ];
+    let flavor = prop_oneof![Just(Flavor::Php71), Just(Flavor::Php52)];
+    let strategy = (flavor, any::<u32>(), prop::collection::vec(range, 1..=8));
+
+    let prepared = test_gpu();
+    let mut runner = TestRunner::new(Config {
+        cases: 24,
+        ..Config::default()
+    });
+    runner
+        .run(&strategy, |(flavor, seed, ranges)| {
+            // The outputs of `seed` in the random ranges become the constraints.
+            let mut mt = crate::reference::Mt19937::new(seed, flavor);
+            let mut arguments = Vec::new();
+            for (range_min, range_max) in ranges {
+                let value = mt.next_in_range(range_min, range_max);
+                arguments.extend_from_slice(&[value, value, range_min, range_max]);
+            }
+
+            // Only the workgroup holding the seed is searched, software rasterizers are slow.
+            let step = seed % 256;
+            let workgroup = seed >> 16;
+            let workgroups = workgroup..workgroup + 1;
+            let mut result =
+                execute_workgroups(&prepared, flavor, &arguments, step, workgroups.clone())
+                    .unwrap();
+            result.sort();
+            let expected =
+                crate::reference::search_workgroups(flavor, &arguments, step, workgroups);
+            prop_assert!(expected.contains(&seed));
+            prop_assert_eq!(result, expected);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_max_results_within() {
+    assert_eq!(max_results_within(u64::MAX, 2), Some(MAX_RESULTS));
+    assert_eq!(max_results_within(2 * dispatch_size(64), 2), Some(64));
+    let smallest = dispatch_size(MIN_RESULTS);
+    assert_eq!(max_results_within(smallest, 1), Some(MIN_RESULTS));
+    assert_eq!(max_results_within(smallest - 1, 1), None);
+    assert_eq!(max_results_within(0, 1), None);
+}
+
+#[test]
+fn test_gpu_mem_limit() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let prepared = prepare_gpu(&Options {
+        allow_software: true,
+        gpu_mem_limit: Some(dispatch_size(64)),
+        in_flight: 1,
+        ..Options::default()
+    })
+    .unwrap();
+    assert_eq!(prepared.max_results, 64);
+    // About 200 seeds match, more than the main output holds but not its sub-ranges.
+    let arguments = vec![0, 25599, 0, 0x7fffffff];
+    let results = execute_with_prepared_gpu(&prepared, &arguments, 0).unwrap();
+    assert!(results.len() > 64);
+    assert!(results.iter().all(|&seed| crate::reference::seed_matches(
+        Flavor::Php71,
+        &arguments,
+        seed
+    )));
+}
+
+#[test]
+fn test_fit_binding_size() {
+    // The downlevel defaults bind 128 MiB
+    assert_eq!(
+        fit_binding_size(128 << 20, MAX_RESULTS),
+        Some((MAX_RESULTS, KERNEL_SLOTS))
+    );
+    // A header and 2 slots, or 11 u32 of output
+    assert_eq!(fit_binding_size(44, MAX_RESULTS), Some((11, 2)));
+    assert_eq!(fit_binding_size(43, MAX_RESULTS), Some((10, 1)));
+    assert_eq!(fit_binding_size(28, MAX_RESULTS), Some((7, 1)));
+    assert_eq!(fit_binding_size(24, MAX_RESULTS), None);
+}
+
+#[test]
+fn test_predicate() {
+    // The sum of the first two mt_rand() of seed 0, which no other seed of the workgroup has
+    let mut mt = crate::reference::Mt19937::new(0, Flavor::Php71);
+    let sum = (mt.next_u32() >> 1).wrapping_add(mt.next_u32() >> 1);
+    let predicate = crate::predicate::Predicate::parse(&format!(
+        "fn matches(outputs: array<u32, 2>) -> bool {{\n\
+         \x20   return (outputs[0] >> 1) + (outputs[1] >> 1) == {sum}u;\n\
+         }}\n"
+    ))
+    .unwrap();
+    let prepared = prepare_gpu(&Options {
+        allow_software: true,
+        predicate: Some(predicate),
+        ..Options::default()
+    })
+    .unwrap();
+    // The predicate alone, then along with a constraint the seed passes, or not
+    assert_eq!(
+        execute_workgroups(&prepared, Flavor::Php71, &[], 0, 0..1),
+        Ok(vec![0])
+    );
+    let passing = [1178568022, 1178568022, 0, 0x7fffffff];
+    assert_eq!(
+        execute_workgroups(&prepared, Flavor::Php71, &passing, 0, 0..1),
+        Ok(vec![0])
+    );
+    let failing = [0, 0, 0, 0x7fffffff];
+    assert_eq!(
+        execute_workgroups(&prepared, Flavor::Php71, &failing, 0, 0..1),
+        Ok(vec![])
+    );
+}
+
+#[test]
+fn test_legacy_rand() {
+    use crate::legacy_rand::LegacyRand;
+
+    for generator in [Generator::Glibc, Generator::Msvc, Generator::CGlibc] {
+        let prepared = prepare_gpu(&Options {
+            allow_software: true,
+            generator: Some(generator),
+            ..Options::default()
+        })
+        .unwrap();
+        // rand(); rand(); rand(0, 99); with the shader matching the reference
+        let mut rand = LegacyRand::new(generator, 4242);
+        let first = rand.next_in_range(0, 0x7fffffff);
+        let second = rand.next_in_range(0, 0x7fffffff);
+        let third = rand.next_in_range(0, 99);
+        let arguments = [
+            first, first, 0, 0x7fffffff, second, second, 0, 0x7fffffff, third, third, 0, 99,
+        ];
+        assert_eq!(
+            execute_workgroups(&prepared, Flavor::Php71, &arguments, 4242 % 256, 0..1),
+            Ok(vec![4242])
+        );
+    }
+
+    // Seeds giving the same outputs as another are not reported: srandom(0) is srandom(1), and
+    // srand(0x80000001) is srand(1) on Windows.
+    let prepared = prepare_gpu(&Options {
+        allow_software: true,
+        generator: Some(Generator::Glibc),
+        ..Options::default()
+    })
+    .unwrap();
+    let arguments = [1804289383, 1804289383, 0, 0x7fffffff];
+    assert_eq!(
+        execute_workgroups(&prepared, Flavor::Php71, &arguments, 0, 0..1),
+        Ok(vec![])
+    );
+    assert_eq!(
+        execute_workgroups(&prepared, Flavor::Php71, &arguments, 1, 0..1),
+        Ok(vec![1])
+    );
+    let prepared = prepare_gpu(&Options {
+        allow_software: true,
+        generator: Some(Generator::Msvc),
+        ..Options::default()
+    })
+    .unwrap();
+    let arguments = [41, 41, 0, 0x7fffffff, 18467, 18467, 0, 0x7fffffff];
+    assert_eq!(
+        execute_workgroups(&prepared, Flavor::Php71, &arguments, 1, 0x8000..0x8001),
+        Ok(vec![])
+    );
+}
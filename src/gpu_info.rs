@@ -0,0 +1,233 @@
+// The gpu-info subcommand: every adapter found, with what the search needs from it and whether it
+// could run there, for orchestration systems choosing where to schedule jobs.
+
+use std::fmt::Write;
+
+use crate::engine::{MAX_RESULTS, WORKGROUPS_PER_STEP};
+use crate::gpu::{fit_binding_size, is_software_adapter};
+
+/// Invocations per workgroup of the kernels, their `@workgroup_size`.
+const WORKGROUP_SIZE: u32 = 256;
+
+/// An adapter as wgpu reports it.
+pub struct AdapterReport {
+    pub info: wgpu::AdapterInfo,
+    pub downlevel: wgpu::DownlevelCapabilities,
+    pub limits: wgpu::Limits,
+    pub features: wgpu::Features,
+}
+
+impl AdapterReport {
+    /// Why the search would not run on the adapter, empty if it would. Software rasterizers only
+    /// run with `--allow-software`.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if is_software_adapter(&self.info) {
+            problems.push("software rasterizer, only used with --allow-software".to_string());
+        }
+        if !self
+            .downlevel
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            problems.push("no compute shaders".to_string());
+        }
+        let max_binding_size = u64::from(self.limits.max_storage_buffer_binding_size);
+        if fit_binding_size(max_binding_size, MAX_RESULTS).is_none() {
+            problems.push(format!(
+                "storage buffers of {max_binding_size} bytes cannot hold a single constraint"
+            ));
+        }
+        if self.limits.max_compute_invocations_per_workgroup < WORKGROUP_SIZE
+            || self.limits.max_compute_workgroup_size_x < WORKGROUP_SIZE
+        {
+            problems.push(format!(
+                "workgroups of {WORKGROUP_SIZE} invocations are not supported"
+            ));
+        }
+        // A whole step is dispatched as two rows of workgroups.
+        if self.limits.max_compute_workgroups_per_dimension < WORKGROUPS_PER_STEP / 2 {
+            problems.push(format!(
+                "dispatches of {} workgroups per dimension are not supported",
+                WORKGROUPS_PER_STEP / 2
+            ));
+        }
+        problems
+    }
+
+    /// The limits relevant to the search, by their wgpu names.
+    fn relevant_limits(&self) -> [(&'static str, u64); 5] {
+        let limits = &self.limits;
+        [
+            (
+                "max_storage_buffer_binding_size",
+                u64::from(limits.max_storage_buffer_binding_size),
+            ),
+            ("max_buffer_size", limits.max_buffer_size),
+            (
+                "max_compute_invocations_per_workgroup",
+                u64::from(limits.max_compute_invocations_per_workgroup),
+            ),
+            (
+                "max_compute_workgroup_size_x",
+                u64::from(limits.max_compute_workgroup_size_x),
+            ),
+            (
+                "max_compute_workgroups_per_dimension",
+                u64::from(limits.max_compute_workgroups_per_dimension),
+            ),
+        ]
+    }
+
+    /// The names of the downlevel flags the adapter supports.
+    fn downlevel_flags(&self) -> Vec<&'static str> {
+        self.downlevel
+            .flags
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// The adapter as a JSON object, on a single line.
+    pub fn json(&self) -> String {
+        let info = &self.info;
+        let flags: Vec<String> = self
+            .downlevel_flags()
+            .iter()
+            .map(|f| json_string(f))
+            .collect();
+        let limits: Vec<String> = self
+            .relevant_limits()
+            .iter()
+            .map(|(name, value)| format!("\"{name}\":{value}"))
+            .collect();
+        let problems: Vec<String> = self.problems().iter().map(|p| json_string(p)).collect();
+        format!(
+            "{{\"name\":{},\"backend\":\"{}\",\"device_type\":\"{:?}\",\"driver\":{},\
+             \"driver_info\":{},\"downlevel_flags\":[{}],\"limits\":{{{}}},\"timestamps\":{},\
+             \"usable\":{},\"problems\":[{}]}}",
+            json_string(&info.name),
+            info.backend.to_str(),
+            info.device_type,
+            json_string(&info.driver),
+            json_string(&info.driver_info),
+            flags.join(","),
+            limits.join(","),
+            self.features.contains(wgpu::Features::TIMESTAMP_QUERY),
+            problems.is_empty(),
+            problems.join(",")
+        )
+    }
+
+    /// The adapter in a few lines for people.
+    pub fn text(&self) -> String {
+        let info = &self.info;
+        let mut text = format!(
+            "{} ({}, {:?}, driver {} {})\n",
+            info.name,
+            info.backend.to_str(),
+            info.device_type,
+            info.driver,
+            info.driver_info
+        );
+        for (name, value) in self.relevant_limits() {
+            writeln!(text, "  {name}: {value}").unwrap();
+        }
+        writeln!(
+            text,
+            "  downlevel flags: {}",
+            self.downlevel_flags().join(", ")
+        )
+        .unwrap();
+        match self.problems().as_slice() {
+            [] => text.push_str("  usable\n"),
+            problems => writeln!(text, "  not usable: {}", problems.join(", ")).unwrap(),
+        }
+        text
+    }
+}
+
+/// A string as a JSON string literal.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            c if c < ' ' => write!(literal, "\\u{:04x}", c as u32).unwrap(),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Every adapter of every backend wgpu was built with.
+pub fn adapters() -> Vec<AdapterReport> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .iter()
+        .map(|adapter| AdapterReport {
+            info: adapter.get_info(),
+            downlevel: adapter.get_downlevel_capabilities(),
+            limits: adapter.limits(),
+            features: adapter.features(),
+        })
+        .collect()
+}
+
+/// The `reports` as a JSON array, one adapter per line.
+pub fn json(reports: &[AdapterReport]) -> String {
+    let adapters: Vec<String> = reports.iter().map(|r| format!("  {}", r.json())).collect();
+    match adapters.is_empty() {
+        true => "[]\n".to_string(),
+        false => format!("[\n{}\n]\n", adapters.join(",\n")),
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_report(name: &str, device_type: wgpu::DeviceType) -> AdapterReport {
+    AdapterReport {
+        info: wgpu::AdapterInfo {
+            name: name.to_string(),
+            vendor: 0,
+            device: 0,
+            device_type,
+            driver: "driver".to_string(),
+            driver_info: "1.0".to_string(),
+            backend: wgpu::Backend::Vulkan,
+        },
+        downlevel: wgpu::DownlevelCapabilities::default(),
+        limits: wgpu::Limits::downlevel_defaults(),
+        features: wgpu::Features::empty(),
+    }
+}
+
+#[test]
+fn test_problems() {
+    let report = test_report("GPU", wgpu::DeviceType::DiscreteGpu);
+    assert!(report.problems().is_empty());
+    let report = test_report("llvmpipe (LLVM 15.0.7, 256 bits)", wgpu::DeviceType::Cpu);
+    assert_eq!(report.problems().len(), 1);
+
+    let mut report = test_report("GPU", wgpu::DeviceType::IntegratedGpu);
+    report.downlevel.flags = wgpu::DownlevelFlags::empty();
+    report.limits.max_storage_buffer_binding_size = 8;
+    report.limits.max_compute_workgroups_per_dimension = 16384;
+    assert_eq!(report.problems().len(), 3);
+}
+
+#[test]
+fn test_json() {
+    let mut report = test_report("GPU \"1\"", wgpu::DeviceType::DiscreteGpu);
+    report.downlevel.flags = wgpu::DownlevelFlags::COMPUTE_SHADERS;
+    let line = report.json();
+    assert!(line.starts_with(
+        "{\"name\":\"GPU \\\"1\\\"\",\"backend\":\"vulkan\",\"device_type\":\"DiscreteGpu\",\
+         \"driver\":\"driver\",\"driver_info\":\"1.0\",\"downlevel_flags\":[\"COMPUTE_SHADERS\"],\
+         \"limits\":{\"max_storage_buffer_binding_size\":134217728,"
+    ));
+    assert!(line.ends_with("\"timestamps\":false,\"usable\":true,\"problems\":[]}"));
+    assert_eq!(json(&[]), "[]\n");
+}
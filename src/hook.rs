@@ -0,0 +1,65 @@
+// `--on-seed` and `--on-finish`: shell commands notifying of the seeds found and of the end of the
+// search, such as a chat message or a mail, for searches nobody watches.
+
+use std::process::Stdio;
+
+use crate::verify_command::shell;
+
+/// A shell command run on an event of the search, its `{NAME}` placeholders replaced by the values
+/// of the event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    template: String,
+}
+
+impl Hook {
+    pub fn new(template: &str) -> Self {
+        Hook {
+            template: template.to_string(),
+        }
+    }
+
+    /// The command line run for the `values` of an event.
+    fn command_line(&self, values: &[(&str, String)]) -> String {
+        values
+            .iter()
+            .fold(self.template.clone(), |command_line, (name, value)| {
+                command_line.replace(&format!("{{{name}}}"), value)
+            })
+    }
+
+    /// Run the command and wait for it. Its output is discarded, but not its errors, and its
+    /// failures are only logged: the search goes on.
+    pub fn run(&self, values: &[(&str, String)]) {
+        let command_line = self.command_line(values);
+        let status = shell(&command_line)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => log::warn!("{command_line:?} failed: {status}"),
+            Err(e) => log::warn!("cannot run {command_line:?}: {e}"),
+        }
+    }
+}
+
+#[test]
+fn test_hook_command_line() {
+    let hook = Hook::new("notify {found} seeds: {seeds} {unknown}");
+    let values = [("found", "2".to_string()), ("seeds", "7 42".to_string())];
+    assert_eq!(hook.command_line(&values), "notify 2 seeds: 7 42 {unknown}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hook_run() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_hook.txt");
+    let _ = std::fs::remove_file(&path);
+    let hook = Hook::new(&format!("echo {{seed}} > {}", path.display()));
+    hook.run(&[("seed", "4242".to_string())]);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "4242\n");
+    std::fs::remove_file(&path).unwrap();
+    // Failures are not fatal.
+    Hook::new("exit 3").run(&[]);
+}
@@ -0,0 +1,293 @@
+// `--mem-limit SIZE`: the host memory taken by the seeds found and by the candidates waiting for the
+// CPU stage, tracked so that its peak can be reported, and the candidates spilled to a temporary file
+// while they would take the search over the limit, instead of weak constraints running the machine
+// out of memory.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// What the tracked memory holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Usage {
+    /// The seeds `search` reported, which the command line tool keeps until the end.
+    Results,
+    /// The candidates the GPU found, queued for the CPU stage to verify.
+    Candidates,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    current: AtomicU64,
+    peak: AtomicU64,
+}
+
+impl Counter {
+    fn add(&self, bytes: u64) {
+        let current = self.current.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.peak.fetch_max(current, Ordering::SeqCst);
+    }
+
+    fn sub(&self, bytes: u64) {
+        self.current.fetch_sub(bytes, Ordering::SeqCst);
+    }
+}
+
+/// The host memory of a search, and its limit with `--mem-limit`.
+#[derive(Debug, Default)]
+pub struct HostMemory {
+    limit: Option<u64>,
+    results: Counter,
+    candidates: Counter,
+    total: Counter,
+    spilled: AtomicU64,
+}
+
+impl HostMemory {
+    pub fn with_limit(limit: u64) -> HostMemory {
+        HostMemory {
+            limit: Some(limit),
+            ..HostMemory::default()
+        }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    pub fn allocate(&self, usage: Usage, bytes: u64) {
+        self.counter(usage).add(bytes);
+        self.total.add(bytes);
+    }
+
+    pub fn release(&self, usage: Usage, bytes: u64) {
+        self.counter(usage).sub(bytes);
+        self.total.sub(bytes);
+    }
+
+    /// Whether `bytes` more would go over the limit.
+    pub fn over_limit(&self, bytes: u64) -> bool {
+        self.limit
+            .is_some_and(|limit| self.total.current.load(Ordering::SeqCst) + bytes > limit)
+    }
+
+    /// Queue `candidates` for the CPU stage: in memory, or written to `spill` when they would take
+    /// the search over the limit.
+    pub fn queue(&self, spill: &mut Spill, candidates: Vec<u32>) -> Result<Candidates, String> {
+        let bytes = 4 * candidates.len() as u64;
+        if !self.over_limit(bytes) {
+            self.allocate(Usage::Candidates, bytes);
+            return Ok(Candidates::InMemory(candidates));
+        }
+        if self.spilled.fetch_add(bytes, Ordering::SeqCst) == 0 {
+            log::warn!(
+                "over --mem-limit, spilling candidates to {}",
+                spill.path().display()
+            );
+        }
+        spill.write(&candidates)
+    }
+
+    /// The candidates of `queue`, read back from `reader` if they were spilled.
+    pub fn take(
+        &self,
+        reader: &mut SpillReader,
+        candidates: Candidates,
+    ) -> Result<Vec<u32>, String> {
+        match candidates {
+            Candidates::InMemory(candidates) => {
+                self.release(Usage::Candidates, 4 * candidates.len() as u64);
+                Ok(candidates)
+            }
+            Candidates::Spilled { offset, count } => reader.read(offset, count),
+        }
+    }
+
+    pub fn peak(&self) -> u64 {
+        self.total.peak.load(Ordering::SeqCst)
+    }
+
+    /// `peak host memory 1.5 MiB (results 512.0 KiB, candidates 1.0 MiB), 0 B spilled to disk`
+    pub fn summary(&self) -> String {
+        format!(
+            "peak host memory {} (results {}, candidates {}), {} spilled to disk",
+            format_size(self.peak()),
+            format_size(self.results.peak.load(Ordering::SeqCst)),
+            format_size(self.candidates.peak.load(Ordering::SeqCst)),
+            format_size(self.spilled.load(Ordering::SeqCst))
+        )
+    }
+
+    fn counter(&self, usage: Usage) -> &Counter {
+        match usage {
+            Usage::Results => &self.results,
+            Usage::Candidates => &self.candidates,
+        }
+    }
+}
+
+/// `512 B`, `1.5 KiB`, `2.0 GiB`, powers of 1024 like the sizes of `--mem-limit`.
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    for unit in ["KiB", "MiB"] {
+        if size < 1024.0 {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} GiB")
+}
+
+/// Candidates on their way to the CPU stage: in memory, or at an offset of the spill file.
+#[derive(Debug, PartialEq)]
+pub enum Candidates {
+    InMemory(Vec<u32>),
+    Spilled { offset: u64, count: usize },
+}
+
+/// A temporary file candidates are appended to, read back in the order they were written, and
+/// removed once dropped.
+pub struct Spill {
+    path: PathBuf,
+    file: Option<File>,
+    written: u64,
+}
+
+impl Spill {
+    /// A spill whose file is only created when the first candidates are written.
+    pub fn new() -> Spill {
+        static SPILLS: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "php_mt_seed_rs-{}-{}.candidates",
+            std::process::id(),
+            SPILLS.fetch_add(1, Ordering::SeqCst)
+        );
+        Spill {
+            path: std::env::temp_dir().join(name),
+            file: None,
+            written: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A reader of the candidates written, for another thread.
+    pub fn reader(&self) -> SpillReader {
+        SpillReader {
+            path: self.path.clone(),
+            file: None,
+        }
+    }
+
+    pub fn write(&mut self, seeds: &[u32]) -> Result<Candidates, String> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = File::create(&self.path)
+                    .map_err(|e| format!("cannot create {:?}: {e}", self.path))?;
+                self.file.insert(file)
+            }
+        };
+        let bytes: Vec<u8> = seeds.iter().flat_map(|seed| seed.to_le_bytes()).collect();
+        file.write_all(&bytes)
+            .and_then(|()| file.flush())
+            .map_err(|e| format!("cannot write to {:?}: {e}", self.path))?;
+        let offset = self.written;
+        self.written += bytes.len() as u64;
+        Ok(Candidates::Spilled {
+            offset,
+            count: seeds.len(),
+        })
+    }
+}
+
+impl Default for Spill {
+    fn default() -> Self {
+        Spill::new()
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        if self.file.is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Reads back the candidates of a `Spill`, opening its file on first use.
+pub struct SpillReader {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl SpillReader {
+    /// The `count` candidates written at `offset`.
+    pub fn read(&mut self, offset: u64, count: usize) -> Result<Vec<u32>, String> {
+        let path = &self.path;
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = File::open(path).map_err(|e| format!("cannot open {path:?}: {e}"))?;
+                self.file.insert(file)
+            }
+        };
+        let mut bytes = vec![0; 4 * count];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut bytes))
+            .map_err(|e| format!("cannot read {path:?}: {e}"))?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|seed| u32::from_le_bytes(seed.try_into().unwrap()))
+            .collect())
+    }
+}
+
+#[test]
+fn test_host_memory() {
+    let memory = HostMemory::with_limit(1000);
+    memory.allocate(Usage::Candidates, 600);
+    memory.allocate(Usage::Results, 200);
+    memory.release(Usage::Candidates, 600);
+    memory.allocate(Usage::Candidates, 100);
+    assert!(!memory.over_limit(700));
+    assert!(memory.over_limit(701));
+    assert_eq!(memory.peak(), 800);
+    assert_eq!(
+        memory.summary(),
+        "peak host memory 800 B (results 200 B, candidates 600 B), 0 B spilled to disk"
+    );
+    assert!(!HostMemory::default().over_limit(u64::MAX / 2));
+    assert_eq!(format_size(3 << 20), "3.0 MiB");
+}
+
+#[test]
+fn test_spill() {
+    let memory = HostMemory::with_limit(16);
+    let mut spill = Spill::new();
+    let mut reader = spill.reader();
+    let first = memory.queue(&mut spill, vec![1, 2, 3]).unwrap();
+    assert_eq!(first, Candidates::InMemory(vec![1, 2, 3]));
+    let second = memory.queue(&mut spill, vec![4, 5]).unwrap();
+    let third = memory.queue(&mut spill, vec![0xdeadbeef, 7]).unwrap();
+    assert_eq!(
+        third,
+        Candidates::Spilled {
+            offset: 8,
+            count: 2
+        }
+    );
+    assert_eq!(memory.take(&mut reader, first).unwrap(), [1, 2, 3]);
+    assert_eq!(memory.take(&mut reader, third).unwrap(), [0xdeadbeef, 7]);
+    assert_eq!(memory.take(&mut reader, second).unwrap(), [4, 5]);
+    assert!(memory.summary().ends_with("16 B spilled to disk"));
+    let path = spill.path().to_path_buf();
+    drop(spill);
+    assert!(!path.exists());
+}
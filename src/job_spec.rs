@@ -0,0 +1,454 @@
+// The job specifications of `run`: a search described in a YAML or JSON file, with its flavors,
+// constraints, seeds, outputs and notification hooks, so that complex attacks can be reviewed,
+// kept along with their results and run again as they were.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::jobs::split_words;
+
+/// A value of a specification: a scalar as written, or a list of them.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    fn items(&self) -> &[String] {
+        match self {
+            Value::Scalar(scalar) => std::slice::from_ref(scalar),
+            Value::List(items) => items,
+        }
+    }
+}
+
+/// A YAML scalar without its quotes.
+fn yaml_scalar(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    let unquoted = |quote| s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote));
+    if let Some(single) = unquoted('\'').filter(|_| s.len() > 1) {
+        return Ok(single.replace("''", "'"));
+    }
+    if let Some(double) = unquoted('"').filter(|_| s.len() > 1) {
+        return Ok(double.replace("\\\"", "\"").replace("\\\\", "\\"));
+    }
+    if s.starts_with(['\'', '"']) {
+        return Err(format!("unterminated quote in {s:?}"));
+    }
+    Ok(s.to_string())
+}
+
+/// The items of a YAML flow sequence, `[a, 'b, c']`, without its brackets.
+fn yaml_flow(s: &str) -> Result<Vec<String>, String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut quote = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ',') => {
+                items.push(yaml_scalar(&item)?);
+                item.clear();
+                continue;
+            }
+            _ => {}
+        }
+        item.push(c);
+    }
+    if !item.trim().is_empty() || !items.is_empty() {
+        items.push(yaml_scalar(&item)?);
+    }
+    Ok(items)
+}
+
+/// `line` without its comment, a `#` starting the line or following a space outside quotes.
+///
+/// Quotes only count at the start of a scalar, so that `it's` does not open one.
+fn yaml_strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut after_space = true;
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '#' && after_space => return &line[..i],
+            // `''` is a quote inside a single-quoted scalar.
+            None if c == '\'' && previous == Some('\'') => quote = Some(c),
+            None if matches!(c, '\'' | '"')
+                && matches!(previous, None | Some(':' | '-' | '[' | ',')) =>
+            {
+                quote = Some(c)
+            }
+            None => {}
+        }
+        after_space = c.is_whitespace();
+        if !after_space {
+            previous = Some(c);
+        }
+    }
+    line
+}
+
+/// The entries of a YAML mapping of scalars and lists of scalars, the block (`- item`) or the
+/// flow (`[a, b]`) ones.
+fn parse_yaml(text: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut entries: Vec<(String, Value)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let error = |e: String| format!("line {}: {e}", i + 1);
+        let line = yaml_strip_comment(line).trim_end();
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed == "---" {
+            continue;
+        }
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or((trimmed == "-").then_some(""))
+        {
+            match entries.last_mut() {
+                Some((_, Value::List(items))) if line.starts_with(char::is_whitespace) => {
+                    items.push(yaml_scalar(item).map_err(error)?)
+                }
+                _ => {
+                    return Err(error(
+                        "list items must be indented under their key".to_string(),
+                    ))
+                }
+            }
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            return Err(error(format!(
+                "cannot read {trimmed:?}, nothing can be nested"
+            )));
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| error(format!("cannot read {line:?} as KEY: VALUE")))?;
+        let value = value.trim();
+        let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(flow) => Value::List(yaml_flow(flow).map_err(error)?),
+            None if value.is_empty() => Value::List(Vec::new()),
+            None => Value::Scalar(yaml_scalar(value).map_err(error)?),
+        };
+        entries.push((key.trim().to_string(), value));
+    }
+    Ok(entries)
+}
+
+/// A reader of a JSON object of scalars and arrays of scalars.
+struct JsonReader<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl JsonReader<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected {expected:?}, found {c:?}")),
+            None => Err(format!("expected {expected:?}, found the end")),
+        }
+    }
+
+    /// Whether the next character is `c`, which is then consumed.
+    fn next_is(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.chars.next().ok_or("unterminated string")? {
+                '"' => return Ok(string),
+                '\\' => match self.chars.next().ok_or("unterminated string")? {
+                    'n' => string.push('\n'),
+                    't' => string.push('\t'),
+                    'u' => {
+                        let hex: String = self.chars.by_ref().take(4).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("cannot read \\u{hex}"))?;
+                        string.push(c);
+                    }
+                    c => string.push(c),
+                },
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// A string, number or boolean as written, `None` for `null`.
+    fn scalar(&mut self) -> Result<Option<String>, String> {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'"') {
+            return self.string().map(Some);
+        }
+        let mut literal = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_alphanumeric() || "+-.".contains(*c))
+        {
+            literal.push(c);
+        }
+        match literal.as_str() {
+            "null" => Ok(None),
+            "" => Err(format!(
+                "expected a string, number or boolean, found {:?}",
+                self.chars.peek()
+            )),
+            _ => Ok(Some(literal)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Option<Value>, String> {
+        if !self.next_is('[') {
+            return Ok(self.scalar()?.map(Value::Scalar));
+        }
+        let mut items = Vec::new();
+        if !self.next_is(']') {
+            loop {
+                items.extend(self.scalar()?);
+                if self.next_is(']') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(Some(Value::List(items)))
+    }
+
+    fn object(&mut self) -> Result<Vec<(String, Value)>, String> {
+        let mut entries = Vec::new();
+        self.expect('{')?;
+        if !self.next_is('}') {
+            loop {
+                let key = self.string()?;
+                self.expect(':')?;
+                let value = self.value().map_err(|e| format!("{key}: {e}"))?;
+                entries.extend(value.map(|value| (key, value)));
+                if self.next_is('}') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(c) => Err(format!("unexpected {c:?} after the object")),
+            None => Ok(entries),
+        }
+    }
+}
+
+fn parse_json(text: &str) -> Result<Vec<(String, Value)>, String> {
+    JsonReader {
+        chars: text.chars().peekable(),
+    }
+    .object()
+}
+
+/// The constraints of an item of `constraints`: words like on the command line, or `skip` and
+/// `skip N` for outputs which were not observed.
+fn constraint_words(item: &str) -> Result<Vec<String>, String> {
+    let words: Vec<&str> = item.split_whitespace().collect();
+    match words[..] {
+        ["skip"] => Ok(vec!["0-2147483647".to_string()]),
+        ["skip", count] => Ok(vec!["0-2147483647".to_string(), format!("*{count}")]),
+        ["skip", ..] => Err(format!("cannot read {item:?}, expected skip or skip N")),
+        _ => Ok(words.into_iter().map(String::from).collect()),
+    }
+}
+
+/// The command line arguments of a specification, as `run` gives them to the search: its
+/// options, then its constraints.
+fn spec_arguments(entries: &[(String, Value)]) -> Result<Vec<String>, String> {
+    let mut options = Vec::new();
+    let mut constraints = Vec::new();
+    for (key, value) in entries {
+        let name = key.replace('_', "-");
+        let option = format!("--{name}");
+        let items = value.items();
+        match name.as_str() {
+            "php" => {
+                let flavor = match items {
+                    [flavor] => flavor.as_str(),
+                    [a, b] if a != b => "auto",
+                    _ => return Err(format!("{key}: expected one flavor, or both")),
+                };
+                options.extend(["--php".to_string(), flavor.to_string()]);
+            }
+            "constraints" => {
+                for item in items {
+                    constraints.extend(constraint_words(item).map_err(|e| format!("{key}: {e}"))?);
+                }
+            }
+            // The seeds of the range are checked on the CPU, like the seeds of `--seed-fn`.
+            "seeds" => {
+                let [range] = items else {
+                    return Err(format!("{key}: expected a single MIN-MAX range"));
+                };
+                options.extend([
+                    "--seed-fn".to_string(),
+                    "seed".to_string(),
+                    "--seed-var".to_string(),
+                    format!("seed={range}"),
+                ]);
+            }
+            "emit" => {
+                for item in items {
+                    let words = split_words(item).map_err(|e| format!("{key}: {e}"))?;
+                    if words.len() != 2 {
+                        return Err(format!("{key}: expected LANG FILE, found {item:?}"));
+                    }
+                    options.push(option.clone());
+                    options.extend(words);
+                }
+            }
+            "run" | "bench" | "verify" | "gpu-info" | "crack-many" => {
+                return Err(format!("{key}: subcommands cannot be run from a job"));
+            }
+            _ => match value {
+                Value::Scalar(flag) if flag == "true" => options.push(option),
+                Value::Scalar(flag) if flag == "false" => {}
+                _ => {
+                    for item in items {
+                        options.extend([option.clone(), item.clone()]);
+                    }
+                }
+            },
+        }
+    }
+    options.extend(constraints);
+    Ok(options)
+}
+
+/// The command line arguments of the job specification `text`: a JSON object when it starts with
+/// `{`, a YAML mapping otherwise. Its keys are the long options, with `-` or `_` between their
+/// words, given the scalar or each item of the list they map to (`true` for flags), along with:
+/// - `php`: a flavor, or both of them for `--php auto`;
+/// - `constraints`: the constraints, each item written like on the command line, or `skip` and
+///   `skip N` for outputs which were not observed;
+/// - `seeds`: `MIN-MAX`, the only seeds checked, on the CPU like those of `--seed-fn`;
+/// - `emit`: `LANG FILE` items.
+pub fn parse_spec(text: &str) -> Result<Vec<String>, String> {
+    let entries = match text.trim_start().starts_with('{') {
+        true => parse_json(text)?,
+        false => parse_yaml(text)?,
+    };
+    if entries.is_empty() {
+        return Err("the job is empty".to_string());
+    }
+    spec_arguments(&entries)
+}
+
+#[test]
+fn test_parse_yaml() {
+    let yaml =
+        "# reset tokens\nphp: [7.1, 5.2.1]\ncharset: 'abc def'\ntoken:\n  - ab?\n  - \"c a\"\n\
+                first: true\nconstraints:\n";
+    assert_eq!(
+        parse_yaml(yaml),
+        Ok(vec![
+            (
+                "php".to_string(),
+                Value::List(vec!["7.1".to_string(), "5.2.1".to_string()])
+            ),
+            ("charset".to_string(), Value::Scalar("abc def".to_string())),
+            (
+                "token".to_string(),
+                Value::List(vec!["ab?".to_string(), "c a".to_string()])
+            ),
+            ("first".to_string(), Value::Scalar("true".to_string())),
+            ("constraints".to_string(), Value::List(Vec::new())),
+        ])
+    );
+    assert_eq!(
+        parse_yaml("charset: 'ab #cd' # letters\ntoken: [\"a #b\", 'it''s #c']\nemit: it's #d\n"),
+        Ok(vec![
+            ("charset".to_string(), Value::Scalar("ab #cd".to_string())),
+            (
+                "token".to_string(),
+                Value::List(vec!["a #b".to_string(), "it's #c".to_string()])
+            ),
+            ("emit".to_string(), Value::Scalar("it's".to_string())),
+        ])
+    );
+    assert!(parse_yaml("- 1178568022\n").is_err());
+    assert!(parse_yaml("seeds:\n  min: 0\n").is_err());
+    assert!(parse_yaml("charset: 'abc\n").is_err());
+}
+
+#[test]
+fn test_parse_json() {
+    let json = "{\"php\": \"auto\", \"constraints\": [1178568022, \"skip 2\"], \"first\": true,\n\
+                \"session\": null, \"charset\": \"a\\\"\\u0062\"}";
+    assert_eq!(
+        parse_json(json),
+        Ok(vec![
+            ("php".to_string(), Value::Scalar("auto".to_string())),
+            (
+                "constraints".to_string(),
+                Value::List(vec!["1178568022".to_string(), "skip 2".to_string()])
+            ),
+            ("first".to_string(), Value::Scalar("true".to_string())),
+            ("charset".to_string(), Value::Scalar("a\"b".to_string())),
+        ])
+    );
+    assert!(parse_json("{\"php\": \"auto\"").is_err());
+    assert!(parse_json("{\"php\": {}}").is_err());
+}
+
+#[test]
+fn test_parse_spec() {
+    let yaml = "php: [7.1, 5.2.1]\nconstraints:\n  - 1178568022\n  - skip 3\n  - 5-9@0-61\n\
+                seeds: 0-1000\nfirst: true\nforce: false\nexclude_seeds: [a.txt, b.txt]\n\
+                emit: rust 'replay 1.rs'\non-seed: notify-send {seed}\n";
+    assert_eq!(
+        parse_spec(yaml),
+        Ok([
+            "--php",
+            "auto",
+            "--seed-fn",
+            "seed",
+            "--seed-var",
+            "seed=0-1000",
+            "--first",
+            "--exclude-seeds",
+            "a.txt",
+            "--exclude-seeds",
+            "b.txt",
+            "--emit",
+            "rust",
+            "replay 1.rs",
+            "--on-seed",
+            "notify-send {seed}",
+            "1178568022",
+            "0-2147483647",
+            "*3",
+            "5-9@0-61",
+        ]
+        .map(String::from)
+        .to_vec())
+    );
+    let json = "{\"php\": \"5.2.1\", \"constraints\": [1178568022]}";
+    assert_eq!(
+        parse_spec(json),
+        Ok(["--php", "5.2.1", "1178568022"].map(String::from).to_vec())
+    );
+    assert!(parse_spec("# nothing\n").is_err());
+    assert!(parse_spec("bench: true\n").is_err());
+    assert!(parse_spec("constraints: [skip a b]\n").is_err());
+}
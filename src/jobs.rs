@@ -0,0 +1,119 @@
+// The job files of `crack-many`: independent searches, each with its own options and constraints,
+// such as tokens captured from different sessions.
+
+/// The words of a line, split on whitespace like a shell would, `'...'` and `"..."` keeping
+/// spaces (a charset, a regex).
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// The arguments of a job written as a YAML document: a block sequence of arguments (`- --php`,
+/// `- auto`, ...), or words like on a job line, over as many lines as needed.
+fn parse_document(lines: &[&str]) -> Result<Vec<String>, String> {
+    let mut arguments = Vec::new();
+    for line in lines {
+        let line = line.trim();
+        match line.strip_prefix("- ").or((line == "-").then_some("")) {
+            Some(item) => match split_words(item)?[..] {
+                [ref item] => arguments.push(item.clone()),
+                _ => return Err(format!("cannot read {line:?} as a single argument")),
+            },
+            None => arguments.extend(split_words(line)?),
+        }
+    }
+    Ok(arguments)
+}
+
+/// A line without its comment, which `#` starts at the beginning of the line or after a space.
+pub(crate) fn strip_comment(line: &str) -> &str {
+    match line.find(" #") {
+        _ if line.trim_start().starts_with('#') => "",
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+/// The arguments of each job of a `crack-many` file: one job per line, written like on the
+/// command line, or one per YAML document when the file has `---` separators. Empty lines and `#`
+/// comments are skipped.
+pub fn parse_jobs(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let lines: Vec<&str> = text.lines().map(strip_comment).collect();
+    let documents = lines.iter().any(|line| line.trim_end() == "---");
+    let mut jobs = Vec::new();
+    if documents {
+        for (i, document) in lines.split(|line| line.trim_end() == "---").enumerate() {
+            let arguments =
+                parse_document(document).map_err(|e| format!("document {}: {e}", i + 1))?;
+            if !arguments.is_empty() {
+                jobs.push(arguments);
+            }
+        }
+    } else {
+        for (i, line) in lines.iter().enumerate() {
+            let arguments = split_words(line).map_err(|e| format!("line {}: {e}", i + 1))?;
+            if !arguments.is_empty() {
+                jobs.push(arguments);
+            }
+        }
+    }
+    match jobs.is_empty() {
+        true => Err("no job".to_string()),
+        false => Ok(jobs),
+    }
+}
+
+#[test]
+fn test_split_words() {
+    assert_eq!(
+        split_words("--charset 'a b' --token \"c d\"  7 ''"),
+        Ok(["--charset", "a b", "--token", "c d", "7", ""]
+            .map(String::from)
+            .to_vec())
+    );
+    assert!(split_words("--charset 'ab").is_err());
+}
+
+#[test]
+fn test_parse_jobs() {
+    let lines = "# captured tokens\n1178568022\n\n--php auto 963932192 # session 2\n";
+    assert_eq!(
+        parse_jobs(lines),
+        Ok(vec![
+            vec!["1178568022".to_string()],
+            ["--php", "auto", "963932192"].map(String::from).to_vec(),
+        ])
+    );
+
+    let documents =
+        "---\n- --charset\n- 'ab c'\n- --token\n- cab\n---\n# next\n--php 5.2.1\n963932192\n";
+    assert_eq!(
+        parse_jobs(documents),
+        Ok(vec![
+            ["--charset", "ab c", "--token", "cab"]
+                .map(String::from)
+                .to_vec(),
+            ["--php", "5.2.1", "963932192"].map(String::from).to_vec(),
+        ])
+    );
+    assert!(parse_jobs("---\n- a b\n").is_err());
+    assert!(parse_jobs("# nothing\n").is_err());
+}
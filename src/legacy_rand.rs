@@ -0,0 +1,353 @@
+// The `rand()` of PHP before 7.1.0, which was not yet an alias of `mt_rand()` but the generator of
+// the C library: glibc's `random()` on Linux, the LCG of the Microsoft C runtime on Windows.
+// Searched with `--rand`, by the entry points of legacy_rand.wgsl, which this checks the results
+// of like reference.rs does for MT19937. glibc's generator is also what C programs get from
+// `rand()`, which they reduce to ranges with a modulo instead.
+
+use rayon::prelude::*;
+
+use crate::reference::VERIFY_CHUNK;
+
+/// The generators behind `rand()` before PHP 7.1.0, selected with `--rand`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Generator {
+    /// glibc's `random()`, seeded with `srandom()`, on non-thread-safe builds for Linux.
+    Glibc,
+    /// `rand()` of the Microsoft C runtime, seeded with `srand()`, on Windows.
+    Msvc,
+    /// glibc's `rand()` called from C, seeded with `srand()`: the outputs of `Glibc`, reduced to
+    /// ranges like `min + rand() % (max - min + 1)`.
+    CGlibc,
+}
+
+impl Generator {
+    /// The main value `--rand` accepts for this generator.
+    pub fn name(self) -> &'static str {
+        match self {
+            Generator::Glibc => "glibc",
+            Generator::Msvc => "msvc",
+            Generator::CGlibc => "c",
+        }
+    }
+
+    /// The `getrandmax()` of PHP on the platforms of the generator, or `RAND_MAX` in C.
+    pub fn getrandmax(self) -> u32 {
+        match self {
+            Generator::Glibc | Generator::CGlibc => 0x7fffffff,
+            Generator::Msvc => 0x7fff,
+        }
+    }
+
+    /// The entry point of legacy_rand.wgsl searching the generator.
+    pub fn entry_point(self) -> &'static str {
+        match self {
+            Generator::Glibc => "glibc_main",
+            Generator::Msvc => "msvc_main",
+            Generator::CGlibc => "c_glibc_main",
+        }
+    }
+
+    /// The search shader: legacy_rand.wgsl uses the bindings and the scaling of mt19937.wgsl.
+    pub fn shader_source() -> String {
+        format!(
+            "{}\n{}",
+            include_str!("mt19937.wgsl"),
+            include_str!("legacy_rand.wgsl")
+        )
+    }
+
+    /// Whether the kernels report `seed`. Seeds giving the same outputs as another are only found
+    /// once: `srandom(0)` is `srandom(1)`, and the top bit of the seed of the Microsoft LCG never
+    /// reaches its outputs.
+    pub fn reports(self, seed: u32) -> bool {
+        match self {
+            Generator::Glibc | Generator::CGlibc => seed != 0,
+            Generator::Msvc => seed < 0x80000000,
+        }
+    }
+
+    /// The seed giving the same outputs as a reported `seed`, if any.
+    pub fn twin(self, seed: u32) -> Option<u32> {
+        match self {
+            Generator::Glibc | Generator::CGlibc => (seed == 1).then_some(0),
+            Generator::Msvc => Some(seed | 0x80000000),
+        }
+    }
+
+    /// A result line, along with the seed giving the same outputs.
+    pub fn format_seed(self, seed: u32) -> String {
+        let platform = match self {
+            Generator::Glibc => "PHP rand() before 7.1.0 on glibc",
+            Generator::Msvc => "PHP rand() before 7.1.0 on Windows",
+            Generator::CGlibc => "C rand() on glibc",
+        };
+        match self.twin(seed) {
+            Some(twin) => format!("seed = {seed:#x} = {seed} or {twin} ({platform})"),
+            None => format!("seed = {seed:#x} = {seed} ({platform})"),
+        }
+    }
+
+    /// Check that raw outputs in the normalized `arguments` are within `getrandmax()`.
+    pub fn check_arguments(self, arguments: &[u32]) -> Result<(), String> {
+        let getrandmax = self.getrandmax();
+        match arguments
+            .chunks(4)
+            .find(|slot| slot[2..] == [0, 0x7fffffff] && slot[0] > getrandmax)
+        {
+            Some(slot) => Err(format!(
+                "rand() returns at most {getrandmax} with --rand {}, not {}",
+                self.name(),
+                slot[0]
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Generator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "glibc" | "linux" | "random" => Ok(Generator::Glibc),
+            "msvc" | "windows" | "win32" => Ok(Generator::Msvc),
+            "c" | "c-glibc" => Ok(Generator::CGlibc),
+            _ => Err(format!(
+                "unknown generator {s:?}, expected glibc, msvc or c"
+            )),
+        }
+    }
+}
+
+/// glibc's `random()` with its default state of 31 words, as seeded by `srandom()`.
+pub struct GlibcRandom {
+    state: [u32; 31],
+    front: usize,
+    rear: usize,
+}
+
+impl GlibcRandom {
+    pub fn new(seed: u32) -> Self {
+        let mut state = [0; 31];
+        // srandom() computes 16807 * word % 2147483647 on signed words, without overflowing.
+        let mut word = if seed == 0 { 1 } else { seed as i32 };
+        state[0] = word as u32;
+        for value in &mut state[1..] {
+            let (hi, lo) = (word / 127773, word % 127773);
+            word = 16807 * lo - 2836 * hi;
+            if word < 0 {
+                word += 2147483647;
+            }
+            *value = word as u32;
+        }
+        let mut random = GlibcRandom {
+            state,
+            front: 3,
+            rear: 0,
+        };
+        for _ in 0..310 {
+            random.next_u31();
+        }
+        random
+    }
+
+    /// The next output of `random()`.
+    pub fn next_u31(&mut self) -> u32 {
+        self.state[self.front] = self.state[self.front].wrapping_add(self.state[self.rear]);
+        let result = self.state[self.front] >> 1;
+        self.front = (self.front + 1) % 31;
+        self.rear = (self.rear + 1) % 31;
+        result
+    }
+}
+
+/// `rand()` of the Microsoft C runtime, as seeded by `srand()`.
+pub struct MsvcRand {
+    holdrand: u32,
+}
+
+impl MsvcRand {
+    pub fn new(seed: u32) -> Self {
+        MsvcRand { holdrand: seed }
+    }
+
+    /// The next output of `rand()`.
+    pub fn next_u15(&mut self) -> u32 {
+        self.holdrand = self.holdrand.wrapping_mul(214013).wrapping_add(2531011);
+        (self.holdrand >> 16) & 0x7fff
+    }
+}
+
+/// Any of the generators, seeded.
+pub enum LegacyRand {
+    Glibc(GlibcRandom),
+    Msvc(MsvcRand),
+    CGlibc(GlibcRandom),
+}
+
+impl LegacyRand {
+    pub fn new(generator: Generator, seed: u32) -> Self {
+        match generator {
+            Generator::Glibc => LegacyRand::Glibc(GlibcRandom::new(seed)),
+            Generator::Msvc => LegacyRand::Msvc(MsvcRand::new(seed)),
+            Generator::CGlibc => LegacyRand::CGlibc(GlibcRandom::new(seed)),
+        }
+    }
+
+    /// `rand()` without arguments, or `rand(range_min, range_max)`, which scales the output with
+    /// `RAND_RANGE`: `range_min + (long)((range_max - range_min + 1.0) * (n / (getrandmax() +
+    /// 1.0)))`. C reduces it with a modulo instead.
+    pub fn next_in_range(&mut self, range_min: u32, range_max: u32) -> u32 {
+        let (next, getrandmax) = match self {
+            LegacyRand::Glibc(random) | LegacyRand::CGlibc(random) => {
+                (random.next_u31(), 0x7fffffff)
+            }
+            LegacyRand::Msvc(rand) => (rand.next_u15(), 0x7fff),
+        };
+        if range_min == 0 && range_max == 0x7fffffff {
+            return next;
+        }
+        // 64-bit PHP takes ranges up to 2^32 values.
+        let range = u64::from(range_max.wrapping_sub(range_min)) + 1;
+        if let LegacyRand::CGlibc(_) = self {
+            return ((u64::from(next) % range) as u32).wrapping_add(range_min);
+        }
+        let scaled = range as f64 * (f64::from(next) / (f64::from(getrandmax) + 1.0));
+        range_min.wrapping_add(scaled as u32)
+    }
+}
+
+/// Whether `seed` produces outputs matching the normalized `arguments`, like the search shader,
+/// though it also holds for the seeds the shader does not report.
+pub fn seed_matches(generator: Generator, arguments: &[u32], seed: u32) -> bool {
+    let mut rand = LegacyRand::new(generator, seed);
+    arguments.chunks(4).all(|chunk| {
+        let &[match_min, match_max, range_min, range_max] = chunk else {
+            panic!("arguments must be normalized");
+        };
+        (match_min..=match_max).contains(&rand.next_in_range(range_min, range_max))
+    })
+}
+
+/// Keep the candidates matching all of `arguments`, in parallel and in order, like
+/// `reference::verify_candidates`.
+pub fn verify_candidates(generator: Generator, arguments: &[u32], candidates: &[u32]) -> Vec<u32> {
+    candidates
+        .par_chunks(VERIFY_CHUNK)
+        .flat_map_iter(|chunk| {
+            chunk
+                .iter()
+                .copied()
+                .filter(|&seed| seed_matches(generator, arguments, seed))
+        })
+        .collect()
+}
+
+#[test]
+fn test_glibc_outputs() {
+    // srandom(1); random(); the first outputs of any C program calling rand() unseeded.
+    let mut random = GlibcRandom::new(1);
+    assert_eq!(random.next_u31(), 1804289383);
+    assert_eq!(random.next_u31(), 846930886);
+    assert_eq!(random.next_u31(), 1681692777);
+    let mut random = GlibcRandom::new(0);
+    assert_eq!(random.next_u31(), 1804289383);
+
+    // srandom(42) and a seed with the top bit set, negative in the signed words of srandom().
+    let mut random = GlibcRandom::new(42);
+    assert_eq!(random.next_u31(), 71876166);
+    assert_eq!(random.next_u31(), 708592740);
+    let mut random = GlibcRandom::new(0x80000000);
+    assert_eq!(random.next_u31(), 1336741213);
+}
+
+#[test]
+fn test_msvc_outputs() {
+    // srand(1); rand(); as Microsoft documents it.
+    let mut rand = MsvcRand::new(1);
+    assert_eq!(rand.next_u15(), 41);
+    assert_eq!(rand.next_u15(), 18467);
+    assert_eq!(rand.next_u15(), 6334);
+
+    // The top bit of the seed makes no difference.
+    let mut rand = MsvcRand::new(0x80000001);
+    assert_eq!(rand.next_u15(), 41);
+    assert!(Generator::Msvc.reports(1) && !Generator::Msvc.reports(0x80000001));
+    assert_eq!(Generator::Msvc.twin(1), Some(0x80000001));
+}
+
+#[test]
+fn test_next_in_range() {
+    // srand(1); rand(0, 99); is 41 * 100 / 32768 on Windows
+    let mut rand = LegacyRand::new(Generator::Msvc, 1);
+    assert_eq!(rand.next_in_range(0, 99), 0);
+    assert_eq!(rand.next_in_range(0, 99), 56);
+    assert_eq!(rand.next_in_range(10, 19), 11);
+    // and 1804289383 * 100 / 2^31 on glibc
+    let mut rand = LegacyRand::new(Generator::Glibc, 1);
+    assert_eq!(rand.next_in_range(0, 99), 84);
+    assert_eq!(rand.next_in_range(0, 0x7fffffff), 846930886);
+    // A range of 2^32 values on 64-bit PHP doubles the output.
+    assert_eq!(rand.next_in_range(0, u32::MAX), 2 * 1681692777);
+}
+
+#[test]
+fn test_c_glibc() {
+    // srand(1); rand() % 100; rand() % 6 + 1; in C
+    let mut rand = LegacyRand::new(Generator::CGlibc, 1);
+    assert_eq!(rand.next_in_range(0, 99), 83);
+    assert_eq!(rand.next_in_range(1, 6), 5);
+    assert_eq!(rand.next_in_range(0, 0x7fffffff), 1681692777);
+    assert_eq!(
+        "c".parse::<Generator>().unwrap().format_seed(1),
+        "seed = 0x1 = 1 or 0 (C rand() on glibc)"
+    );
+    assert!(seed_matches(
+        Generator::CGlibc,
+        &[83, 83, 0, 99, 5, 5, 1, 6],
+        1
+    ));
+    assert!(!seed_matches(
+        Generator::Glibc,
+        &[83, 83, 0, 99, 5, 5, 1, 6],
+        1
+    ));
+}
+
+#[test]
+fn test_seed_matches() {
+    let arguments = [1804289383, 1804289383, 0, 0x7fffffff, 84, 84, 0, 99];
+    assert!(!seed_matches(Generator::Glibc, &arguments, 1));
+    assert!(seed_matches(Generator::Glibc, &arguments[..4], 1));
+    assert!(seed_matches(
+        Generator::Glibc,
+        &[1804289383, 1804289383, 0, 0x7fffffff, 39, 39, 0, 99],
+        0
+    ));
+    let candidates: Vec<u32> = (0..2 * VERIFY_CHUNK as u32).collect();
+    assert_eq!(
+        verify_candidates(
+            Generator::Msvc,
+            &[41, 41, 0, 0x7fffffff, 50, 59, 0, 99],
+            &candidates
+        ),
+        vec![1]
+    );
+}
+
+#[test]
+fn test_check_arguments() {
+    assert!(Generator::Msvc
+        .check_arguments(&[32767, 32767, 0, 0x7fffffff])
+        .is_ok());
+    assert!(Generator::Msvc
+        .check_arguments(&[40000, 40000, 0, 99999])
+        .is_ok());
+    assert_eq!(
+        Generator::Msvc.check_arguments(&[32768, 32768, 0, 0x7fffffff]),
+        Err("rand() returns at most 32767 with --rand msvc, not 32768".to_string())
+    );
+    assert!(Generator::Glibc
+        .check_arguments(&[32768, 32768, 0, 0x7fffffff])
+        .is_ok());
+}
@@ -0,0 +1,159 @@
+// The rand() of PHP before 7.1.0, and glibc's rand() called from C, appended to mt19937.wgsl by
+// `--rand` for its bindings and bad_scaling. legacy_rand.rs is the CPU implementation of the same
+// generators.
+//
+// The input is the one of main, whose flavor is ignored: each generator has its entry point.
+
+// glibc's random() after srandom(seed): an additive feedback generator on 31 words, filled by an
+// LCG and run 310 times before its first output.
+struct GlibcRandom {
+    state: array<u32, 31>,
+    front: u32,
+    rear: u32,
+}
+
+fn glibc_srandom(seed: u32) -> GlibcRandom {
+    var random = GlibcRandom(array<u32, 31>(), 3, 0);
+    // srandom() computes 16807 * word % 2147483647 on signed words, without overflowing.
+    var word = bitcast<i32>(select(seed, 1u, seed == 0));
+    random.state[0] = bitcast<u32>(word);
+    for (var i: u32 = 1; i < 31; i++) {
+        let hi = word / 127773;
+        let lo = word % 127773;
+        word = 16807 * lo - 2836 * hi;
+        if word < 0 {
+            word += 2147483647;
+        }
+        random.state[i] = bitcast<u32>(word);
+    }
+    for (var i: u32 = 0; i < 310; i++) {
+        glibc_next(&random);
+    }
+    return random;
+}
+
+fn glibc_next(random: ptr<function, GlibcRandom>) -> u32 {
+    let front = (*random).front;
+    let rear = (*random).rear;
+    let value = (*random).state[front] + (*random).state[rear];
+    (*random).state[front] = value;
+    (*random).front = select(front + 1, 0u, front == 30);
+    (*random).rear = select(rear + 1, 0u, rear == 30);
+    return value >> 1;
+}
+
+// Whether the output `n` of rand() on a platform whose getrandmax() is 2^bits - 1 is in
+// match_min..=match_max, once scaled to range_min..=range_max by RAND_RANGE.
+fn legacy_slot_matches(
+    n: u32,
+    bits: u32,
+    match_min: u32,
+    match_max: u32,
+    range_min: u32,
+    range_max: u32,
+) -> bool {
+    // A range of 2^32 values wraps to 0: it multiplies the output by 2^32 / 2^bits.
+    let range = range_max - range_min + 1;
+    var scaled: u32;
+    if bits == 31 {
+        scaled = select(bad_scaling(n, range), n << 1, range == 0);
+    } else {
+        // range * n / 2^15 is exact in doubles, and computed from the 16-bit halves of range.
+        let exact = (((range >> 16) * n) << 1) + (((range & 0xffff) * n) >> 15);
+        scaled = select(exact, n << 17, range == 0);
+    }
+    let randint = select(scaled + range_min, n, range_min == 0 && range_max == 0x7fffffff);
+    return randint >= match_min && randint <= match_max;
+}
+
+// Whether the output `n` of glibc's rand() called from C is in match_min..=match_max, once reduced
+// to range_min..=range_max with a modulo.
+fn modulo_slot_matches(
+    n: u32,
+    match_min: u32,
+    match_max: u32,
+    range_min: u32,
+    range_max: u32,
+) -> bool {
+    let range = range_max - range_min + 1;
+    let reduced = select(n % range, n, range == 0) + range_min;
+    let randint = select(reduced, n, range_min == 0 && range_max == 0x7fffffff);
+    return randint >= match_min && randint <= match_max;
+}
+
+// The search of glibc_main, and of c_glibc_main with `modulo`: ranges are then reduced with a
+// modulo.
+fn search_glibc(global_id: vec3<u32>, num_workgroups: vec3<u32>, modulo: bool) {
+    if aborted() {
+        return;
+    }
+    let step = input[0];
+    let offset = input[1];
+
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
+    // srandom(0) seeds like srandom(1), which is the one reported.
+    if seed == 0 {
+        return;
+    }
+    var random = glibc_srandom(seed);
+    let end = arrayLength(&input);
+    for (var i: u32 = 3; i < end; i += 4) {
+        let n = glibc_next(&random);
+        let matches = select(
+            legacy_slot_matches(n, 31, input[i], input[i + 1], input[i + 2], input[i + 3]),
+            modulo_slot_matches(n, input[i], input[i + 1], input[i + 2], input[i + 3]),
+            modulo
+        );
+        if !matches {
+            return;
+        }
+    }
+    report(seed);
+}
+
+@compute @workgroup_size(256, 1, 1)
+fn glibc_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    search_glibc(global_id, num_workgroups, false);
+}
+
+@compute @workgroup_size(256, 1, 1)
+fn c_glibc_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    search_glibc(global_id, num_workgroups, true);
+}
+
+// rand() of the Microsoft C runtime after srand(seed): the top bit of its 32-bit LCG never reaches
+// the outputs, so only the seeds below 2^31 are searched, the others return right away.
+@compute @workgroup_size(256, 1, 1)
+fn msvc_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if aborted() {
+        return;
+    }
+    let step = input[0];
+    let offset = input[1];
+
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
+    if seed >= 0x80000000 {
+        return;
+    }
+    var holdrand = seed;
+    let end = arrayLength(&input);
+    for (var i: u32 = 3; i < end; i += 4) {
+        holdrand = holdrand * 214013 + 2531011;
+        let n = (holdrand >> 16) & 0x7fff;
+        if !legacy_slot_matches(n, 15, input[i], input[i + 1], input[i + 2], input[i + 3]) {
+            return;
+        }
+    }
+    report(seed);
+}
@@ -0,0 +1,1319 @@
+use std::{
+    io,
+    io::{IsTerminal, Write},
+    ops::{Range, RangeInclusive},
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    time::{Duration, Instant, SystemTime},
+};
+
+#[cfg(target_os = "android")]
+pub mod android;
+pub mod bench;
+pub mod context;
+#[cfg(feature = "cuda")]
+pub mod cuda;
+pub mod diagnose;
+pub mod doctor;
+pub mod emit;
+pub mod engine;
+pub mod epoch;
+pub mod explain;
+pub mod flavor;
+pub mod gpu;
+pub mod gpu_info;
+pub mod hook;
+pub mod host_memory;
+pub mod job_spec;
+pub mod jobs;
+pub mod legacy_rand;
+#[cfg(feature = "webhook")]
+pub mod notify;
+#[cfg(feature = "opencl")]
+pub mod opencl;
+pub mod openwall;
+pub mod pause;
+pub mod permutation;
+pub mod php_dump;
+pub mod platform;
+pub mod potfile;
+pub mod predicate;
+pub mod presets;
+pub mod progress;
+pub mod query;
+pub mod rank;
+pub mod reference;
+pub mod replay;
+pub mod schedule;
+pub mod seed_filter;
+pub mod seed_fn;
+pub mod seed_search;
+pub mod session;
+#[cfg(feature = "sink")]
+pub mod sink;
+pub mod skip_range;
+pub mod stats;
+pub mod stop;
+pub mod syntax;
+#[cfg(feature = "syslog")]
+pub mod syslog;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+pub mod thermal;
+pub mod token;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod transform;
+pub mod unknown_max;
+pub mod utilization;
+pub mod verify_command;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(feature = "distributed")]
+pub mod work_unit;
+
+use context::GpuContext;
+use emit::Language;
+use engine::{
+    execute_subdivided, is_any_value, kernel_slot_count, prepare_engine, Engine, EngineKind,
+    ExecuteError, WORKGROUPS_PER_STEP,
+};
+use flavor::Flavor;
+use hook::Hook;
+use host_memory::{Candidates, HostMemory, Spill, Usage};
+use legacy_rand::Generator;
+#[cfg(feature = "webhook")]
+use notify::Notify;
+use platform::Platform;
+use predicate::Predicate;
+use query::{Constraint, SeedResult};
+use schedule::StepOrder;
+use seed_filter::SeedFilter;
+use seed_search::SeedSearch;
+use session::Checkpoint;
+#[cfg(feature = "sink")]
+use sink::Sink;
+use stats::MatchStats;
+use stop::Stop;
+#[cfg(feature = "syslog")]
+use syslog::Syslog;
+#[cfg(feature = "telemetry")]
+use telemetry::Telemetry;
+use thermal::Throttle;
+use transform::SeedTransform;
+use utilization::{StepTimer, Utilization};
+use verify_command::VerifyCommand;
+#[cfg(feature = "webhook")]
+use webhook::{Webhook, WebhookEvent};
+#[cfg(feature = "distributed")]
+use work_unit::WorkUnit;
+
+/// Command line options, everything that is not part of the constraints themselves.
+#[derive(Debug)]
+pub struct Options {
+    pub engine: EngineKind,
+    pub target_dispatch_ms: Option<u32>,
+    /// Workgroups of every dispatch, whatever their duration, with `--dispatch-workgroups`.
+    pub dispatch_workgroups: Option<u32>,
+    pub nice: bool,
+    pub allow_software: bool,
+    pub in_flight: usize,
+    pub bench: bool,
+    pub bench_steps: u32,
+    /// The seed whose outputs are compared with the constraints, with the `verify` subcommand.
+    pub verify: Option<u32>,
+    /// Whether the adapters are listed instead of searching, with the `gpu-info` subcommand.
+    pub gpu_info: bool,
+    /// Whether the environment is checked instead of searching, with the `doctor` subcommand.
+    pub doctor: bool,
+    /// Whether gpu-info prints JSON, with `--json`.
+    pub json: bool,
+    /// Whether the presets of `--preset` are listed instead of searching, with `--list-presets`.
+    pub list_presets: bool,
+    pub compat_openwall: bool,
+    /// Searched one after the other, several with `--php auto`.
+    pub flavors: Vec<Flavor>,
+    /// The integers of the PHP build, which decide how ranges wider than `mt_getrandmax()` are
+    /// drawn, with `--platform`.
+    pub platform: Platform,
+    /// The `mt_getrandmax()` of the target with `--getrandmax`, whose successor divides the outputs
+    /// PHP 5.2.1 to 7.0.x scales to ranges.
+    pub getrandmax: u32,
+    pub explain: bool,
+    pub dry_run: bool,
+    pub seed_filter: SeedFilter,
+    /// Bytes the buffers of all the dispatches in flight may take, with `--gpu-mem-limit`.
+    pub gpu_mem_limit: Option<u64>,
+    /// The host memory taken by the seeds found and the candidates of the CPU stage, which are
+    /// spilled to disk past `--mem-limit`.
+    pub host_memory: HostMemory,
+    /// Constraint sets searched together instead of the constraints, with `--batch`.
+    pub batch: Option<Vec<Vec<u32>>>,
+    /// Maximums the values without a range may have been drawn up to, searched together instead
+    /// of `mt_rand()` without arguments, with `--unknown-max`.
+    pub unknown_max: Option<Vec<u32>>,
+    /// Numbers of outputs that may have been drawn before the constraints, searched together, with
+    /// `--skip-range`.
+    pub skip_range: Option<RangeInclusive<u32>>,
+    /// The constraints of each way the shuffles of `--shuffle` and `--str-shuffle` may have been
+    /// done when elements repeat, searched together, with what they assume.
+    pub interpretations: Option<Vec<(String, Vec<u32>)>>,
+    /// Arguments of the searches of the command line tool, one after the other, with `crack-many`.
+    pub jobs: Option<Vec<Vec<String>>>,
+    /// Jobs of `crack-many` running at once on the same engine, with `--concurrent-jobs`.
+    pub concurrent_jobs: usize,
+    /// Where the command line tool writes the work units of the search, and how many, with
+    /// `export-work`.
+    #[cfg(feature = "distributed")]
+    pub export_work: Option<(PathBuf, u32)>,
+    /// The file of the work unit the command line tool searches, with `work`.
+    #[cfg(feature = "distributed")]
+    pub work_unit: Option<(PathBuf, WorkUnit)>,
+    /// The results files of work units the command line tool merges, with `import-results`.
+    #[cfg(feature = "distributed")]
+    pub import_results: Option<Vec<String>>,
+    /// Checked by the wgpu engine besides the constraints, with `--predicate`.
+    pub predicate: Option<Predicate>,
+    /// The `rand()` of PHP before 7.1.0 searched by the wgpu engine instead of MT19937, with
+    /// `--rand`. The flavors are then ignored.
+    pub generator: Option<Generator>,
+    /// Number of steps the seeds are searched in, a power of two up to `MAX_PARTITIONS`, with
+    /// `--partitions`.
+    pub partitions: u32,
+    /// Timestamps checked on the CPU before the search, with `--quick-epoch-pass`.
+    pub quick_epoch_pass: Option<RangeInclusive<u32>>,
+    /// Whether the command line tool leaves the potfile alone, with `--potfile-disable`.
+    pub potfile_disable: bool,
+    /// Where the command line tool keeps its progress and results, with `--session`.
+    pub session: Option<String>,
+    /// Whether the command line tool goes on with the search of a session, with `--restore`.
+    pub restore: bool,
+    /// The shortest time between two checkpoints of the progress of a session, with
+    /// `--checkpoint-interval`.
+    pub checkpoint_interval: Duration,
+    /// The checkpoint of a session whose remaining steps the command line tool searches again, in
+    /// the order recorded, with `--replay`.
+    pub replay: Option<(PathBuf, Checkpoint)>,
+    /// The order the command line tool searches the steps in, with `--step-order`.
+    pub step_order: StepOrder,
+    /// Whether the command line tool stops after the first step finding seeds, with `--first`.
+    pub first: bool,
+    /// The most seeds a search reports, with `--max-results`: it stops once it has found them, the
+    /// dispatches not submitted yet being skipped.
+    pub max_results: Option<usize>,
+    /// Whether a dispatch itself stops midway once it has found the seeds `max_results` still
+    /// needs, with `--abort-dispatches`. Which of its seeds are found then depends on the order
+    /// the GPU runs it in, so that the results are no longer reproducible.
+    pub abort_dispatches: bool,
+    /// The only steps the command line tool searches, those of the unit of `work`.
+    pub step_range: Option<Range<u32>>,
+    /// Whether progress events are written to stderr as JSON lines, with `--progress-json`.
+    pub progress_json: bool,
+    /// The shortest time between two progress events, with `--progress-interval`.
+    pub progress_interval: Duration,
+    /// The shortest time between two progress lines when stdout is not a terminal, with
+    /// `--progress-line-interval`.
+    pub progress_line_interval: Duration,
+    /// Seeds expected to match by chance over which the command line tool refuses to search,
+    /// with `--max-expected`.
+    pub max_expected: u32,
+    /// Whether to search constraints expected to match too many seeds, with `--force`.
+    pub force: bool,
+    /// Degrees Celsius the GPU is kept under by pausing between dispatches, with `--max-temp`.
+    pub max_temp: Option<u32>,
+    /// Weights of the slots the command line tool ranks the seeds found with, with `--weights`.
+    pub weights: Option<Vec<u32>>,
+    /// The survivors of each constraint, step by step, counted by `search` with `--stats`.
+    pub stats: Option<MatchStats>,
+    /// Scripts the command line tool writes to replay the seeds found, with `--emit` and
+    /// `--emit-php`.
+    pub emit: Vec<(Language, PathBuf)>,
+    /// How the command line tool gets back to the secrets behind the seeds found, with
+    /// `--seed-offset` and `--crc32-wordlist`.
+    pub seed_transforms: Vec<SeedTransform>,
+    /// Where the log goes instead of stderr, with `--log-file`.
+    pub log_file: Option<PathBuf>,
+    /// Where the command line tool sends the seeds found as they are confirmed, with `--sink`.
+    #[cfg(feature = "sink")]
+    pub sink: Option<Sink>,
+    /// Where the command line tool logs the start, the seeds found and the end of the search,
+    /// with `--syslog`.
+    #[cfg(feature = "syslog")]
+    pub syslog: Option<Syslog>,
+    /// Where the command line tool serves the progress of the search over HTTP, with
+    /// `--telemetry-port`.
+    #[cfg(feature = "telemetry")]
+    pub telemetry: Option<Telemetry>,
+    /// The address the telemetry listens on, with `--telemetry-bind`: the loopback by default.
+    #[cfg(feature = "telemetry")]
+    pub telemetry_bind: std::net::IpAddr,
+    /// Where the command line tool writes the spans of the search, with `--trace-output`.
+    #[cfg(feature = "trace")]
+    pub trace_output: Option<PathBuf>,
+    /// Where the command line tool posts a summary of the search and of the seeds found, with
+    /// `--webhook`.
+    #[cfg(feature = "webhook")]
+    pub webhook: Option<Webhook>,
+    /// Whether the webhook is also posted as soon as the first seed is found, with `--webhook-on`.
+    #[cfg(feature = "webhook")]
+    pub webhook_on: WebhookEvent,
+    /// The Slack and Discord channels the command line tool notifies once the search is done,
+    /// with `--notify`.
+    #[cfg(feature = "webhook")]
+    pub notify: Vec<Notify>,
+    /// Whether the events of the search are logged, along with how busy the GPU is kept, and the
+    /// kernels timed, with `--verbose`.
+    pub verbose: bool,
+    /// Run on each seed found, which is only reported if it succeeds, with `--verify-cmd`.
+    pub verify_command: Option<VerifyCommand>,
+    /// Run on each seed found, with `--on-seed`.
+    pub on_seed: Option<Hook>,
+    /// Run once the search is done, with `--on-finish`.
+    pub on_finish: Option<Hook>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            engine: EngineKind::default(),
+            target_dispatch_ms: None,
+            dispatch_workgroups: None,
+            nice: false,
+            allow_software: false,
+            in_flight: DEFAULT_IN_FLIGHT,
+            bench: false,
+            bench_steps: 4,
+            verify: None,
+            gpu_info: false,
+            doctor: false,
+            json: false,
+            list_presets: false,
+            compat_openwall: false,
+            flavors: vec![Flavor::default()],
+            platform: Platform::default(),
+            getrandmax: 0x7fffffff,
+            explain: false,
+            dry_run: false,
+            seed_filter: SeedFilter::default(),
+            gpu_mem_limit: None,
+            host_memory: HostMemory::default(),
+            batch: None,
+            unknown_max: None,
+            skip_range: None,
+            interpretations: None,
+            jobs: None,
+            concurrent_jobs: 1,
+            #[cfg(feature = "distributed")]
+            export_work: None,
+            #[cfg(feature = "distributed")]
+            work_unit: None,
+            #[cfg(feature = "distributed")]
+            import_results: None,
+            predicate: None,
+            generator: None,
+            verify_command: None,
+            on_seed: None,
+            on_finish: None,
+            partitions: DEFAULT_PARTITIONS,
+            quick_epoch_pass: None,
+            potfile_disable: false,
+            session: None,
+            restore: false,
+            checkpoint_interval: session::DEFAULT_CHECKPOINT_INTERVAL,
+            replay: None,
+            step_order: StepOrder::default(),
+            first: false,
+            max_results: None,
+            abort_dispatches: false,
+            step_range: None,
+            progress_json: false,
+            progress_interval: progress::DEFAULT_PROGRESS_INTERVAL,
+            progress_line_interval: progress::DEFAULT_PROGRESS_LINE_INTERVAL,
+            log_file: None,
+            #[cfg(feature = "sink")]
+            sink: None,
+            #[cfg(feature = "syslog")]
+            syslog: None,
+            #[cfg(feature = "telemetry")]
+            telemetry: None,
+            #[cfg(feature = "telemetry")]
+            telemetry_bind: telemetry::DEFAULT_BIND,
+            #[cfg(feature = "trace")]
+            trace_output: None,
+            #[cfg(feature = "webhook")]
+            webhook: None,
+            #[cfg(feature = "webhook")]
+            webhook_on: WebhookEvent::default(),
+            #[cfg(feature = "webhook")]
+            notify: Vec::new(),
+            emit: Vec::new(),
+            weights: None,
+            stats: None,
+            seed_transforms: Vec::new(),
+            max_temp: None,
+            max_expected: DEFAULT_MAX_EXPECTED,
+            force: false,
+            verbose: false,
+        }
+    }
+}
+
+/// Seeds expected to match by chance past which searches need `--force`: their output would be
+/// useless anyway, and they may run for hours reading back results.
+pub const DEFAULT_MAX_EXPECTED: u32 = 100_000;
+
+/// Steps of a search unless `--partitions` says otherwise, those of the kernels: step `s` is made
+/// of the seeds whose lowest byte is `s`.
+pub const DEFAULT_PARTITIONS: u32 = 256;
+
+/// The finest partition, whose steps are 256 workgroups of a kernel step.
+pub const MAX_PARTITIONS: u32 = 65536;
+
+/// Workgroups in all the steps of the kernels, one after the other. The steps of a search are
+/// contiguous ranges of them, whatever their number, so that seeds keep coming ordered by their
+/// lowest byte.
+const WORKGROUPS: u32 = 256 * WORKGROUPS_PER_STEP;
+
+/// Seeds in each step of a search split in `partitions` steps.
+pub fn seeds_per_step(partitions: u32) -> f64 {
+    f64::from(WORKGROUPS / partitions) * 256.0
+}
+
+/// The dispatch of at most `workgroups` workgroups from `position` among all the workgroups of
+/// the kernels, as the kernel step and its workgroups. Dispatches do not cross the end of a kernel
+/// step, nor of a step of `per_step` workgroups.
+fn next_dispatch(position: u32, per_step: u32, workgroups: u32) -> (u32, Range<u32>) {
+    let kernel_step = position / WORKGROUPS_PER_STEP;
+    let end = (position + workgroups)
+        .min((kernel_step + 1) * WORKGROUPS_PER_STEP)
+        .min((position / per_step + 1) * per_step);
+    let first = position % WORKGROUPS_PER_STEP;
+    (kernel_step, first..first + end - position)
+}
+
+/// Dispatches submitted before waiting for the first one, so that the GPU does not sit idle while
+/// results are read back.
+pub const DEFAULT_IN_FLIGHT: usize = 2;
+
+pub fn normalize_arguments(arguments: &mut Vec<u32>) {
+    let mut len = arguments.len();
+    if len % 4 == 1 {
+        arguments.push(arguments[len - 1]);
+    }
+
+    len = arguments.len();
+    if len % 4 == 2 {
+        arguments.push(0);
+        arguments.push(0x7fffffff);
+    }
+}
+
+pub fn lint_arguments(arguments: &[u32]) -> bool {
+    if arguments.is_empty() {
+        return false;
+    }
+
+    for chunk in arguments.chunks(4) {
+        match chunk {
+            &[match_min, match_max, range_min, range_max] => {
+                if match_min > match_max
+                    || range_min > range_max
+                    || match_max < range_min
+                    || match_min > range_max
+                {
+                    return false;
+                }
+            }
+            _ => return false, // if the normalized argument number isn't a multiple of 4
+        }
+    }
+
+    true
+}
+
+/// How many times a single step is retried on a freshly prepared GPU before giving up.
+const MAX_DEVICE_LOSS_RETRIES: u32 = 3;
+
+/// Dispatch duration used by `--nice` when no `--target-dispatch-ms` is given, short enough for
+/// the compositor to get GPU time at every frame or so.
+const NICE_DISPATCH_MS: u32 = 20;
+
+/// Decides how many workgroups go into each dispatch of a step.
+///
+/// Without a target the whole step is dispatched at once. With a target, the duration of each
+/// dispatch is measured and the next one is resized (by powers of two) to get close to the target,
+/// which keeps long-running kernels from triggering the driver watchdog.
+struct DispatchSizer {
+    target: Option<Duration>,
+    workgroups: u32,
+}
+
+impl DispatchSizer {
+    fn new(target_dispatch_ms: Option<u32>) -> Self {
+        match target_dispatch_ms {
+            None => DispatchSizer {
+                target: None,
+                workgroups: WORKGROUPS_PER_STEP,
+            },
+            // Start small, we do not know yet how fast the GPU is.
+            Some(ms) => DispatchSizer {
+                target: Some(Duration::from_millis(ms.into())),
+                workgroups: 256,
+            },
+        }
+    }
+
+    /// The sizer of `--target-dispatch-ms`, or of `--nice` without it, unless
+    /// `--dispatch-workgroups` fixes the size of the dispatches.
+    fn for_options(options: &Options) -> Self {
+        if let Some(workgroups) = options.dispatch_workgroups {
+            return DispatchSizer {
+                target: None,
+                workgroups,
+            };
+        }
+        match options.target_dispatch_ms {
+            None if options.nice => DispatchSizer::new(Some(NICE_DISPATCH_MS)),
+            target => DispatchSizer::new(target),
+        }
+    }
+
+    fn record(&mut self, workgroups: u32, elapsed: Duration) {
+        let Some(target) = self.target else {
+            return;
+        };
+        // Only resize from full-sized dispatches, the tail of a step is not representative.
+        if workgroups != self.workgroups {
+            return;
+        }
+        if elapsed > target && self.workgroups > 1 {
+            self.workgroups /= 2;
+        } else if elapsed * 2 < target && self.workgroups < WORKGROUPS_PER_STEP {
+            self.workgroups *= 2;
+        }
+    }
+}
+
+/// The progress line the command line tool prints after each step of `steps`, in the style of
+/// openwall's php_mt_seed with `--compat-openwall`, along with the events of `--progress-json`
+/// and the progress served with `--telemetry-port`. Without a terminal to rewrite it in, such as
+/// the logs of CI or `nohup`, it is a timestamped line every `--progress-line-interval` instead.
+/// To be given to `search` as its `on_step`.
+pub fn print_progress<'a>(
+    options: &'a Options,
+    flavors: &[Flavor],
+    steps: Range<u32>,
+) -> impl FnMut(u32, usize) + 'a {
+    let flavors = flavors.to_vec();
+    let start_time = Instant::now();
+    let mut last_event: Option<Instant> = None;
+    let lines = !io::stdout().is_terminal();
+    let mut last_line: Option<Instant> = None;
+    move |step, found| {
+        let json_line = || {
+            let seeds = seeds_per_step(options.partitions);
+            let elapsed = start_time.elapsed();
+            progress::json_line(&flavors, step, &steps, seeds, found, elapsed)
+        };
+        let due = last_event.is_none_or(|last| last.elapsed() >= options.progress_interval);
+        if options.progress_json && (due || step == steps.end) {
+            last_event = Some(Instant::now());
+            eprintln!("{}", json_line());
+        }
+        #[cfg(feature = "telemetry")]
+        if let Some(telemetry) = &options.telemetry {
+            telemetry.update(json_line());
+        }
+        if options.compat_openwall {
+            let done = step - steps.start;
+            let seeds = f64::from(done) * seeds_per_step(options.partitions);
+            let speed = seeds / start_time.elapsed().as_secs_f64();
+            print!("\r{}", openwall::progress(found, step, steps.end, speed));
+        } else if lines {
+            let due = last_line.is_none_or(|last| last.elapsed() >= options.progress_line_interval);
+            if due || step == steps.end {
+                last_line = Some(Instant::now());
+                println!(
+                    "{}",
+                    progress::log_line(SystemTime::now(), step, &steps, found)
+                );
+            }
+        } else {
+            print!("\rprogress: {:03} / {}", step, steps.end);
+        }
+        io::stdout().flush().unwrap();
+    }
+}
+
+/// Find the PHP 7.1.0+ seeds matching `constraints`, on the context of the process, for embedders
+/// which do not need to manage one:
+///
+/// ```no_run
+/// use php_mt_seed::query::Constraint;
+///
+/// let seeds = php_mt_seed::quick_find(&[Constraint::exact(1178568022)]).unwrap();
+/// ```
+///
+/// Fails on invalid constraints, and when the context cannot be prepared: no adapter, no device,
+/// or only a software rasterizer, which the default options do not allow.
+pub fn quick_find(constraints: &[Constraint]) -> Result<Vec<SeedResult>, String> {
+    constraints
+        .iter()
+        .fold(SeedSearch::new(), |search, &constraint| {
+            search.constraint(constraint)
+        })
+        .run(GpuContext::global()?)
+}
+
+/// Search the given steps, calling `on_seed` for every matching seed, and return the engine.
+///
+/// `on_step` is called after each step with the number of steps completed since 0 (so the last
+/// step searched, plus one), and the number of seeds found so far. There are
+/// `options.partitions` steps.
+///
+/// Seeds come step by step, in ascending order within each step, so that identical inputs
+/// always print identical results, unless `options.abort_dispatches` lets the GPU stop a dispatch
+/// midway.
+///
+/// `stop` counts the seeds found against `options.max_results`, and ends the search once it has
+/// found them, or after the first step finding seeds with `options.first`. A fresh one is made for
+/// each search with `Stop::new`, several searches sharing one when their seeds count together.
+///
+/// The engine is prepared again if the device gets lost on the way, the search then resumes
+/// from the last completed dispatch. Fails when it is lost too many times, when it cannot be
+/// prepared again, or when a workgroup finds more seeds than the output buffer holds.
+#[allow(clippy::too_many_arguments)]
+pub fn search(
+    options: &Options,
+    stop: &Stop,
+    engine: Arc<dyn Engine>,
+    flavor: Flavor,
+    arguments: &[u32],
+    steps: Range<u32>,
+    mut on_seed: impl FnMut(u32),
+    on_step: impl FnMut(u32, usize),
+) -> Result<Arc<dyn Engine>, String> {
+    let on_seed = |_: Flavor, seed: u32| on_seed(seed);
+    search_flavors(
+        options,
+        stop,
+        engine,
+        &[flavor],
+        arguments,
+        steps,
+        on_seed,
+        on_step,
+    )
+}
+
+/// Search the given steps for every flavor at once like `search`, calling `on_seed` with the
+/// flavor of every matching seed, and return the engine.
+///
+/// Engines whose `single_pass_flavors` is true initialize the state of each seed once for both
+/// flavors, which is about twice as fast as searching them one after the other. Seeds come step
+/// by step, then flavor by flavor, in ascending order. `on_step` is given the seeds found across
+/// flavors. Neither `--stats` nor predicates are supported, they are flavor-specific.
+pub fn search_all_flavors(
+    options: &Options,
+    stop: &Stop,
+    engine: Arc<dyn Engine>,
+    arguments: &[u32],
+    steps: Range<u32>,
+    on_seed: impl FnMut(Flavor, u32),
+    on_step: impl FnMut(u32, usize),
+) -> Result<Arc<dyn Engine>, String> {
+    assert!(
+        options.stats.is_none() && options.predicate.is_none(),
+        "--stats and predicates need a search per flavor"
+    );
+    search_flavors(
+        options,
+        stop,
+        engine,
+        &Flavor::ALL,
+        arguments,
+        steps,
+        on_seed,
+        on_step,
+    )
+}
+
+/// Seeds refined by the CPU stage, and the step they complete with how busy it kept the GPU.
+type Refined = (Option<(u32, Utilization)>, Vec<(Flavor, u32)>);
+
+/// The search of `search` and `search_all_flavors`, for a single flavor or all of them.
+#[allow(clippy::too_many_arguments)]
+fn search_flavors(
+    options: &Options,
+    stop: &Stop,
+    mut engine: Arc<dyn Engine>,
+    flavors: &[Flavor],
+    arguments: &[u32],
+    steps: Range<u32>,
+    mut on_seed: impl FnMut(Flavor, u32),
+    mut on_step: impl FnMut(u32, usize),
+) -> Result<Arc<dyn Engine>, String> {
+    let mut sizer = DispatchSizer::for_options(options);
+    // Bursts of dispatches would defeat the purpose of --nice.
+    let in_flight = if options.nice { 1 } else { options.in_flight };
+    // Slots past what the kernels handle are checked on the CPU, on the seeds matching the others.
+    let mut kernel_slots = kernel_slot_count(arguments, engine.kernel_slots());
+    if options.stats.is_some() {
+        // Fewer slots on the GPU, so that the CPU counts the survivors of the others.
+        let stats_slots = stats::kernel_slots(arguments, seeds_per_step(options.partitions));
+        kernel_slots = kernel_slots.min(stats_slots);
+    }
+    let kernel_arguments = &arguments[..4 * kernel_slots];
+    // Seeds found for every flavor at once may only match the kernel slots in one of them.
+    let checked_arguments: &[u32] = match flavors {
+        [_] => kernel_arguments,
+        _ => &[],
+    };
+    // When the CPU stage drops none of the seeds the GPU finds, a dispatch can stop as soon as it
+    // has found those `--max-results` still needs, if `--abort-dispatches` allows it.
+    let exact = options.abort_dispatches
+        && flavors.len() == 1
+        && arguments[kernel_arguments.len()..]
+            .chunks(4)
+            .all(is_any_value)
+        && options.seed_filter.is_empty()
+        && options.verify_command.is_none();
+    let per_step = workgroups_per_partition(options);
+    let mut position = steps.start * per_step;
+    let mut retries = 0;
+    let mut found = 0;
+    let mut step_timer = StepTimer::start(&*engine);
+    let mut search_timer = StepTimer::start(&*engine);
+    let mut throttle = Throttle::new(options.max_temp);
+    std::thread::scope(|scope| {
+        // The CPU stage: the seeds found by the GPU are refined on another thread while the GPU
+        // goes on with the next dispatches, and come back in the order they were sent.
+        // Past --mem-limit, the candidates are queued in a file rather than in memory.
+        let (survivors, survivors_rx) = mpsc::channel::<(Option<(u32, Utilization)>, Candidates)>();
+        let mut spill = Spill::new();
+        let mut spill_reader = spill.reader();
+        let (refined_tx, refined) = mpsc::channel();
+        scope.spawn(move || {
+            let mut step_survivors = Vec::new();
+            for (completed_step, candidates) in survivors_rx {
+                let results = options
+                    .host_memory
+                    .take(&mut spill_reader, candidates)
+                    .unwrap_or_else(|e| panic!("Cannot read the spilled candidates: {e}."));
+                if let (Some(stats), &[flavor]) = (&options.stats, flavors) {
+                    stats::count(
+                        flavor,
+                        arguments,
+                        kernel_slots,
+                        &results,
+                        &mut step_survivors,
+                    );
+                    if completed_step.is_some() {
+                        stats.record(flavor, kernel_slots, std::mem::take(&mut step_survivors));
+                    }
+                }
+                let results: Vec<(Flavor, u32)> = flavors
+                    .iter()
+                    .flat_map(|&flavor| {
+                        refine(
+                            options,
+                            flavor,
+                            arguments,
+                            checked_arguments,
+                            results.clone(),
+                        )
+                        .into_iter()
+                        .map(move |seed| (flavor, seed))
+                    })
+                    .collect();
+                if refined_tx.send((completed_step, results)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut report = |(completed_step, results): Refined| {
+            // Dispatches in flight when the search stopped may find more than it needs.
+            let results: Vec<_> = results.into_iter().filter(|_| stop.admit()).collect();
+            found += results.len();
+            options
+                .host_memory
+                .allocate(Usage::Results, 4 * results.len() as u64);
+            for (flavor, seed) in results {
+                log::info!("found seed {seed} (PHP {})", flavor.versions());
+                on_seed(flavor, seed);
+            }
+            if let Some((completed_step, utilization)) = completed_step {
+                if options.first && found > 0 {
+                    stop.stop();
+                }
+                log_step(options, flavors, completed_step, found, &utilization);
+                on_step(completed_step, found);
+            }
+        };
+
+        while position < steps.end * per_step && !stop.is_stopped() {
+            pause::wait();
+            throttle.wait();
+            // Plan the next dispatches, they are all submitted before waiting for the first one.
+            let mut batch = Vec::new();
+            let mut next_position = position;
+            while batch.len() < in_flight && next_position < steps.end * per_step {
+                let (kernel_step, workgroups) =
+                    next_dispatch(next_position, per_step, sizer.workgroups);
+                next_position += workgroups.len() as u32;
+                batch.push((kernel_step, workgroups));
+            }
+            let step = position / per_step;
+
+            let start_time = Instant::now();
+            let dispatch = tracing::info_span!("dispatch", step, dispatches = batch.len());
+            let batch_results = dispatch.in_scope(|| {
+                let limit = stop.remaining().filter(|_| exact);
+                let batch_results = match (flavors, limit) {
+                    (&[flavor], Some(limit)) => engine.execute_batch_limited(
+                        flavor,
+                        kernel_arguments,
+                        &batch,
+                        limit.try_into().unwrap_or(u32::MAX),
+                    ),
+                    (&[flavor], None) => engine.execute_batch(flavor, kernel_arguments, &batch),
+                    _ => engine.execute_batch_all_flavors(kernel_arguments, &batch),
+                };
+                match batch_results {
+                    // Which dispatch overflowed is not known, each is run again on its own, flavor
+                    // by flavor.
+                    Err(ExecuteError::Overflow) => batch
+                        .iter()
+                        .map(|(kernel_step, workgroups)| {
+                            let mut seeds = Vec::new();
+                            for &flavor in flavors {
+                                seeds.extend(execute_subdivided(
+                                    &*engine,
+                                    flavor,
+                                    kernel_arguments,
+                                    *kernel_step,
+                                    workgroups.clone(),
+                                    options.predicate.is_none() && options.generator.is_none(),
+                                )?);
+                            }
+                            seeds.sort_unstable();
+                            seeds.dedup();
+                            Ok(seeds)
+                        })
+                        .collect(),
+                    result => result,
+                }
+            });
+            match batch_results {
+                Err(ExecuteError::Overflow) => return Err(overflow_error(step)),
+                Err(ExecuteError::DeviceLost) => {
+                    if retries == MAX_DEVICE_LOSS_RETRIES {
+                        return Err(device_lost_error(step));
+                    }
+                    retries += 1;
+                    eprintln!("\rWARNING: the GPU device was lost, resuming from step {step}");
+                    engine = prepare_engine(options)?;
+                }
+                Ok(batch_results) => {
+                    let elapsed = start_time.elapsed();
+                    if options.nice {
+                        // Leave the GPU idle for as long as it was busy.
+                        std::thread::sleep(elapsed);
+                    }
+                    for ((kernel_step, workgroups), results) in batch.iter().zip(batch_results) {
+                        sizer.record(workgroups.len() as u32, elapsed / batch.len() as u32);
+                        step_timer.record(elapsed / batch.len() as u32);
+                        let end = kernel_step * WORKGROUPS_PER_STEP + workgroups.end;
+                        let completed_step = end
+                            .is_multiple_of(per_step)
+                            .then(|| (end / per_step, step_timer.finish(&*engine)));
+                        let candidates = options
+                            .host_memory
+                            .queue(&mut spill, results)
+                            .unwrap_or_else(|e| panic!("Cannot spill the candidates: {e}."));
+                        survivors.send((completed_step, candidates)).unwrap();
+                    }
+                    search_timer.record(elapsed);
+                    // Report what the CPU stage is done with, without waiting for the rest.
+                    refined.try_iter().for_each(&mut report);
+
+                    retries = 0;
+                    position = next_position;
+                }
+            }
+        }
+        drop(survivors);
+        refined.iter().for_each(report);
+        Ok(())
+    })?;
+    log_search(flavors, &search_timer.finish(&*engine));
+
+    Ok(engine)
+}
+
+/// Search several independent queries over the given steps at once, calling `on_seed` with the
+/// index of the query for every matching seed, and return the engine.
+///
+/// Engines able to do so check all the queries in each dispatch, which saves computing the state
+/// of each seed again for every query. Dispatches are sized and retried like in `search`, but
+/// are not kept in flight. `on_step` is given the seeds found across all queries.
+#[allow(clippy::too_many_arguments)]
+pub fn search_queries(
+    options: &Options,
+    stop: &Stop,
+    mut engine: Arc<dyn Engine>,
+    flavor: Flavor,
+    queries: &[Vec<u32>],
+    steps: Range<u32>,
+    mut on_seed: impl FnMut(usize, u32),
+    mut on_step: impl FnMut(u32, usize),
+) -> Result<Arc<dyn Engine>, String> {
+    let mut sizer = DispatchSizer::for_options(options);
+    let per_step = workgroups_per_partition(options);
+    let mut position = steps.start * per_step;
+    let mut retries = 0;
+    let mut found = 0;
+    let mut step_timer = StepTimer::start(&*engine);
+    let mut search_timer = StepTimer::start(&*engine);
+    let mut throttle = Throttle::new(options.max_temp);
+    while position < steps.end * per_step && !stop.is_stopped() {
+        pause::wait();
+        throttle.wait();
+        let kernel_slots = engine.kernel_slots();
+        let kernel_queries: Vec<&[u32]> = queries
+            .iter()
+            .map(|arguments| &arguments[..4 * kernel_slot_count(arguments, kernel_slots)])
+            .collect();
+        let (kernel_step, workgroups) = next_dispatch(position, per_step, sizer.workgroups);
+        let step = position / per_step;
+
+        let start_time = Instant::now();
+        let dispatch = workgroups.clone();
+        let query_results =
+            match engine.execute_queries(flavor, &kernel_queries, kernel_step, dispatch) {
+                Err(ExecuteError::Overflow) => kernel_queries
+                    .iter()
+                    .map(|arguments| {
+                        execute_subdivided(
+                            &*engine,
+                            flavor,
+                            arguments,
+                            kernel_step,
+                            workgroups.clone(),
+                            options.predicate.is_none() && options.generator.is_none(),
+                        )
+                    })
+                    .collect(),
+                result => result,
+            };
+        match query_results {
+            Err(ExecuteError::Overflow) => return Err(overflow_error(step)),
+            Err(ExecuteError::DeviceLost) => {
+                if retries == MAX_DEVICE_LOSS_RETRIES {
+                    return Err(device_lost_error(step));
+                }
+                retries += 1;
+                eprintln!("\rWARNING: the GPU device was lost, resuming from step {step}");
+                engine = prepare_engine(options)?;
+            }
+            Ok(query_results) => {
+                let elapsed = start_time.elapsed();
+                if options.nice {
+                    std::thread::sleep(elapsed);
+                }
+                sizer.record(workgroups.len() as u32, elapsed);
+                step_timer.record(elapsed);
+                search_timer.record(elapsed);
+                for (i, results) in query_results.into_iter().enumerate() {
+                    let mut results =
+                        refine(options, flavor, &queries[i], kernel_queries[i], results);
+                    results.retain(|_| stop.admit());
+                    found += results.len();
+                    for seed in results {
+                        log::info!(
+                            "query {}: found seed {seed} (PHP {})",
+                            i + 1,
+                            flavor.versions()
+                        );
+                        on_seed(i, seed);
+                    }
+                }
+                retries = 0;
+                position += workgroups.len() as u32;
+                if position.is_multiple_of(per_step) {
+                    if options.first && found > 0 {
+                        stop.stop();
+                    }
+                    let utilization = step_timer.finish(&*engine);
+                    log_step(options, &[flavor], position / per_step, found, &utilization);
+                    on_step(position / per_step, found);
+                }
+            }
+        }
+    }
+    log_search(&[flavor], &search_timer.finish(&*engine));
+
+    Ok(engine)
+}
+
+/// Workgroups in each of the `options.partitions` steps.
+fn workgroups_per_partition(options: &Options) -> u32 {
+    assert!(
+        options.partitions.is_power_of_two() && options.partitions <= MAX_PARTITIONS,
+        "the seeds are searched in a power of two of steps, up to {MAX_PARTITIONS}"
+    );
+    WORKGROUPS / options.partitions
+}
+
+/// The versions of the `flavors` searched, for the log.
+fn versions(flavors: &[Flavor]) -> String {
+    let versions: Vec<&str> = flavors.iter().map(|flavor| flavor.versions()).collect();
+    versions.join(" and ")
+}
+
+/// Log how long the step just completed took, and how busy it kept the GPU.
+fn log_step(
+    options: &Options,
+    flavors: &[Flavor],
+    completed_step: u32,
+    found: usize,
+    utilization: &Utilization,
+) {
+    log::info!(
+        "PHP {} step {completed_step}/{} done in {:.3?}, {found} seeds found so far, {utilization}",
+        versions(flavors),
+        options.partitions,
+        utilization.elapsed
+    );
+}
+
+/// Log how busy a whole search kept the GPU, and what held it back.
+fn log_search(flavors: &[Flavor], utilization: &Utilization) {
+    log::info!(
+        "PHP {} search done in {:.3?}, {utilization}, limited by {}",
+        versions(flavors),
+        utilization.elapsed,
+        utilization.bottleneck()
+    );
+}
+
+/// The error of a workgroup whose seeds overflow the output buffer while a predicate or a
+/// generator of `--rand` keeps it on the GPU, the only cases where dispatches cannot be split
+/// until their results fit.
+fn overflow_error(step: u32) -> String {
+    format!(
+        "more seeds matched in a single workgroup of step {step} than the GPU can transfer to \
+         the CPU, and neither predicates nor --rand are searched on the CPU: tighten the \
+         constraints or raise --gpu-mem-limit"
+    )
+}
+
+/// The error of a device lost `MAX_DEVICE_LOSS_RETRIES` times in a row during `step`.
+fn device_lost_error(step: u32) -> String {
+    format!("the GPU device was lost too many times during step {step}")
+}
+
+/// The seeds of `results` matching the slots the kernel did not check, the seed filter and the
+/// command of `--verify-cmd`, in ascending order as the GPU appends results in whatever order its
+/// threads finish.
+fn refine(
+    options: &Options,
+    flavor: Flavor,
+    arguments: &[u32],
+    kernel_arguments: &[u32],
+    results: Vec<u32>,
+) -> Vec<u32> {
+    let candidates = results.len();
+    let _span = tracing::info_span!("verify", php = %flavor.name(), candidates).entered();
+    let unchecked = &arguments[kernel_arguments.len()..];
+    let results = if unchecked.chunks(4).any(|slot| !is_any_value(slot)) {
+        match options.generator {
+            Some(generator) => legacy_rand::verify_candidates(generator, arguments, &results),
+            None => reference::verify_candidates(flavor, arguments, &results),
+        }
+    } else {
+        results
+    };
+    let mut results: Vec<u32> = results
+        .into_iter()
+        .filter(|&seed| options.seed_filter.allows(seed))
+        .collect();
+    results.sort_unstable();
+    if let Some(verify_command) = &options.verify_command {
+        results.retain(|&seed| verify_command.accepts(seed, flavor));
+    }
+    results
+}
+
+#[test]
+fn test_lint_wide_range() {
+    // 64-bit PHP takes ranges up to 2^32 values.
+    let arguments = vec![
+        1395647406, 1395647406, 0, 4294967295, 3472777710, 3472777710, 0, 4294967295, 4039049869,
+        4039049869, 0, 4294967295,
+    ];
+    assert!(lint_arguments(&arguments));
+    assert!(!lint_arguments(&[3000000000, 3000000000, 0, 0x7fffffff]));
+}
+
+#[test]
+fn test_lint_more_slots_than_the_kernels() {
+    let arguments = [0, 0, 0, 61].repeat(engine::KERNEL_SLOTS + 4);
+    assert!(lint_arguments(&arguments));
+}
+
+#[test]
+fn test_search_order_is_deterministic() {
+    let options = Options {
+        allow_software: true,
+        ..Options::default()
+    };
+    let mut arguments = vec![0, 99, 0, 999999];
+    normalize_arguments(&mut arguments);
+    let run = || {
+        let mut seeds = Vec::new();
+        let engine = prepare_engine(&options).unwrap();
+        let on_seed = |seed| seeds.push(seed);
+        search(
+            &options,
+            &Stop::default(),
+            engine,
+            Flavor::Php71,
+            &arguments,
+            3..4,
+            on_seed,
+            |_, _| {},
+        )
+        .unwrap();
+        seeds
+    };
+    let seeds = run();
+    assert!(seeds.len() > 1000);
+    assert!(seeds.is_sorted());
+    assert_eq!(run(), seeds);
+}
+
+#[test]
+fn test_next_dispatch() {
+    // 64 steps of 4 kernel steps, dispatched one kernel step at a time at most
+    let per_step = WORKGROUPS / 64;
+    assert_eq!(
+        next_dispatch(0, per_step, 1 << 20),
+        (0, 0..WORKGROUPS_PER_STEP)
+    );
+    assert_eq!(
+        next_dispatch(WORKGROUPS_PER_STEP + 100, per_step, 1 << 20),
+        (1, 100..WORKGROUPS_PER_STEP)
+    );
+    // 4096 steps of 4096 workgroups, 16 per kernel step
+    let per_step = WORKGROUPS / 4096;
+    assert_eq!(next_dispatch(4096 * 17, per_step, 65536), (1, 4096..8192));
+    assert_eq!(
+        next_dispatch(4096 * 17 + 1000, per_step, 1024),
+        (1, 5096..6120)
+    );
+}
+
+#[test]
+fn test_search_partitions() {
+    let options = Options {
+        allow_software: true,
+        partitions: MAX_PARTITIONS,
+        ..Options::default()
+    };
+    // Step 1 is the second 256 workgroups of kernel step 0, with seed 256 * 256 * 256 + 0.
+    let mut mt = reference::Mt19937::new(1 << 24, Flavor::Php71);
+    let first = mt.next_u32() >> 1;
+    let mut seeds = Vec::new();
+    let mut steps = Vec::new();
+    search(
+        &options,
+        &Stop::default(),
+        prepare_engine(&options).unwrap(),
+        Flavor::Php71,
+        &[first, first, 0, 0x7fffffff],
+        1..2,
+        |seed| seeds.push(seed),
+        |step, found| steps.push((step, found)),
+    )
+    .unwrap();
+    assert_eq!(seeds, vec![1 << 24]);
+    assert_eq!(steps, vec![(2, 1)]);
+}
+
+#[test]
+fn test_search_stops_at_max_results() {
+    let options = Options {
+        allow_software: true,
+        partitions: MAX_PARTITIONS,
+        max_results: Some(5),
+        ..Options::default()
+    };
+    let stop = Stop::new(options.max_results);
+    // About 256 seeds of each step match.
+    let mut seeds = Vec::new();
+    let mut steps = Vec::new();
+    let engine = search(
+        &options,
+        &stop,
+        prepare_engine(&options).unwrap(),
+        Flavor::Php71,
+        &[0, 0x7fffff, 0, 0x7fffffff],
+        0..16,
+        |seed| seeds.push(seed),
+        |step, found| steps.push((step, found)),
+    )
+    .unwrap();
+    assert_eq!(seeds.len(), 5);
+    assert!(stop.is_stopped());
+    // Only the dispatches in flight when it stopped went on, the other steps were skipped.
+    assert!(steps.len() <= options.in_flight);
+
+    // The next search of the same options starts over, and keeps the same seeds.
+    let mut again = Vec::new();
+    let engine = search(
+        &options,
+        &Stop::new(options.max_results),
+        engine,
+        Flavor::Php71,
+        &[0, 0x7fffff, 0, 0x7fffffff],
+        0..16,
+        |seed| again.push(seed),
+        |_, _| {},
+    )
+    .unwrap();
+    assert_eq!(again, seeds);
+
+    // Dispatches stopped midway still find as many seeds, whichever the GPU ran first.
+    let options = Options {
+        abort_dispatches: true,
+        ..options
+    };
+    let mut aborted = Vec::new();
+    search(
+        &options,
+        &Stop::new(options.max_results),
+        engine,
+        Flavor::Php71,
+        &[0, 0x7fffff, 0, 0x7fffffff],
+        0..16,
+        |seed| aborted.push(seed),
+        |_, _| {},
+    )
+    .unwrap();
+    assert_eq!(aborted.len(), 5);
+}
+
+#[test]
+fn test_search_all_flavors() {
+    let options = Options {
+        allow_software: true,
+        partitions: MAX_PARTITIONS,
+        ..Options::default()
+    };
+    // The first output of seed 2^24 differs between the flavors.
+    for flavor in Flavor::ALL {
+        let mut mt = reference::Mt19937::new(1 << 24, flavor);
+        let first = mt.next_u32() >> 1;
+        let mut seeds = Vec::new();
+        let mut steps = Vec::new();
+        search_all_flavors(
+            &options,
+            &Stop::default(),
+            prepare_engine(&options).unwrap(),
+            &[first, first, 0, 0x7fffffff],
+            1..2,
+            |flavor, seed| seeds.push((flavor, seed)),
+            |step, found| steps.push((step, found)),
+        )
+        .unwrap();
+        assert_eq!(seeds, vec![(flavor, 1 << 24)]);
+        assert_eq!(steps, vec![(2, 1)]);
+    }
+}
+
+#[test]
+fn test_search_queries() {
+    let options = Options {
+        allow_software: true,
+        ..Options::default()
+    };
+    // Overflows the output region of its query, so it is executed again on its own.
+    let mut many = vec![0, 99, 0, 999999];
+    normalize_arguments(&mut many);
+    let mut mt = reference::Mt19937::new(3 + 256 * 42, Flavor::Php71);
+    let (first, second) = (mt.next_u32() >> 1, mt.next_u32() >> 1);
+    let queries = vec![
+        many.clone(),
+        vec![first, first, 0, 0x7fffffff, second, second, 0, 0x7fffffff],
+        vec![first, first, 0, 0x7fffffff, 0, 0, 0, 0x7fffffff],
+    ];
+
+    let mut found = vec![Vec::new(); queries.len()];
+    let engine = prepare_engine(&options).unwrap();
+    let engine = search_queries(
+        &options,
+        &Stop::default(),
+        engine,
+        Flavor::Php71,
+        &queries,
+        3..4,
+        |query, seed| found[query].push(seed),
+        |_, _| {},
+    )
+    .unwrap();
+    let mut seeds = Vec::new();
+    search(
+        &options,
+        &Stop::default(),
+        engine,
+        Flavor::Php71,
+        &many,
+        3..4,
+        |seed| seeds.push(seed),
+        |_, _| {},
+    )
+    .unwrap();
+    assert_eq!(found[0], seeds);
+    assert!(found[1].contains(&(3 + 256 * 42)));
+    assert!(found[2].is_empty());
+}
+
+#[test]
+fn test_search_refines_on_the_cpu() {
+    let options = Options {
+        allow_software: true,
+        ..Options::default()
+    };
+    // The GPU only checks the first slot, the skipped ones after it cost nothing, and the last
+    // one is past what the kernels handle.
+    let mut mt = reference::Mt19937::new(0, Flavor::Php71);
+    let outputs: Vec<u32> = (0..=engine::KERNEL_SLOTS)
+        .map(|_| mt.next_u32() >> 1)
+        .collect();
+    let mut arguments = vec![outputs[0], outputs[0], 0, 0x7fffffff];
+    arguments.extend([0, 0x7fffffff, 0, 0x7fffffff].repeat(engine::KERNEL_SLOTS - 1));
+    arguments.extend([
+        outputs[engine::KERNEL_SLOTS],
+        outputs[engine::KERNEL_SLOTS],
+        0,
+        0x7fffffff,
+    ]);
+    assert_eq!(kernel_slot_count(&arguments, engine::KERNEL_SLOTS), 1);
+
+    let mut seeds = Vec::new();
+    let mut steps = Vec::new();
+    search(
+        &options,
+        &Stop::default(),
+        prepare_engine(&options).unwrap(),
+        Flavor::Php71,
+        &arguments,
+        0..1,
+        |seed| seeds.push(seed),
+        |step, found| steps.push((step, found)),
+    )
+    .unwrap();
+    assert_eq!(seeds, vec![0]);
+    assert_eq!(steps, vec![(1, 1)]);
+}
@@ -1,396 +1,2954 @@
-use std::{io, io::Write, num::NonZeroU64, str::FromStr};
-use wgpu::util::DeviceExt;
+use std::{
+    io,
+    io::{IsTerminal, Read},
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use php_mt_seed::context::GpuContext;
+use php_mt_seed::doctor::{self, Status};
+use php_mt_seed::emit::{self, Language};
+use php_mt_seed::engine::{prepare_engine, Engine, EngineKind, WORKGROUPS_PER_STEP};
+use php_mt_seed::flavor::{self, format_seed, Flavor};
+use php_mt_seed::hook::Hook;
+use php_mt_seed::host_memory::HostMemory;
+use php_mt_seed::legacy_rand::Generator;
+#[cfg(feature = "webhook")]
+use php_mt_seed::notify::Notify;
+use php_mt_seed::permutation::{self, Original};
+use php_mt_seed::platform::{self, Platform, WideCall};
+use php_mt_seed::potfile::{self, Potfile};
+use php_mt_seed::predicate::Predicate;
+use php_mt_seed::presets::{self, Preset};
+use php_mt_seed::schedule::{self, StepOrder};
+use php_mt_seed::seed_fn::{self, SeedFunction};
+use php_mt_seed::session::{self, Checkpoint, Session, DEFAULT_SESSION};
+#[cfg(feature = "sink")]
+use php_mt_seed::sink::{Sink, SinkAddress};
+use php_mt_seed::stats::MatchStats;
+use php_mt_seed::stop::Stop;
+#[cfg(feature = "syslog")]
+use php_mt_seed::syslog::Syslog;
+#[cfg(feature = "telemetry")]
+use php_mt_seed::telemetry::Telemetry;
+#[cfg(feature = "trace")]
+use php_mt_seed::trace;
+use php_mt_seed::transform::{self, SeedTransform};
+use php_mt_seed::verify_command::VerifyCommand;
+#[cfg(feature = "webhook")]
+use php_mt_seed::webhook::{Webhook, WebhookEvent, WebhookUrl};
+#[cfg(feature = "distributed")]
+use php_mt_seed::work_unit::{self, WorkUnit};
+use php_mt_seed::{
+    bench, diagnose, epoch, gpu_info, job_spec, jobs, rank, skip_range, unknown_max,
+};
+use php_mt_seed::{
+    explain, lint_arguments, normalize_arguments, openwall, php_dump, print_progress, reference,
+    replay, search, search_all_flavors, search_queries, seed_filter, seeds_per_step, syntax, token,
+    Options, MAX_PARTITIONS,
+};
 
 fn print_usage() {
     println!(
-        "Usage: php_mt_seed.rs VALUE_OR_MATCH_MIN [MATCH_MAX [RANGE_MIN RANGE_MAX]] ...\n\n\
-         This tool is similar to openwall's php_mt_seed, though php_mt_seed.rs only supports PHP 7.1.0+\n\
+        "Usage: php_mt_seed.rs [OPTIONS] VALUE_OR_MATCH_MIN [MATCH_MAX [RANGE_MIN RANGE_MAX]] ...\n\
+         \x20      php_mt_seed.rs bench [--steps N] [OPTIONS] [VALUE_OR_MATCH_MIN ...]\n\
+         \x20      php_mt_seed.rs crack-many FILE [OPTIONS]\n\
+         \x20      php_mt_seed.rs verify SEED [OPTIONS] VALUE_OR_MATCH_MIN ...\n\
+         \x20      php_mt_seed.rs gpu-info [--json]\n\
+         \x20      php_mt_seed.rs doctor [--engine ENGINE] [--allow-software]\n\
+         \x20      php_mt_seed.rs run JOB [OPTIONS]\n\
+         \x20      php_mt_seed.rs export-work DIR UNITS [OPTIONS] VALUE_OR_MATCH_MIN ...\n\
+         \x20      php_mt_seed.rs work UNIT [OPTIONS]\n\
+         \x20      php_mt_seed.rs import-results RESULTS...\n\n\
+         The bench subcommand measures how many seeds per second are tested, end-to-end and, when\n\
+         the adapter supports timestamp queries, in the kernel alone (default: 4 steps), and\n\
+         compares them with the CPU.\n\n\
+         The crack-many subcommand runs the independent searches of FILE one after the other, on\n\
+         the same GPU, then sums them up: one per line, written like on the command line after the\n\
+         OPTIONS shared by all of them, or one per YAML document (`---` separators) listing the\n\
+         arguments (`- --token`, `- abc`, ...).\n\n\
+         The verify subcommand replays the calls of the constraints for SEED on the CPU, and prints\n\
+         its outputs next to the values expected, in green when they match and in red when they do\n\
+         not, along with the first call diverging (exit status 1 if one does).\n\n\
+         The gpu-info subcommand lists every adapter with its backend, driver, downlevel flags and\n\
+         the limits the search depends on, and whether it is usable (exit status 1 if none is),\n\
+         as a JSON array with --json.\n\n\
+         The doctor subcommand checks the adapters, runs each kernel on a known seed, compares how\n\
+         long a step takes with the driver watchdog, and prints what to do about every problem\n\
+         found (exit status 1 if the search cannot run).\n\n\
+         The run subcommand runs the search described by the YAML or JSON file JOB: its keys are\n\
+         the long options (`first: true`, `charset: abc`, `token: [a?c, bca]`), along with `php`,\n\
+         `constraints` (`- 5-9@0-61`, `- skip 3`, ...) and `seeds` (MIN-MAX, checked on the CPU).\n\n\
+         The export-work subcommand splits the search in UNITS work units, files written to DIR\n\
+         with its arguments and a range of its steps, for machines without any connection to each\n\
+         other. The work subcommand searches the steps of UNIT, and writes the seeds found next to\n\
+         it, in a .results file. The import-results subcommand merges the results files of a\n\
+         search, and prints the steps no unit covered (exit status 1 if some are missing).\n\n\
+         A constraint can also be written MATCH_MIN-MATCH_MAX@RANGE_MIN-RANGE_MAX (e.g. 7505@1000-10000\n\
+         or 10-20), with a tolerance as VALUE~DELTA (7505~10@1000-10000 is 7495-7515@1000-10000),\n\
+         and repeated with *N: `0 0 0 61 *32` is the same constraint 32 times.\n\n\
+         Options:\n\
+         \x20 --from-php-dump FILE      add the integers of a pasted var_dump()/print_r() output as\n\
+         \x20                          exact constraints, after the others (- reads stdin)\n\
+         \x20 --dump-range MIN-MAX      range the dumped values or numeric tokens were drawn from\n\
+         \x20 --charset CHARS           tokens are drawn character by character from CHARS, with\n\
+         \x20                          CHARS[mt_rand(0, len - 1)]\n\
+         \x20 --charset-bytes           draw the bytes of CHARS rather than its characters, like\n\
+         \x20                          $chars[mt_rand(0, strlen($chars) - 1)] with non-ASCII\n\
+         \x20                          alphabets (bytes of tokens as \\xNN)\n\
+         \x20 --ignore-case             each character of the tokens matches both its cases in\n\
+         \x20                          CHARS, for tokens seen lowercased\n\
+         \x20 --mask MASK               tokens are drawn position by position from the charsets of\n\
+         \x20                          the hashcat mask MASK, such as ?d?d?d?d?l?l (?l, ?u, ?d, ?h,\n\
+         \x20                          ?H, ?s, ?a, ?? and literals)\n\
+         \x20 --shuffle A,B,...         tokens are the array A,B,... after shuffle(), written\n\
+         \x20                          with their elements separated by commas (PHP 7.1+)\n\
+         \x20 --str-shuffle STRING      tokens are STRING after str_shuffle() (PHP 7.1+); in both,\n\
+         \x20                          ? is an element not seen\n\
+         \x20 --token TOKEN             add the constraints of a token, after the others, ? masking\n\
+         \x20                          the characters which are not known\n\
+         \x20 --import-tokens FILE      search a token of a Burp Sequencer export (one per line)\n\
+         \x20 --token-regex REGEX       ...or of any capture, such as HTTP responses: tokens are the\n\
+         \x20                          matches of REGEX, or of its first group\n\
+         \x20 --token-index N           which imported token to search (default: 1, the first)\n\
+         \x20 --preset NAME             search the tokens of a common generator, such as\n\
+         \x20                          captcha-6digit or token-alnum-32: its range or charset,\n\
+         \x20                          which the other options override\n\
+         \x20 --list-presets            list the presets with the PHP code they stand for\n\
+         \x20 --infer-charset           propose a charset from the imported tokens, and use it\n\
+         \x20 --exclude-seeds FILE      leave the seeds of FILE out of the results, such as seeds\n\
+         \x20                          ruled out in a previous session (one per line, or the\n\
+         \x20                          output of a previous run)\n\
+         \x20 --only-seeds FILE         only check the seeds of FILE, candidates from another tool,\n\
+         \x20                          on the CPU instead of searching every seed\n\
+         \x20 --seed-fn EXPR            only check the seeds the target can reach with\n\
+         \x20                          mt_srand(EXPR), such as 'x * 1000 + pid', on the CPU\n\
+         \x20 --seed-var NAME=MIN-MAX   range of the variable NAME of --seed-fn\n\
+         \x20 --batch FILE              search the constraint sets of FILE, one per line written\n\
+         \x20                          like on the command line, together in each dispatch\n\
+         \x20 --unknown-max LIST        the values without a range come from mt_rand(0, N), N being\n\
+         \x20                          one of LIST (commas, or alphabets for 9,15,25,35,61,63):\n\
+         \x20                          search every N at once\n\
+         \x20 --skip-range MIN..MAX     between MIN and MAX mt_rand() calls, an unknown number, were\n\
+         \x20                          made before the constraints: search every count at once\n\
+         \x20 --predicate FILE          also call the WGSL function `fn matches(outputs: array<u32,\n\
+         \x20                          N>) -> bool` of FILE on the GPU, with the first N raw\n\
+         \x20                          MT19937 outputs of the seeds matching the constraints\n\
+         \x20                          (wgpu engine only, constraints are then optional)\n\
+         \x20 --rand GENERATOR          search the seeds of rand() before PHP 7.1.0 instead: glibc\n\
+         \x20                          (random() on Linux) or msvc (the LCG of Windows), its\n\
+         \x20                          values going up to getrandmax(), or of glibc's rand()\n\
+         \x20                          called from C with c, ranges being `MIN + rand() % (MAX -\n\
+         \x20                          MIN + 1)` (wgpu engine only)\n\
+         \x20 --verify-cmd COMMAND      only report the seeds for which the shell command COMMAND\n\
+         \x20                          succeeds, {{seed}} being replaced by the seed and {{php}} by\n\
+         \x20                          its flavor (e.g. 'php check.php {{seed}}')\n\
+         \x20 --on-seed COMMAND         run the shell command COMMAND on each seed found, such as a\n\
+         \x20                          notification, with {{seed}} and {{php}} like --verify-cmd\n\
+         \x20 --on-finish COMMAND       run COMMAND once the search is done, {{found}} being replaced\n\
+         \x20                          by the number of seeds found, {{seeds}} by them and\n\
+         \x20                          {{seconds}} by how long it took\n\
+         \x20 --engine ENGINE           compute backend: wgpu (default), opencl or cuda when built\n\
+         \x20                          with the `opencl` or `cuda` feature (cuda is then the\n\
+         \x20                          default, and falls back to wgpu without a CUDA device), or\n\
+         \x20                          reference, a very slow CPU implementation without any GPU\n\
+         \x20 --php FLAVOR              mt_rand() to search: 7.1 (PHP 7.1.0+, default), 5.2.1\n\
+         \x20                          (PHP 5.2.1 to 7.0.x, HHVM, and MT_RAND_PHP since 7.1.0), or\n\
+         \x20                          auto for both, which tells which one the target runs\n\
+         \x20 --platform BITS           the PHP build: 64 (default), whose mt_rand(MIN, MAX) takes\n\
+         \x20                          MAX up to PHP_INT_MAX (ranges of more than 2^32 values need\n\
+         \x20                          --php 7.1 or 5.2.1), or 32, whose MAX stops at 2147483647\n\
+         \x20 --getrandmax N            mt_getrandmax() of the target, for builds and emulations\n\
+         \x20                          where it is not 2147483647 (needs --php 5.2.1, whose ranges\n\
+         \x20                          divide the outputs by N + 1)\n\
+         \x20 --explain                 print how each constraint is checked, and how selective it\n\
+         \x20                          is, before searching\n\
+         \x20 --dry-run                 only check the constraints and print them along with the\n\
+         \x20                          plan, without touching the GPU (exit status 1 if invalid)\n\
+         \x20 --target-dispatch-ms MS  split each step into GPU dispatches of about MS milliseconds,\n\
+         \x20                          to stay under the driver watchdog (TDR) of desktop GPUs\n\
+         \x20 --dispatch-workgroups N   split each step into GPU dispatches of N workgroups of 256\n\
+         \x20                          seeds, whatever their duration (up to 65536, a whole step)\n\
+         \x20 --partitions N            search the seeds in N steps instead of 256, a power of two\n\
+         \x20                          up to 65536: fewer on large GPUs, more on small ones\n\
+         \x20 --step-order ORDER        search the steps in order (sequential, default), in random\n\
+         \x20                          order, or closest to the current time first (time, needs\n\
+         \x20                          --partitions 512 or more), seeds then come unordered\n\
+         \x20 --quick-epoch-pass WINDOW first check the timestamps of WINDOW (FROM:TO, or back\n\
+         \x20                          from now like 12h or 30d) and the seeds of microtime(),\n\
+         \x20                          on the CPU, then search the other seeds\n\
+         \x20 --session NAME            keep the progress and results in the session NAME\n\
+         \x20 --restore                 go on with the search of the session (default:\n\
+         \x20                          php_mt_seed_rs), with its arguments\n\
+         \x20 --checkpoint-interval MS  save the progress of the session at most every MS\n\
+         \x20                          milliseconds (default: 5000)\n\
+         \x20 --replay CHECKPOINT       search again the steps the progress file of a session had\n\
+         \x20                          left, with its arguments, in the order recorded and in\n\
+         \x20                          dispatches of a whole step, to reproduce missed seeds\n\
+         \x20 --potfile-disable         search again even if the potfile has the answer, and do\n\
+         \x20                          not add it there\n\
+         \x20 --emit-php FILE           write to FILE a PHP script replaying the mt_rand() calls for\n\
+         \x20                          each seed found, to check them on the PHP of the target\n\
+         \x20 --emit LANG FILE          ...or a script in LANG: php, or rust or python, which\n\
+         \x20                          reimplement mt_rand() where PHP is not available\n\
+         \x20 --weights LIST            rank the seeds found by the weights of the constraints they\n\
+         \x20                          hit exactly, one per constraint separated by commas (1 past\n\
+         \x20                          them), with their scores\n\
+         \x20 --stats                   count how many candidates pass each constraint, step by\n\
+         \x20                          step, and print them as a histogram after the search\n\
+         \x20 --seed-offset N           also print the $base of mt_srand($base + N) for each seed\n\
+         \x20 --crc32-wordlist FILE     also print the lines of FILE whose crc32() is a seed found,\n\
+         \x20                          for mt_srand(crc32($secret))\n\
+         \x20 --first                   stop after the first step finding seeds\n\
+         \x20 --max-results N           stop once N seeds are found, without waiting for the\n\
+         \x20                          steps and flavors left\n\
+         \x20 --abort-dispatches        with --max-results, also stop the dispatch finding the\n\
+         \x20                          last seeds midway, at the cost of reproducible results\n\
+         \x20 --max-expected N          refuse to search constraints expected to match more than N\n\
+         \x20                          seeds by chance (default: 100000)\n\
+         \x20 --force                   search them anyway\n\
+         \x20 --concurrent-jobs N       run N jobs of crack-many at once on the GPU (default: 1)\n\
+         \x20 --in-flight N             number of dispatches queued on the GPU at once (default: 2)\n\
+         \x20 --gpu-mem-limit SIZE      bound the buffers of the wgpu engine to SIZE bytes (K, M or G\n\
+         \x20                          suffixes), by shrinking their result capacity, to share the\n\
+         \x20                          GPU with other workloads\n\
+         \x20 --mem-limit SIZE          keep the candidates waiting to be verified on the CPU in a\n\
+         \x20                          temporary file past SIZE bytes of host memory, and print\n\
+         \x20                          its peak\n\
+         \x20 --nice                    use short dispatches and let the GPU rest between them, so\n\
+         \x20                          that the desktop stays responsive (slower)\n\
+         \x20 --max-temp C              pause between dispatches while the GPU is over C degrees\n\
+         \x20                          Celsius, until it cools down 5 degrees (NVML with the `nvml`\n\
+         \x20                          feature, sysfs sensors on Linux)\n\
+         \x20 --allow-software          run even if the only adapter is a software rasterizer\n\
+         \x20                          (llvmpipe, SwiftShader, ...), which is extremely slow\n\
+         \x20 --progress-json           also write the progress to stderr as JSON lines (step,\n\
+         \x20                          fraction, rate in seeds/s, eta in seconds) for wrappers\n\
+         \x20 --progress-interval MS    write them at most every MS milliseconds (default: 1000)\n\
+         \x20 --progress-line-interval MS without a terminal, print the progress as timestamped\n\
+         \x20                          lines, at most every MS milliseconds (default: 10000)\n\
+         \x20 --log-file FILE           write the log to FILE instead of stderr, with timestamps:\n\
+         \x20                          adapter, step timings, overflows and seeds found\n\
+         \x20 --sink ADDRESS            send each seed found as a JSON line as soon as it is\n\
+         \x20                          confirmed, to tcp://HOST:PORT or unix:PATH\n\
+         \x20 --syslog                  also log the start, the seeds found and the end of the\n\
+         \x20                          search to journald or syslog, with structured fields\n\
+         \x20 --telemetry-port N        serve the progress as JSON over HTTP on port N (step, rate,\n\
+         \x20                          eta, seeds found), for remote monitoring\n\
+         \x20 --telemetry-bind ADDR     listen on ADDR for --telemetry-port, such as 0.0.0.0 for\n\
+         \x20                          every interface (default: 127.0.0.1, this machine only)\n\
+         \x20 --webhook URL             POST a JSON summary of the search and of the seeds found\n\
+         \x20                          to URL once it ends (with curl)\n\
+         \x20 --webhook-on EVENT        finish (default), or first-hit to also POST as soon as the\n\
+         \x20                          first seed is found\n\
+         \x20 --notify CHANNEL          post the duration and the seeds found to a chat channel\n\
+         \x20                          once the search ends, slack://T000/B000/XXXX or\n\
+         \x20                          discord://ID/TOKEN (with curl, repeatable)\n\
+         \x20 -v, --verbose             log the events of the search to stderr, with how busy the\n\
+         \x20                          GPU is kept and whether the kernels or the host limit it\n\
+         \x20 --trace-output FILE       write the spans of the search (prepare, dispatch, readback,\n\
+         \x20                          verify) to FILE, in the Chrome trace format\n\
+         \x20 --compat-openwall         read the remaining arguments exactly like openwall's\n\
+         \x20                          php_mt_seed, and print its Pattern/Found lines (last option)\n\n\
+         This tool is similar to openwall's php_mt_seed, though php_mt_seed.rs only supports PHP 5.2.1+\n\
          Have a look at openwall's php_mt_seed documentation for more information on CLI arguments:\n\
          - https://www.openwall.com/php_mt_seed/README\n\
          - https://github.com/openwall/php_mt_seed"
     );
 }
 
-fn get_arguments() -> Vec<u32> {
-    return std::env::args()
-        .skip(1) // skip the name of the program
-        .map(|s| {
-            u32::from_str(&s)
-                .unwrap_or_else(|_| panic!("Cannot parse argument {s:?} as an integer."))
-        })
-        .collect();
+fn parse_integer(s: &str) -> u32 {
+    u32::from_str(s).unwrap_or_else(|_| panic!("Cannot parse argument {s:?} as an integer."))
+}
+
+/// A size in bytes, with an optional K, M or G suffix (powers of 1024).
+fn parse_size(s: &str) -> u64 {
+    let (number, shift) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 30),
+        _ => (s, 0),
+    };
+    u64::from_str(number)
+        .ok()
+        .and_then(|number| number.checked_mul(1 << shift))
+        .unwrap_or_else(|| panic!("Cannot parse size {s:?}."))
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, arg: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| panic!("Missing value for {arg}."))
+}
+
+/// Stop on an option of a `feature` this build leaves out.
+#[cfg(not(all(
+    feature = "distributed",
+    feature = "sink",
+    feature = "syslog",
+    feature = "telemetry",
+    feature = "trace",
+    feature = "webhook"
+)))]
+fn without_feature(arg: &str, feature: &str) -> ! {
+    panic!("Cannot use {arg}: this build does not include it, rebuild with `--features {feature}`.")
+}
+
+/// The value of `result`, or its error printed before exiting with status 1: the engine cannot
+/// be prepared, or the search cannot go on.
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("\rERROR: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// The content of a file given on the command line, or of stdin for `-`.
+fn read_input(path: &str) -> String {
+    let mut input = String::new();
+    let read = if path == "-" {
+        io::stdin().read_to_string(&mut input)
+    } else {
+        std::fs::File::open(path).and_then(|mut file| file.read_to_string(&mut input))
+    };
+    read.unwrap_or_else(|e| panic!("Cannot read {path:?}: {e}."));
+    input
+}
+
+fn read_seeds(path: &str) -> Vec<u32> {
+    seed_filter::parse_seeds(&read_input(path))
+        .unwrap_or_else(|e| panic!("Cannot use the seeds of {path:?}: {e}."))
 }
 
-fn normalize_arguments(arguments: &mut Vec<u32>) {
-    let mut len = arguments.len();
-    if len % 4 == 1 {
-        arguments.push(arguments[len - 1]);
+fn get_arguments(args: impl IntoIterator<Item = String>) -> (Options, Vec<u32>) {
+    let (args, preset) = presets::expand(args.into_iter().collect())
+        .unwrap_or_else(|e| panic!("Cannot use the preset: {e}."));
+    let mut options = Options::default();
+    let mut arguments = Vec::new();
+    let mut php_dump = None;
+    let mut dump_range = (0, 0x7fffffff);
+    let mut charset = None;
+    let mut mask = None;
+    let mut charset_bytes = false;
+    let mut ignore_case = false;
+    let mut shuffled = None;
+    let mut ambiguous_shuffles = Vec::new();
+    let mut wide_calls = Vec::new();
+    let mut tokens = Vec::new();
+    let mut token_import = None;
+    let mut token_regex = None;
+    let mut token_index = 1;
+    let mut infer_charset = false;
+    let mut seed_function = None;
+    let mut seed_variables = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--engine" => {
+                let value = next_value(&mut args, &arg);
+                options.engine = EngineKind::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use engine {value:?}: {e}."));
+            }
+            "--target-dispatch-ms" => {
+                let value = next_value(&mut args, &arg);
+                options.target_dispatch_ms = Some(parse_integer(&value));
+            }
+            "--dispatch-workgroups" => {
+                let workgroups = parse_integer(&next_value(&mut args, &arg));
+                assert!(
+                    (1..=WORKGROUPS_PER_STEP).contains(&workgroups),
+                    "The workgroups of a dispatch must be between 1 and {WORKGROUPS_PER_STEP}."
+                );
+                options.dispatch_workgroups = Some(workgroups);
+            }
+            "--php" => {
+                let value = next_value(&mut args, &arg);
+                options.flavors = match value.as_str() {
+                    "auto" => Flavor::ALL.to_vec(),
+                    _ => vec![Flavor::from_str(&value)
+                        .unwrap_or_else(|e| panic!("Cannot search {value:?}: {e}."))],
+                };
+            }
+            "--platform" => {
+                let value = next_value(&mut args, &arg);
+                options.platform = Platform::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use platform {value:?}: {e}."));
+            }
+            "--getrandmax" => options.getrandmax = parse_integer(&next_value(&mut args, &arg)),
+            "--concurrent-jobs" => {
+                let value = next_value(&mut args, &arg);
+                options.concurrent_jobs = parse_integer(&value).max(1) as usize;
+            }
+            "--in-flight" => {
+                let value = next_value(&mut args, &arg);
+                options.in_flight = parse_integer(&value).max(1) as usize;
+            }
+            "--gpu-mem-limit" => {
+                options.gpu_mem_limit = Some(parse_size(&next_value(&mut args, &arg)));
+            }
+            "--mem-limit" => {
+                let limit = parse_size(&next_value(&mut args, &arg));
+                options.host_memory = HostMemory::with_limit(limit);
+            }
+            "--from-php-dump" => php_dump = Some(next_value(&mut args, &arg)),
+            "--dump-range" => {
+                let value = next_value(&mut args, &arg);
+                dump_range = syntax::parse_range(&value)
+                    .unwrap_or_else(|e| panic!("Cannot parse range {value:?}: {e}."));
+            }
+            "--charset" => charset = Some(next_value(&mut args, &arg)),
+            "--charset-bytes" => charset_bytes = true,
+            "--ignore-case" => ignore_case = true,
+            "--shuffle" => {
+                let value = next_value(&mut args, &arg);
+                shuffled = Some(Original::Array(permutation::parse_array(&value)));
+            }
+            "--str-shuffle" => {
+                let value = next_value(&mut args, &arg);
+                shuffled = Some(Original::String(value.into_bytes()));
+            }
+            "--mask" => {
+                let value = next_value(&mut args, &arg);
+                let positions = token::parse_mask(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the mask {value:?}: {e}."));
+                mask = Some(positions);
+            }
+            "--token" => tokens.push(next_value(&mut args, &arg)),
+            "--import-tokens" => token_import = Some(next_value(&mut args, &arg)),
+            "--token-regex" => {
+                let value = next_value(&mut args, &arg);
+                let regex = regex::Regex::new(&value)
+                    .unwrap_or_else(|e| panic!("Cannot parse regex {value:?}: {e}."));
+                token_regex = Some(regex);
+            }
+            "--infer-charset" => infer_charset = true,
+            "--token-index" => token_index = parse_integer(&next_value(&mut args, &arg)),
+            "--steps" => options.bench_steps = parse_integer(&next_value(&mut args, &arg)),
+            "bench" if !options.bench && arguments.is_empty() => options.bench = true,
+            "verify" if options.verify.is_none() && arguments.is_empty() => {
+                let value = next_value(&mut args, &arg);
+                let seed = seed_filter::parse_seed(&value)
+                    .unwrap_or_else(|| panic!("Cannot parse seed {value:?}."));
+                options.verify = Some(seed);
+            }
+            "gpu-info" if !options.gpu_info && arguments.is_empty() => options.gpu_info = true,
+            "doctor" if !options.doctor && arguments.is_empty() => options.doctor = true,
+            #[cfg(feature = "distributed")]
+            "export-work" if options.export_work.is_none() && arguments.is_empty() => {
+                let dir = next_value(&mut args, &arg);
+                let units = parse_integer(&next_value(&mut args, &arg));
+                options.export_work = Some((PathBuf::from(dir), units));
+            }
+            #[cfg(feature = "distributed")]
+            "work" if options.work_unit.is_none() && arguments.is_empty() => {
+                let path = next_value(&mut args, &arg);
+                let unit = WorkUnit::parse(&read_input(&path))
+                    .unwrap_or_else(|e| panic!("Cannot search the work unit {path:?}: {e}."));
+                options.work_unit = Some((PathBuf::from(path), unit));
+            }
+            #[cfg(feature = "distributed")]
+            "import-results" if options.import_results.is_none() && arguments.is_empty() => {
+                options.import_results = Some(args.by_ref().collect());
+            }
+            #[cfg(not(feature = "distributed"))]
+            "export-work" | "work" | "import-results" if arguments.is_empty() => {
+                without_feature(&arg, "distributed")
+            }
+            "--json" => options.json = true,
+            "--list-presets" => options.list_presets = true,
+            "crack-many" if options.jobs.is_none() && arguments.is_empty() => {
+                let path = next_value(&mut args, &arg);
+                let jobs = jobs::parse_jobs(&read_input(&path))
+                    .unwrap_or_else(|e| panic!("Cannot use the jobs {path:?}: {e}."));
+                options.jobs = Some(jobs);
+            }
+            "--nice" => options.nice = true,
+            "--explain" => options.explain = true,
+            "--dry-run" => options.dry_run = true,
+            "--exclude-seeds" => {
+                let path = next_value(&mut args, &arg);
+                options.seed_filter.exclude(read_seeds(&path));
+            }
+            "--only-seeds" => {
+                let path = next_value(&mut args, &arg);
+                options.seed_filter.only(read_seeds(&path));
+            }
+            "--seed-fn" => {
+                let value = next_value(&mut args, &arg);
+                let function = SeedFunction::parse(&value)
+                    .unwrap_or_else(|e| panic!("Cannot parse the seed function {value:?}: {e}."));
+                seed_function = Some(function);
+            }
+            "--seed-var" => {
+                let value = next_value(&mut args, &arg);
+                let variable = seed_fn::parse_variable(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the variable {value:?}: {e}."));
+                seed_variables.push(variable);
+            }
+            "--batch" => {
+                let path = next_value(&mut args, &arg);
+                let batch = syntax::parse_batch(&read_input(&path))
+                    .unwrap_or_else(|e| panic!("Cannot use the batch {path:?}: {e}."));
+                options.batch = Some(batch);
+            }
+            "--unknown-max" => {
+                let value = next_value(&mut args, &arg);
+                let maxes = unknown_max::parse_maxes(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the maximums {value:?}: {e}."));
+                options.unknown_max = Some(maxes);
+            }
+            "--skip-range" => {
+                let value = next_value(&mut args, &arg);
+                let skips = skip_range::parse_skip_range(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the skip range {value:?}: {e}."));
+                options.skip_range = Some(skips);
+            }
+            "--predicate" => {
+                let path = next_value(&mut args, &arg);
+                let predicate = Predicate::parse(&read_input(&path))
+                    .unwrap_or_else(|e| panic!("Cannot use the predicate {path:?}: {e}."));
+                options.predicate = Some(predicate);
+            }
+            "--rand" => {
+                let value = next_value(&mut args, &arg);
+                let generator = Generator::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot search {value:?}: {e}."));
+                options.generator = Some(generator);
+            }
+            "--verify-cmd" => {
+                let value = next_value(&mut args, &arg);
+                let command = VerifyCommand::new(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the command {value:?}: {e}."));
+                options.verify_command = Some(command);
+            }
+            "--on-seed" => options.on_seed = Some(Hook::new(&next_value(&mut args, &arg))),
+            "--on-finish" => options.on_finish = Some(Hook::new(&next_value(&mut args, &arg))),
+            "--partitions" => {
+                let partitions = parse_integer(&next_value(&mut args, &arg));
+                assert!(
+                    partitions.is_power_of_two() && partitions <= MAX_PARTITIONS,
+                    "The number of steps must be a power of two, up to {MAX_PARTITIONS}."
+                );
+                options.partitions = partitions;
+            }
+            "--step-order" => {
+                let value = next_value(&mut args, &arg);
+                options.step_order = StepOrder::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the step order {value:?}: {e}."));
+            }
+            "--first" => options.first = true,
+            "--max-results" => {
+                let limit = parse_integer(&next_value(&mut args, &arg));
+                assert!(
+                    limit > 0,
+                    "Cannot stop after 0 seeds, --max-results must be 1 or more."
+                );
+                options.max_results = Some(limit as usize);
+            }
+            "--abort-dispatches" => options.abort_dispatches = true,
+            "--session" => options.session = Some(next_value(&mut args, &arg)),
+            "--restore" => options.restore = true,
+            "--checkpoint-interval" => {
+                let ms = parse_integer(&next_value(&mut args, &arg));
+                options.checkpoint_interval = Duration::from_millis(u64::from(ms));
+            }
+            "--replay" => {
+                let path = next_value(&mut args, &arg);
+                let checkpoint = Checkpoint::parse(&read_input(&path));
+                options.replay = Some((PathBuf::from(path), checkpoint));
+            }
+            "--potfile-disable" => options.potfile_disable = true,
+            "--quick-epoch-pass" => {
+                let value = next_value(&mut args, &arg);
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let window = epoch::parse_window(&value, now)
+                    .unwrap_or_else(|e| panic!("Cannot use the window {value:?}: {e}."));
+                options.quick_epoch_pass = Some(window);
+            }
+            "--progress-json" => options.progress_json = true,
+            "--progress-interval" => {
+                let ms = parse_integer(&next_value(&mut args, &arg));
+                options.progress_interval = Duration::from_millis(u64::from(ms));
+            }
+            "--progress-line-interval" => {
+                let ms = parse_integer(&next_value(&mut args, &arg));
+                options.progress_line_interval = Duration::from_millis(u64::from(ms));
+            }
+            "--emit" => {
+                let value = next_value(&mut args, &arg);
+                let language = Language::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot emit a script: {e}."));
+                let path = PathBuf::from(next_value(&mut args, &arg));
+                options.emit.push((language, path));
+            }
+            "--emit-php" => {
+                let path = PathBuf::from(next_value(&mut args, &arg));
+                options.emit.push((Language::Php, path));
+            }
+            "--weights" => {
+                let value = next_value(&mut args, &arg);
+                let weights = rank::parse_weights(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the weights {value:?}: {e}."));
+                options.weights = Some(weights);
+            }
+            "--stats" => options.stats = Some(MatchStats::default()),
+            "--seed-offset" => {
+                let value = next_value(&mut args, &arg);
+                let offset = i64::from_str(&value)
+                    .unwrap_or_else(|_| panic!("Cannot parse offset {value:?} as an integer."));
+                options.seed_transforms.push(SeedTransform::Offset(offset));
+            }
+            "--crc32-wordlist" => {
+                let path = PathBuf::from(next_value(&mut args, &arg));
+                options.seed_transforms.push(SeedTransform::Crc32(path));
+            }
+            "--max-expected" => options.max_expected = parse_integer(&next_value(&mut args, &arg)),
+            "--force" => options.force = true,
+            "--max-temp" => options.max_temp = Some(parse_integer(&next_value(&mut args, &arg))),
+            "--verbose" | "-v" => options.verbose = true,
+            "--log-file" => options.log_file = Some(PathBuf::from(next_value(&mut args, &arg))),
+            #[cfg(feature = "sink")]
+            "--sink" => {
+                let value = next_value(&mut args, &arg);
+                let address = SinkAddress::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the sink {value:?}: {e}."));
+                options.sink = Some(Sink::new(address));
+            }
+            #[cfg(not(feature = "sink"))]
+            "--sink" => without_feature(&arg, "sink"),
+            #[cfg(feature = "syslog")]
+            "--syslog" => options.syslog = Some(Syslog::default()),
+            #[cfg(not(feature = "syslog"))]
+            "--syslog" => without_feature(&arg, "syslog"),
+            #[cfg(feature = "telemetry")]
+            "--telemetry-port" => {
+                let port = parse_integer(&next_value(&mut args, &arg));
+                let port = u16::try_from(port)
+                    .unwrap_or_else(|_| panic!("Cannot listen on port {port}, it is too large."));
+                options.telemetry = Some(Telemetry::new(port));
+            }
+            #[cfg(feature = "telemetry")]
+            "--telemetry-bind" => {
+                let value = next_value(&mut args, &arg);
+                options.telemetry_bind = value
+                    .parse()
+                    .unwrap_or_else(|e| panic!("Cannot listen on {value:?}: {e}."));
+            }
+            #[cfg(not(feature = "telemetry"))]
+            "--telemetry-port" | "--telemetry-bind" => without_feature(&arg, "telemetry"),
+            #[cfg(feature = "trace")]
+            "--trace-output" => {
+                options.trace_output = Some(PathBuf::from(next_value(&mut args, &arg)));
+            }
+            #[cfg(not(feature = "trace"))]
+            "--trace-output" => without_feature(&arg, "trace"),
+            #[cfg(feature = "webhook")]
+            "--webhook" => {
+                let value = next_value(&mut args, &arg);
+                let url = WebhookUrl::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use the webhook {value:?}: {e}."));
+                options.webhook = Some(Webhook::new(url));
+            }
+            #[cfg(feature = "webhook")]
+            "--webhook-on" => {
+                let value = next_value(&mut args, &arg);
+                options.webhook_on = WebhookEvent::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot use {arg}: {e}."));
+            }
+            #[cfg(feature = "webhook")]
+            "--notify" => {
+                let value = next_value(&mut args, &arg);
+                let channel = Notify::from_str(&value)
+                    .unwrap_or_else(|e| panic!("Cannot notify {value:?}: {e}."));
+                options.notify.push(channel);
+            }
+            #[cfg(not(feature = "webhook"))]
+            "--webhook" | "--webhook-on" | "--notify" => without_feature(&arg, "webhook"),
+            "--allow-software" => options.allow_software = true,
+            // Everything after it is read like php_mt_seed would, numbers only.
+            "--compat-openwall" => {
+                options.compat_openwall = true;
+                arguments.extend(args.by_ref().map(|arg| parse_integer(&arg)));
+            }
+            // Calls drawing from more than 2^32 values do not fit a slot, their slots depend on
+            // the flavor.
+            _ if platform::is_wide_call(&arg) => {
+                assert!(
+                    arguments.len().is_multiple_of(4),
+                    "The constraints before {arg:?} must be whole (4 numbers each)."
+                );
+                let call = WideCall::from_str(&arg)
+                    .unwrap_or_else(|e| panic!("Cannot parse constraint {arg:?}: {e}."));
+                wide_calls.push((arguments.len(), call));
+            }
+            _ => syntax::push_token(&mut arguments, &arg)
+                .unwrap_or_else(|e| panic!("Cannot parse constraint {arg:?}: {e}.")),
+        }
     }
+    platform::insert_wide_calls(
+        &mut arguments,
+        &wide_calls,
+        &options.flavors,
+        options.platform,
+        options.getrandmax,
+    )
+    .unwrap_or_else(|e| panic!("Cannot use a wide range: {e}."));
 
-    len = arguments.len();
-    if len % 4 == 2 {
-        arguments[len - 2] = arguments[len - 2];
-        arguments[len - 1] = arguments[len - 1];
-        arguments.push(0);
-        arguments.push(0x7fffffff);
+    // The seeds the target can reach are the only candidates, like with --only-seeds.
+    if let Some(function) = seed_function {
+        let seeds = function
+            .seeds(&seed_variables)
+            .unwrap_or_else(|e| panic!("Cannot use the seed function: {e}."));
+        options.seed_filter.only(seeds);
     }
-}
 
-fn lint_arguments(arguments: &Vec<u32>) -> bool {
-    if arguments.is_empty() {
-        return false;
+    // The dumped values come after the constraints given on the command line, which can skip
+    // the outputs drawn before them.
+    if let Some(path) = php_dump {
+        let dump = read_input(&path);
+        let values = php_dump::extract_values(&dump)
+            .unwrap_or_else(|e| panic!("Cannot use the dump {path:?}: {e}."));
+        assert!(
+            arguments.len().is_multiple_of(4),
+            "The constraints before --from-php-dump must be whole (4 numbers each)."
+        );
+        for value in values {
+            arguments.extend_from_slice(&[value, value, dump_range.0, dump_range.1]);
+        }
     }
 
-    if arguments.len() / 4 > 8 {
-        eprintln!(
-            "because of some half-baked optimizations, arguments are currently\n\
-             limited to 8 slots (where a slot is one couple of match_min, \n\
-             match_max, range_min, and range_max)"
+    // Tokens from a capture usually come from different requests, so from different seeds:
+    // only one of them is searched.
+    if let Some(path) = token_import {
+        let imported = token::extract_tokens(&read_input(&path), token_regex.as_ref());
+        if infer_charset && charset.is_none() {
+            let analysis = token::analyze_tokens(&imported);
+            print!("{analysis}");
+            charset = analysis.charsets.into_iter().next();
+        }
+        let token = imported
+            .get((token_index as usize).wrapping_sub(1))
+            .unwrap_or_else(|| panic!("There is no token {token_index} in {path:?}."));
+        tokens.push(token.clone());
+    }
+    if let Some(preset) = preset {
+        warn_token_lengths(preset, &tokens);
+    }
+    assert!(
+        !ignore_case
+            || (charset.is_some() && mask.is_none() && shuffled.is_none() && !charset_bytes),
+        "--ignore-case needs --charset, without --mask, a shuffle or --charset-bytes."
+    );
+    // Before PHP 7.1.0, shuffle() and str_shuffle() draw from rand() rather than mt_rand().
+    assert!(
+        shuffled.is_none() || options.flavors == [Flavor::Php71],
+        "Cannot search shuffles with --php 5.2.1 or auto, shuffle() and str_shuffle() only use \
+         mt_rand() since PHP 7.1.0."
+    );
+    for token in tokens {
+        assert!(
+            arguments.len().is_multiple_of(4),
+            "The constraints before a token must be whole (4 numbers each)."
         );
-        return false;
-    }
-
-    for chunk in arguments.chunks(4) {
-        match chunk {
-            &[match_min, match_max, range_min, range_max] => {
-                if match_min > match_max
-                    || range_min > range_max
-                    || match_max < range_min
-                    || match_min > range_max
-                    || range_max > 0x7fffffff
-                    || match_max > 0x7fffffff
-                {
-                    return false;
+        // A shuffle makes the token a permutation rather than drawn characters, and a mask gives
+        // each position its own charset, overriding the charset of all of them.
+        match (&shuffled, &mask, &charset) {
+            (Some(original), _, _) => {
+                let interpretations = original
+                    .constraints(&token)
+                    .unwrap_or_else(|e| panic!("Cannot use token {token:?}: {e}."));
+                arguments.extend(&interpretations[0]);
+                if interpretations.len() > 1 {
+                    ambiguous_shuffles.push((arguments.len(), interpretations));
                 }
             }
-            _ => return false, // if the normalized argument number isn't a multiple of 4
+            (None, Some(mask), _) => arguments.extend(
+                token::mask_constraints(&token, mask)
+                    .unwrap_or_else(|e| panic!("Cannot use token {token:?}: {e}.")),
+            ),
+            (None, None, Some(charset)) => arguments.extend(
+                match charset_bytes {
+                    true => token::byte_constraints(&token, charset),
+                    false => token::token_constraints(&token, charset, ignore_case),
+                }
+                .unwrap_or_else(|e| panic!("Cannot use token {token:?}: {e}.")),
+            ),
+            // Without a charset, tokens are mt_rand() outputs themselves.
+            (None, None, None) => {
+                let value = parse_integer(&token);
+                arguments.extend_from_slice(&[value, value, dump_range.0, dump_range.1]);
+            }
         }
     }
 
-    return true;
-}
+    if !ambiguous_shuffles.is_empty() {
+        options.interpretations = Some(interpretations(&arguments, &ambiguous_shuffles));
+    }
 
-// A small struct holding the prepared GPU resources to reuse across multiple workloads.
-struct GpuPrepared {
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    pipeline: wgpu::ComputePipeline,
-    bind_group_layout: wgpu::BindGroupLayout,
+    (options, arguments)
 }
 
-/// Prepare the GPU once: instance, adapter, device, queue, shader module, pipeline, bind group layout.
-/// If `print_adapter_info` is true, prints adapter info.
-///
-/// This function is intended to be called once and its result reused across many `execute_with_prepared_gpu` calls.
-fn prepare_gpu() -> GpuPrepared {
-    // We first initialize an wgpu `Instance`, which contains any "global" state wgpu needs.
-    //
-    // This is what loads the vulkan/dx12/metal/opengl libraries.
-    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
-
-    // We then create an `Adapter` which represents a physical gpu in the system. It allows
-    // us to query information about it and create a `Device` from it.
-    //
-    // This function is asynchronous in WebGPU, so request_adapter returns a future. On native/webgl
-    // the future resolves immediately, so we can block on it without harm.
-    let adapter =
-        pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
-            .expect("Failed to create adapter");
-
-    // Print out some basic information about the adapter.
-    println!("\rRunning on Adapter: {:#?}", adapter.get_info());
-
-    // Check to see if the adapter supports compute shaders. While WebGPU guarantees support for
-    // compute shaders, wgpu supports a wider range of devices through the use of "downlevel" devices.
-    let downlevel_capabilities = adapter.get_downlevel_capabilities();
-    if !downlevel_capabilities
-        .flags
-        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
-    {
-        panic!("Adapter does not support compute shaders");
-    }
-
-    // We then create a `Device` and a `Queue` from the `Adapter`.
-    //
-    // The `Device` is used to create and manage GPU resources.
-    // The `Queue` is a queue used to submit work for the GPU to process.
-    let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
-        label: None,
-        required_features: wgpu::Features::empty(),
-        required_limits: wgpu::Limits::downlevel_defaults(),
-        memory_hints: wgpu::MemoryHints::MemoryUsage,
-        trace: wgpu::Trace::Off,
-    }))
-    .expect("Failed to create device");
-
-    // Create a shader module from our shader code. This will parse and validate the shader.
-    //
-    // `include_wgsl` is a macro provided by wgpu like `include_str` which constructs a ShaderModuleDescriptor.
-    // If you want to load shaders differently, you can construct the ShaderModuleDescriptor manually.
-    let module = device.create_shader_module(wgpu::include_wgsl!("mt19937.wgsl"));
-
-    // A bind group layout describes the types of resources that a bind group can contain.
-    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: None,
-        entries: &[
-            // Input buffer
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: true },
-                    // This is the size of a single element in the buffer.
-                    min_binding_size: Some(NonZeroU64::new(4).unwrap()),
-                    has_dynamic_offset: false,
-                },
-                count: None,
-            },
-            // Output buffer
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    // This is the size of a single element in the buffer.
-                    min_binding_size: Some(NonZeroU64::new(8).unwrap()),
-                    has_dynamic_offset: false,
-                },
-                count: None,
-            },
-        ],
-    });
-
-    // The pipeline layout describes the bind groups that a pipeline expects
-    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: None,
-        bind_group_layouts: &[&bind_group_layout],
-        push_constant_ranges: &[],
-    });
-
-    // The pipeline is the ready-to-go program state for the GPU. It contains the shader modules,
-    // the interfaces (bind group layouts) and the shader entry point.
-    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-        label: None,
-        layout: Some(&pipeline_layout),
-        module: &module,
-        entry_point: Some("main"),
-        compilation_options: wgpu::PipelineCompilationOptions::default(),
-        cache: None,
-    });
+/// The arguments of each way the shuffled tokens may have been shuffled, the `interpretations`
+/// of each ending where `arguments` has their first. The draws of each are told, so that seeds
+/// can be told apart.
+fn interpretations(
+    arguments: &[u32],
+    ambiguous_shuffles: &[(usize, Vec<Vec<u32>>)],
+) -> Vec<(String, Vec<u32>)> {
+    let mut queries = vec![(String::new(), arguments.to_vec())];
+    for (end, interpretations) in ambiguous_shuffles {
+        queries = queries
+            .into_iter()
+            .flat_map(|(label, query)| {
+                interpretations.iter().map(move |constraints| {
+                    let mut query = query.clone();
+                    query[end - constraints.len()..*end].copy_from_slice(constraints);
+                    let draws = permutation::draws_label(constraints);
+                    (format!("{label} (shuffled by {draws})"), query)
+                })
+            })
+            .collect();
+        assert!(
+            queries.len() <= permutation::MAX_INTERPRETATIONS,
+            "The shuffles may have been done in more than {} ways.",
+            permutation::MAX_INTERPRETATIONS
+        );
+    }
+    queries
+}
 
-    GpuPrepared {
-        device,
-        queue,
-        pipeline,
-        bind_group_layout,
+/// Warn about the tokens which the code of `preset` cannot have generated, being too short or too
+/// long: the preset is likely not the right one.
+fn warn_token_lengths(preset: &Preset, tokens: &[String]) {
+    let Some(length) = preset.length else {
+        return;
+    };
+    for token in tokens {
+        let token_length = token.chars().count();
+        if token_length != length {
+            eprintln!(
+                "WARNING: the token {token:?} has {token_length} characters, the tokens of the \
+                 preset {} have {length}",
+                preset.name
+            );
+        }
     }
 }
 
-/// Execute the workload using an already prepared GPU context.
-///
-/// This function mirrors the original `find_mersenne_seed` implementation but assumes the device,
-/// queue, pipeline, etc. are already available in `prepared`. It returns `Some(Vec<u32>)` on success.
-fn execute_with_prepared_gpu(
-    prepared: &GpuPrepared,
-    arguments: &[u32],
-    step: u32,
-) -> Option<Vec<u32>> {
-    assert!(step < 256);
-
-    let device = &prepared.device;
-    let queue = &prepared.queue;
-    let pipeline = &prepared.pipeline;
-    let bind_group_layout = &prepared.bind_group_layout;
-
-    let mut input_data = Vec::new();
-    input_data.push(step);
-    input_data.extend_from_slice(arguments);
-
-    // Create a buffer with the data we want to process on the GPU.
-    let input_data_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: None,
-        contents: bytemuck::cast_slice(&input_data),
-        usage: wgpu::BufferUsages::STORAGE,
-    });
+/// Measure how fast the engine tests seeds, on the first `options.bench_steps` steps.
+fn bench(options: &Options, arguments: &[u32]) {
+    let engine = or_exit(prepare_engine(options));
 
-    // Now we create a buffer to store the output data.
-    let max_results = 1_000;
-    let output_buffer_size = max_results * std::mem::size_of::<u32>() as u64;
-    let output_data_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: output_buffer_size,
-        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
-        mapped_at_creation: false,
-    });
+    let steps = options.bench_steps.clamp(1, options.partitions);
+    let start_time = Instant::now();
+    let engine = or_exit(search(
+        options,
+        &Stop::default(),
+        engine,
+        options.flavors[0],
+        arguments,
+        0..steps,
+        |_| {},
+        print_progress(options, &options.flavors[..1], 0..steps),
+    ));
+    let elapsed = start_time.elapsed();
+    println!();
 
-    // Finally we create a buffer which can be read by the CPU.
-    let download_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: output_buffer_size,
-        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-        mapped_at_creation: false,
-    });
+    let seeds = f64::from(steps) * seeds_per_step(options.partitions);
+    println!("seeds tested: {seeds}");
+    println!(
+        "end-to-end:  {:.0} seeds/s ({:.3} s)",
+        seeds / elapsed.as_secs_f64(),
+        elapsed.as_secs_f64()
+    );
+    let engine_name = format!("{:?}", options.engine).to_lowercase();
+    let mut rows = vec![(engine_name.clone(), seeds / elapsed.as_secs_f64())];
+    match engine.kernel_time() {
+        Some(kernel_time) => {
+            println!(
+                "kernel only: {:.0} seeds/s ({:.3} s)",
+                seeds / kernel_time.as_secs_f64(),
+                kernel_time.as_secs_f64()
+            );
+            rows.push((
+                format!("{engine_name}, kernel only"),
+                seeds / kernel_time.as_secs_f64(),
+            ));
+        }
+        None => {
+            println!("kernel only: unavailable, the adapter does not support timestamp queries")
+        }
+    }
 
-    // The bind group contains the actual resources to bind to the pipeline.
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: None,
-        layout: &bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: input_data_buffer.as_entire_binding(),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: output_data_buffer.as_entire_binding(),
-            },
-        ],
-    });
+    // The CPU on a sample of seeds, with the same constraints, to tell whether the GPU is worth it.
+    let cpu_rate = bench::cpu_rate(options.flavors[0], arguments, bench::CPU_BENCH_TIME);
+    let threads = rayon::current_num_threads();
+    let plural = if threads > 1 { "s" } else { "" };
+    rows.push((format!("CPU, {threads} thread{plural}"), cpu_rate));
+    println!();
+    print!("{}", bench::comparison_table(&rows));
+}
 
-    // The command encoder allows us to record commands that we will later submit to the GPU.
-    let mut encoder =
-        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+/// Print the adapters of the gpu-info subcommand, and exit with status 1 if none is usable.
+fn print_gpu_info(options: &Options) {
+    let reports = gpu_info::adapters();
+    if options.json {
+        print!("{}", gpu_info::json(&reports));
+    } else {
+        for report in &reports {
+            print!("{}", report.text());
+        }
+        if reports.is_empty() {
+            println!("no adapter found");
+        }
+    }
+    if reports.iter().all(|report| !report.problems().is_empty()) {
+        std::process::exit(1);
+    }
+}
 
-    // A compute pass is a single series of compute operations.
-    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-        label: None,
-        timestamp_writes: None,
-    });
+/// Print the checks of the doctor subcommand, and exit with status 1 if one found a problem. The
+/// kernels are only run when an adapter can run them.
+fn print_doctor(options: &Options) {
+    let reports = gpu_info::adapters();
+    let mut checks = doctor::adapter_checks(&reports);
+    if doctor::can_run_kernels(&reports, options.allow_software) {
+        let ctx = GpuContext::new(Options {
+            engine: options.engine,
+            allow_software: options.allow_software,
+            ..Options::default()
+        });
+        match ctx {
+            Ok(ctx) => {
+                let (check, step_time) = doctor::kernel_check(&ctx);
+                checks.push(check);
+                checks.push(doctor::system_watchdog_check(step_time));
+            }
+            Err(e) => checks.push(doctor::engine_check(e)),
+        }
+    }
+    #[cfg(feature = "webhook")]
+    checks.push(doctor::system_curl_check());
+    checks.push(doctor::webgpu_check());
+    println!();
+    for check in &checks {
+        print!("{}", check.text());
+    }
+    if checks.iter().any(|check| check.status == Status::Problem) {
+        std::process::exit(1);
+    }
+}
 
-    // Set the pipeline and bind group
-    compute_pass.set_pipeline(pipeline);
-    compute_pass.set_bind_group(0, &bind_group, &[]);
+/// Compare the outputs of the seed of the verify subcommand with the normalized `arguments`, for
+/// each flavor, and tell whether they match for one of them.
+fn verify(options: &Options, seed: u32, arguments: &[u32]) -> bool {
+    let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let mut matches = false;
+    for &flavor in &options.flavors {
+        print!("{}", replay::comparison(flavor, arguments, seed, color));
+        matches |= replay::first_divergence(arguments, &replay::outputs(flavor, arguments, seed))
+            .is_none();
+    }
+    matches
+}
 
-    // Now we dispatch a series of workgroups.
-    compute_pass.dispatch_workgroups(65535, 1, 1);
+/// Search the constraint sets of `--batch` together, printing each seed after the number of the
+/// set it matches.
+fn search_batch(options: &Options, queries: &[Vec<u32>]) {
+    let mut engine = or_exit(prepare_engine(options));
+    let stop = Stop::new(options.max_results);
+    for &flavor in &options.flavors {
+        let on_seed = |query: usize, seed| {
+            println!("\r{}: {}", query + 1, format_result(options, seed, flavor));
+        };
+        let steps = 0..options.partitions;
+        let on_step = print_progress(options, &[flavor], steps.clone());
+        engine = or_exit(search_queries(
+            options, &stop, engine, flavor, queries, steps, on_seed, on_step,
+        ));
+    }
+    println!();
+}
 
-    // End compute pass
-    drop(compute_pass);
+/// The queries of `--unknown-max` and `--skip-range` for the normalized `arguments`, or for each
+/// of their interpretations, each maximum with each number of calls skipped, along with what they
+/// assume.
+fn variants(options: &Options, arguments: &[u32]) -> Vec<(String, Vec<u32>)> {
+    let interpreted = match &options.interpretations {
+        Some(interpretations) => interpretations.clone(),
+        None => vec![(String::new(), arguments.to_vec())],
+    };
+    let maxed = match &options.unknown_max {
+        Some(maxes) => interpreted
+            .into_iter()
+            .flat_map(|(label, query)| {
+                unknown_max::queries(&query, maxes)
+                    .into_iter()
+                    .map(move |(max, query)| (format!("{label} with mt_rand(0, {max})"), query))
+            })
+            .collect(),
+        None => interpreted,
+    };
+    let Some(skips) = &options.skip_range else {
+        return maxed;
+    };
+    maxed
+        .into_iter()
+        .flat_map(|(label, query)| {
+            skip_range::queries(&query, skips.clone())
+                .into_iter()
+                .map(move |(skipped, query)| (format!("{label} after {skipped} calls"), query))
+        })
+        .collect()
+}
 
-    // Copy the GPU output to the CPU-readable buffer.
-    encoder.copy_buffer_to_buffer(
-        &output_data_buffer,
-        0,
-        &download_buffer,
-        0,
-        output_data_buffer.size(),
+/// Search the queries of `--unknown-max`, `--skip-range` and the interpretations of shuffles
+/// together, printing each seed with the maximum, the number of calls skipped and the draws that
+/// explain the values.
+fn search_variants(options: &Options, arguments: &[u32]) {
+    let (labels, queries): (Vec<String>, Vec<Vec<u32>>) =
+        variants(options, arguments).into_iter().unzip();
+    assert!(
+        !queries.is_empty(),
+        "The values exceed every maximum of --unknown-max."
     );
+    let mut engine = or_exit(prepare_engine(options));
+    let stop = Stop::new(options.max_results);
+    for &flavor in &options.flavors {
+        let on_seed = |query: usize, seed| {
+            println!(
+                "\r{}{}",
+                format_result(options, seed, flavor),
+                labels[query]
+            );
+        };
+        let steps = 0..options.partitions;
+        let on_step = print_progress(options, &[flavor], steps.clone());
+        engine = or_exit(search_queries(
+            options, &stop, engine, flavor, &queries, steps, on_seed, on_step,
+        ));
+        if stop.is_stopped() {
+            break;
+        }
+    }
+    println!();
+}
 
-    // Finish and submit
-    let command_buffer = encoder.finish();
-    queue.submit([command_buffer]);
-
-    // Map and read the download buffer
-    let buffer_slice = download_buffer.slice(..);
-    buffer_slice.map_async(wgpu::MapMode::Read, |_| {});
-    device.poll(wgpu::PollType::Wait).unwrap();
-    let data = buffer_slice.get_mapped_range();
-    let result: &[u32] = bytemuck::cast_slice(&data);
-
-    // Extract results (length prefix + data)
-    let subslice_start = 1;
-    let subslice_end = 1 + result[0] as usize;
-    if subslice_end > result.len() {
-        eprintln!(
-            "\rERROR: there were many more results than what the GPU could transfer to the CPU,\n\
-             please use another tool for now, like https://www.openwall.com/php_mt_seed/"
+/// The seeds of `candidates` matching `arguments`, checked on the CPU, that the seed filter
+/// allows and `--verify-cmd` accepts. They are printed as they would be by a search, after
+/// `prefix`.
+fn check_on_cpu(
+    options: &Options,
+    stop: &Stop,
+    session: Option<&Session>,
+    prefix: &str,
+    flavor: Flavor,
+    arguments: &[u32],
+    candidates: &[u32],
+) -> Vec<u32> {
+    let mut seeds = reference::verify_candidates(flavor, arguments, candidates);
+    seeds.retain(|&seed| options.seed_filter.allows(seed));
+    if let Some(verify_command) = &options.verify_command {
+        seeds.retain(|&seed| verify_command.accepts(seed, flavor));
+    }
+    seeds.retain(|_| stop.admit());
+    if options.first && !seeds.is_empty() {
+        stop.stop();
+    }
+    for &seed in &seeds {
+        log::info!("found seed {seed} (PHP {})", flavor.versions());
+        println!("\r{prefix}{}", format_result(options, seed, flavor));
+        if let Some(session) = session {
+            session.record_seed(seed, flavor);
+        }
+        publish(options, seed, flavor);
+    }
+    seeds
+}
+
+/// The result line of a seed, written like openwall's php_mt_seed with `--compat-openwall`.
+fn format_result(options: &Options, seed: u32, flavor: Flavor) -> String {
+    match options.compat_openwall {
+        true => openwall::format_seed(seed, flavor),
+        false => format_seed(seed, flavor),
+    }
+}
+
+/// Send a seed found to the sink of `--sink`, to syslog with `--syslog` and to the webhook with
+/// `--webhook-on first-hit`, and run the command of `--on-seed`.
+fn publish(options: &Options, seed: u32, flavor: Flavor) {
+    #[cfg(feature = "sink")]
+    if let Some(sink) = &options.sink {
+        sink.send(seed, flavor);
+    }
+    #[cfg(feature = "webhook")]
+    if let (Some(webhook), WebhookEvent::FirstHit) = (&options.webhook, options.webhook_on) {
+        webhook.first_hit(seed, flavor);
+    }
+    if let Some(on_seed) = &options.on_seed {
+        on_seed.run(&[
+            ("seed", seed.to_string()),
+            ("php", flavor.name().to_string()),
+        ]);
+    }
+    #[cfg(feature = "syslog")]
+    if let Some(syslog) = &options.syslog {
+        let fields = [
+            ("seed", seed.to_string()),
+            ("php", flavor.name().to_string()),
+        ];
+        syslog.event(
+            log::Level::Info,
+            "seed",
+            &fields,
+            &format_seed(seed, flavor),
         );
-        return None;
     }
-    let useful_results = &result[subslice_start..subslice_end];
+}
 
-    Some(Vec::from(useful_results))
+/// The potfile, unless `--potfile-disable` is given or the options change the results of the
+/// search: they are then neither looked up nor cached.
+fn open_potfile(options: &Options) -> Option<Potfile> {
+    let plain = options.predicate.is_none()
+        && options.verify_command.is_none()
+        && options.seed_filter.is_empty()
+        && options.quick_epoch_pass.is_none()
+        && options.session.is_none()
+        && !options.restore
+        && !options.first
+        && options.max_results.is_none()
+        && options.step_range.is_none()
+        && options.replay.is_none();
+    (plain && !options.potfile_disable).then(|| Potfile::open(&potfile::default_path()))
 }
 
-fn main() {
-    let mut arguments = get_arguments();
-    normalize_arguments(&mut arguments);
-    if !lint_arguments(&arguments) {
-        print_usage();
+/// The session of `--session` or `--restore`, started over or restored.
+fn open_session(options: &Options, args: &[String]) -> Option<Session> {
+    let session = match (&options.session, options.restore) {
+        (None, false) => return None,
+        (name, true) => Session::restore(name.as_deref().unwrap_or(DEFAULT_SESSION)),
+        (Some(name), false) => Session::create(name, args),
+    };
+    Some(session.unwrap_or_else(|e| panic!("Cannot use the session: {e}.")))
+}
+
+/// wgpu uses `log` for all of our logging, so we initialize a logger with the `env_logger` crate.
+///
+/// To change the log level, set the `RUST_LOG` environment variable. See the `env_logger`
+/// documentation for more information. With `--log-file`, the log goes to the file, timestamped,
+/// and the events of the search are logged unless `RUST_LOG` says otherwise. `--verbose` logs
+/// them to stderr.
+fn init_logger(options: &Options) {
+    if options.log_file.is_none() && !options.verbose {
+        env_logger::init();
         return;
     }
+    let mut builder = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("warn,php_mt_seed=info,php_mt_seed_rs=info"),
+    );
+    if let Some(path) = &options.log_file {
+        let file = std::fs::File::create(path)
+            .unwrap_or_else(|e| panic!("Cannot create the log file {path:?}: {e}."));
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+    builder.format_timestamp_millis().init();
+    log::info!("php_mt_seed_rs {} started", env!("CARGO_PKG_VERSION"));
+}
+
+/// Print how each constraint is checked, with `--explain`.
+fn print_explanation(options: &Options, arguments: &[u32]) {
+    print!("{}", explain::explain(arguments, &options.flavors));
+    if let Some(predicate) = &options.predicate {
+        print!("{}", explain::explain_predicate(predicate));
+    }
+}
 
-    // wgpu uses `log` for all of our logging, so we initialize a logger with the `env_logger` crate.
-    //
-    // To change the log level, set the `RUST_LOG` environment variable. See the `env_logger`
-    // documentation for more information.
-    env_logger::init();
+/// Search the seeds matching the normalized `arguments`, print them, and return them per flavor
+/// searched, after `prefix`. The engine is prepared the first time the GPU is needed, and kept
+/// for the next searches.
+fn crack(
+    options: &mut Options,
+    arguments: &[u32],
+    session: Option<&Session>,
+    engine: &Mutex<Option<Arc<dyn Engine>>>,
+    prefix: &str,
+) -> Vec<(Flavor, Vec<u32>)> {
+    let mut potfile = open_potfile(options);
+    // Shared by the quick pass and the searches of every step and flavor, which count together
+    // toward --max-results.
+    let stop = Stop::new(options.max_results);
+    if options.compat_openwall {
+        println!("{}", openwall::pattern(arguments));
+    }
 
-    // Prepare GPU once and reuse it for all steps (print adapter info once).
-    let prepared = prepare_gpu();
+    // Seeds found before the search, by the session being restored or by the quick pass, are not
+    // searched again.
+    let mut early: Vec<(Flavor, Vec<u32>)> =
+        options.flavors.iter().map(|&f| (f, Vec::new())).collect();
+    if let Some(session) = session {
+        for &(seed, flavor) in session.results() {
+            println!("\r{prefix}{}", format_result(options, seed, flavor));
+            if let Some((_, seeds)) = early.iter_mut().find(|(f, _)| *f == flavor) {
+                seeds.push(seed);
+            }
+        }
+        options
+            .seed_filter
+            .exclude(session.results().iter().map(|&(seed, _)| seed));
+    }
+    if let (Some(window), None) = (&options.quick_epoch_pass, &options.predicate) {
+        let candidates = epoch::candidates(window.clone());
+        for (flavor, early_seeds) in &mut early {
+            let seeds = check_on_cpu(
+                options,
+                &stop,
+                session,
+                prefix,
+                *flavor,
+                arguments,
+                &candidates,
+            );
+            early_seeds.extend_from_slice(&seeds);
+            options.seed_filter.exclude(seeds);
+        }
+    }
+    let early_hit = early.iter().any(|(_, seeds)| !seeds.is_empty());
+    if (options.first && early_hit) || stop.is_stopped() {
+        return early;
+    }
 
-    for step in 0..256 {
-        match execute_with_prepared_gpu(&prepared, &arguments, step) {
-            None => std::process::exit(1),
-            Some(results) => {
-                for seed in results {
-                    println!("\rseed = {:#x} = {} (PHP 7.1.0+)", seed, seed);
+    let mut found = Vec::new();
+    let candidates = match options.predicate {
+        // The CPU cannot run the predicate, candidates are searched like any seed.
+        Some(_) => None,
+        None => options.seed_filter.candidates(),
+    };
+    if let Some(candidates) = candidates {
+        // A few candidates are much faster to check on the CPU than a whole search.
+        for &flavor in &options.flavors {
+            if options.compat_openwall {
+                println!("\rVersion: {}", flavor.versions());
+            }
+            let seeds = check_on_cpu(
+                options,
+                &stop,
+                session,
+                prefix,
+                flavor,
+                arguments,
+                &candidates,
+            );
+            found.push((flavor, seeds));
+            if stop.is_stopped() {
+                break;
+            }
+        }
+    } else if let Some(all) = crack_all_flavors(
+        options,
+        &stop,
+        arguments,
+        session,
+        engine,
+        prefix,
+        &mut potfile,
+    ) {
+        found = all;
+    } else {
+        // Prepare GPU once and reuse it for all steps, unless the device gets lost on the way, and
+        // only if the potfile does not have all the answers.
+        for &flavor in &options.flavors {
+            if options.compat_openwall {
+                println!("\rVersion: {}", flavor.versions());
+            }
+            if let Some(cached) = potfile.as_ref().and_then(|p| p.lookup(flavor, arguments)) {
+                eprintln!("\rfound in the potfile, use --potfile-disable to search again");
+                for &seed in cached {
+                    println!("\r{prefix}{}", format_result(options, seed, flavor));
+                }
+                found.push((flavor, cached.to_vec()));
+                continue;
+            }
+            let mut seeds = Vec::new();
+            let mut prepared = engine
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| or_exit(prepare_engine(options)))
+                .clone();
+            let mut progress = print_progress(options, &[flavor], 0..options.partitions);
+            let runs = match (&options.replay, session) {
+                (Some((_, checkpoint)), _) => checkpoint.replay(flavor, schedule::runs(options)),
+                (None, Some(session)) => session.remaining(flavor, schedule::runs(options)),
+                (None, None) => schedule::runs(options),
+            };
+            // Steps searched before the current run, whatever their order, or by the session.
+            let mut done =
+                options.partitions - runs.iter().map(|run| run.len() as u32).sum::<u32>();
+            for run in runs {
+                let (start, previous) = (run.start, seeds.len());
+                let on_seed = |seed| {
+                    seeds.push(seed);
+                    println!("\r{prefix}{}", format_result(options, seed, flavor));
+                    if let Some(session) = session {
+                        session.record_seed(seed, flavor);
+                    }
+                    publish(options, seed, flavor);
+                };
+                let on_step = |step, found| {
+                    if let Some(session) = session {
+                        session.complete_step(flavor, step - 1);
+                    }
+                    // Progress lines of jobs running at once would overwrite each other.
+                    if options.concurrent_jobs == 1 {
+                        progress(done + step - start, previous + found)
+                    }
+                };
+                prepared = or_exit(search(
+                    options,
+                    &stop,
+                    prepared,
+                    flavor,
+                    arguments,
+                    run.clone(),
+                    on_seed,
+                    on_step,
+                ));
+                done += run.len() as u32;
+                if stop.is_stopped() {
+                    break;
                 }
+            }
+            *engine.lock().unwrap() = Some(prepared);
+            if let Some(potfile) = &mut potfile {
+                potfile.record(flavor, arguments, &seeds);
+            }
+            found.push((flavor, seeds));
+            if stop.is_stopped() {
+                break;
+            }
+        }
+    }
+
+    for ((_, seeds), (_, early_seeds)) in found.iter_mut().zip(early) {
+        seeds.splice(0..0, early_seeds);
+    }
+    found
+}
+
+/// Search every flavor of `--php auto` in a single pass, on engines whose kernels check them all
+/// at once, and return the seeds found per flavor. `None` means the flavors are to be searched one
+/// after the other: the engine cannot, or sessions, the potfile having the seeds of a flavor,
+/// `--compat-openwall`, `--stats` or a predicate need a search per flavor.
+fn crack_all_flavors(
+    options: &Options,
+    stop: &Stop,
+    arguments: &[u32],
+    session: Option<&Session>,
+    engine: &Mutex<Option<Arc<dyn Engine>>>,
+    prefix: &str,
+    potfile: &mut Option<Potfile>,
+) -> Option<Vec<(Flavor, Vec<u32>)>> {
+    let cached = |potfile: &Potfile| {
+        Flavor::ALL
+            .iter()
+            .any(|&flavor| potfile.lookup(flavor, arguments).is_some())
+    };
+    if options.flavors != Flavor::ALL
+        || session.is_some()
+        || options.replay.is_some()
+        || potfile.as_ref().is_some_and(cached)
+        || options.compat_openwall
+        || options.stats.is_some()
+        || options.predicate.is_some()
+    {
+        return None;
+    }
+    let mut prepared = engine
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| or_exit(prepare_engine(options)))
+        .clone();
+    if !prepared.single_pass_flavors() {
+        return None;
+    }
 
-                print!("\rprogress: {:03} / 256", step + 1);
-                io::stdout().flush().unwrap();
+    let mut seeds: Vec<(Flavor, Vec<u32>)> =
+        options.flavors.iter().map(|&f| (f, Vec::new())).collect();
+    let mut total = 0;
+    let mut progress = print_progress(options, &options.flavors, 0..options.partitions);
+    let mut done = 0;
+    for run in schedule::runs(options) {
+        let (start, previous) = (run.start, total);
+        let on_seed = |flavor: Flavor, seed: u32| {
+            total += 1;
+            if let Some((_, flavor_seeds)) = seeds.iter_mut().find(|(f, _)| *f == flavor) {
+                flavor_seeds.push(seed);
             }
+            println!("\r{prefix}{}", format_result(options, seed, flavor));
+            publish(options, seed, flavor);
+        };
+        let on_step = |step, found| {
+            // Progress lines of jobs running at once would overwrite each other.
+            if options.concurrent_jobs == 1 {
+                progress(done + step - start, previous + found)
+            }
+        };
+        prepared = or_exit(search_all_flavors(
+            options,
+            stop,
+            prepared,
+            arguments,
+            run.clone(),
+            on_seed,
+            on_step,
+        ));
+        done += run.len() as u32;
+        if stop.is_stopped() {
+            break;
+        }
+    }
+    *engine.lock().unwrap() = Some(prepared);
+    if let Some(potfile) = potfile {
+        for (flavor, flavor_seeds) in &seeds {
+            potfile.record(*flavor, arguments, flavor_seeds);
+        }
+    }
+    Some(seeds)
+}
+
+/// The first option given along with `--rand` that only applies to the searches of `mt_rand()`.
+fn rand_conflict(options: &Options) -> Option<&'static str> {
+    [
+        ("crack-many", options.jobs.is_some()),
+        ("--batch", options.batch.is_some()),
+        ("--predicate", options.predicate.is_some()),
+        ("--verify", options.verify.is_some()),
+        ("--verify-cmd", options.verify_command.is_some()),
+        ("--session", options.session.is_some() || options.restore),
+        ("--stats", options.stats.is_some()),
+        ("--quick-epoch-pass", options.quick_epoch_pass.is_some()),
+        ("--only-seeds", options.seed_filter.candidates().is_some()),
+        ("--bench", options.bench),
+        ("--emit", !options.emit.is_empty()),
+        ("--weights", options.weights.is_some()),
+        ("--unknown-max", options.unknown_max.is_some()),
+        ("--skip-range", options.skip_range.is_some()),
+        ("--shuffle", options.interpretations.is_some()),
+    ]
+    .into_iter()
+    .find(|(_, given)| *given)
+    .map(|(option, _)| option)
+}
+
+/// Search the seeds of the `rand()` of `--rand`, printing them as they are found, and return
+/// them. Sessions, the potfile and the reports of the searches of `mt_rand()` do not apply.
+fn crack_rand(options: &Options, generator: Generator, arguments: &[u32]) -> Vec<u32> {
+    // The entry points of the generators ignore the flavor.
+    let flavor = options.flavors[0];
+    let mut engine = or_exit(prepare_engine(options));
+    let mut progress = print_progress(options, &[flavor], 0..options.partitions);
+    let mut seeds = Vec::new();
+    let mut done = 0;
+    // Shared by the runs, which count together toward --first and --max-results.
+    let stop = Stop::new(options.max_results);
+    for run in schedule::runs(options) {
+        let (start, previous) = (run.start, seeds.len());
+        let on_seed = |seed| {
+            seeds.push(seed);
+            println!("\r{}", generator.format_seed(seed));
+        };
+        let on_step = |step, found| progress(done + step - start, previous + found);
+        engine = or_exit(search(
+            options,
+            &stop,
+            engine,
+            flavor,
+            arguments,
+            run.clone(),
+            on_seed,
+            on_step,
+        ));
+        done += run.len() as u32;
+        if stop.is_stopped() {
+            break;
         }
     }
+    seeds
+}
 
-    println!("");
+/// Whether the constraints are expected to match more seeds by chance than `--max-expected`, for
+/// a search of every seed: a predicate may still filter them, and `--first`, `--max-results` or
+/// candidates of `--only-seeds` keep the search short.
+fn too_weak(options: &Options, arguments: &[u32]) -> bool {
+    explain::expected_seeds(arguments) > f64::from(options.max_expected)
+        && options.predicate.is_none()
+        && !options.first
+        && options.max_results.is_none()
+        && options.seed_filter.candidates().is_none()
 }
 
-#[test]
-fn test_find_seed_0() {
-    let mut arguments = vec![1178568022];
-    let expected_seed = 0;
-    normalize_arguments(&mut arguments);
-    let step = expected_seed % 256;
-    let prepared = prepare_gpu();
-    let result = execute_with_prepared_gpu(&prepared, &arguments, step);
-    assert_eq!(result, Some(vec![expected_seed]));
+/// Print the total of seeds found like openwall's php_mt_seed with `--compat-openwall`, and when
+/// several flavors were searched, the seeds found in each of them, once, and which flavor the
+/// target runs.
+fn print_conclusion(options: &Options, found: &[(Flavor, Vec<u32>)]) {
+    let counts: Vec<(Flavor, usize)> = found.iter().map(|(f, seeds)| (*f, seeds.len())).collect();
+    if options.compat_openwall {
+        println!(
+            "Found {}",
+            counts.iter().map(|(_, count)| count).sum::<usize>()
+        );
+    } else if found.len() > 1 {
+        // Seeds of different flavors are printed as they are found, interleaved.
+        let merged = flavor::merge(found);
+        if !merged.is_empty() {
+            println!("\rSeeds found, with the flavors they match:");
+        }
+        for (seed, flavors) in merged {
+            println!("{}", flavor::format_merged_seed(seed, &flavors));
+        }
+    }
+    if let Some(conclusion) = flavor::conclusion(&counts) {
+        println!("{conclusion}");
+    }
 }
 
-#[test]
-fn test_find_seed_0_short_range() {
-    let mut arguments = vec![16378811, 16378811, 0, 21474836];
-    let expected_seed = 0;
-    normalize_arguments(&mut arguments);
-    let step = expected_seed % 256;
-    let prepared = prepare_gpu();
-    let result = execute_with_prepared_gpu(&prepared, &arguments, step).unwrap();
+/// Log the start of a search to syslog, with the flavors and the normalized `arguments`.
+#[cfg(feature = "syslog")]
+fn syslog_start(syslog: &Syslog, options: &Options, arguments: &[u32]) {
+    let flavors: Vec<&str> = options.flavors.iter().map(|f| f.name()).collect();
+    let constraints: Vec<String> = arguments.iter().map(u32::to_string).collect();
+    let fields = [
+        ("php", flavors.join(",")),
+        ("constraints", constraints.join(" ")),
+        ("partitions", options.partitions.to_string()),
+    ];
+    syslog.event(log::Level::Info, "start", &fields, "search started");
+}
+
+/// Log the end of a search to syslog, with how many seeds were `found` in how long.
+#[cfg(feature = "syslog")]
+fn syslog_stop(syslog: &Syslog, found: &[(Flavor, Vec<u32>)], elapsed: Duration) {
+    let count: usize = found.iter().map(|(_, seeds)| seeds.len()).sum();
+    let fields = [
+        ("found", count.to_string()),
+        ("seconds", format!("{:.1}", elapsed.as_secs_f64())),
+    ];
+    let text = format!("search finished, {count} seeds found");
+    syslog.event(log::Level::Info, "stop", &fields, &text);
+}
+
+/// Run the command of `--on-finish` with how many seeds were `found`, which ones, and in how long.
+fn run_on_finish(on_finish: &Hook, found: &[(Flavor, Vec<u32>)], elapsed: Duration) {
+    let seeds: Vec<String> = found
+        .iter()
+        .flat_map(|(_, seeds)| seeds.iter().map(u32::to_string))
+        .collect();
+    on_finish.run(&[
+        ("found", seeds.len().to_string()),
+        ("seeds", seeds.join(" ")),
+        ("seconds", format!("{:.1}", elapsed.as_secs_f64())),
+    ]);
+}
+
+/// Print the secret candidates behind the seeds found, for the transforms of `--seed-offset` and
+/// `--crc32-wordlist`.
+fn print_secrets(options: &Options, found: &[(Flavor, Vec<u32>)]) {
+    let mut seeds: Vec<u32> = found.iter().flat_map(|(_, seeds)| seeds).copied().collect();
+    seeds.sort_unstable();
+    seeds.dedup();
+    for transform in &options.seed_transforms {
+        let secrets = transform::secrets(transform, &seeds)
+            .unwrap_or_else(|e| panic!("Cannot get back to the secrets: {e}."));
+        if let (SeedTransform::Crc32(path), true) = (transform, secrets.is_empty()) {
+            println!(
+                "no line of {} has the crc32() of a seed found",
+                path.display()
+            );
+        }
+        for secret in secrets {
+            println!("{secret}");
+        }
+    }
+}
+
+/// The line of a job in the summary of `crack-many`.
+fn job_summary(found: &[(Flavor, Vec<u32>)]) -> String {
+    let found: Vec<String> = found
+        .iter()
+        .filter(|(_, seeds)| !seeds.is_empty())
+        .map(|(flavor, seeds)| {
+            let seeds: Vec<String> = seeds.iter().map(u32::to_string).collect();
+            format!("{} (PHP {})", seeds.join(", "), flavor.versions())
+        })
+        .collect();
+    match found.is_empty() {
+        true => "no seed found".to_string(),
+        false => found.join("; "),
+    }
+}
+
+/// Run a job of `crack-many`, with the options of the command line followed by its own
+/// arguments, and return the seeds it found per flavor, or `None` if its constraints are invalid.
+fn crack_job(
+    shared: &[String],
+    job: &[String],
+    number: usize,
+    engine: &Mutex<Option<Arc<dyn Engine>>>,
+) -> Option<Vec<(Flavor, Vec<u32>)>> {
+    let (mut options, mut arguments) = get_arguments([shared, job].concat());
     assert!(
-        result.contains(&expected_seed),
-        "expected that the results contain the seed {expected_seed} : {result:?}"
+        options.batch.is_none()
+            && options.jobs.is_none()
+            && options.session.is_none()
+            && options.emit.is_empty()
+            && options.seed_transforms.is_empty()
+            && options.weights.is_none()
+            && options.stats.is_none()
+            && options.unknown_max.is_none()
+            && options.skip_range.is_none()
+            && options.interpretations.is_none()
+            && !(options.bench
+                || options.gpu_info
+                || options.doctor
+                || options.list_presets
+                || options.restore
+                || options.dry_run),
+        "bench, gpu-info, doctor, --list-presets, crack-many, --batch, --unknown-max, --skip-range, ambiguous shuffles, --session, --restore, --dry-run, --emit, \
+         --weights, --stats, --seed-offset and --crc32-wordlist cannot be used in job {number}."
+    );
+    normalize_arguments(&mut arguments);
+    let scaled = platform::check_ranges(&arguments, options.platform).and_then(|()| {
+        platform::scale_with_getrandmax(&mut arguments, &options.flavors, options.getrandmax)
+    });
+    if let Err(e) = scaled {
+        eprintln!("\rERROR: {e} in job {number}");
+        return None;
+    }
+    let predicate_only = options.predicate.is_some() && arguments.is_empty();
+    if !lint_arguments(&arguments) && !predicate_only {
+        eprintln!("\rERROR: invalid constraints in job {number}");
+        return None;
+    }
+    if options.explain {
+        print_explanation(&options, &arguments);
+    }
+    let found = crack(
+        &mut options,
+        &arguments,
+        None,
+        engine,
+        &format!("{number}: "),
     );
+    // The conclusions of jobs running at once would come mixed up, the summary has them all.
+    if options.concurrent_jobs == 1 {
+        println!();
+        print_conclusion(&options, &found);
+    }
+    Some(found)
 }
 
-#[test]
-fn test_lint_too_big_range() {
-    let arguments = vec![
-        1395647406, 1395647406, 0, 4294967295, 3472777710, 3472777710, 0, 4294967295, 4039049869,
-        4039049869, 0, 4294967295,
-    ];
-    assert_eq!(false, lint_arguments(&arguments));
+/// Write the work units of `export-work` to `dir`, each with the arguments of the search but
+/// those of `export-work`, and `units` of its steps.
+#[cfg(feature = "distributed")]
+fn export_work(options: &Options, args: &[String], dir: &std::path::Path, units: u32) {
+    assert!(
+        !options.first
+            && options.max_results.is_none()
+            && !options.bench
+            && options.generator.is_none()
+            && options.batch.is_none()
+            && options.unknown_max.is_none()
+            && options.skip_range.is_none()
+            && options.interpretations.is_none(),
+        "Cannot export the search: --first, --max-results, --bench, --rand, --batch, \
+         --unknown-max, --skip-range and ambiguous shuffles do not split in work units."
+    );
+    let position = args.iter().position(|arg| arg == "export-work").unwrap();
+    let search = [&args[..position], &args[position + 3..]].concat();
+    let units = work_unit::split(&search, options.partitions, units)
+        .unwrap_or_else(|e| panic!("Cannot export the search: {e}."));
+    std::fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Cannot create {dir:?}: {e}."));
+    for unit in &units {
+        let path = dir.join(unit.file_name());
+        std::fs::write(&path, unit.to_text())
+            .unwrap_or_else(|e| panic!("Cannot write the work unit {path:?}: {e}."));
+        println!(
+            "{}: steps {}..{} of {}",
+            path.display(),
+            unit.steps.start,
+            unit.steps.end,
+            unit.partitions
+        );
+    }
+    println!(
+        "search each unit with `php_mt_seed_rs work UNIT`, then merge the .results files with \
+         `php_mt_seed_rs import-results RESULTS...`"
+    );
 }
 
-#[test]
-fn test_find_seed_with_multiple_outputs_default_range() {
-    let arguments = vec![
-        697823703, 697823703, 0, 0x7fffffff, 1736388855, 1736388855, 0, 0x7fffffff, 2019524934,
-        2019524934, 0, 0x7fffffff,
-    ];
-    let expected_seed = 4242;
-    let step = expected_seed % 256;
-    let prepared = prepare_gpu();
-    let result = execute_with_prepared_gpu(&prepared, &arguments, step);
-    assert_eq!(result, Some(vec![expected_seed]));
+/// Merge and print the results files of `import-results`, and whether they cover every step.
+#[cfg(feature = "distributed")]
+fn import_results(paths: &[String]) -> bool {
+    let results = paths
+        .iter()
+        .map(|path| {
+            work_unit::parse_results(&read_input(path))
+                .unwrap_or_else(|e| panic!("Cannot import {path:?}: {e}."))
+        })
+        .collect();
+    let merged =
+        work_unit::merge(results).unwrap_or_else(|e| panic!("Cannot import the results: {e}."));
+    for &(seed, flavor) in &merged.seeds {
+        println!("{}", format_seed(seed, flavor));
+    }
+    println!(
+        "{} of {} units imported, {} seeds found",
+        merged.units.len(),
+        merged.count,
+        merged.seeds.len()
+    );
+    if !merged.missing.is_empty() {
+        let missing: Vec<String> = merged
+            .missing
+            .iter()
+            .map(|steps| format!("{}..{}", steps.start, steps.end))
+            .collect();
+        eprintln!(
+            "ERROR: steps {} are not covered, search their units too",
+            missing.join(", ")
+        );
+    }
+    merged.missing.is_empty()
 }
 
-#[test]
-fn test_find_seed_with_multiple_outputs_shorter_ranges() {
-    let arguments = vec![
-        7505, 7505, 1000, 10000, 2986, 2986, 1000, 10000, 1457, 1457, 1000, 10000,
-    ];
-    let expected_seed = 424242;
-    let step = expected_seed % 256;
-    let prepared = prepare_gpu();
-    let result = execute_with_prepared_gpu(&prepared, &arguments, step);
-    assert_eq!(result, Some(vec![expected_seed]));
+/// Run the jobs of `crack-many` on the same engine, `concurrent_jobs` at once so that the
+/// dispatches of small searches fill the GPU together, then sum them up.
+fn crack_many(args: &[String], jobs: &[Vec<String>], concurrent_jobs: usize) {
+    // The shared options are the command line without the subcommand and its file.
+    let position = args.iter().position(|arg| arg == "crack-many").unwrap();
+    let shared = [&args[..position], &args[position + 2..]].concat();
+    let engine = Mutex::new(None);
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; jobs.len()]);
+    std::thread::scope(|scope| {
+        for _ in 0..concurrent_jobs.min(jobs.len()) {
+            scope.spawn(|| loop {
+                let i = next_job.fetch_add(1, Ordering::Relaxed);
+                let Some(job) = jobs.get(i) else {
+                    break;
+                };
+                println!("\rjob {}: {}", i + 1, job.join(" "));
+                let start_time = Instant::now();
+                let found = crack_job(&shared, job, i + 1, &engine);
+                results.lock().unwrap()[i] = Some((found, start_time.elapsed()));
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let cracked = results
+        .iter()
+        .flatten()
+        .filter(|(found, _)| found.iter().flatten().any(|(_, seeds)| !seeds.is_empty()))
+        .count();
+    println!(
+        "\nsummary: seeds found for {cracked} of {} jobs",
+        jobs.len()
+    );
+    for (i, (found, elapsed)) in results.into_iter().flatten().enumerate() {
+        match found {
+            Some(found) => println!(
+                "job {}: {} ({:.1} s)",
+                i + 1,
+                job_summary(&found),
+                elapsed.as_secs_f64()
+            ),
+            None => println!("job {}: invalid constraints", i + 1),
+        }
+    }
+}
+
+/// The arguments of `run JOB`: those of the job specification JOB, followed by the options given
+/// after it. Other command lines are kept as they are.
+fn expand_run(args: Vec<String>) -> Vec<String> {
+    let [run, path, options @ ..] = &args[..] else {
+        return args;
+    };
+    if run != "run" {
+        return args;
+    }
+    let mut expanded = job_spec::parse_spec(&read_input(path))
+        .unwrap_or_else(|e| panic!("Cannot use the job {path:?}: {e}."));
+    expanded.extend_from_slice(options);
+    expanded
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect(); // skip the name of the program
+    let args = expand_run(args);
+    let (mut options, mut arguments) = get_arguments(args.clone());
+    let mut session = open_session(&options, &args);
+    if let (Some(session), true) = (&session, options.restore) {
+        (options, arguments) = get_arguments(session.arguments().to_vec());
+    }
+    // A work unit is searched with its arguments, followed by the options given along with it, on
+    // its steps only.
+    #[cfg(feature = "distributed")]
+    let work_unit = options.work_unit.take();
+    #[cfg(feature = "distributed")]
+    if let Some((_, unit)) = &work_unit {
+        assert!(
+            arguments.is_empty(),
+            "Constraints cannot be given along with work, but in its unit."
+        );
+        let position = args.iter().position(|arg| arg == "work").unwrap();
+        let local = [&args[..position], &args[position + 2..]].concat();
+        (options, arguments) = get_arguments([unit.arguments.clone(), local].concat());
+        options.step_range = Some(unit.steps.clone());
+    }
+    // A replay searches the arguments of the session of its checkpoint, followed by the options
+    // given along with it, whole steps at a time unless they say otherwise.
+    if let Some((path, checkpoint)) = options.replay.take() {
+        assert!(
+            arguments.is_empty(),
+            "Constraints cannot be given along with --replay, but in its session."
+        );
+        let session_arguments = path.with_file_name("arguments");
+        let session_arguments = std::fs::read_to_string(&session_arguments)
+            .unwrap_or_else(|e| panic!("Cannot read {session_arguments:?}: {e}."));
+        let position = args.iter().position(|arg| arg == "--replay").unwrap();
+        let local = [&args[..position], &args[position + 2..]].concat();
+        let replayed = session_arguments.lines().map(String::from).chain(local);
+        (options, arguments) = get_arguments(replayed.collect::<Vec<_>>());
+        options.replay = Some((path, checkpoint));
+        options
+            .dispatch_workgroups
+            .get_or_insert(WORKGROUPS_PER_STEP);
+    }
+    #[cfg(unix)]
+    php_mt_seed::pause::handle_signals()
+        .unwrap_or_else(|e| panic!("Cannot handle SIGUSR1 and SIGUSR2: {e}."));
+    if let Some(option) = options.generator.and_then(|_| rand_conflict(&options)) {
+        panic!("Cannot use {option} with --rand, it only applies to mt_rand().");
+    }
+    if let Some(jobs) = &options.jobs {
+        assert!(
+            arguments.is_empty(),
+            "Constraints cannot be given along with crack-many, but in its jobs."
+        );
+        init_logger(&options);
+        crack_many(&args, jobs, options.concurrent_jobs);
+        return;
+    }
+    if let Some(batch) = &options.batch {
+        assert!(
+            arguments.is_empty(),
+            "Constraints cannot be given along with --batch."
+        );
+        init_logger(&options);
+        search_batch(&options, batch);
+        return;
+    }
+    if options.list_presets {
+        print!("{}", presets::list());
+        return;
+    }
+    if options.gpu_info {
+        assert!(
+            arguments.is_empty(),
+            "Constraints cannot be given along with gpu-info."
+        );
+        print_gpu_info(&options);
+        return;
+    }
+    if options.doctor {
+        assert!(
+            arguments.is_empty(),
+            "Constraints cannot be given along with doctor."
+        );
+        print_doctor(&options);
+        return;
+    }
+    #[cfg(feature = "distributed")]
+    if let Some(paths) = &options.import_results {
+        if !import_results(paths) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if options.bench && arguments.is_empty() {
+        // mt_rand() right after mt_srand(0), only seed 0 matches
+        arguments.push(1178568022);
+    }
+    normalize_arguments(&mut arguments);
+    // Builds with another mt_getrandmax() are searched as the outputs they scale.
+    let scaled = platform::check_ranges(&arguments, options.platform).and_then(|()| {
+        platform::scale_with_getrandmax(&mut arguments, &options.flavors, options.getrandmax)
+    });
+    let checked = match options.generator {
+        Some(generator) => scaled.and_then(|()| generator.check_arguments(&arguments)),
+        None => scaled,
+    };
+    if let Err(e) = checked {
+        eprintln!("ERROR: {e}");
+        std::process::exit(1);
+    }
+    // A predicate may do all the matching by itself.
+    let predicate_only = options.predicate.is_some() && arguments.is_empty();
+    if !lint_arguments(&arguments) && !predicate_only {
+        print_usage();
+        eprint!("{}", diagnose::beyond_getrandmax(&arguments));
+        if options.dry_run {
+            // Scripts validating generated arguments need to tell
+            eprintln!("ERROR: invalid constraints");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    #[cfg(feature = "distributed")]
+    if let Some((dir, units)) = &options.export_work {
+        export_work(&options, &args, dir, *units);
+        return;
+    }
+
+    if options.dry_run {
+        print!("{}", explain::constraint_table(&arguments));
+        print_explanation(&options, &arguments);
+        if !arguments.is_empty() {
+            print!("{}", explain::entropy(&arguments));
+        }
+        println!("dry run: the constraints are valid, nothing was searched");
+        return;
+    }
+
+    if let Some(seed) = options.verify {
+        if !verify(&options, seed, &arguments) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    init_logger(&options);
+    #[cfg(feature = "sink")]
+    if let Some(sink) = &options.sink {
+        sink.connect()
+            .unwrap_or_else(|e| panic!("Cannot use the sink: {e}."));
+    }
+    #[cfg(feature = "syslog")]
+    if let Some(syslog) = &options.syslog {
+        syslog
+            .connect()
+            .unwrap_or_else(|e| panic!("Cannot log to syslog: {e}."));
+    }
+    #[cfg(feature = "telemetry")]
+    if let Some(telemetry) = &options.telemetry {
+        let address = telemetry
+            .start(options.telemetry_bind)
+            .unwrap_or_else(|e| panic!("Cannot serve the telemetry: {e}."));
+        log::info!("serving the progress on http://{address}/");
+    }
+    // The trace is completed when the guard is dropped, at the end of the search.
+    #[cfg(feature = "trace")]
+    let _trace = options
+        .trace_output
+        .as_ref()
+        .map(|path| trace::start(path).unwrap_or_else(|e| panic!("Cannot write the trace: {e}.")));
+
+    if let Some(generator) = options.generator {
+        crack_rand(&options, generator, &arguments);
+        println!();
+        return;
+    }
+
+    if options.explain {
+        print_explanation(&options, &arguments);
+    }
+
+    if options.bench {
+        // Every step is searched, for the throughput to hold.
+        assert!(
+            !options.first && options.max_results.is_none(),
+            "Cannot bench with --first or --max-results, which stop the search early."
+        );
+        bench(&options, &arguments);
+        return;
+    }
+
+    if options.unknown_max.is_some()
+        || options.skip_range.is_some()
+        || options.interpretations.is_some()
+    {
+        search_variants(&options, &arguments);
+        return;
+    }
+
+    // Lines openwall's php_mt_seed does not print would get in the way of its scripts.
+    if !arguments.is_empty() && !options.compat_openwall {
+        print!("{}", explain::entropy(&arguments));
+    }
+    if !options.force && too_weak(&options, &arguments) {
+        eprintln!(
+            "ERROR: about {:.1e} seeds are expected to match by chance, more than the {} of \
+             --max-expected: add observations, or search anyway with --force",
+            explain::expected_seeds(&arguments),
+            options.max_expected
+        );
+        std::process::exit(1);
+    }
+    let query = session::query_hash(&options, &arguments);
+    if let Some(session) = &mut session {
+        session
+            .start(query, options.checkpoint_interval)
+            .unwrap_or_else(|e| panic!("Cannot use the session: {e}."));
+    }
+    if let Some((_, checkpoint)) = &options.replay {
+        checkpoint
+            .check_query(query)
+            .unwrap_or_else(|e| panic!("Cannot replay the checkpoint: {e}."));
+    }
+    let engine = Mutex::new(None);
+    let start_time = Instant::now();
+    #[cfg(feature = "syslog")]
+    if let Some(syslog) = &options.syslog {
+        syslog_start(syslog, &options, &arguments);
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(webhook) = &options.webhook {
+        webhook
+            .start(&options.flavors, &arguments, options.partitions)
+            .unwrap_or_else(|e| panic!("Cannot use the webhook: {e}."));
+    }
+    let found = crack(&mut options, &arguments, session.as_ref(), &engine, "");
+    #[cfg(feature = "distributed")]
+    if let Some((path, unit)) = &work_unit {
+        let results = path.with_extension("results");
+        std::fs::write(&results, unit.results_text(&found))
+            .unwrap_or_else(|e| panic!("Cannot write the results {results:?}: {e}."));
+        println!(
+            "\rthe results of unit {}/{} are in {}",
+            unit.index,
+            unit.count,
+            results.display()
+        );
+    }
+    #[cfg(feature = "syslog")]
+    if let Some(syslog) = &options.syslog {
+        syslog_stop(syslog, &found, start_time.elapsed());
+    }
+    if let Some(on_finish) = &options.on_finish {
+        run_on_finish(on_finish, &found, start_time.elapsed());
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(webhook) = &options.webhook {
+        webhook.finish(&found);
+    }
+    #[cfg(feature = "webhook")]
+    for channel in &options.notify {
+        channel.send(&found, start_time.elapsed());
+    }
+    println!();
+    print_conclusion(&options, &found);
+    log::info!("{}", options.host_memory.summary());
+    if options.host_memory.limit().is_some() {
+        println!("{}", options.host_memory.summary());
+    }
+    if !arguments.is_empty() && found.iter().all(|(_, seeds)| seeds.is_empty()) {
+        eprint!("{}", diagnose::no_results(&arguments, &options.flavors));
+    }
+    if let Some(weights) = &options.weights {
+        println!("ranked by score:");
+        print!("{}", rank::ranking(&arguments, weights, &found));
+    }
+    if let Some(stats) = &options.stats {
+        print!(
+            "{}",
+            stats.histogram(&arguments, seeds_per_step(options.partitions))
+        );
+    }
+    print_secrets(&options, &found);
+    for (language, path) in &options.emit {
+        std::fs::write(path, emit::script(*language, &arguments, &found))
+            .unwrap_or_else(|e| panic!("Cannot write the script {path:?}: {e}."));
+        println!("run `{}` to check the seeds", language.command(path));
+    }
+}
+
+#[test]
+fn test_get_arguments_target_dispatch_ms() {
+    let args = ["--target-dispatch-ms", "500", "1178568022"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.target_dispatch_ms, Some(500));
+    assert!(!options.nice);
+    assert_eq!(arguments, vec![1178568022]);
+}
+
+#[test]
+fn test_get_arguments_nice() {
+    let args = ["1178568022", "--nice"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.nice);
+    assert_eq!(options.target_dispatch_ms, None);
+    assert_eq!(options.in_flight, php_mt_seed::DEFAULT_IN_FLIGHT);
+    assert_eq!(arguments, vec![1178568022]);
+}
+
+#[test]
+fn test_get_arguments_engine() {
+    let args = ["--engine", "wgpu", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.engine, EngineKind::Wgpu);
+    assert!(EngineKind::from_str("metal").is_err());
+
+    let args = ["--engine", "reference", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.engine, EngineKind::Reference);
+}
+
+#[test]
+fn test_get_arguments_gpu_mem_limit() {
+    let args = ["--gpu-mem-limit", "64M"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.gpu_mem_limit, Some(64 << 20));
+    assert_eq!(parse_size("1000"), 1000);
+    assert_eq!(parse_size("512k"), 512 << 10);
+    assert_eq!(parse_size("2G"), 2 << 30);
+}
+
+#[test]
+fn test_get_arguments_mem_limit() {
+    let args = ["--mem-limit", "1G", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.host_memory.limit(), Some(1 << 30));
+    let (options, _) = get_arguments(["1178568022".to_string()]);
+    assert_eq!(options.host_memory.limit(), None);
+}
+
+#[test]
+fn test_get_arguments_in_flight() {
+    let args = ["--in-flight", "8", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.in_flight, 8);
+}
+
+#[test]
+fn test_get_arguments_bench() {
+    let args = ["bench", "--steps", "2"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.bench);
+    assert_eq!(options.bench_steps, 2);
+    assert!(arguments.is_empty());
+}
+
+#[test]
+fn test_get_arguments_verify() {
+    let args = ["verify", "0x1092", "697823703-697823703", "5-9@0-61"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.verify, Some(4242));
+    assert_eq!(
+        arguments,
+        vec![697823703, 697823703, 0, 0x7fffffff, 5, 9, 0, 61]
+    );
+}
+
+#[test]
+fn test_get_arguments_compat_openwall() {
+    let args = [
+        "--compat-openwall",
+        "0",
+        "0",
+        "0",
+        "0",
+        "55",
+        "55",
+        "0",
+        "61",
+    ]
+    .map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.compat_openwall);
+    assert_eq!(arguments, vec![0, 0, 0, 0, 55, 55, 0, 61]);
+}
+
+#[test]
+fn test_get_arguments_php() {
+    let (options, _) = get_arguments(["1178568022"].map(String::from));
+    assert_eq!(options.flavors, vec![Flavor::Php71]);
+
+    let args = ["--php", "5.2.1", "963932192"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.flavors, vec![Flavor::Php52]);
+
+    let args = ["--php", "auto", "963932192"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.flavors, Flavor::ALL);
+}
+
+#[test]
+fn test_get_arguments_repetition() {
+    let args = ["--nice", "0", "0", "0", "61", "*2", "7"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.nice);
+    assert_eq!(arguments, vec![0, 0, 0, 61, 0, 0, 0, 61, 7]);
+}
+
+#[test]
+fn test_get_arguments_from_php_dump() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_dump.txt");
+    std::fs::write(&path, "Array\n(\n    [0] => 5\n    [1] => 61\n)\n").unwrap();
+    let args = [
+        "0-61@0-61",
+        "--from-php-dump",
+        path.to_str().unwrap(),
+        "--dump-range",
+        "0-61",
+    ]
+    .map(String::from);
+    let (_, arguments) = get_arguments(args);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(arguments, vec![0, 61, 0, 61, 5, 5, 0, 61, 61, 61, 0, 61]);
+}
+
+#[test]
+fn test_get_arguments_tokens() {
+    let args = ["--charset", "abc", "--token", "cab"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, vec![2, 2, 0, 2, 0, 0, 0, 2, 1, 1, 0, 2]);
+
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_tokens.txt");
+    std::fs::write(&path, "Set-Cookie: id=ab\nSet-Cookie: id=ca\n").unwrap();
+    let args = [
+        "--import-tokens",
+        path.to_str().unwrap(),
+        "--token-regex",
+        "id=([a-c]+)",
+        "--token-index",
+        "2",
+        "--charset",
+        "abc",
+    ]
+    .map(String::from);
+    let (_, arguments) = get_arguments(args);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(arguments, vec![2, 2, 0, 2, 0, 0, 0, 2]);
+}
+
+#[test]
+fn test_get_arguments_infer_charset() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_infer.txt");
+    let tokens: Vec<String> = (0..64u32)
+        .map(|i| format!("{:08x}", i.wrapping_mul(0x9e3779b9)))
+        .collect();
+    std::fs::write(&path, tokens.join("\n")).unwrap();
+    let args = ["--import-tokens", path.to_str().unwrap(), "--infer-charset"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    std::fs::remove_file(&path).unwrap();
+    // The first token is 00000000
+    assert_eq!(arguments, [0, 0, 0, 15].repeat(8));
+}
+
+#[test]
+fn test_get_arguments_dry_run() {
+    let args = ["--dry-run", "7505@1000-10000"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.dry_run);
+    assert!(lint_arguments(&arguments));
+}
+
+#[test]
+fn test_get_arguments_seed_filter() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_seeds.txt");
+    std::fs::write(&path, "0\nseed = 0x0000002a = 42 (PHP 7.1.0+)\n").unwrap();
+    let excluded = ["--exclude-seeds", path.to_str().unwrap()].map(String::from);
+    let (options, _) = get_arguments(excluded);
+    let only = ["--only-seeds", path.to_str().unwrap()].map(String::from);
+    let (only_options, _) = get_arguments(only);
+    std::fs::remove_file(&path).unwrap();
+    assert!(!options.seed_filter.allows(42));
+    assert!(options.seed_filter.allows(7));
+    assert_eq!(only_options.seed_filter.candidates(), Some(vec![0, 42]));
+}
+
+#[test]
+fn test_get_arguments_seed_fn() {
+    let args = [
+        "--seed-fn",
+        "$x * 1000 + $pid",
+        "--seed-var",
+        "x=0-1",
+        "--seed-var",
+        "pid=7",
+        "1178568022",
+    ];
+    let (options, _) = get_arguments(args.map(String::from));
+    assert_eq!(options.seed_filter.candidates(), Some(vec![7, 1007]));
+}
+
+#[test]
+fn test_get_arguments_unknown_max() {
+    let args = ["--unknown-max", "alphabets", "3-3", "--token", "12"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(
+        options.unknown_max,
+        Some(unknown_max::ALPHABET_MAXES.to_vec())
+    );
+    assert_eq!(arguments, vec![3, 3, 0, 0x7fffffff, 12, 12, 0, 0x7fffffff]);
+}
+
+#[test]
+fn test_get_arguments_skip_range() {
+    let args = ["--skip-range", "0..2", "--unknown-max", "9,15", "12-12"].map(String::from);
+    let (options, mut arguments) = get_arguments(args);
+    assert_eq!(options.skip_range, Some(0..=2));
+    normalize_arguments(&mut arguments);
+    let variants = variants(&options, &arguments);
+    assert_eq!(variants.len(), 3);
+    assert_eq!(
+        variants[2],
+        (
+            " with mt_rand(0, 15) after 2 calls".to_string(),
+            vec![0, 0x7fffffff, 0, 0x7fffffff, 0, 0x7fffffff, 0, 0x7fffffff, 12, 12, 0, 15]
+        )
+    );
+}
+
+#[test]
+fn test_get_arguments_charset_bytes() {
+    let args = ["--charset", "αβ", "--token", "β"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [1, 1, 0, 1]);
+    let args = ["--charset", "αβ", "--charset-bytes", "--token", "\\xb2"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [3, 3, 0, 3]);
+}
+
+#[test]
+fn test_get_arguments_ignore_case() {
+    let args = ["--charset", "abAB", "--ignore-case", "--token", "Ab"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [0, 2, 0, 3, 1, 3, 0, 3]);
+}
+
+#[test]
+fn test_get_arguments_platform() {
+    let (options, _) = get_arguments(["7"].map(String::from));
+    assert_eq!(options.platform, Platform::Bits64);
+    let args = ["--platform", "32", "10", "10", "0", "4294967295"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.platform, Platform::Bits32);
+    assert_eq!(arguments, [10, 10, 0, u32::MAX]);
+
+    // mt_rand(0, 2 ** 33 - 1) draws two outputs in PHP 7.1.0+.
+    let args = ["--php", "7.1", "4294967296@0-8589934591", "7"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [2, 2, 1, 2, 0, 0, 0, u32::MAX, 7]);
+}
+
+#[test]
+fn test_get_arguments_getrandmax() {
+    let (options, _) = get_arguments(["7"].map(String::from));
+    assert_eq!(options.getrandmax, 0x7fffffff);
+    let args = [
+        "--getrandmax",
+        "1073741823",
+        "--php",
+        "5.2.1",
+        "0",
+        "0",
+        "0",
+        "61",
+    ];
+    let (options, arguments) = get_arguments(args.map(String::from));
+    assert_eq!(options.getrandmax, 0x3fffffff);
+    assert_eq!(arguments, [0, 0, 0, 61]);
+}
+
+#[test]
+fn test_get_arguments_shuffle() {
+    let args = ["--shuffle", "10,J,Q,K", "--token", "K,10,J,Q"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [2, 2, 0, 3, 1, 1, 0, 2, 0, 0, 0, 1]);
+    let args = ["--str-shuffle", "abcd", "--token", "dacb"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [1, 1, 0, 3, 2, 2, 0, 2, 0, 0, 0, 1]);
+    let args = ["--str-shuffle", "abcd", "--token", "da?b"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [1, 1, 0, 3, 0, 2, 0, 2, 0, 0, 0, 1]);
+
+    // Both a of "aab" may have been drawn last: each way is searched.
+    let args = [
+        "7",
+        "7",
+        "0",
+        "10",
+        "--str-shuffle",
+        "aab",
+        "--token",
+        "aba",
+    ];
+    let (options, arguments) = get_arguments(args.map(String::from));
+    assert_eq!(arguments, [7, 7, 0, 10, 0, 0, 0, 2, 0, 0, 0, 1]);
+    assert_eq!(
+        variants(&options, &arguments),
+        [
+            (" (shuffled by 0 0)".to_string(), arguments.clone()),
+            (
+                " (shuffled by 1 1)".to_string(),
+                vec![7, 7, 0, 10, 1, 1, 0, 2, 1, 1, 0, 1]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_get_arguments_mask() {
+    let args = ["--mask", "?d?d?l", "--token", "42b"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [4, 4, 0, 9, 2, 2, 0, 9, 1, 1, 0, 25]);
+}
+
+#[test]
+fn test_get_arguments_preset() {
+    let args = ["--preset", "captcha-6digit", "--token", "483920"].map(String::from);
+    let (_, arguments) = get_arguments(args);
+    assert_eq!(arguments, [483920, 483920, 100000, 999999]);
+
+    // The options given explicitly override those of the preset, before or after it.
+    let args = [
+        "--charset",
+        "abc",
+        "--preset",
+        "token-alnum-16",
+        "--token",
+        "cab",
+    ];
+    let (_, arguments) = get_arguments(args.map(String::from));
+    assert_eq!(arguments, [2, 2, 0, 2, 0, 0, 0, 2, 1, 1, 0, 2]);
+
+    // The seeding of historical generators is known.
+    let args = ["--preset", "vbulletin-3-reset-id", "--token", "48392011"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(arguments, [48392011, 48392011, 0, 100000000]);
+    let candidates = options.seed_filter.candidates().unwrap();
+    assert_eq!(candidates.len(), 1000001);
+    assert_eq!(candidates[..2], [0, 1]);
+
+    let (options, _) = get_arguments(["--list-presets".to_string()]);
+    assert!(options.list_presets);
+}
+
+#[test]
+fn test_preset_historical_seed() {
+    // The activation ID of a vBulletin 3 reset, mt_srand() being given 523871 microseconds.
+    let seed = 523871;
+    let token = reference::Mt19937::new(seed, Flavor::Php52).next_in_range(0, 100000000);
+    let args = [
+        "--preset",
+        "vbulletin-3-reset-id",
+        "--token",
+        &token.to_string(),
+    ]
+    .map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.flavors, [Flavor::Php52]);
+    let candidates = options.seed_filter.candidates().unwrap();
+    let seeds = check_on_cpu(
+        &options,
+        &Stop::default(),
+        None,
+        "",
+        Flavor::Php52,
+        &arguments,
+        &candidates,
+    );
+    assert_eq!(seeds, [seed]);
+}
+
+#[test]
+fn test_get_arguments_hooks() {
+    let args = [
+        "--on-seed",
+        "notify-send {seed}",
+        "--on-finish",
+        "mail -s '{found} seeds' me",
+        "1178568022",
+    ]
+    .map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.on_seed, Some(Hook::new("notify-send {seed}")));
+    assert_eq!(
+        options.on_finish,
+        Some(Hook::new("mail -s '{found} seeds' me"))
+    );
+    assert_eq!(arguments, [1178568022]);
+}
+
+#[test]
+fn test_expand_run() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_job.yaml");
+    std::fs::write(&path, "charset: abc\ntoken: [c?b]\nconstraints: [skip]\n").unwrap();
+    let args = ["run", path.to_str().unwrap(), "--first"].map(String::from);
+    let (options, arguments) = get_arguments(expand_run(args.to_vec()));
+    assert!(options.first);
+    assert_eq!(
+        arguments,
+        [0, 0x7fffffff, 0, 0x7fffffff, 2, 2, 0, 2, 0, 2, 0, 2, 1, 1, 0, 2]
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    let args = ["1178568022".to_string()];
+    assert_eq!(expand_run(args.to_vec()), args);
+}
+
+#[test]
+fn test_get_arguments_verify_command() {
+    let args = ["--verify-cmd", "php check.php {seed}", "1178568022"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(
+        options.verify_command,
+        Some(VerifyCommand::new("php check.php {seed}").unwrap())
+    );
+    assert_eq!(arguments, [1178568022]);
+}
+
+#[test]
+fn test_get_arguments_rand() {
+    let args = ["--rand", "msvc", "41", "41"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.generator, Some(Generator::Msvc));
+    assert_eq!(rand_conflict(&options), None);
+    assert_eq!(arguments, [41, 41]);
+
+    let args = ["--rand", "glibc", "--stats", "1804289383"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.generator, Some(Generator::Glibc));
+    assert_eq!(rand_conflict(&options), Some("--stats"));
+
+    let args = ["--rand", "c", "83", "83", "0", "99"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.generator, Some(Generator::CGlibc));
+    assert_eq!(arguments, [83, 83, 0, 99]);
+}
+
+#[test]
+fn test_crack_rand_stops() {
+    // About 256 seeds of each step give 100: the runs of a random order stop together.
+    let args = [
+        "--rand",
+        "msvc",
+        "--allow-software",
+        "--step-order",
+        "random",
+        "--max-results",
+        "3",
+        "100",
+    ]
+    .map(String::from);
+    let (options, mut arguments) = get_arguments(args);
+    normalize_arguments(&mut arguments);
+    assert!(schedule::runs(&options).len() > 1);
+    assert_eq!(crack_rand(&options, Generator::Msvc, &arguments).len(), 3);
+
+    // Only the first step finding seeds is searched, rather than all of them.
+    let args = ["--rand", "msvc", "--allow-software", "--first", "100"].map(String::from);
+    let (options, mut arguments) = get_arguments(args);
+    normalize_arguments(&mut arguments);
+    let seeds = crack_rand(&options, Generator::Msvc, &arguments);
+    assert!(!seeds.is_empty() && seeds.len() < 1000);
+}
+
+#[test]
+fn test_get_arguments_partitions() {
+    let args = ["--partitions", "4096", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.partitions, 4096);
+}
+
+#[test]
+#[should_panic]
+fn test_get_arguments_shuffle_before_php_7_1() {
+    let args = ["--php", "auto", "--str-shuffle", "abcd", "--token", "dacb"].map(String::from);
+    get_arguments(args);
+}
+
+#[test]
+#[should_panic]
+fn test_get_arguments_partitions_power_of_two() {
+    let args = ["--partitions", "100", "1178568022"].map(String::from);
+    get_arguments(args);
+}
+
+#[test]
+fn test_get_arguments_quick_epoch_pass() {
+    let args = ["--quick-epoch-pass", "1600000000:1600086400", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.quick_epoch_pass, Some(1600000000..=1600086400));
+}
+
+#[test]
+fn test_get_arguments_potfile_disable() {
+    let args = ["--potfile-disable", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.potfile_disable);
+    assert!(open_potfile(&options).is_none());
+}
+
+#[test]
+fn test_get_arguments_session() {
+    let args = ["--session", "target", "--restore"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.session.as_deref(), Some("target"));
+    assert!(options.restore);
+    assert!(arguments.is_empty());
+}
+
+#[test]
+fn test_get_arguments_replay() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_progress");
+    std::fs::write(&path, "order 7.1 = 1..2 0..1 2..3\n7.1 1\n").unwrap();
+    let args = [
+        "--replay",
+        path.to_str().unwrap(),
+        "--dispatch-workgroups",
+        "64",
+    ];
+    let (options, arguments) = get_arguments(args.map(String::from));
+    let (replayed, checkpoint) = options.replay.unwrap();
+    assert_eq!(replayed, path);
+    let all = 0..3;
+    assert_eq!(checkpoint.replay(Flavor::Php71, vec![all]), [0..1, 2..3]);
+    assert_eq!(options.dispatch_workgroups, Some(64));
+    assert!(arguments.is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_get_arguments_checkpoint_interval() {
+    let args = ["--session", "target", "--checkpoint-interval", "60000", "1"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.checkpoint_interval, Duration::from_secs(60));
+    let (options, _) = get_arguments(["1".to_string()]);
+    assert_eq!(
+        options.checkpoint_interval,
+        session::DEFAULT_CHECKPOINT_INTERVAL
+    );
+}
+
+#[test]
+fn test_get_arguments_step_order() {
+    let args = ["--step-order", "time", "--first", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.step_order, StepOrder::Time);
+    assert!(options.first);
+}
+
+#[test]
+fn test_get_arguments_max_results() {
+    let args = ["--max-results", "3", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.max_results, Some(3));
+    assert!(!options.first);
+    assert!(!options.abort_dispatches);
+    let args = ["--max-results", "3", "--abort-dispatches", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.abort_dispatches);
+}
+
+#[test]
+fn test_get_arguments_progress_json() {
+    let args = [
+        "--progress-json",
+        "--progress-interval",
+        "250",
+        "--progress-line-interval",
+        "60000",
+        "1178568022",
+    ]
+    .map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.progress_json);
+    assert_eq!(options.progress_interval, Duration::from_millis(250));
+    assert_eq!(options.progress_line_interval, Duration::from_secs(60));
+}
+
+#[test]
+fn test_get_arguments_log_file() {
+    let args = ["--log-file", "run.log", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.log_file, Some(PathBuf::from("run.log")));
+}
+
+#[test]
+fn test_get_arguments_gpu_info() {
+    let args = ["gpu-info", "--json"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.gpu_info && options.json);
+    assert!(arguments.is_empty());
+}
+
+#[test]
+fn test_get_arguments_doctor() {
+    let args = ["doctor", "--allow-software"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.doctor && options.allow_software);
+    assert!(arguments.is_empty());
+}
+
+#[cfg(feature = "distributed")]
+#[test]
+fn test_get_arguments_work_units() {
+    let args = ["export-work", "units", "16", "--php", "5.2.1", "42"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.export_work, Some((PathBuf::from("units"), 16)));
+    assert_eq!(arguments, [42]);
+
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_unit.work");
+    let unit = &work_unit::split(&["42".to_string()], 256, 4).unwrap()[2];
+    std::fs::write(&path, unit.to_text()).unwrap();
+    let args = ["work", path.to_str().unwrap()].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.work_unit, Some((path.clone(), unit.clone())));
+    assert!(arguments.is_empty());
+    std::fs::remove_file(&path).unwrap();
+
+    let args = ["import-results", "a.results", "b.results"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(
+        options.import_results,
+        Some(vec!["a.results".to_string(), "b.results".to_string()])
+    );
+}
+
+#[cfg(feature = "telemetry")]
+#[test]
+fn test_get_arguments_telemetry_port() {
+    let args = ["--telemetry-port", "9100", "1178568022"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.telemetry.is_some());
+    assert!(options.telemetry_bind.is_loopback());
+    assert_eq!(arguments, vec![1178568022]);
+    let args = [
+        "--telemetry-bind",
+        "0.0.0.0",
+        "--telemetry-port",
+        "9100",
+        "1",
+    ]
+    .map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.telemetry_bind.is_unspecified());
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn test_get_arguments_trace_output() {
+    let args = ["--trace-output", "search.json", "1178568022"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert_eq!(options.trace_output, Some(PathBuf::from("search.json")));
+    assert_eq!(arguments, vec![1178568022]);
+}
+
+#[cfg(feature = "syslog")]
+#[test]
+fn test_get_arguments_syslog() {
+    let args = ["--syslog", "1178568022"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.syslog.is_some());
+    assert_eq!(arguments, vec![1178568022]);
+}
+
+#[cfg(feature = "sink")]
+#[test]
+fn test_get_arguments_sink() {
+    let args = ["--sink", "tcp://127.0.0.1:9000", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.sink.is_some());
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn test_get_arguments_webhook() {
+    let args = ["--webhook", "http://127.0.0.1:8080/hook", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.webhook.is_some());
+    assert_eq!(options.webhook_on, WebhookEvent::Finish);
+    let args = ["--webhook", "https://hooks.local/done", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert!(options.webhook.is_some());
+    let args = ["--webhook-on", "first-hit", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.webhook_on, WebhookEvent::FirstHit);
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn test_get_arguments_notify() {
+    let args = [
+        "--notify",
+        "slack://T000/B000/XXXX",
+        "--notify",
+        "discord://1234/abcd",
+        "1178568022",
+    ]
+    .map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(
+        options.notify,
+        [
+            Notify::Slack("T000/B000/XXXX".to_string()),
+            Notify::Discord("1234/abcd".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_get_arguments_emit() {
+    let args = ["--emit-php", "verify.php", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(
+        options.emit,
+        vec![(Language::Php, PathBuf::from("verify.php"))]
+    );
+
+    let args = [
+        "--emit",
+        "python",
+        "verify.py",
+        "--emit",
+        "rust",
+        "verify.rs",
+    ];
+    let (options, _) = get_arguments(args.map(String::from));
+    assert_eq!(
+        options.emit,
+        vec![
+            (Language::Python, PathBuf::from("verify.py")),
+            (Language::Rust, PathBuf::from("verify.rs"))
+        ]
+    );
+}
+
+#[test]
+fn test_get_arguments_weights() {
+    let args = ["--weights", "1,5", "7505~10@1000-10000", "0-61@0-61"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.weights, Some(vec![1, 5]));
+}
+
+#[test]
+fn test_get_arguments_max_expected() {
+    let args = ["--max-expected", "10", "0-61@0-61"].map(String::from);
+    let (options, arguments) = get_arguments(args.clone());
+    assert_eq!(options.max_expected, 10);
+    assert!(too_weak(&options, &arguments));
+    let (options, _) = get_arguments([&["--force".to_string()], &args[..]].concat());
+    assert!(options.force);
+    let (options, mut arguments) = get_arguments(["1178568022"].map(String::from));
+    normalize_arguments(&mut arguments);
+    assert_eq!(options.max_expected, php_mt_seed::DEFAULT_MAX_EXPECTED);
+    assert!(!too_weak(&options, &arguments));
+}
+
+#[test]
+fn test_get_arguments_stats() {
+    let args = ["--stats", "5-5@0-61*6"].map(String::from);
+    let (options, arguments) = get_arguments(args);
+    assert!(options.stats.is_some());
+    assert_eq!(arguments.len(), 24);
+}
+
+#[test]
+fn test_get_arguments_seed_transforms() {
+    let args = [
+        "--seed-offset",
+        "-1000",
+        "--crc32-wordlist",
+        "words.txt",
+        "1178568022",
+    ];
+    let (options, _) = get_arguments(args.map(String::from));
+    assert_eq!(
+        options.seed_transforms,
+        vec![
+            SeedTransform::Offset(-1000),
+            SeedTransform::Crc32(PathBuf::from("words.txt"))
+        ]
+    );
+}
+
+#[test]
+fn test_get_arguments_max_temp() {
+    let args = ["--max-temp", "80", "1178568022"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.max_temp, Some(80));
+}
+
+#[test]
+fn test_get_arguments_verbose() {
+    let (options, _) = get_arguments(["-v", "1178568022"].map(String::from));
+    assert!(options.verbose);
+    let (options, _) = get_arguments(["--verbose"].map(String::from));
+    assert!(options.verbose);
+}
+
+#[test]
+fn test_get_arguments_batch() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_batch.txt");
+    std::fs::write(&path, "1178568022\n# skipped\n5 9 0 61\n").unwrap();
+    let args = ["--batch".to_string(), path.display().to_string()];
+    let (options, arguments) = get_arguments(args);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        options.batch,
+        Some(vec![
+            vec![1178568022, 1178568022, 0, 0x7fffffff],
+            vec![5, 9, 0, 61]
+        ])
+    );
+    assert!(arguments.is_empty());
+}
+
+#[test]
+fn test_get_arguments_crack_many() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_crack_many.txt");
+    std::fs::write(&path, "1178568022\n--php 5.2.1 963932192\n").unwrap();
+    let args = [
+        "--nice".to_string(),
+        "crack-many".to_string(),
+        path.display().to_string(),
+    ];
+    let (options, arguments) = get_arguments(args);
+    std::fs::remove_file(&path).unwrap();
+    assert!(options.nice);
+    assert_eq!(options.concurrent_jobs, 1);
+    assert_eq!(
+        options.jobs,
+        Some(vec![
+            vec!["1178568022".to_string()],
+            ["--php", "5.2.1", "963932192"].map(String::from).to_vec(),
+        ])
+    );
+    assert!(arguments.is_empty());
+}
+
+#[test]
+fn test_get_arguments_concurrent_jobs() {
+    let args = ["--concurrent-jobs", "4"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.concurrent_jobs, 4);
+    let args = ["--concurrent-jobs", "0"].map(String::from);
+    let (options, _) = get_arguments(args);
+    assert_eq!(options.concurrent_jobs, 1);
+}
+
+#[test]
+fn test_job_summary() {
+    let found = [(Flavor::Php71, vec![0, 42]), (Flavor::Php52, vec![])];
+    assert_eq!(job_summary(&found), "0, 42 (PHP 7.1.0+)");
+    assert_eq!(job_summary(&found[1..]), "no seed found");
 }
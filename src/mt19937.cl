@@ -0,0 +1,236 @@
+// License: Apache 2.0 OR MIT, at your option
+// OpenCL port of mt19937.wgsl, keep both in sync.
+// Based on code from https://github.com/dcrewi/rust-mersenne-twister
+
+#define N 624
+#define M 397
+#define MATRIX_A 0x9908b0dfu
+#define UPPER_MASK 0x80000000u
+#define LOWER_MASK 0x7fffffffu
+
+// Flavors, see flavor.rs
+#define FLAVOR_PHP71 0u
+#define FLAVOR_PHP52 1u
+// Both flavors at once: seeds matching in either are found.
+#define FLAVOR_ALL 2u
+
+typedef struct {
+    uint idx;
+    uint args_len;
+    uint flavor;
+    uint state[20];
+} Mersenne;
+
+void reseed(Mersenne *mt, uint seed) {
+    mt->idx = N;
+    mt->state[0] = seed;
+    uint args_len = mt->args_len;
+    for (uint i = 1; i <= args_len; i++) {
+        mt->state[i] = 1812433253u * (mt->state[i - 1] ^ (mt->state[i - 1] >> 30)) + i;
+    }
+    uint m = mt->state[args_len];
+    for (uint i = args_len + 1; i < M; i++) {
+        m = 1812433253u * (m ^ (m >> 30)) + i;
+    }
+    mt->state[args_len + 1] = m;
+    for (uint i = M; i < M + args_len; i++) {
+        uint j = i + args_len + 2 - M;
+        mt->state[j] = 1812433253u * (mt->state[j - 1] ^ (mt->state[j - 1] >> 30)) + i;
+    }
+}
+
+uint temper(uint y) {
+    uint x = y;
+    x ^= x >> 11;
+    x ^= (x << 7) & 0x9d2c5680u;
+    x ^= (x << 15) & 0xefc60000u;
+    x ^= x >> 18;
+    return x;
+}
+
+void fill_next_state(Mersenne *mt) {
+    uint args_len = mt->args_len;
+    for (uint i = 0; i < args_len; i++) {
+        uint x = (mt->state[i] & UPPER_MASK) | (mt->state[i + 1] & LOWER_MASK);
+        // PHP before 7.1.0 took the low bit of the wrong word.
+        uint low_bit = mt->flavor == FLAVOR_PHP52 ? mt->state[i] & 1 : x & 1;
+        mt->state[i] = mt->state[i + args_len + 2] ^ (x >> 1) ^ (low_bit * MATRIX_A);
+    }
+    mt->idx = 0;
+}
+
+uint next(Mersenne *mt) {
+    if (mt->idx >= N) {
+        fill_next_state(mt);
+    }
+    uint x = mt->state[mt->idx];
+    mt->idx++;
+    return temper(x);
+}
+
+// PHP's RAND_RANGE_BADSCALING: `(long)((double)range * (n / 2147483648.0))`, that is the 63-bit
+// product `range * n` rounded to 53 significant bits like a double, then divided by 2^31.
+// Doubles are optional in OpenCL, so this is done on integers. 64-bit PHP takes ranges up to 2^32
+// values.
+uint bad_scaling(uint n, ulong range) {
+    ulong product = (ulong)n * range;
+    uint bits = 64 - clz(product);
+    if (bits > 53) {
+        uint shift = bits - 53;
+        ulong mask = (1ul << shift) - 1;
+        ulong half = 1ul << (shift - 1);
+        ulong dropped = product & mask;
+        product &= ~mask;
+        if (dropped > half || (dropped == half && ((product >> shift) & 1))) {
+            product += 1ul << shift;
+        }
+    }
+    return (uint)(product >> 31);
+}
+
+// Whether the outputs of `mt` match every slot of `input`.
+bool input_matches(Mersenne *mt, global const uint *input, uint input_len) {
+    for (uint i = 3; i < input_len; i += 4) {
+        uint match_min = input[i + 0];
+        uint match_max = input[i + 1];
+        uint range_min = input[i + 2];
+        uint range_max = input[i + 3];
+
+        uint nextint = next(mt);
+        // A range of 2^32 values wraps to 0, and takes the whole output in PHP 7.1.0+.
+        uint range = range_max - range_min + 1;
+        uint randint = (range_min == 0 && range_max == 0x7fffffffu)
+            ? nextint >> 1
+            : (mt->flavor == FLAVOR_PHP52
+                ? bad_scaling(nextint >> 1, (ulong)(range_max - range_min) + 1)
+                : (range == 0 ? nextint : nextint % range)) + range_min;
+
+        if (randint < match_min || randint > match_max) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// `input` is laid out like in the WGSL shader: step, offset, flavor, then the constraints.
+// `output` starts with the number of matching seeds, followed by the seeds themselves.
+kernel void search(
+    global const uint *input,
+    uint input_len,
+    global uint *output,
+    uint output_len
+) {
+    uint step = input[0];
+    uint offset = input[1];
+
+    uint flavor = input[2];
+
+    Mersenne mt;
+    mt.flavor = flavor == FLAVOR_ALL ? FLAVOR_PHP71 : flavor;
+    mt.args_len = (input_len - 3) / 4;
+    uint seed = (offset + (uint)get_global_id(0)) * 256 + step;
+    reseed(&mt, seed);
+    // The flavors only differ from the first twist on: with both, the state is initialized once,
+    // the most expensive part, and copied before the first one consumes it.
+    Mersenne php52 = mt;
+    php52.flavor = FLAVOR_PHP52;
+
+    bool seed_is_valid = input_matches(&mt, input, input_len);
+    if (!seed_is_valid && flavor == FLAVOR_ALL) {
+        seed_is_valid = input_matches(&php52, input, input_len);
+    }
+
+    if (seed_is_valid) {
+        uint insert_index = atomic_inc(&output[0]);
+        if (insert_index < output_len - 1) {
+            output[1 + insert_index] = seed;
+        }
+    }
+}
+
+// Searches a single exact output of mt_rand() without arguments, like exact_main in the WGSL
+// shader: the first output only depends on state[0], state[1] and state[M] after seeding.
+kernel void exact_search(
+    global const uint *input,
+    uint input_len,
+    global uint *output,
+    uint output_len
+) {
+    uint step = input[0];
+    uint offset = input[1];
+    uint flavor = input[2];
+    uint value = input[3];
+
+    uint seed = (offset + (uint)get_global_id(0)) * 256 + step;
+    uint second = 1812433253u * (seed ^ (seed >> 30)) + 1;
+    uint m = second;
+    for (uint i = 2; i <= M; i++) {
+        m = 1812433253u * (m ^ (m >> 30)) + i;
+    }
+    uint x = (seed & UPPER_MASK) | (second & LOWER_MASK);
+    // PHP before 7.1.0 took the low bit of the wrong word.
+    uint php71 = temper(m ^ (x >> 1) ^ ((x & 1) * MATRIX_A)) >> 1;
+    uint php52 = temper(m ^ (x >> 1) ^ ((seed & 1) * MATRIX_A)) >> 1;
+
+    bool seed_is_valid = (flavor != FLAVOR_PHP52 && php71 == value)
+        || (flavor != FLAVOR_PHP71 && php52 == value);
+    if (seed_is_valid) {
+        uint insert_index = atomic_inc(&output[0]);
+        if (insert_index < output_len - 1) {
+            output[1 + insert_index] = seed;
+        }
+    }
+}
+
+// Searches slots that are all small ranges mt_rand(0, MAX), packed into a word each like for
+// small_main in the WGSL shader: the words of each output are seeded as it is drawn, so that a
+// seed stops at its first mismatching output.
+bool small_slot_matches(uint nextint, uint flavor, uint slot) {
+    uint match_min = slot & 0x3ff;
+    uint match_max = (slot >> 10) & 0x3ff;
+    uint range = (slot >> 20) + 1;
+    uint randint = flavor == FLAVOR_PHP52 ? bad_scaling(nextint >> 1, range) : nextint % range;
+    return randint >= match_min && randint <= match_max;
+}
+
+kernel void small_search(
+    global const uint *input,
+    uint input_len,
+    global uint *output,
+    uint output_len
+) {
+    uint step = input[0];
+    uint offset = input[1];
+    uint flavor = input[2];
+
+    uint seed = (offset + (uint)get_global_id(0)) * 256 + step;
+    // state[i], state[i + 1] and state[i + M], which the output i is twisted from.
+    uint current = seed;
+    uint following = 1812433253u * (seed ^ (seed >> 30)) + 1;
+    uint far = following;
+    for (uint i = 2; i <= M; i++) {
+        far = 1812433253u * (far ^ (far >> 30)) + i;
+    }
+
+    bool php71 = flavor != FLAVOR_PHP52;
+    bool php52 = flavor != FLAVOR_PHP71;
+    for (uint i = 0; i < input_len - 3 && (php71 || php52); i++) {
+        uint slot = input[3 + i];
+        uint x = (current & UPPER_MASK) | (following & LOWER_MASK);
+        // PHP before 7.1.0 took the low bit of the wrong word.
+        uint output71 = temper(far ^ (x >> 1) ^ ((x & 1) * MATRIX_A));
+        uint output52 = temper(far ^ (x >> 1) ^ ((current & 1) * MATRIX_A));
+        php71 = php71 && small_slot_matches(output71, FLAVOR_PHP71, slot);
+        php52 = php52 && small_slot_matches(output52, FLAVOR_PHP52, slot);
+        current = following;
+        following = 1812433253u * (following ^ (following >> 30)) + i + 2;
+        far = 1812433253u * (far ^ (far >> 30)) + i + M + 1;
+    }
+
+    if (php71 || php52) {
+        uint insert_index = atomic_inc(&output[0]);
+        if (insert_index < output_len - 1) {
+            output[1 + insert_index] = seed;
+        }
+    }
+}
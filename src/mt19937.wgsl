@@ -1,5 +1,6 @@
 // License: Apache 2.0 OR MIT, at your option
 // Based on code from https://github.com/dcrewi/rust-mersenne-twister
+// mt19937.cl is an OpenCL port of this shader, keep both in sync.
 
 struct OutputVec {
     size: atomic<u32>,
@@ -14,6 +15,21 @@ var<storage, read> input: array<u32>;
 // Output of the shader.  
 @group(0) @binding(1)
 var<storage, read_write> output: OutputVec;
+// Output of batch_main, bound instead of `output`: one region of the same size per query, each
+// with its own count followed by the seeds.
+@group(0) @binding(1)
+var<storage, read_write> regions: array<atomic<u32>>;
+
+// When a dispatch has found as many seeds as the search still needs (`limit`, 0xffffffff when it
+// needs them all), `flag` is raised and the invocations that did not start yet return right away.
+// batch_main ignores it, and mt19937.cl has none: its dispatches always run whole.
+struct Abort {
+    limit: u32,
+    flag: atomic<u32>,
+}
+
+@group(0) @binding(2)
+var<storage, read_write> abort: Abort;
 
 var<workgroup> output_index: atomic<u32>;
 
@@ -23,14 +39,21 @@ const MATRIX_A: u32 = 0x9908b0df;
 const UPPER_MASK: u32 = 0x80000000;
 const LOWER_MASK: u32 = 0x7fffffff;
 
+// Flavors, see flavor.rs
+const FLAVOR_PHP71: u32 = 0;
+const FLAVOR_PHP52: u32 = 1;
+// Both flavors at once, only understood by main: seeds matching in either are found.
+const FLAVOR_ALL: u32 = 2;
+
 struct Mersenne {
     idx: u32,
     args_len: u32,
+    flavor: u32,
     state: array<u32, 20>,
 }
 
-fn init(args_len: u32) -> Mersenne {
-    return Mersenne(0, args_len, array<u32, 20>());
+fn init(args_len: u32, flavor: u32) -> Mersenne {
+    return Mersenne(0, args_len, flavor, array<u32, 20>());
 }
 
 fn reseed(mt: ptr<function, Mersenne>, seed: u32) {
@@ -64,7 +87,9 @@ fn fill_next_state(mt: ptr<function, Mersenne>) {
     let args_len = (*mt).args_len;
     for (var i: u32 = 0; i < args_len; i++) {
         let x = ((*mt).state[i] & UPPER_MASK) | ((*mt).state[i + 1] & LOWER_MASK);
-        (*mt).state[i] = (*mt).state[i + args_len + 2] ^ (x >> 1) ^ ((x & 1) * MATRIX_A);
+        // PHP before 7.1.0 took the low bit of the wrong word.
+        let low_bit = select(x & 1, (*mt).state[i] & 1, (*mt).flavor == FLAVOR_PHP52);
+        (*mt).state[i] = (*mt).state[i + args_len + 2] ^ (x >> 1) ^ (low_bit * MATRIX_A);
     }
 
     /*
@@ -89,47 +114,265 @@ fn next(mt: ptr<function, Mersenne>) -> u32 {
     return temper(x);
 }
 
+// PHP's RAND_RANGE_BADSCALING: `(long)((double)range * (n / 2147483648.0))`, that is the 63-bit
+// product `range * n` rounded to 53 significant bits like a double, then divided by 2^31.
+// Shaders do not have doubles everywhere, so this is done with two u32 halves. A range of 2^32
+// values, which only 64-bit PHP takes, wraps to 0 and is left to the caller.
+fn bad_scaling(n: u32, range: u32) -> u32 {
+    // 32x32 -> 64 bits multiplication, from 16-bit halves
+    let lo_lo = (n & 0xffff) * (range & 0xffff);
+    let hi_lo = (n >> 16) * (range & 0xffff);
+    let lo_hi = (n & 0xffff) * (range >> 16);
+    let hi_hi = (n >> 16) * (range >> 16);
+    let cross = (lo_lo >> 16) + (hi_lo & 0xffff) + (lo_hi & 0xffff);
+    var lo = (cross << 16) | (lo_lo & 0xffff);
+    var hi = hi_hi + (hi_lo >> 16) + (lo_hi >> 16) + (cross >> 16);
+
+    // Round to nearest even when the product does not fit in 53 bits, that is when `hi` has
+    // more than 21 bits. n < 2^31 and range < 2^32, so at most 10 bits are dropped from `lo`.
+    let hi_bits = 32 - countLeadingZeros(hi);
+    if hi_bits > 21 {
+        let shift = hi_bits - 21;
+        let mask = (1u << shift) - 1;
+        let half = 1u << (shift - 1);
+        let dropped = lo & mask;
+        lo &= ~mask;
+        if dropped > half || (dropped == half && ((lo >> shift) & 1) == 1) {
+            let rounded = lo + (1u << shift);
+            hi += select(0u, 1u, rounded < lo);
+            lo = rounded;
+        }
+    }
+
+    return (hi << 1) | (lo >> 31);
+}
+
+// Whether the output `nextint` of a call to mt_rand(range_min, range_max) is in
+// match_min..=match_max.
+fn slot_matches(
+    nextint: u32,
+    flavor: u32,
+    match_min: u32,
+    match_max: u32,
+    range_min: u32,
+    range_max: u32,
+) -> bool {
+    // A range of 2^32 values wraps to 0: PHP 7.1.0+ takes the whole output, and PHP 5.2.1+
+    // doubles it.
+    let range = range_max - range_min + 1;
+    let scaled = select(
+        select(nextint % range, nextint, range == 0),
+        select(bad_scaling(nextint >> 1, range), nextint & ~1u, range == 0),
+        flavor == FLAVOR_PHP52
+    );
+    let randint = select(
+        scaled + range_min,
+        nextint >> 1,
+        range_min == 0 && range_max == 0x7fffffff
+    );
+    return randint >= match_min && randint <= match_max;
+}
+
+// Whether the outputs of `mt` match every slot of the input of main.
+fn input_matches(mt: ptr<function, Mersenne>) -> bool {
+    let end = arrayLength(&input);
+    for (var i: u32 = 3; i < end; i += 4) {
+        let nextint = next(mt);
+        if !slot_matches(nextint, (*mt).flavor, input[i], input[i + 1], input[i + 2], input[i + 3]) {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Whether the dispatch found enough seeds already.
+fn aborted() -> bool {
+    return atomicLoad(&abort.flag) != 0;
+}
+
+// Append a seed found to the output, raising the abort flag if it is the last one needed.
+fn report(seed: u32) {
+    let insert_index: u32 = atomicAdd(&output.size, 1);
+    if insert_index < arrayLength(&output.data) {
+        output.data[insert_index] = seed;
+    }
+    if insert_index + 1 >= abort.limit {
+        atomicStore(&abort.flag, 1);
+    }
+}
+
 // Ideal workgroup size depends on the hardware, the workload, and other factors. However, it should
 // _generally_ be a multiple of 64. Common sizes are 64x1x1, 256x1x1; or 8x8x1, 16x16x1 for 2D workloads.
 @compute @workgroup_size(256, 1, 1)
-fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if aborted() {
+        return;
+    }
     // Compute the first value and write to the output.
 
     // step is the step X/256
     // as the shader is executed 256 times
     // and each time computes 2^24 seeds
     let step = input[0];
+    // a step may be split into several dispatches, offset is the index of the first invocation
+    let offset = input[1];
+    // which variant of mt_rand() is searched
+    let flavor = input[2];
 
-    let args_len = (arrayLength(&input) - 1) / 4;
-    var mt = init(args_len);
-    let seed = global_id.x * 256 + step;
+    let args_len = (arrayLength(&input) - 3) / 4;
+    var mt = init(args_len, select(flavor, FLAVOR_PHP71, flavor == FLAVOR_ALL));
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
     reseed(&mt, seed);
+    // The flavors only differ from the first twist on: with both, the state is initialized once,
+    // the most expensive part, and copied before the first one consumes it.
+    var php52 = mt;
+    php52.flavor = FLAVOR_PHP52;
 
-    var seed_is_valid = true;
-    let end = arrayLength(&input);
-    for (var i: u32 = 1; i < end; i += 4) {
-        let match_min = input[i + 0];
-        let match_max = input[i + 1];
-        let range_min = input[i + 2];
-        let range_max = input[i + 3];
-
-        let nextint = next(&mt);
-        let randint = select(
-            nextint % (range_max - range_min + 1) + range_min,
-            nextint >> 1,
-            range_min == 0 && range_max == 0x7fffffff
-        );
-        
-        if randint < match_min || randint > match_max {
-            seed_is_valid = false;
-            break;
-        }
+    var seed_is_valid = input_matches(&mt);
+    if !seed_is_valid && flavor == FLAVOR_ALL {
+        seed_is_valid = input_matches(&php52);
+    }
+
+    if seed_is_valid {
+        report(seed);
+    }
+}
+
+// Searches a single exact output of mt_rand() without arguments, the most common query, like main
+// with the slot `value value 0 0x7fffffff`. The first output only depends on state[0], state[1]
+// and state[M] after seeding, which are computed without keeping any state.
+@compute @workgroup_size(256, 1, 1)
+fn exact_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if aborted() {
+        return;
+    }
+    let step = input[0];
+    let offset = input[1];
+    let flavor = input[2];
+    let value = input[3];
+
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
+    let second = 1812433253 * (seed ^ (seed >> 30)) + 1;
+    var m = second;
+    for (var i: u32 = 2; i <= M; i++) {
+        m = 1812433253 * (m ^ (m >> 30)) + i;
     }
+    let x = (seed & UPPER_MASK) | (second & LOWER_MASK);
+    // PHP before 7.1.0 took the low bit of the wrong word.
+    let php71 = temper(m ^ (x >> 1) ^ ((x & 1) * MATRIX_A)) >> 1;
+    let php52 = temper(m ^ (x >> 1) ^ ((seed & 1) * MATRIX_A)) >> 1;
 
+    let seed_is_valid = (flavor != FLAVOR_PHP52 && php71 == value)
+        || (flavor != FLAVOR_PHP71 && php52 == value);
     if seed_is_valid {
-        let insert_index: u32 = atomicAdd(&output.size, 1);
-        if insert_index < arrayLength(&output.data) {
-            output.data[insert_index] = seed;
+        report(seed);
+    }
+}
+
+// Searches slots that are all small ranges mt_rand(0, MAX), such as those of shuffles. Each is
+// packed into a word, MATCH_MIN | MATCH_MAX << 10 | MAX << 20, after the step, offset and flavor
+// of main. Instead of seeding the state of every slot first, the words of each output are seeded
+// as it is drawn, so that a seed stops at its first mismatching output.
+@compute @workgroup_size(256, 1, 1)
+fn small_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if aborted() {
+        return;
+    }
+    let step = input[0];
+    let offset = input[1];
+    let flavor = input[2];
+    let slots = arrayLength(&input) - 3;
+
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
+    // state[i], state[i + 1] and state[i + M], which the output i is twisted from.
+    var current = seed;
+    var following = 1812433253 * (seed ^ (seed >> 30)) + 1;
+    var far = following;
+    for (var i: u32 = 2; i <= M; i++) {
+        far = 1812433253 * (far ^ (far >> 30)) + i;
+    }
+
+    var php71 = flavor != FLAVOR_PHP52;
+    var php52 = flavor != FLAVOR_PHP71;
+    for (var i: u32 = 0; i < slots && (php71 || php52); i++) {
+        let slot = input[3 + i];
+        let match_min = slot & 0x3ff;
+        let match_max = (slot >> 10) & 0x3ff;
+        let range_max = slot >> 20;
+        let x = (current & UPPER_MASK) | (following & LOWER_MASK);
+        // PHP before 7.1.0 took the low bit of the wrong word.
+        let output71 = temper(far ^ (x >> 1) ^ ((x & 1) * MATRIX_A));
+        let output52 = temper(far ^ (x >> 1) ^ ((current & 1) * MATRIX_A));
+        php71 = php71 && slot_matches(output71, FLAVOR_PHP71, match_min, match_max, 0, range_max);
+        php52 = php52 && slot_matches(output52, FLAVOR_PHP52, match_min, match_max, 0, range_max);
+        current = following;
+        following = 1812433253 * (following ^ (following >> 30)) + i + 2;
+        far = 1812433253 * (far ^ (far >> 30)) + i + M + 1;
+    }
+
+    if php71 || php52 {
+        report(seed);
+    }
+}
+
+// Checks several queries at once, so that the MT19937 state of each seed is only computed once.
+//
+// The input starts like the one of main, then has the number of queries and the number of slots
+// of the longest one. Each query follows: its number of slots, then its slots, padded to the
+// longest one.
+@compute @workgroup_size(256, 1, 1)
+fn batch_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    let step = input[0];
+    let offset = input[1];
+    let flavor = input[2];
+    let query_count = input[3];
+    let args_len = input[4];
+    let region_size = arrayLength(&regions) / query_count;
+
+    var mt = init(args_len, flavor);
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
+    reseed(&mt, seed);
+    // As many outputs as main keeps state for
+    var outputs: array<u32, 8>;
+    for (var i: u32 = 0; i < args_len; i++) {
+        outputs[i] = next(&mt);
+    }
+
+    let stride = 1 + 4 * args_len;
+    for (var query: u32 = 0; query < query_count; query++) {
+        let block = 5 + query * stride;
+        let slots = input[block];
+        var seed_is_valid = true;
+        for (var slot: u32 = 0; slot < slots; slot++) {
+            let i = block + 1 + 4 * slot;
+            if !slot_matches(outputs[slot], flavor, input[i], input[i + 1], input[i + 2], input[i + 3]) {
+                seed_is_valid = false;
+                break;
+            }
+        }
+
+        if seed_is_valid {
+            let region = query * region_size;
+            let insert_index = atomicAdd(&regions[region], 1);
+            if insert_index < region_size - 1 {
+                atomicStore(&regions[region + 1 + insert_index], seed);
+            }
         }
     }
 }
@@ -0,0 +1,131 @@
+// `--notify slack://...` and `--notify discord://...`: the end of the search posted to the incoming
+// webhook of a Slack or Discord channel, with how long it took and the seeds found, so that the
+// results reach the channel of the engagement instead of a log file.
+
+use std::time::Duration;
+
+use crate::bench::format_duration;
+use crate::flavor::{format_merged_seed, merge, Flavor};
+use crate::gpu_info::json_string;
+use crate::webhook::post_json;
+
+/// How many seeds the message lists, the others are only counted: chat messages are short.
+const MAX_SEEDS: usize = 20;
+
+/// A chat channel of `--notify`, by its incoming webhook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notify {
+    /// `slack://T000/B000/XXXX`, for `https://hooks.slack.com/services/T000/B000/XXXX`.
+    Slack(String),
+    /// `discord://ID/TOKEN`, for `https://discord.com/api/webhooks/ID/TOKEN`.
+    Discord(String),
+}
+
+impl std::str::FromStr for Notify {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, path) = s.split_once("://").unwrap_or((s, ""));
+        let parts = path.split('/').filter(|part| !part.is_empty()).count();
+        match (scheme, parts) {
+            ("slack", 3) => Ok(Notify::Slack(path.to_string())),
+            ("slack", _) => Err(format!("{s:?} is not slack://T000/B000/XXXX")),
+            ("discord", 2) => Ok(Notify::Discord(path.to_string())),
+            ("discord", _) => Err(format!("{s:?} is not discord://ID/TOKEN")),
+            _ => Err(format!(
+                "unknown channel {s:?}, expected slack://T000/B000/XXXX or discord://ID/TOKEN"
+            )),
+        }
+    }
+}
+
+impl Notify {
+    /// The incoming webhook the message is posted to.
+    fn url(&self) -> String {
+        match self {
+            Notify::Slack(path) => format!("https://hooks.slack.com/services/{path}"),
+            Notify::Discord(path) => format!("https://discord.com/api/webhooks/{path}"),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Notify::Slack(_) => "Slack",
+            Notify::Discord(_) => "Discord",
+        }
+    }
+
+    /// The JSON body of the webhook: Slack takes the message as `text`, Discord as `content`.
+    pub fn body(&self, found: &[(Flavor, Vec<u32>)], elapsed: Duration) -> String {
+        let field = match self {
+            Notify::Slack(_) => "text",
+            Notify::Discord(_) => "content",
+        };
+        format!("{{\"{field}\":{}}}", json_string(&message(found, elapsed)))
+    }
+
+    /// Post the message of the search, like `--webhook` posts its payload. Failures are only
+    /// logged: the results are printed anyway.
+    pub fn send(&self, found: &[(Flavor, Vec<u32>)], elapsed: Duration) {
+        match post_json(&self.url(), &self.body(found, elapsed)) {
+            Ok(200..=299) => log::info!("notified {}", self.name()),
+            Ok(status) => log::warn!("cannot notify {}: got {status}", self.name()),
+            Err(e) => log::warn!("cannot notify {}: {e}", self.name()),
+        }
+    }
+}
+
+/// The chat message of a search done in `elapsed`: a line with its duration and how many seeds
+/// were `found`, then the result lines of the first ones in a code block.
+pub fn message(found: &[(Flavor, Vec<u32>)], elapsed: Duration) -> String {
+    let seeds = merge(found);
+    let mut message = format!(
+        "php_mt_seed_rs search finished in {}: {} found",
+        format_duration(elapsed.as_secs_f64()),
+        match seeds.len() {
+            1 => "1 seed".to_string(),
+            count => format!("{count} seeds"),
+        }
+    );
+    if seeds.is_empty() {
+        return message;
+    }
+    message.push_str("\n```\n");
+    for (seed, flavors) in seeds.iter().take(MAX_SEEDS) {
+        message.push_str(&format_merged_seed(*seed, flavors));
+        message.push('\n');
+    }
+    if seeds.len() > MAX_SEEDS {
+        message.push_str(&format!("... and {} more\n", seeds.len() - MAX_SEEDS));
+    }
+    message.push_str("```");
+    message
+}
+
+#[test]
+fn test_notify() {
+    assert_eq!(
+        "slack://T000/B000/XXXX".parse(),
+        Ok(Notify::Slack("T000/B000/XXXX".to_string()))
+    );
+    let discord: Notify = "discord://1234/abcd".parse().unwrap();
+    assert_eq!(discord.url(), "https://discord.com/api/webhooks/1234/abcd");
+    assert!("slack://T000".parse::<Notify>().is_err());
+    assert!("https://hooks.slack.com/services/T000/B000/XXXX"
+        .parse::<Notify>()
+        .is_err());
+
+    let elapsed = Duration::from_secs(90);
+    assert_eq!(
+        message(&[], elapsed),
+        "php_mt_seed_rs search finished in 1.5 min: 0 seeds found"
+    );
+    let found = [(Flavor::Php71, vec![4242])];
+    assert_eq!(
+        discord.body(&found, elapsed),
+        "{\"content\":\"php_mt_seed_rs search finished in 1.5 min: 1 seed found\\u000a```\
+         \\u000aseed = 0x1092 = 4242 (PHP 7.1.0+)\\u000a```\"}"
+    );
+    let many = [(Flavor::Php71, (0..25).collect())];
+    assert!(message(&many, elapsed).ends_with("(PHP 7.1.0+)\n... and 5 more\n```"));
+}
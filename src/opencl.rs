@@ -0,0 +1,184 @@
+use std::{ops::Range, ptr, sync::Mutex};
+
+use opencl3::{
+    command_queue::CommandQueue,
+    context::Context,
+    device::{get_all_devices, Device, CL_DEVICE_TYPE_ALL, CL_DEVICE_TYPE_GPU},
+    kernel::{ExecuteKernel, Kernel},
+    memory::{Buffer, CL_MEM_READ_ONLY, CL_MEM_READ_WRITE},
+    program::Program,
+    types::{cl_uint, CL_BLOCKING},
+};
+
+use crate::engine::{
+    extract_results, is_exact_output, pack_small_ranges, Engine, ExecuteError, MAX_RESULTS,
+    WORKGROUPS_PER_STEP,
+};
+use crate::flavor::Flavor;
+use crate::Options;
+
+/// The OpenCL counterpart of `GpuPrepared`: everything needed to run `mt19937.cl` repeatedly.
+pub struct OpenClPrepared {
+    context: Context,
+    queue: CommandQueue,
+    // Kernel arguments are not thread-safe, they are set and enqueued under the lock.
+    kernel: Mutex<Kernel>,
+    // `exact_search`, for a single exact output, and `small_search`, for small ranges.
+    exact_kernel: Mutex<Kernel>,
+    small_kernel: Mutex<Kernel>,
+}
+
+/// Prepare the first OpenCL device once: context, queue, program and kernel.
+///
+/// GPUs are preferred, other devices (accelerators, CPUs) are only used when there is no GPU.
+pub fn prepare_opencl(_options: &Options) -> OpenClPrepared {
+    let device_id = get_all_devices(CL_DEVICE_TYPE_GPU)
+        .ok()
+        .and_then(|devices| devices.first().copied())
+        .or_else(|| {
+            get_all_devices(CL_DEVICE_TYPE_ALL)
+                .ok()
+                .and_then(|devices| devices.first().copied())
+        })
+        .expect("Failed to find an OpenCL device");
+    let device = Device::new(device_id);
+
+    println!(
+        "\rRunning on OpenCL device: {} ({})",
+        device.name().unwrap_or_default(),
+        device.vendor().unwrap_or_default()
+    );
+    log::info!(
+        "OpenCL device: {} ({})",
+        device.name().unwrap_or_default(),
+        device.vendor().unwrap_or_default()
+    );
+
+    let context = Context::from_device(&device).expect("Failed to create OpenCL context");
+    let queue = CommandQueue::create_default(&context, 0).expect("Failed to create OpenCL queue");
+    let program = Program::create_and_build_from_source(&context, include_str!("mt19937.cl"), "")
+        .unwrap_or_else(|log| panic!("Failed to build mt19937.cl:\n{log}"));
+    let kernel = Kernel::create(&program, "search").expect("Failed to create OpenCL kernel");
+    let exact_kernel =
+        Kernel::create(&program, "exact_search").expect("Failed to create OpenCL kernel");
+    let small_kernel =
+        Kernel::create(&program, "small_search").expect("Failed to create OpenCL kernel");
+
+    OpenClPrepared {
+        context,
+        queue,
+        kernel: Mutex::new(kernel),
+        exact_kernel: Mutex::new(exact_kernel),
+        small_kernel: Mutex::new(small_kernel),
+    }
+}
+
+impl OpenClPrepared {
+    /// Execute the given workgroups of a step for `kernel_flavor`, the `Flavor::kernel_id` of the
+    /// flavor searched or `Flavor::ALL_KERNEL_ID`.
+    fn execute_kernel(
+        &self,
+        kernel_flavor: u32,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        assert!(step < 256);
+        assert!(!workgroups.is_empty() && workgroups.end <= WORKGROUPS_PER_STEP);
+
+        // OpenCL errors while running usually mean the device went away (driver reset, ...),
+        // which the caller handles by preparing the engine again.
+        let lost = |error| {
+            log::warn!("OpenCL error: {error:?}");
+            ExecuteError::DeviceLost
+        };
+
+        // Single exact outputs and small ranges have leaner kernels, the latter with packed slots.
+        let packed = pack_small_ranges(arguments);
+        let (kernel, arguments) = match (is_exact_output(arguments), &packed) {
+            (true, _) => (&self.exact_kernel, arguments),
+            (false, Some(packed)) => (&self.small_kernel, packed.as_slice()),
+            (false, None) => (&self.kernel, arguments),
+        };
+
+        let mut input_data = Vec::new();
+        input_data.push(step);
+        input_data.push(workgroups.start * 256);
+        input_data.push(kernel_flavor);
+        input_data.extend_from_slice(arguments);
+
+        let mut result = vec![0u32; MAX_RESULTS];
+        unsafe {
+            let mut input_buffer = Buffer::<cl_uint>::create(
+                &self.context,
+                CL_MEM_READ_ONLY,
+                input_data.len(),
+                ptr::null_mut(),
+            )
+            .map_err(lost)?;
+            let mut output_buffer = Buffer::<cl_uint>::create(
+                &self.context,
+                CL_MEM_READ_WRITE,
+                MAX_RESULTS,
+                ptr::null_mut(),
+            )
+            .map_err(lost)?;
+
+            self.queue
+                .enqueue_write_buffer(&mut input_buffer, CL_BLOCKING, 0, &input_data, &[])
+                .map_err(lost)?;
+            // Only the length prefix needs to be cleared.
+            self.queue
+                .enqueue_write_buffer(&mut output_buffer, CL_BLOCKING, 0, &[0], &[])
+                .map_err(lost)?;
+
+            let input_len = input_data.len() as cl_uint;
+            let output_len = MAX_RESULTS as cl_uint;
+            let kernel = kernel.lock().unwrap();
+            let kernel_event = ExecuteKernel::new(&kernel)
+                .set_arg(&input_buffer)
+                .set_arg(&input_len)
+                .set_arg(&output_buffer)
+                .set_arg(&output_len)
+                .set_global_work_size(workgroups.len() * 256)
+                .enqueue_nd_range(&self.queue)
+                .map_err(lost)?;
+            drop(kernel);
+
+            self.queue
+                .enqueue_read_buffer(&output_buffer, CL_BLOCKING, 0, &mut result, &[kernel_event.get()])
+                .map_err(lost)?;
+        }
+
+        extract_results(&result)
+    }
+}
+
+impl Engine for OpenClPrepared {
+    fn execute_workgroups(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        self.execute_kernel(flavor.kernel_id(), arguments, step, workgroups)
+    }
+
+    fn execute_batch_all_flavors(
+        &self,
+        arguments: &[u32],
+        batch: &[(u32, Range<u32>)],
+    ) -> Result<Vec<Vec<u32>>, ExecuteError> {
+        batch
+            .iter()
+            .map(|(step, workgroups)| {
+                self.execute_kernel(Flavor::ALL_KERNEL_ID, arguments, *step, workgroups.clone())
+            })
+            .collect()
+    }
+
+    fn single_pass_flavors(&self) -> bool {
+        true
+    }
+}
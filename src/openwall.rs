@@ -0,0 +1,66 @@
+// Compatibility with the command line and the output of openwall's php_mt_seed, so that scripts
+// written for it work unchanged with `--compat-openwall`.
+//
+// The arguments themselves already follow php_mt_seed (see `normalize_arguments`), this module
+// only covers what php_mt_seed prints.
+
+use crate::flavor::Flavor;
+
+/// How php_mt_seed names a slot in its `Pattern:` line.
+fn slot_pattern(slot: &[u32]) -> String {
+    let &[match_min, match_max, range_min, range_max] = slot else {
+        panic!("arguments must be normalized");
+    };
+    let from = if range_min == 0 && range_max == 0x7fffffff {
+        String::new()
+    } else {
+        format!("-FROM-{}", u64::from(range_max - range_min) + 1)
+    };
+    if match_min == range_min && match_max == range_max {
+        "SKIP".to_string()
+    } else if match_min == match_max {
+        format!("EXACT{from}")
+    } else {
+        format!("RANGE{from}")
+    }
+}
+
+/// The `Pattern:` line php_mt_seed prints before searching, e.g. `Pattern: SKIP EXACT-FROM-62`.
+pub fn pattern(arguments: &[u32]) -> String {
+    let slots: Vec<_> = arguments.chunks(4).map(slot_pattern).collect();
+    format!("Pattern: {}", slots.join(" "))
+}
+
+/// A result line, with the seed in hexadecimal on 8 digits like php_mt_seed prints it.
+pub fn format_seed(seed: u32, flavor: Flavor) -> String {
+    format!("seed = {seed:#010x} = {seed} (PHP {})", flavor.versions())
+}
+
+/// The progress line php_mt_seed rewrites as it goes, ours counts steps rather than seeds as
+/// steps do not cover contiguous seeds.
+pub fn progress(found: usize, step: u32, steps: u32, seeds_per_second: f64) -> String {
+    format!(
+        "Found {found}, trying step {step:03} / {steps}, speed {:.1} Mseeds/s",
+        seeds_per_second / 1e6
+    )
+}
+
+#[test]
+fn test_pattern() {
+    let arguments = [
+        1178568022, 1178568022, 0, 0x7fffffff, 0, 10, 0, 0x7fffffff, 7, 7, 0, 61, 0, 61, 0, 61, 5,
+        9, 0, 61,
+    ];
+    assert_eq!(
+        pattern(&arguments),
+        "Pattern: EXACT RANGE EXACT-FROM-62 SKIP RANGE-FROM-62"
+    );
+}
+
+#[test]
+fn test_format_seed() {
+    assert_eq!(
+        format_seed(42, Flavor::Php71),
+        "seed = 0x0000002a = 42 (PHP 7.1.0+)"
+    );
+}
@@ -0,0 +1,30 @@
+// License: Apache 2.0 OR MIT, at your option
+
+// Runs right after a dispatch of mt19937.wgsl, and decides whether the sub-ranges of that
+// dispatch have to be re-run (with their own output buffers) because its output overflowed.
+
+struct Plan {
+    // number of seeds the output buffer of the main dispatch can hold
+    capacity: u32,
+    // arguments of the indirect dispatches of the sub-ranges, 3 per sub-range
+    arguments: array<u32>,
+}
+
+// Only the size (first element) of the main dispatch output is read.
+@group(0) @binding(0)
+var<storage, read> output: array<u32>;
+@group(0) @binding(1)
+var<storage, read> plan: Plan;
+@group(0) @binding(2)
+var<storage, read_write> indirect: array<u32>;
+
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    let overflowed = output[0] > plan.capacity;
+    for (var i: u32 = 0; i < arrayLength(&plan.arguments); i += 3) {
+        // An indirect dispatch of 0 workgroups does nothing.
+        indirect[i + 0] = select(0u, plan.arguments[i + 0], overflowed);
+        indirect[i + 1] = plan.arguments[i + 1];
+        indirect[i + 2] = plan.arguments[i + 2];
+    }
+}
@@ -0,0 +1,72 @@
+// SIGUSR1 and SIGUSR2: pause the search once the dispatches in flight are done, and resume it, so
+// that operators can lend the GPU to another workload for a while without losing the search.
+
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+static PAUSED: Mutex<bool> = Mutex::new(false);
+static CHANGED: Condvar = Condvar::new();
+
+/// Pause the searches of the process, from their next dispatches on, or resume them.
+pub fn set_paused(paused: bool) {
+    *PAUSED.lock().unwrap() = paused;
+    CHANGED.notify_all();
+}
+
+pub fn is_paused() -> bool {
+    *PAUSED.lock().unwrap()
+}
+
+/// Called between dispatches: wait while the searches are paused. Their state is kept as is.
+pub fn wait() {
+    let mut paused = PAUSED.lock().unwrap();
+    if !*paused {
+        return;
+    }
+    log::warn!("search paused");
+    let start_time = Instant::now();
+    while *paused {
+        paused = CHANGED.wait(paused).unwrap();
+    }
+    log::info!("resuming after {:.1?}", start_time.elapsed());
+}
+
+/// Pause on SIGUSR1 and resume on SIGUSR2, for the command line tool. The signals are handled
+/// on another thread.
+#[cfg(unix)]
+pub fn handle_signals() -> Result<(), String> {
+    use signal_hook::consts::{SIGUSR1, SIGUSR2};
+
+    let mut signals =
+        signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2]).map_err(|e| e.to_string())?;
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            let pause = signal == SIGUSR1;
+            if pause != is_paused() {
+                match pause {
+                    true => eprintln!(
+                        "\rpausing after the dispatches in flight, kill -USR2 {} to resume",
+                        std::process::id()
+                    ),
+                    false => eprintln!("\rresuming"),
+                }
+            }
+            set_paused(pause);
+        }
+    });
+    Ok(())
+}
+
+#[test]
+fn test_pause() {
+    wait();
+    set_paused(true);
+    assert!(is_paused());
+    let resume = std::thread::spawn(|| {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        set_paused(false);
+    });
+    wait();
+    assert!(!is_paused());
+    resume.join().unwrap();
+}
@@ -0,0 +1,350 @@
+// `--shuffle` and `--str-shuffle`: the `mt_rand()` draws behind an observed permutation, for
+// shuffled decks of cards, prize wheel orders and question orders as much as for strings.
+
+/// Ways an observed permutation may have been shuffled at most, each searched.
+pub const MAX_INTERPRETATIONS: usize = 4096;
+
+/// What PHP shuffled: the array given to `shuffle()`, as its elements, or the string given to
+/// `str_shuffle()`, as its bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Original {
+    Array(Vec<String>),
+    String(Vec<u8>),
+}
+
+impl Original {
+    /// The constraints of `shuffled`, its permutation as it was observed, one set per way it may
+    /// have been shuffled: the elements of an array separated by commas, or the string itself.
+    /// Unless it was shuffled, a `?` is an element which was not seen.
+    pub fn constraints(&self, shuffled: &str) -> Result<Vec<Vec<u32>>, String> {
+        match self {
+            Original::Array(elements) => {
+                let shuffled = parse_array(shuffled);
+                shuffle_constraints(elements, &observed(elements, &shuffled, &"?".to_string()))
+            }
+            Original::String(bytes) => {
+                shuffle_constraints(bytes, &observed(bytes, shuffled.as_bytes(), &b'?'))
+            }
+        }
+    }
+}
+
+/// The array of `--shuffle`: its elements, separated by commas.
+pub fn parse_array(s: &str) -> Vec<String> {
+    s.split(',').map(String::from).collect()
+}
+
+/// The elements of `shuffled`, `None` where it has `unknown` and `original` does not.
+fn observed<T: PartialEq + Clone>(original: &[T], shuffled: &[T], unknown: &T) -> Vec<Option<T>> {
+    let masks = !original.contains(unknown);
+    shuffled
+        .iter()
+        .map(|element| (!masks || element != unknown).then(|| element.clone()))
+        .collect()
+}
+
+/// Shuffle `elements` like `shuffle()` and `str_shuffle()` do since PHP 7.1, with the outputs of
+/// `draws`: from the last element to the second, each is swapped with one drawn among those before
+/// it or itself, `mt_rand(0, n_left)`.
+pub fn shuffle<T>(elements: &mut [T], draws: &[u32]) {
+    for (n_left, &draw) in (1..elements.len()).rev().zip(draws) {
+        elements.swap(n_left, draw as usize);
+    }
+}
+
+/// The draws which may have shuffled `original` into `shuffled`, the reverse of `shuffle`. Each
+/// position from the last is final once it is drawn, so that its draw is where its element is in
+/// what is left to shuffle. An element appearing several times, such as a letter of a string, may
+/// have been drawn from each of its places: every sequence of draws giving `shuffled` is returned,
+/// `MAX_INTERPRETATIONS` at most.
+///
+/// `shuffled` may miss elements, `None`, whose draws are then not known. Such a draw swaps the
+/// element of its position with one which is not seen again: the elements seen later were not
+/// moved, and only the one swapped away is lost track of. Its own draw is then not known either.
+pub fn shuffle_draws<T: PartialEq>(
+    original: &[T],
+    shuffled: &[Option<T>],
+) -> Result<Vec<Vec<Option<u32>>>, String> {
+    if original.len() != shuffled.len() {
+        return Err(format!(
+            "{} elements were shuffled, {} are observed",
+            original.len(),
+            shuffled.len()
+        ));
+    }
+    for (i, element) in shuffled.iter().enumerate() {
+        let Some(element) = element else { continue };
+        let seen = shuffled[..=i]
+            .iter()
+            .filter(|e| e.as_ref() == Some(element));
+        if seen.count() > original.iter().filter(|e| *e == element).count() {
+            return Err(format!("element {} is not one of those shuffled", i + 1));
+        }
+    }
+    if original.is_empty() {
+        return Ok(vec![Vec::new()]);
+    }
+    let mut interpretations = Vec::new();
+    let unshuffle = Unshuffle {
+        left: original.iter().map(Some).collect(),
+        lost: Vec::new(),
+        draws: Vec::new(),
+    };
+    unshuffle.explore(shuffled, &mut interpretations)?;
+    if interpretations.is_empty() {
+        return Err("no shuffle of the elements gives what was observed".to_string());
+    }
+    Ok(interpretations)
+}
+
+/// A shuffle undone from its last position down to `left.len() - 1`.
+struct Unshuffle<'a, T> {
+    /// The element at each position left to shuffle, as long as it is seen later.
+    left: Vec<Option<&'a T>>,
+    /// The elements swapped away by draws which are not known, and not seen since.
+    lost: Vec<&'a T>,
+    draws: Vec<Option<u32>>,
+}
+
+// Not derived, which would need the elements to be `Clone` too.
+impl<T> Clone for Unshuffle<'_, T> {
+    fn clone(&self) -> Self {
+        Unshuffle {
+            left: self.left.clone(),
+            lost: self.lost.clone(),
+            draws: self.draws.clone(),
+        }
+    }
+}
+
+impl<T: PartialEq> Unshuffle<'_, T> {
+    /// Undo the rest of the shuffle in every way that gives `shuffled`, adding the draws of each
+    /// to `interpretations`.
+    fn explore(
+        self,
+        shuffled: &[Option<T>],
+        interpretations: &mut Vec<Vec<Option<u32>>>,
+    ) -> Result<(), String> {
+        let n_left = self.left.len() - 1;
+        if n_left == 0 {
+            if interpretations.len() == MAX_INTERPRETATIONS {
+                return Err(format!(
+                    "more than {MAX_INTERPRETATIONS} shuffles give what was observed"
+                ));
+            }
+            interpretations.push(self.draws);
+            return Ok(());
+        }
+        let Some(element) = &shuffled[n_left] else {
+            return self.lose().explore(shuffled, interpretations);
+        };
+        for draw in 0..=n_left {
+            if self.left[draw] == Some(element) {
+                let mut next = self.clone();
+                next.left.swap(n_left, draw);
+                next.left.pop();
+                next.draws.push(Some(draw as u32));
+                next.explore(shuffled, interpretations)?;
+            }
+        }
+        // Or it is a copy which was swapped away: it was drawn from anywhere.
+        if let Some(lost) = self.lost.iter().position(|e| *e == element) {
+            let mut next = self.clone();
+            next.lost.remove(lost);
+            next.lose().explore(shuffled, interpretations)?;
+        }
+        Ok(())
+    }
+
+    /// Draw the last position left from where it is not known.
+    fn lose(mut self) -> Self {
+        self.lost.extend(self.left.pop().flatten());
+        self.draws.push(None);
+        self
+    }
+}
+
+/// The constraints of a shuffle, one set per sequence of `shuffle_draws`: one match per draw,
+/// `len - 1` of them. The draws which are not known match anything in their range.
+pub fn shuffle_constraints<T: PartialEq>(
+    original: &[T],
+    shuffled: &[Option<T>],
+) -> Result<Vec<Vec<u32>>, String> {
+    let interpretations = shuffle_draws(original, shuffled)?;
+    Ok(interpretations
+        .into_iter()
+        .map(|draws| {
+            (1..original.len() as u32)
+                .rev()
+                .zip(draws)
+                .flat_map(|(n_left, draw)| match draw {
+                    Some(draw) => [draw, draw, 0, n_left],
+                    None => [0, n_left, 0, n_left],
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// The draws of constraints of `shuffle_constraints`, `?` for those not known, to tell the
+/// interpretations of a shuffle apart.
+pub fn draws_label(constraints: &[u32]) -> String {
+    let draws: Vec<String> = constraints
+        .chunks(4)
+        .map(|slot| match slot[0] == slot[1] {
+            true => slot[0].to_string(),
+            false => "?".to_string(),
+        })
+        .collect();
+    draws.join(" ")
+}
+
+#[cfg(test)]
+fn seen<T: Clone>(elements: &[T]) -> Vec<Option<T>> {
+    elements.iter().cloned().map(Some).collect()
+}
+
+#[test]
+fn test_shuffle_draws() {
+    let original = ["A", "B", "C", "D"];
+    let mut shuffled = original;
+    shuffle(&mut shuffled, &[1, 2, 0]);
+    assert_eq!(shuffled, ["D", "A", "C", "B"]);
+    let shuffled = seen(&shuffled);
+    assert_eq!(
+        shuffle_draws(&original, &shuffled),
+        Ok(vec![vec![Some(1), Some(2), Some(0)]])
+    );
+    assert_eq!(
+        shuffle_constraints(&original, &shuffled),
+        Ok(vec![vec![1, 1, 0, 3, 2, 2, 0, 2, 0, 0, 0, 1]])
+    );
+    // Drawing the last position left keeps it in place.
+    assert_eq!(
+        shuffle_draws(&original, &seen(&original)),
+        Ok(vec![vec![Some(3), Some(2), Some(1)]])
+    );
+
+    assert!(shuffle_draws(&original, &seen(&["A", "B", "C"])).is_err());
+    assert!(shuffle_draws(&original, &seen(&["A", "B", "C", "E"])).is_err());
+    assert!(shuffle_draws(&original, &seen(&["E", "B", "C", "D"])).is_err());
+    assert!(shuffle_draws(&original, &seen(&["A", "B", "C", "A"])).is_err());
+    assert_eq!(shuffle_draws::<u8>(&[], &[]), Ok(vec![vec![]]));
+}
+
+#[test]
+fn test_partial_shuffle() {
+    let original = ["A", "B", "C", "D"];
+    // D A C B, shuffled by the draws 1, 2 and 0: the first element is not needed.
+    let shuffled = [None, Some("A"), Some("C"), Some("B")];
+    assert_eq!(
+        shuffle_draws(&original, &shuffled),
+        Ok(vec![vec![Some(1), Some(2), Some(0)]])
+    );
+    // Not seeing C loses track of the element swapped with it, but A was not.
+    let shuffled = [Some("D"), Some("A"), None, Some("B")];
+    assert_eq!(
+        shuffle_draws(&original, &shuffled),
+        Ok(vec![vec![Some(1), None, Some(0)]])
+    );
+    assert_eq!(
+        shuffle_constraints(&original, &shuffled),
+        Ok(vec![vec![1, 1, 0, 3, 0, 2, 0, 2, 0, 0, 0, 1]])
+    );
+    // C D B A, shuffled by the draws 0, 1 and 0: D was swapped away by the draw not seen.
+    let shuffled = [Some("C"), Some("D"), Some("B"), None];
+    assert_eq!(
+        shuffle_draws(&original, &shuffled),
+        Ok(vec![vec![None, Some(1), None]])
+    );
+    assert_eq!(
+        shuffle_draws(&original, &[None, None, None, None]),
+        Ok(vec![vec![None, None, None]])
+    );
+}
+
+#[test]
+fn test_multiset_shuffle() {
+    // Both a of "aab" may have been drawn last.
+    let original = b"aab";
+    assert_eq!(
+        shuffle_draws(original, &seen(b"aba")),
+        Ok(vec![vec![Some(0), Some(0)], vec![Some(1), Some(1)]])
+    );
+    for draws in [[0, 0], [1, 1]] {
+        let mut shuffled = *original;
+        shuffle(&mut shuffled, &draws);
+        assert_eq!(&shuffled, b"aba");
+    }
+    // Or the copy swapped away by a draw which is not known.
+    assert_eq!(
+        shuffle_draws(original, &[Some(b'b'), None, Some(b'a')]),
+        Ok(vec![vec![Some(0), None], vec![Some(1), None]])
+    );
+    assert_eq!(
+        shuffle_draws(b"aba", &[None, Some(b'a'), None]),
+        Ok(vec![vec![None, Some(0)], vec![None, None]])
+    );
+    assert!(shuffle_draws(&[0; 12], &seen(&[0; 12])).is_err());
+    assert_eq!(draws_label(&[1, 1, 0, 3, 0, 2, 0, 2]), "1 ?");
+}
+
+#[test]
+fn test_shuffle_seed() {
+    use crate::flavor::Flavor;
+    use crate::replay::{first_divergence, outputs};
+
+    // A deck of 52 cards shuffled after mt_srand(1234): its draws are the outputs of the seed.
+    let deck: Vec<u32> = (1..=52).collect();
+    let constraints = shuffle_constraints(&deck, &seen(&deck)).unwrap().remove(0);
+    let draws = outputs(Flavor::Php71, &constraints, 1234);
+    let mut shuffled = deck.clone();
+    shuffle(&mut shuffled, &draws);
+    let constraints = shuffle_constraints(&deck, &seen(&shuffled)).unwrap();
+    assert_eq!(outputs(Flavor::Php71, &constraints[0], 1234), draws);
+    // The seed still matches with a card out of three not seen.
+    let partial: Vec<Option<u32>> = shuffled
+        .iter()
+        .enumerate()
+        .map(|(i, &card)| (i % 3 != 0).then_some(card))
+        .collect();
+    let constraints = shuffle_constraints(&deck, &partial).unwrap();
+    assert_eq!(constraints.len(), 1);
+    let outputs_of = |constraints: &[u32]| outputs(Flavor::Php71, constraints, 1234);
+    assert_eq!(
+        first_divergence(&constraints[0], &outputs_of(&constraints[0])),
+        None
+    );
+    // Or with pairs of cards of the same value, in one of the 16 ways they may have been shuffled.
+    let pairs: Vec<u32> = (0..8).map(|card| card / 2).collect();
+    let draws = outputs_of(&shuffle_constraints(&pairs, &seen(&pairs)).unwrap()[0]);
+    let mut shuffled = pairs.clone();
+    shuffle(&mut shuffled, &draws);
+    let interpretations = shuffle_constraints(&pairs, &seen(&shuffled)).unwrap();
+    assert_eq!(interpretations.len(), 16);
+    assert!(interpretations
+        .iter()
+        .any(|constraints| first_divergence(constraints, &draws).is_none()));
+
+    let original = Original::String(b"abcdef".to_vec());
+    assert_eq!(original.constraints("abcdef").unwrap()[0].len(), 5 * 4);
+    assert_eq!(
+        original.constraints("ab?def").unwrap()[0][12..16],
+        [0, 2, 0, 2]
+    );
+    // A ? which was shuffled is seen.
+    let original = Original::String(b"ab?".to_vec());
+    assert_eq!(
+        original.constraints("?ab"),
+        Ok(vec![vec![1, 1, 0, 2, 0, 0, 0, 1]])
+    );
+    let original = Original::Array(parse_array("10,J,Q,K"));
+    assert_eq!(
+        original.constraints("K,10,J,Q"),
+        Ok(vec![vec![2, 2, 0, 3, 1, 1, 0, 2, 0, 0, 0, 1]])
+    );
+    assert_eq!(
+        original.constraints("K,?,J,Q"),
+        Ok(vec![vec![2, 2, 0, 3, 1, 1, 0, 2, 0, 1, 0, 1]])
+    );
+}
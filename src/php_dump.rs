@@ -0,0 +1,80 @@
+// Extraction of observed values from `var_dump()`, `print_r()` or `var_export()` output pasted
+// from a debug page, for `--from-php-dump`.
+
+/// The integer at the start of `s`, with its sign, if there is one.
+fn leading_integer(s: &str) -> Option<&str> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let len = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    if len == 0 {
+        return None;
+    }
+    Some(&s[..s.len() - digits.len() + len])
+}
+
+fn to_output(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(value) if value <= 0x7fffffff => Ok(value),
+        _ => Err(format!("{value} cannot be an mt_rand() output")),
+    }
+}
+
+/// The integer values of a dump, in order. Keys, string lengths and non-integer values are
+/// skipped.
+///
+/// - `var_dump()`: `int(42)`
+/// - `print_r()`: `[0] => 42`
+/// - `var_export()`: `0 => 42,`
+pub fn extract_values(dump: &str) -> Result<Vec<u32>, String> {
+    let mut values = Vec::new();
+    for line in dump.lines() {
+        let line = line.trim();
+        let value = if let Some(rest) = line.strip_prefix("int(") {
+            leading_integer(rest).filter(|value| rest[value.len()..].starts_with(')'))
+        } else if let Some((_, rest)) = line.split_once("=> ") {
+            let rest = rest.trim_end();
+            let rest = rest.strip_suffix(',').unwrap_or(rest);
+            leading_integer(rest).filter(|value| value.len() == rest.len())
+        } else {
+            None
+        };
+        if let Some(value) = value {
+            values.push(to_output(value)?);
+        }
+    }
+    Ok(values)
+}
+
+#[test]
+fn test_var_dump() {
+    let dump = r#"array(4) {
+  [0]=>
+  int(1178568022)
+  [1]=>
+  string(3) "abc"
+  ["token"]=>
+  int(7)
+  [2]=>
+  float(1.5)
+}"#;
+    assert_eq!(extract_values(dump), Ok(vec![1178568022, 7]));
+}
+
+#[test]
+fn test_print_r() {
+    let dump = "Array\n(\n    [0] => 1178568022\n    [1] => abc\n    [2] => Array\n        (\n            [0] => 5\n        )\n\n    [3] => 12ab\n)\n";
+    assert_eq!(extract_values(dump), Ok(vec![1178568022, 5]));
+}
+
+#[test]
+fn test_var_export() {
+    let dump = "array (\n  0 => 1178568022,\n  1 => 'x',\n  2 => 9,\n)";
+    assert_eq!(extract_values(dump), Ok(vec![1178568022, 9]));
+}
+
+#[test]
+fn test_negative_values_are_rejected() {
+    assert!(extract_values("int(-5)").is_err());
+    assert!(extract_values("[0] => 4294967295").is_err());
+}
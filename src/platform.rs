@@ -0,0 +1,401 @@
+// `--platform`: the size of the integers of the PHP build, which decides whether `mt_rand()` can
+// draw from ranges wider than `mt_getrandmax()`, and how many outputs it takes for them. Along
+// with `--getrandmax`, for builds whose `mt_getrandmax()` differs.
+
+use std::fmt;
+
+use crate::flavor::Flavor;
+
+const MT_GETRANDMAX: u32 = 0x7fffffff;
+
+/// `PHP_INT_MAX` on 64-bit PHP, the largest `MAX` of `mt_rand(MIN, MAX)`.
+const PHP_INT_MAX: u64 = i64::MAX as u64;
+
+/// The PHP builds `--platform` selects.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Platform {
+    /// 64-bit integers: `mt_rand(MIN, MAX)` takes ranges up to 2^32 values from a single output,
+    /// and wider ones from two outputs in PHP 7.1.0+.
+    #[default]
+    Bits64,
+    /// 32-bit integers: `MAX` stops at `mt_getrandmax()`.
+    Bits32,
+}
+
+impl std::str::FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "64" | "64-bit" => Ok(Platform::Bits64),
+            "32" | "32-bit" => Ok(Platform::Bits32),
+            _ => Err(format!("unknown platform {s:?}, expected 32 or 64")),
+        }
+    }
+}
+
+/// Refuse the slots of the normalized `arguments` drawing from ranges wider than
+/// `mt_getrandmax()` on 32-bit PHP, whose integers stop there. The kernels scale them like 64-bit
+/// PHP does.
+pub fn check_ranges(arguments: &[u32], platform: Platform) -> Result<(), String> {
+    if platform == Platform::Bits64 {
+        return Ok(());
+    }
+    for (i, slot) in arguments.chunks_exact(4).enumerate() {
+        let &[_, _, range_min, range_max] = slot else {
+            unreachable!()
+        };
+        if range_max > MT_GETRANDMAX {
+            return Err(format!(
+                "slot {}: mt_rand({range_min}, {range_max}) cannot be called on 32-bit PHP, whose \
+                 integers stop at {MT_GETRANDMAX}",
+                i + 1
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `RAND_RANGE_BADSCALING` with 64-bit integers, in doubles like PHP does: ranges wider than
+/// 2^31 values spread the outputs over the range. The outputs are divided by `getrandmax + 1`.
+fn bad_scaling(n: u32, range: f64, getrandmax: u32) -> u64 {
+    (range * (f64::from(n) / (f64::from(getrandmax) + 1.0))) as u64
+}
+
+/// The outputs of `mt_rand()` without arguments, `next >> 1`, that PHP 5.2.1 to 7.0.x scales to
+/// `offsets` above the minimum of a range of `range` values, or `None` when there is none.
+fn scaled_outputs(offsets: (u64, u64), range: f64, getrandmax: u32) -> Option<(u32, u32)> {
+    let low = first_output(|n| bad_scaling(n, range, getrandmax) >= offsets.0);
+    let high = first_output(|n| bad_scaling(n, range, getrandmax) > offsets.1);
+    (low < high).then(|| (low, high - 1))
+}
+
+/// Rewrite the slots of the normalized `arguments` as constraints on `mt_rand()` without
+/// arguments, for a build whose `mt_getrandmax()` is `getrandmax`: the kernels divide by
+/// `MT_GETRANDMAX + 1`. Only PHP 5.2.1 to 7.0.x divides its outputs, the only flavor searched.
+pub fn scale_with_getrandmax(
+    arguments: &mut [u32],
+    flavors: &[Flavor],
+    getrandmax: u32,
+) -> Result<(), String> {
+    if getrandmax == MT_GETRANDMAX {
+        return Ok(());
+    }
+    if flavors != [Flavor::Php52] {
+        return Err(
+            "--getrandmax only changes how PHP 5.2.1 to 7.0.x scales ranges, it needs --php 5.2.1"
+                .to_string(),
+        );
+    }
+    for (i, slot) in arguments.chunks_exact_mut(4).enumerate() {
+        let &mut [match_min, match_max, range_min, range_max] = slot else {
+            unreachable!()
+        };
+        if (range_min, range_max) == (0, MT_GETRANDMAX) || range_min > range_max {
+            continue;
+        }
+        let (low, high) = (match_min.max(range_min), match_max.min(range_max));
+        let range = f64::from(range_max - range_min) + 1.0;
+        let outputs = (low <= high)
+            .then(|| {
+                let offsets = (u64::from(low - range_min), u64::from(high - range_min));
+                scaled_outputs(offsets, range, getrandmax)
+            })
+            .flatten()
+            .ok_or_else(|| {
+                format!(
+                    "slot {}: mt_rand({range_min}, {range_max}) never returns \
+                     {match_min}-{match_max} with mt_getrandmax() = {getrandmax}",
+                    i + 1
+                )
+            })?;
+        slot.copy_from_slice(&[outputs.0, outputs.1, 0, MT_GETRANDMAX]);
+    }
+    Ok(())
+}
+
+/// The first of the outputs of `mt_rand()` without arguments for which `scales_past` is true, or
+/// 2^31 if it is for none of them. `scales_past` must not be true for an output and false for the
+/// next.
+fn first_output(scales_past: impl Fn(u32) -> bool) -> u32 {
+    let (mut low, mut high) = (0, MT_GETRANDMAX + 1);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        match scales_past(middle) {
+            true => high = middle,
+            false => low = middle + 1,
+        }
+    }
+    low
+}
+
+/// A call to `mt_rand(MIN, MAX)` drawing from more than 2^32 values on 64-bit PHP, written
+/// `MATCH_MIN-MATCH_MAX@MIN-MAX` like a slot: its values do not fit one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WideCall {
+    matches: (u64, u64),
+    range: (u64, u64),
+}
+
+/// `MIN-MAX`, or a single number, up to `PHP_INT_MAX`.
+fn parse_wide_range(s: &str) -> Result<(u64, u64), String> {
+    let parse = |s: &str| match s.parse() {
+        Ok(value) if value <= PHP_INT_MAX => Ok(value),
+        _ => Err(format!("cannot parse {s:?} as a PHP integer")),
+    };
+    match s.split_once('-') {
+        Some((min, max)) => Ok((parse(min)?, parse(max)?)),
+        None => parse(s).map(|value| (value, value)),
+    }
+}
+
+/// Whether the constraint `token` is a `WideCall` rather than a slot.
+pub fn is_wide_call(token: &str) -> bool {
+    match token
+        .split_once('@')
+        .map(|(_, range)| parse_wide_range(range))
+    {
+        Some(Ok((min, max))) => max.checked_sub(min) > Some(u64::from(u32::MAX)),
+        _ => false,
+    }
+}
+
+impl std::str::FromStr for WideCall {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (matches, range) = s.split_once('@').ok_or("a wide range needs @MIN-MAX")?;
+        let (matches, range) = (parse_wide_range(matches)?, parse_wide_range(range)?);
+        let call = WideCall { matches, range };
+        if matches.0 > matches.1 || matches.1 < range.0 || matches.0 > range.1 {
+            return Err(format!("{call} never returns {}-{}", matches.0, matches.1));
+        }
+        Ok(WideCall {
+            matches: (matches.0.max(range.0), matches.1.min(range.1)),
+            range,
+        })
+    }
+}
+
+impl fmt::Display for WideCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "mt_rand({}, {})", self.range.0, self.range.1)
+    }
+}
+
+impl WideCall {
+    /// The slots searching the call in `flavor`. PHP 7.1.0+ draws two outputs, the high then the
+    /// low 32 bits of a number reduced with a modulo, and PHP 5.2.1 to 7.0.x scales a single one
+    /// by `getrandmax + 1`.
+    fn slots(&self, flavor: Flavor, getrandmax: u32) -> Result<Vec<u32>, String> {
+        let (min, max) = self.range;
+        let offsets = (self.matches.0 - min, self.matches.1 - min);
+        match flavor {
+            Flavor::Php71 => {
+                // Only ranges of a multiple of 2^32 values keep the low output as it is, the high
+                // one is then reduced modulo the multiple. Its slot is shifted by one, for the 2^31
+                // multiple not to be read as `mt_rand()` without arguments.
+                let range = max - min + 1;
+                if range % (1 << 32) != 0 {
+                    return Err(format!(
+                        "{self} draws from {range} values, which is not a multiple of 2^32: its \
+                         outputs cannot be searched"
+                    ));
+                }
+                let high = ((offsets.0 >> 32) as u32, (offsets.1 >> 32) as u32);
+                let low = match high.0 == high.1 {
+                    true => (offsets.0 as u32, offsets.1 as u32),
+                    false => (0, u32::MAX),
+                };
+                let multiple = (range >> 32) as u32;
+                Ok(vec![
+                    high.0 + 1,
+                    high.1 + 1,
+                    1,
+                    multiple,
+                    low.0,
+                    low.1,
+                    0,
+                    u32::MAX,
+                ])
+            }
+            Flavor::Php52 => {
+                // `(double)MAX - MIN + 1.0`, in doubles like PHP does.
+                let range = max as f64 - min as f64 + 1.0;
+                match scaled_outputs(offsets, range, getrandmax) {
+                    Some((low, high)) => Ok(vec![low, high, 0, MT_GETRANDMAX]),
+                    None => Err(format!(
+                        "{self} never returns {}-{} in PHP 5.2.1 to 7.0.x",
+                        self.matches.0, self.matches.1
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Insert the slots of the `calls` at their positions in the `arguments`, for the `flavors`
+/// searched: they take a different number of outputs in each flavor, so only one can be.
+pub fn insert_wide_calls(
+    arguments: &mut Vec<u32>,
+    calls: &[(usize, WideCall)],
+    flavors: &[Flavor],
+    platform: Platform,
+    getrandmax: u32,
+) -> Result<(), String> {
+    let Some((_, call)) = calls.first() else {
+        return Ok(());
+    };
+    if platform == Platform::Bits32 {
+        return Err(format!(
+            "{call} cannot be called on 32-bit PHP, whose integers stop at {MT_GETRANDMAX}"
+        ));
+    }
+    let &[flavor] = flavors else {
+        return Err(format!(
+            "{call} takes two outputs in PHP 7.1.0+ and one in PHP 5.2.1 to 7.0.x, --php must \
+             pick one of them"
+        ));
+    };
+    for (position, call) in calls.iter().rev() {
+        arguments.splice(position..position, call.slots(flavor, getrandmax)?);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_check_ranges() {
+    let arguments = [7, 7, 0, 10, 5, 5, 0, MT_GETRANDMAX];
+    assert_eq!(check_ranges(&arguments, Platform::Bits32), Ok(()));
+    let arguments = [7, 7, 0, 10, 10, 10, 0, u32::MAX];
+    assert_eq!(check_ranges(&arguments, Platform::Bits64), Ok(()));
+    assert!(check_ranges(&arguments, Platform::Bits32)
+        .unwrap_err()
+        .starts_with("slot 2: mt_rand(0, 4294967295)"));
+    assert_eq!("32".parse(), Ok(Platform::Bits32));
+    assert!("16".parse::<Platform>().is_err());
+}
+
+#[test]
+fn test_wide_calls() {
+    use crate::reference::{seed_matches, Mt19937};
+
+    assert!(is_wide_call("7@0-4294967296"));
+    assert!(!is_wide_call("7@0-4294967295"));
+    assert!(!is_wide_call("7@10-4294967296"));
+    assert!(!is_wide_call("7"));
+    assert!("7@0-9223372036854775808".parse::<WideCall>().is_err());
+    assert!("20000000000@0-10000000000".parse::<WideCall>().is_err());
+
+    // mt_rand(0, 2 ** 40 - 1) and mt_rand(5, 2 ** 40 + 4) after mt_srand(1234).
+    let seed = 1234;
+    let mut mt = Mt19937::new(seed, Flavor::Php71);
+    let number = u64::from(mt.next_u32()) << 32 | u64::from(mt.next_u32());
+    let value = number & ((1 << 40) - 1);
+    let call: WideCall = format!("{value}@0-1099511627775").parse().unwrap();
+    let slots = call.slots(Flavor::Php71, MT_GETRANDMAX).unwrap();
+    assert_eq!(slots[2..4], [1, 256]);
+    assert!(seed_matches(Flavor::Php71, &slots, seed));
+    assert!(!seed_matches(Flavor::Php71, &slots, seed + 1));
+    let call: WideCall = format!("{}@5-1099511627780", value + 5).parse().unwrap();
+    assert_eq!(call.slots(Flavor::Php71, MT_GETRANDMAX), Ok(slots));
+    let call: WideCall = "0-1099511627775@0-1099511627775".parse().unwrap();
+    assert_eq!(
+        call.slots(Flavor::Php71, MT_GETRANDMAX),
+        Ok(vec![1, 256, 1, 256, 0, u32::MAX, 0, u32::MAX])
+    );
+    assert!("5@0-10000000000"
+        .parse::<WideCall>()
+        .unwrap()
+        .slots(Flavor::Php71, MT_GETRANDMAX)
+        .is_err());
+
+    let next = Mt19937::new(seed, Flavor::Php52).next_u32();
+    let value = bad_scaling(next >> 1, 1099511627776.0, MT_GETRANDMAX);
+    let call: WideCall = format!("{value}@0-1099511627775").parse().unwrap();
+    let slots = call.slots(Flavor::Php52, MT_GETRANDMAX).unwrap();
+    assert_eq!(slots, [next >> 1, next >> 1, 0, MT_GETRANDMAX]);
+    assert!("1@0-1099511627775"
+        .parse::<WideCall>()
+        .unwrap()
+        .slots(Flavor::Php52, MT_GETRANDMAX)
+        .is_err());
+}
+
+#[test]
+fn test_insert_wide_calls() {
+    let call: WideCall = "4294967296@0-8589934591".parse().unwrap();
+    let calls = [(4, call)];
+    let mut arguments = vec![7, 7, 0, 10, 5, 5, 0, 9];
+    insert_wide_calls(
+        &mut arguments,
+        &calls,
+        &[Flavor::Php71],
+        Platform::Bits64,
+        MT_GETRANDMAX,
+    )
+    .unwrap();
+    assert_eq!(
+        arguments,
+        [7, 7, 0, 10, 2, 2, 1, 2, 0, 0, 0, u32::MAX, 5, 5, 0, 9]
+    );
+
+    let mut arguments = vec![7, 7, 0, 10];
+    assert!(insert_wide_calls(
+        &mut arguments,
+        &calls,
+        &Flavor::ALL,
+        Platform::Bits64,
+        MT_GETRANDMAX
+    )
+    .is_err());
+    assert!(insert_wide_calls(
+        &mut arguments,
+        &calls,
+        &[Flavor::Php71],
+        Platform::Bits32,
+        MT_GETRANDMAX
+    )
+    .is_err());
+    insert_wide_calls(
+        &mut arguments,
+        &[],
+        &Flavor::ALL,
+        Platform::Bits32,
+        MT_GETRANDMAX,
+    )
+    .unwrap();
+    assert_eq!(arguments, [7, 7, 0, 10]);
+}
+
+#[test]
+fn test_scale_with_getrandmax() {
+    use crate::reference::{seed_matches, Mt19937};
+
+    // mt_rand(0, 61) after mt_srand(), on a build whose mt_getrandmax() is 2^30 - 1, for the
+    // first seed whose output is within it.
+    let getrandmax = 0x3fffffff;
+    let first = |seed| Mt19937::new(seed, Flavor::Php52).next_u32();
+    let seed = (0..).find(|&seed| first(seed) >> 1 <= getrandmax).unwrap();
+    let next = first(seed);
+    let value = (62.0 * (f64::from(next >> 1) / 1073741824.0)) as u32;
+    let mut arguments = [value, value, 0, 61, 5, 5, 0, MT_GETRANDMAX];
+    scale_with_getrandmax(&mut arguments, &[Flavor::Php52], getrandmax).unwrap();
+    assert_eq!(arguments[2..], [0, MT_GETRANDMAX, 5, 5, 0, MT_GETRANDMAX]);
+    assert!(seed_matches(Flavor::Php52, &arguments[..4], seed));
+    assert!(!seed_matches(Flavor::Php52, &arguments[..4], seed + 1));
+
+    // Past 2^30, the outputs scale beyond the range.
+    let mut arguments = [0, 0, 0, 61];
+    scale_with_getrandmax(&mut arguments, &[Flavor::Php52], getrandmax).unwrap();
+    assert_eq!(arguments, [0, 17318416, 0, MT_GETRANDMAX]);
+    let mut arguments = [61, 61, 0, 61];
+    scale_with_getrandmax(&mut arguments, &[Flavor::Php52], getrandmax).unwrap();
+    assert_eq!(arguments, [1056423408, 1073741823, 0, MT_GETRANDMAX]);
+
+    // The default needs nothing, and PHP 7.1.0+ does not scale.
+    let mut arguments = [7, 7, 0, 61];
+    scale_with_getrandmax(&mut arguments, &Flavor::ALL, MT_GETRANDMAX).unwrap();
+    assert_eq!(arguments, [7, 7, 0, 61]);
+    assert!(scale_with_getrandmax(&mut arguments, &Flavor::ALL, getrandmax).is_err());
+}
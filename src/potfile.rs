@@ -0,0 +1,117 @@
+// The potfile, like hashcat's: the seeds found by complete searches, so that the same constraints
+// are answered right away the next time. `--potfile-disable` searches again.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::flavor::Flavor;
+use crate::session::data_dir;
+
+/// `php_mt_seed_rs.potfile`, next to the sessions.
+pub fn default_path() -> PathBuf {
+    data_dir().join("php_mt_seed_rs.potfile")
+}
+
+/// One line per search: the key of the flavor and constraints, in hexadecimal, and the seeds
+/// found, `0123456789abcdef:0,42`. Later lines win.
+pub struct Potfile {
+    path: PathBuf,
+    entries: HashMap<u64, Vec<u32>>,
+}
+
+impl Potfile {
+    /// The potfile at `path`, empty when it does not exist yet.
+    pub fn open(path: &Path) -> Potfile {
+        let text = fs::read_to_string(path).unwrap_or_default();
+        let entries = text
+            .lines()
+            .filter_map(|line| {
+                let (key, seeds) = line.split_once(':')?;
+                let key = u64::from_str_radix(key, 16).ok()?;
+                let seeds = match seeds {
+                    "" => Vec::new(),
+                    seeds => seeds
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .ok()?,
+                };
+                Some((key, seeds))
+            })
+            .collect();
+        Potfile {
+            path: path.to_path_buf(),
+            entries,
+        }
+    }
+
+    /// The seeds a previous search found for the normalized `arguments`.
+    pub fn lookup(&self, flavor: Flavor, arguments: &[u32]) -> Option<&[u32]> {
+        self.entries.get(&key(flavor, arguments)).map(Vec::as_slice)
+    }
+
+    /// Add the seeds of a complete search, ordered like they were found.
+    pub fn record(&mut self, flavor: Flavor, arguments: &[u32], seeds: &[u32]) {
+        let key = key(flavor, arguments);
+        let seeds: Vec<String> = seeds.iter().map(u32::to_string).collect();
+        let line = format!("{key:016x}:{}\n", seeds.join(","));
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        // Failing to cache is no reason to lose the results.
+        if let Err(e) = written {
+            eprintln!(
+                "\rWARNING: cannot write to the potfile {:?}: {e}",
+                self.path
+            );
+        }
+        self.entries.insert(
+            key,
+            seeds.iter().map(|seed| seed.parse().unwrap()).collect(),
+        );
+    }
+}
+
+/// FNV-1a over the flavor and the normalized constraints.
+fn key(flavor: Flavor, arguments: &[u32]) -> u64 {
+    let words = std::iter::once(flavor.kernel_id()).chain(arguments.iter().copied());
+    fnv1a(words.flat_map(u32::to_le_bytes))
+}
+
+/// FNV-1a, which stays the same from a build to the next unlike the hashers of the standard
+/// library, for the keys that are written to files.
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[test]
+fn test_potfile() {
+    let path = std::env::temp_dir().join("php_mt_seed_rs_test_potfile.txt");
+    let _ = fs::remove_file(&path);
+    let arguments = [1178568022, 1178568022, 0, 0x7fffffff];
+    let mut potfile = Potfile::open(&path);
+    assert_eq!(potfile.lookup(Flavor::Php71, &arguments), None);
+    potfile.record(Flavor::Php71, &arguments, &[0, 42]);
+    potfile.record(Flavor::Php52, &arguments, &[]);
+
+    let potfile = Potfile::open(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        potfile.lookup(Flavor::Php71, &arguments),
+        Some(&[0, 42][..])
+    );
+    assert_eq!(potfile.lookup(Flavor::Php52, &arguments), Some(&[][..]));
+    assert_eq!(potfile.lookup(Flavor::Php71, &arguments[..2]), None);
+}
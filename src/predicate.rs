@@ -0,0 +1,77 @@
+// Matching logic supplied by the user in WGSL with `--predicate`, for what the constraints cannot
+// express (checksums, values mixing several outputs, ...). The function is spliced into the
+// shader of the wgpu engine.
+
+use regex::Regex;
+
+use crate::engine::KERNEL_SLOTS;
+
+/// A `fn matches(outputs: array<u32, N>) -> bool` function, called with the first N raw MT19937
+/// outputs of each seed passing the constraints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    source: String,
+    outputs: usize,
+}
+
+impl Predicate {
+    /// Find the `matches` function in `source`, and the number of outputs it takes.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let signature = Regex::new(
+            r"fn\s+matches\s*\(\s*\w+\s*:\s*array\s*<\s*u32\s*,\s*(\d+)u?\s*>\s*,?\s*\)\s*->\s*bool",
+        )
+        .unwrap();
+        let captures = signature.captures(source).ok_or(
+            "there is no `fn matches(outputs: array<u32, N>) -> bool` function".to_string(),
+        )?;
+        let outputs: usize = captures[1]
+            .parse()
+            .map_err(|_| format!("cannot parse {:?} as a number of outputs", &captures[1]))?;
+        if !(1..=KERNEL_SLOTS).contains(&outputs) {
+            return Err(format!(
+                "`matches` can take 1 to {KERNEL_SLOTS} outputs, not {outputs}"
+            ));
+        }
+        Ok(Predicate {
+            source: source.to_string(),
+            outputs,
+        })
+    }
+
+    /// How many outputs `matches` takes.
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    /// The search shader, with the `predicate_main` entry point calling `matches`. The predicate
+    /// comes first, so that compilation errors point at the lines of its file.
+    pub fn shader_source(&self) -> String {
+        let entry_point =
+            include_str!("predicate.wgsl").replace("OUTPUTS", &self.outputs.to_string());
+        format!(
+            "{}\n{}\n{entry_point}",
+            self.source,
+            include_str!("mt19937.wgsl")
+        )
+    }
+}
+
+#[test]
+fn test_predicate_parse() {
+    let predicate = Predicate::parse(
+        "// mt_rand() + mt_rand() is a multiple of 97\n\
+         fn matches(outputs: array<u32, 2>) -> bool {\n\
+         \x20   return ((outputs[0] >> 1) + (outputs[1] >> 1)) % 97 == 0;\n\
+         }\n",
+    )
+    .unwrap();
+    assert_eq!(predicate.outputs(), 2);
+    let source = predicate.shader_source();
+    assert!(source.contains("var outputs: array<u32, 2>;"));
+    assert!(source.contains("let args_len = max(slots, 2u);"));
+
+    assert!(Predicate::parse("fn matches(o : array<u32,8u>)->bool { return true; }").is_ok());
+    assert!(Predicate::parse("fn matches(outputs: array<u32, 9>) -> bool {}").is_err());
+    assert!(Predicate::parse("fn matches(outputs: array<u32, 0>) -> bool {}").is_err());
+    assert!(Predicate::parse("fn check(outputs: array<u32, 2>) -> bool {}").is_err());
+}
@@ -0,0 +1,39 @@
+// Appended to the user's `matches` function and mt19937.wgsl by `--predicate`. OUTPUTS is
+// replaced by the size of the array `matches` takes.
+
+@compute @workgroup_size(256, 1, 1)
+fn predicate_main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+    if aborted() {
+        return;
+    }
+    let step = input[0];
+    let offset = input[1];
+    let flavor = input[2];
+
+    // The constraints are checked first, like in main, on as many outputs as `matches` needs
+    let slots = (arrayLength(&input) - 3) / 4;
+    let args_len = max(slots, OUTPUTSu);
+    var mt = init(args_len, flavor);
+    let index = offset + global_id.y * num_workgroups.x * 256 + global_id.x;
+    let seed = index * 256 + step;
+    reseed(&mt, seed);
+
+    var outputs: array<u32, OUTPUTS>;
+    for (var i: u32 = 0; i < args_len; i++) {
+        let nextint = next(&mt);
+        if i < OUTPUTSu {
+            outputs[i] = nextint;
+        }
+        let j = 3 + 4 * i;
+        if i < slots && !slot_matches(nextint, flavor, input[j], input[j + 1], input[j + 2], input[j + 3]) {
+            return;
+        }
+    }
+
+    if matches(outputs) {
+        report(seed);
+    }
+}
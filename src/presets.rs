@@ -0,0 +1,202 @@
+// `--preset`: the ranges, charsets and calls of common PHP token generators, so that their
+// outputs can be searched without working them out again.
+
+use std::fmt::Write;
+
+/// A common way of generating tokens, as the options searching its outputs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    /// The PHP code generating the tokens.
+    pub code: &'static str,
+    /// The options it stands for, which the command line may override.
+    pub arguments: &'static [&'static str],
+    /// The length of the tokens, when the code draws a fixed number of characters.
+    pub length: Option<usize>,
+}
+
+const DIGITS: &str = "0123456789";
+const HEX: &str = "0123456789abcdef";
+const ALNUM: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+/// The characters of `wp_generate_password()` with its special characters.
+const WORDPRESS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!@#$%^&*()";
+/// The `alnum` pool of CodeIgniter's `random_string()`.
+const CODEIGNITER: &str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "captcha-4digit",
+        code: "mt_rand(1000, 9999)",
+        arguments: &["--dump-range", "1000-9999"],
+        length: Some(4),
+    },
+    Preset {
+        name: "captcha-6digit",
+        code: "mt_rand(100000, 999999)",
+        arguments: &["--dump-range", "100000-999999"],
+        length: Some(6),
+    },
+    Preset {
+        name: "otp-6digit",
+        code: "str_pad(mt_rand(0, 999999), 6, '0', STR_PAD_LEFT)",
+        arguments: &["--dump-range", "0-999999"],
+        length: Some(6),
+    },
+    Preset {
+        name: "pin-digits",
+        code: "$pin .= mt_rand(0, 9), per digit",
+        arguments: &["--charset", DIGITS],
+        length: None,
+    },
+    Preset {
+        name: "token-hex-32",
+        code: "$token .= $chars[mt_rand(0, 15)], 32 times, over 0-9a-f",
+        arguments: &["--charset", HEX],
+        length: Some(32),
+    },
+    Preset {
+        name: "token-alnum-16",
+        code: "$token .= $chars[mt_rand(0, 61)], 16 times, over a-zA-Z0-9",
+        arguments: &["--charset", ALNUM],
+        length: Some(16),
+    },
+    Preset {
+        name: "token-alnum-32",
+        code: "$token .= $chars[mt_rand(0, 61)], 32 times, over a-zA-Z0-9",
+        arguments: &["--charset", ALNUM],
+        length: Some(32),
+    },
+    // Historical generators, down to their seeding. Seeds computed from microtime() as floats
+    // may be truncated one below the microseconds, both are searched. They ran on the PHP 5 of
+    // their time: PHP before 5.2.1, which scaled ranges differently, is not modeled.
+    Preset {
+        name: "wordpress-2.6-reset-key",
+        code: "wp_generate_password(20, false) in retrieve_password(), WordPress before 2.6.2 \
+               (CVE-2008-4107), PHP 5.2.1+ (earlier PHP is not modeled)",
+        arguments: &["--php", "5.2.1", "--charset", ALNUM],
+        length: Some(20),
+    },
+    Preset {
+        name: "wordpress-2.6-password",
+        code: "wp_generate_password(), the new passwords of resets and the 'secret' salt of \
+               WordPress 2.5 and 2.6 (CVE-2008-4107), PHP 5.2.1+ (earlier PHP is not \
+               modeled)",
+        arguments: &["--php", "5.2.1", "--charset", WORDPRESS],
+        length: Some(12),
+    },
+    Preset {
+        name: "joomla-1.5-password",
+        code: "JUserHelper::genRandomPassword(), seeded by mt_srand(10000000 * (double) \
+               microtime()), PHP 5.2.1+ (earlier PHP is not modeled)",
+        arguments: &[
+            "--php",
+            "5.2.1",
+            "--charset",
+            ALNUM,
+            "--seed-fn",
+            "usec * 10 - truncated",
+            "--seed-var",
+            "usec=0-999999",
+            "--seed-var",
+            "truncated=0-1",
+        ],
+        length: Some(8),
+    },
+    Preset {
+        name: "vbulletin-3-reset-id",
+        code: "vbrand(0, 100000000), the activation ID of password resets of vBulletin 3, seeded \
+               by mt_srand((double) microtime() * 1000000), PHP 5.2.1+ (earlier PHP is not \
+               modeled)",
+        arguments: &[
+            "--php",
+            "5.2.1",
+            "--dump-range",
+            "0-100000000",
+            "--seed-fn",
+            "usec - truncated",
+            "--seed-var",
+            "usec=0-999999",
+            "--seed-var",
+            "truncated=0-1",
+        ],
+        length: None,
+    },
+    Preset {
+        name: "codeigniter-2-alnum",
+        code: "random_string('alnum', $len) of CodeIgniter 2",
+        arguments: &["--charset", CODEIGNITER],
+        length: None,
+    },
+];
+
+pub fn find(name: &str) -> Result<&'static Preset, String> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| format!("unknown preset {name:?}, --list-presets lists them"))
+}
+
+/// The command line with the options of its `--preset` first, so that those it gives explicitly
+/// override them wherever they are, along with the preset.
+pub fn expand(args: Vec<String>) -> Result<(Vec<String>, Option<&'static Preset>), String> {
+    let Some(position) = args.iter().position(|arg| arg == "--preset") else {
+        return Ok((args, None));
+    };
+    let name = args.get(position + 1).ok_or("missing value for --preset")?;
+    let preset = find(name)?;
+    let mut expanded: Vec<String> = preset.arguments.iter().map(|&arg| arg.into()).collect();
+    expanded.extend_from_slice(&args[..position]);
+    expanded.extend_from_slice(&args[position + 2..]);
+    if expanded.iter().any(|arg| arg == "--preset") {
+        return Err("only one preset can be used".to_string());
+    }
+    Ok((expanded, Some(preset)))
+}
+
+/// The presets for `--list-presets`: their names, the code they search and their options.
+pub fn list() -> String {
+    let mut list = String::new();
+    for preset in PRESETS {
+        writeln!(list, "{:<24} {}", preset.name, preset.code).unwrap();
+        writeln!(list, "{:<24} {}", "", preset.arguments.join(" ")).unwrap();
+    }
+    list
+}
+
+#[test]
+fn test_presets() {
+    for preset in PRESETS {
+        assert_eq!(find(preset.name), Ok(preset));
+    }
+    assert!(find("captcha").is_err());
+    // The charsets are as large as the ranges of their calls.
+    assert_eq!(ALNUM.len(), 62);
+    assert_eq!(HEX.len(), 16);
+    assert_eq!(WORDPRESS.len(), 72);
+    assert_eq!(CODEIGNITER.len(), 62);
+}
+
+#[test]
+fn test_expand() {
+    let args = [
+        "--php",
+        "auto",
+        "--preset",
+        "token-hex-32",
+        "--charset",
+        "abc",
+    ]
+    .map(String::from);
+    let (expanded, preset) = expand(args.to_vec()).unwrap();
+    assert_eq!(
+        expanded,
+        ["--charset", HEX, "--php", "auto", "--charset", "abc"].map(String::from)
+    );
+    assert_eq!(preset.unwrap().length, Some(32));
+
+    let args = ["1178568022".to_string()];
+    assert_eq!(expand(args.to_vec()), Ok((args.to_vec(), None)));
+    assert!(expand(vec!["--preset".to_string()]).is_err());
+    let args = ["--preset", "otp-6digit", "--preset", "pin-digits"].map(String::from);
+    assert!(expand(args.to_vec()).is_err());
+}
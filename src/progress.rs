@@ -0,0 +1,127 @@
+// `--progress-json`: progress events for wrappers, web UIs and CI systems, which should not have to
+// parse the `\r` line meant for humans. Neither do logs: without a terminal, the progress is
+// printed as timestamped lines instead.
+
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::flavor::Flavor;
+
+/// Events are written at most this often unless `--progress-interval` says otherwise.
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Progress lines are printed at most this often unless `--progress-line-interval` says otherwise.
+pub const DEFAULT_PROGRESS_LINE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A progress event, as a JSON object on a single line: the flavors searched, the steps completed
+/// so far out of the last step, the fraction of `steps` done, the seeds tested per second and the
+/// seconds left at that rate (null until a rate is known).
+pub fn json_line(
+    flavors: &[Flavor],
+    step: u32,
+    steps: &Range<u32>,
+    seeds_per_step: f64,
+    found: usize,
+    elapsed: Duration,
+) -> String {
+    let done = step - steps.start;
+    let fraction = f64::from(done) / f64::from(steps.len() as u32);
+    let rate = f64::from(done) * seeds_per_step / elapsed.as_secs_f64();
+    let eta = match rate.is_finite() && rate > 0.0 {
+        true => format!("{:.1}", f64::from(steps.end - step) * seeds_per_step / rate),
+        false => "null".to_string(),
+    };
+    let rate = if rate.is_finite() { rate } else { 0.0 };
+    let names: Vec<&str> = flavors.iter().map(|flavor| flavor.name()).collect();
+    format!(
+        "{{\"php\":\"{}\",\"step\":{step},\"steps\":{},\"fraction\":{fraction:.4},\
+         \"found\":{found},\"rate\":{rate:.0},\"eta\":{eta}}}",
+        names.join(","),
+        steps.end
+    )
+}
+
+/// `time` in UTC, as RFC 3339 without fractions of seconds: `2026-10-16T09:30:00Z`.
+pub fn timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    // The civil date of the days since 1970-01-01, in eras of 400 years starting on March 1st so
+    // that leap days end them (Howard Hinnant's days_from_civil, reversed).
+    let days = days + 719468;
+    let (era, day_of_era) = (days / 146097, days % 146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// The progress as a line for logs, at `time`: the steps completed so far out of the last step,
+/// and the seeds found.
+pub fn log_line(time: SystemTime, step: u32, steps: &Range<u32>, found: usize) -> String {
+    format!(
+        "{} progress: {step:03} / {}, {found} found",
+        timestamp(time),
+        steps.end
+    )
+}
+
+#[test]
+fn test_json_line() {
+    // A quarter of the seeds in 16 s, so 48 s left
+    assert_eq!(
+        json_line(
+            &[Flavor::Php52],
+            64,
+            &(0..256),
+            16777216.0,
+            2,
+            Duration::from_secs(16)
+        ),
+        "{\"php\":\"5.2.1\",\"step\":64,\"steps\":256,\"fraction\":0.2500,\
+         \"found\":2,\"rate\":67108864,\"eta\":48.0}"
+    );
+    assert_eq!(
+        json_line(
+            &[Flavor::Php71],
+            0,
+            &(0..256),
+            16777216.0,
+            0,
+            Duration::ZERO
+        ),
+        "{\"php\":\"7.1\",\"step\":0,\"steps\":256,\"fraction\":0.0000,\
+         \"found\":0,\"rate\":0,\"eta\":null}"
+    );
+    // Both flavors searched at once
+    assert!(
+        json_line(&Flavor::ALL, 0, &(0..256), 16777216.0, 0, Duration::ZERO)
+            .starts_with("{\"php\":\"7.1,5.2.1\",")
+    );
+}
+
+#[test]
+fn test_log_line() {
+    assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    let at = |seconds| UNIX_EPOCH + Duration::from_secs(seconds);
+    assert_eq!(timestamp(at(951868799)), "2000-02-29T23:59:59Z");
+    assert_eq!(timestamp(at(951868800)), "2000-03-01T00:00:00Z");
+    assert_eq!(timestamp(at(1709164800)), "2024-02-29T00:00:00Z");
+    assert_eq!(timestamp(at(1798761599)), "2026-12-31T23:59:59Z");
+    assert_eq!(
+        log_line(at(1798761599), 64, &(0..256), 2),
+        "2026-12-31T23:59:59Z progress: 064 / 256, 2 found"
+    );
+}
@@ -0,0 +1,203 @@
+// Typed constraints and results, for services embedding the library. With the `serde` feature,
+// they round-trip through JSON, YAML or any other serde format.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::engine::is_any_value;
+use crate::flavor::Flavor;
+use crate::{lint_arguments, normalize_arguments};
+
+/// One slot: the output of an `mt_rand()` call must be in `match_min..=match_max`, the call
+/// drawing in `range_min..=range_max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Constraint {
+    pub match_min: u32,
+    pub match_max: u32,
+    pub range_min: u32,
+    pub range_max: u32,
+}
+
+impl Constraint {
+    /// `mt_rand()` without arguments returned `value`.
+    pub fn exact(value: u32) -> Self {
+        Constraint {
+            match_min: value,
+            match_max: value,
+            range_min: 0,
+            range_max: 0x7fffffff,
+        }
+    }
+
+    /// An output that is not known, only skipped.
+    pub fn any() -> Self {
+        Constraint {
+            match_min: 0,
+            match_max: 0x7fffffff,
+            range_min: 0,
+            range_max: 0x7fffffff,
+        }
+    }
+}
+
+/// Everything a search needs to know about its target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Query {
+    /// One per `mt_rand()` call, in order since the seeding.
+    pub constraints: Vec<Constraint>,
+    /// Searched one after the other, PHP 7.1.0+ only when omitted.
+    #[cfg_attr(feature = "serde", serde(default = "default_flavors"))]
+    pub flavors: Vec<Flavor>,
+}
+
+#[cfg(feature = "serde")]
+fn default_flavors() -> Vec<Flavor> {
+    vec![Flavor::default()]
+}
+
+impl Query {
+    /// The constraints given as on the command line: 1, 2 or 4 numbers per slot, the last slot
+    /// only may be shortened.
+    pub fn from_arguments(arguments: &[u32], flavors: Vec<Flavor>) -> Result<Self, String> {
+        let mut arguments = arguments.to_vec();
+        normalize_arguments(&mut arguments);
+        if !lint_arguments(&arguments) {
+            return Err("invalid constraints".to_string());
+        }
+        let constraints = arguments
+            .chunks(4)
+            .map(|slot| Constraint {
+                match_min: slot[0],
+                match_max: slot[1],
+                range_min: slot[2],
+                range_max: slot[3],
+            })
+            .collect();
+        Ok(Query {
+            constraints,
+            flavors,
+        })
+    }
+
+    /// The normalized arguments the engines take, 4 numbers per slot.
+    pub fn arguments(&self) -> Vec<u32> {
+        self.constraints
+            .iter()
+            .flat_map(|c| [c.match_min, c.match_max, c.range_min, c.range_max])
+            .collect()
+    }
+
+    /// The canonical key of this query, equal for the queries with the same results.
+    pub fn cache_key(&self) -> QueryKey {
+        let mut arguments = self.arguments();
+        while arguments.len() > 4 && is_any_value(&arguments[arguments.len() - 4..]) {
+            arguments.truncate(arguments.len() - 4);
+        }
+        QueryKey {
+            flavors: self.flavors.clone(),
+            arguments,
+        }
+    }
+
+    /// Whether the engines can search this query, as constraints built by hand may be
+    /// contradictory or out of range.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.flavors.is_empty() {
+            return Err("no flavor to search".to_string());
+        }
+        match lint_arguments(&self.arguments()) {
+            true => Ok(()),
+            false => Err("invalid constraints".to_string()),
+        }
+    }
+}
+
+/// What the results of a query depend on: its flavors, in order, and its constraints without the
+/// trailing ones that match any output, which rule out no seed. Identical searches running at the
+/// same time on a context share a single execution under this key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryKey {
+    flavors: Vec<Flavor>,
+    arguments: Vec<u32>,
+}
+
+/// A seed matching every constraint of a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SeedResult {
+    pub seed: u32,
+    pub flavor: Flavor,
+}
+
+/// The seeds found in a step of a flavor, one of the `partitions` steps of the context options:
+/// by default the 2^24 seeds sharing their low byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StepResult {
+    pub flavor: Flavor,
+    pub step: u32,
+    pub seeds: Vec<u32>,
+}
+
+#[test]
+fn test_query_from_arguments() {
+    let query = Query::from_arguments(&[5, 9, 0, 61, 1178568022], vec![Flavor::Php71]).unwrap();
+    assert_eq!(query.constraints[1], Constraint::exact(1178568022));
+    assert_eq!(
+        query.arguments(),
+        vec![5, 9, 0, 61, 1178568022, 1178568022, 0, 0x7fffffff]
+    );
+    assert_eq!(query.validate(), Ok(()));
+    assert!(Query::from_arguments(&[9, 5, 0, 61], vec![Flavor::Php71]).is_err());
+    assert!(Query::from_arguments(&[], vec![Flavor::Php71]).is_err());
+
+    let query = Query {
+        constraints: vec![Constraint::any()],
+        flavors: Vec::new(),
+    };
+    assert!(query.validate().is_err());
+}
+
+#[test]
+fn test_cache_key() {
+    let query = Query::from_arguments(&[1178568022], vec![Flavor::Php71]).unwrap();
+    let skipped = [
+        1178568022, 1178568022, 0, 0x7fffffff, 0, 0x7fffffff, 0, 0x7fffffff, 0, 7, 0, 7,
+    ];
+    let skipped = Query::from_arguments(&skipped, vec![Flavor::Php71]).unwrap();
+    assert_eq!(query.cache_key(), skipped.cache_key());
+    let both = Query::from_arguments(&[1178568022], Flavor::ALL.to_vec()).unwrap();
+    assert_ne!(query.cache_key(), both.cache_key());
+    let other = Query::from_arguments(
+        &[1178568022, 1178568022, 0, 0x7fffffff, 5],
+        vec![Flavor::Php71],
+    )
+    .unwrap();
+    assert_ne!(query.cache_key(), other.cache_key());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let query = Query::from_arguments(&[5, 9, 0, 61, 1178568022], Flavor::ALL.to_vec()).unwrap();
+    let json = serde_json::to_string(&query).unwrap();
+    assert!(json.contains(r#""match_min":5,"match_max":9,"range_min":0,"range_max":61"#));
+    assert!(json.contains(r#""flavors":["7.1","5.2.1"]"#));
+    assert_eq!(serde_json::from_str::<Query>(&json).unwrap(), query);
+
+    let query: Query = serde_json::from_str(
+        r#"{"constraints": [{"match_min": 7, "match_max": 7, "range_min": 0, "range_max": 9}]}"#,
+    )
+    .unwrap();
+    assert_eq!(query.flavors, vec![Flavor::Php71]);
+
+    let result = SeedResult {
+        seed: 424242,
+        flavor: Flavor::Php52,
+    };
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(json, r#"{"seed":424242,"flavor":"5.2.1"}"#);
+    assert_eq!(serde_json::from_str::<SeedResult>(&json).unwrap(), result);
+}
@@ -0,0 +1,96 @@
+// `--weights`: the seeds found ranked by how exactly they hit the constraints that matter most, to
+// triage large candidate sets by hand.
+
+use std::fmt::Write;
+
+use crate::engine::is_any_value;
+use crate::flavor::{format_seed, Flavor};
+use crate::replay;
+
+/// The weights of `--weights`: one per slot, in order, separated by commas.
+pub fn parse_weights(s: &str) -> Result<Vec<u32>, String> {
+    s.split(',')
+        .map(|weight| {
+            weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("cannot parse {weight:?} as a weight"))
+        })
+        .collect()
+}
+
+/// The weight of the slot `i`, 1 past the weights given.
+fn weight(weights: &[u32], i: usize) -> u32 {
+    weights.get(i).copied().unwrap_or(1)
+}
+
+/// The value a slot of the normalized arguments was observed as: the middle of its matches,
+/// which is the value of a `VALUE~DELTA` tolerance.
+fn observed(slot: &[u32]) -> u32 {
+    slot[0] + (slot[1] - slot[0]) / 2
+}
+
+/// The score of `seed`: the weights of the slots of the normalized `arguments` its outputs hit
+/// exactly. Slots that any output matches do not count.
+pub fn score(flavor: Flavor, arguments: &[u32], weights: &[u32], seed: u32) -> u32 {
+    let outputs = replay::outputs(flavor, arguments, seed);
+    arguments
+        .chunks(4)
+        .zip(outputs)
+        .enumerate()
+        .filter(|(_, (slot, output))| !is_any_value(slot) && observed(slot) == *output)
+        .map(|(i, _)| weight(weights, i))
+        .sum()
+}
+
+/// The best score a seed can get.
+pub fn max_score(arguments: &[u32], weights: &[u32]) -> u32 {
+    arguments
+        .chunks(4)
+        .enumerate()
+        .filter(|(_, slot)| !is_any_value(slot))
+        .map(|(i, _)| weight(weights, i))
+        .sum()
+}
+
+/// The seeds `found` per flavor, from the best score to the worst, one line each with its score.
+pub fn ranking(arguments: &[u32], weights: &[u32], found: &[(Flavor, Vec<u32>)]) -> String {
+    let mut scored: Vec<(u32, u32, Flavor)> = found
+        .iter()
+        .flat_map(|(flavor, seeds)| {
+            seeds
+                .iter()
+                .map(move |&seed| (score(*flavor, arguments, weights, seed), seed, *flavor))
+        })
+        .collect();
+    // The order of the search among equal scores.
+    scored.sort_by_key(|&(score, _, _)| std::cmp::Reverse(score));
+    let max = max_score(arguments, weights);
+    let mut lines = String::new();
+    for (score, seed, flavor) in scored {
+        writeln!(lines, "score {score}/{max}: {}", format_seed(seed, flavor)).unwrap();
+    }
+    lines
+}
+
+#[test]
+fn test_parse_weights() {
+    assert_eq!(parse_weights("1, 5,0"), Ok(vec![1, 5, 0]));
+    assert!(parse_weights("1,-2").is_err());
+}
+
+#[test]
+fn test_ranking() {
+    // mt_srand(4242) gives 697823703, then 44 for mt_rand(0, 61)
+    let arguments = [697823703, 697823703, 0, 0x7fffffff, 40, 48, 0, 61];
+    assert_eq!(max_score(&arguments, &[2]), 3);
+    assert_eq!(score(Flavor::Php71, &arguments, &[2, 5], 4242), 7);
+    assert_eq!(score(Flavor::Php71, &arguments, &[], 0), 0);
+
+    let found = [(Flavor::Php71, vec![0, 4242])];
+    assert_eq!(
+        ranking(&arguments, &[2, 5], &found),
+        "score 7/7: seed = 0x1092 = 4242 (PHP 7.1.0+)\n\
+         score 0/7: seed = 0x0 = 0 (PHP 7.1.0+)\n"
+    );
+}
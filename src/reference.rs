@@ -0,0 +1,240 @@
+// License: Apache 2.0 OR MIT, at your option
+// A straightforward CPU implementation of MT19937, to check the results of the shaders against.
+// Unlike mt19937.wgsl it computes the whole state, so that both are written differently.
+
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::engine::{Engine, ExecuteError, WORKGROUPS_PER_STEP};
+use crate::flavor::Flavor;
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908b0df;
+const UPPER_MASK: u32 = 0x80000000;
+const LOWER_MASK: u32 = 0x7fffffff;
+
+/// The MT19937 generator as seeded by PHP's `mt_srand()`.
+pub struct Mt19937 {
+    flavor: Flavor,
+    state: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32, flavor: Flavor) -> Self {
+        let mut state = [0; N];
+        state[0] = seed;
+        for i in 1..N {
+            state[i] = 1812433253u32
+                .wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        Mt19937 {
+            flavor,
+            state,
+            index: N,
+        }
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let x = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mut y = self.state[(i + M) % N] ^ (x >> 1);
+            // PHP before 7.1.0 took the low bit of the wrong word.
+            let low_bit = match self.flavor {
+                Flavor::Php71 => x & 1,
+                Flavor::Php52 => self.state[i] & 1,
+            };
+            if low_bit != 0 {
+                y ^= MATRIX_A;
+            }
+            self.state[i] = y;
+        }
+        self.index = 0;
+    }
+
+    /// The next tempered 32-bit output.
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let mut x = self.state[self.index];
+        self.index += 1;
+        x ^= x >> 11;
+        x ^= (x << 7) & 0x9d2c5680;
+        x ^= (x << 15) & 0xefc60000;
+        x ^= x >> 18;
+        x
+    }
+
+    /// `mt_rand()` without arguments, or `mt_rand(range_min, range_max)`.
+    ///
+    /// Like the shaders, PHP 7.1.0+ ranges are reduced with a modulo: PHP only differs when it
+    /// rejects an output, which is rare enough to be ignored.
+    pub fn next_in_range(&mut self, range_min: u32, range_max: u32) -> u32 {
+        let next = self.next_u32();
+        if range_min == 0 && range_max == 0x7fffffff {
+            return next >> 1;
+        }
+        // 64-bit PHP takes ranges up to 2^32 values.
+        let range = u64::from(range_max.wrapping_sub(range_min)) + 1;
+        match self.flavor {
+            Flavor::Php71 => ((u64::from(next) % range) as u32).wrapping_add(range_min),
+            // RAND_RANGE_BADSCALING, in doubles like PHP does.
+            Flavor::Php52 => {
+                let scaled = range as f64 * (f64::from(next >> 1) / 2147483648.0);
+                range_min.wrapping_add(scaled as u32)
+            }
+        }
+    }
+}
+
+/// Whether `seed` produces outputs matching the normalized `arguments`, like the search shader.
+pub fn seed_matches(flavor: Flavor, arguments: &[u32], seed: u32) -> bool {
+    let mut mt = Mt19937::new(seed, flavor);
+    arguments.chunks(4).all(|chunk| {
+        let &[match_min, match_max, range_min, range_max] = chunk else {
+            panic!("arguments must be normalized");
+        };
+        (match_min..=match_max).contains(&mt.next_in_range(range_min, range_max))
+    })
+}
+
+/// The seeds the search shader should find for the given workgroups of a step, in order.
+pub fn search_workgroups(
+    flavor: Flavor,
+    arguments: &[u32],
+    step: u32,
+    workgroups: Range<u32>,
+) -> Vec<u32> {
+    (workgroups.start * 256..workgroups.end * 256)
+        .map(|index| index * 256 + step)
+        .filter(|&seed| seed_matches(flavor, arguments, seed))
+        .collect()
+}
+
+/// How many candidates each rayon task verifies, enough to amortize the scheduling.
+pub(crate) const VERIFY_CHUNK: usize = 4096;
+
+/// Keep the candidates matching all of `arguments`, in parallel and in order.
+///
+/// Kernels only check the first slots, so they find candidates that still have to be checked
+/// against the whole constraints.
+pub fn verify_candidates(flavor: Flavor, arguments: &[u32], candidates: &[u32]) -> Vec<u32> {
+    candidates
+        .par_chunks(VERIFY_CHUNK)
+        .flat_map_iter(|chunk| {
+            chunk
+                .iter()
+                .copied()
+                .filter(|&seed| seed_matches(flavor, arguments, seed))
+        })
+        .collect()
+}
+
+/// The `--engine reference` backend: slow, but deterministic and available without any GPU.
+pub struct ReferenceEngine;
+
+pub fn prepare_reference() -> ReferenceEngine {
+    println!("\rRunning on the CPU reference engine, expect it to be very slow");
+    log::info!("CPU reference engine");
+    ReferenceEngine
+}
+
+impl Engine for ReferenceEngine {
+    fn execute_workgroups(
+        &self,
+        flavor: Flavor,
+        arguments: &[u32],
+        step: u32,
+        workgroups: Range<u32>,
+    ) -> Result<Vec<u32>, ExecuteError> {
+        assert!(step < 256);
+        assert!(!workgroups.is_empty() && workgroups.end <= WORKGROUPS_PER_STEP);
+
+        // There is no output buffer to overflow.
+        Ok(search_workgroups(flavor, arguments, step, workgroups))
+    }
+}
+
+#[test]
+fn test_reference_outputs() {
+    // The reference outputs of MT19937 for its default seed.
+    let mut mt = Mt19937::new(5489, Flavor::Php71);
+    assert_eq!(mt.next_u32(), 3499211612);
+    assert_eq!(mt.next_u32(), 581869302);
+
+    // mt_srand(0); mt_rand();
+    let mut mt = Mt19937::new(0, Flavor::Php71);
+    assert_eq!(mt.next_in_range(0, 0x7fffffff), 1178568022);
+    assert!(seed_matches(
+        Flavor::Php71,
+        &[1178568022, 1178568022, 0, 0x7fffffff],
+        0
+    ));
+    assert!(!seed_matches(
+        Flavor::Php71,
+        &[1178568022, 1178568022, 0, 0x7fffffff],
+        1
+    ));
+
+    let mut mt = Mt19937::new(4242, Flavor::Php71);
+    assert_eq!(mt.next_in_range(0, 0x7fffffff), 697823703);
+    assert_eq!(mt.next_in_range(0, 0x7fffffff), 1736388855);
+    assert_eq!(mt.next_in_range(0, 0x7fffffff), 2019524934);
+
+    // mt_srand(0); mt_rand(); on PHP 5.2.1 to 7.0.x
+    let mut mt = Mt19937::new(0, Flavor::Php52);
+    assert_eq!(mt.next_in_range(0, 0x7fffffff), 963932192);
+
+    // mt_srand(4242); mt_rand(0, 4294967295); on 64-bit PHP, the whole output or its doubled half.
+    let next = Mt19937::new(4242, Flavor::Php71).next_u32();
+    let mut mt = Mt19937::new(4242, Flavor::Php71);
+    assert_eq!(mt.next_in_range(0, u32::MAX), next);
+    let next = Mt19937::new(4242, Flavor::Php52).next_u32();
+    let mut mt = Mt19937::new(4242, Flavor::Php52);
+    assert_eq!(mt.next_in_range(0, u32::MAX), next & !1);
+}
+
+#[test]
+fn test_search_workgroups() {
+    assert_eq!(
+        search_workgroups(
+            Flavor::Php71,
+            &[1178568022, 1178568022, 0, 0x7fffffff],
+            0,
+            0..1
+        ),
+        vec![0]
+    );
+}
+
+#[test]
+fn test_reference_engine() {
+    let arguments = [
+        697823703, 697823703, 0, 0x7fffffff, 1736388855, 1736388855, 0, 0x7fffffff,
+    ];
+    let batch = [(4242 % 256, 0..1), (0, 0..1)];
+    assert_eq!(
+        ReferenceEngine.execute_batch(Flavor::Php71, &arguments, &batch),
+        Ok(vec![vec![4242], vec![]])
+    );
+}
+
+#[test]
+fn test_verify_candidates() {
+    let arguments = [
+        697823703, 697823703, 0, 0x7fffffff, 1736388855, 1736388855, 0, 0x7fffffff,
+    ];
+    let candidates: Vec<u32> = (0..3 * VERIFY_CHUNK as u32).rev().collect();
+    assert_eq!(
+        verify_candidates(Flavor::Php71, &arguments, &candidates),
+        vec![4242]
+    );
+    assert_eq!(
+        verify_candidates(Flavor::Php71, &arguments[..4], &[4242, 0, 4242]),
+        vec![4242, 4242]
+    );
+}
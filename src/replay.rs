@@ -0,0 +1,116 @@
+// The comparison printed by the verify subcommand: the outputs of a seed next to the values
+// observed, call by call, instead of leaving users to compare two lists by eye.
+
+use std::fmt::Write;
+
+use crate::engine::is_any_value;
+use crate::flavor::Flavor;
+use crate::reference::Mt19937;
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// The outputs of `seed` for the calls of the normalized `arguments`, in order.
+pub fn outputs(flavor: Flavor, arguments: &[u32], seed: u32) -> Vec<u32> {
+    let mut mt = Mt19937::new(seed, flavor);
+    arguments
+        .chunks(4)
+        .map(|slot| mt.next_in_range(slot[2], slot[3]))
+        .collect()
+}
+
+/// The index of the first call whose output is not what the normalized `arguments` expect.
+pub fn first_divergence(arguments: &[u32], outputs: &[u32]) -> Option<usize> {
+    arguments
+        .chunks(4)
+        .zip(outputs)
+        .position(|(slot, output)| !(slot[0]..=slot[1]).contains(output))
+}
+
+/// What a slot of the normalized arguments expects, as it can be written on the command line.
+pub fn expected(slot: &[u32]) -> String {
+    let &[match_min, match_max, range_min, range_max] = slot else {
+        panic!("arguments must be normalized");
+    };
+    let value = match (match_min == match_max, is_any_value(slot)) {
+        (_, true) => "any".to_string(),
+        (true, false) => match_min.to_string(),
+        (false, false) => format!("{match_min}-{match_max}"),
+    };
+    match (range_min, range_max) {
+        (0, 0x7fffffff) => value,
+        _ => format!("{value}@{range_min}-{range_max}"),
+    }
+}
+
+/// The calls of the normalized `arguments` replayed for `seed`, one per line with the output and
+/// the value expected, matches in green and mismatches in red when `color` is set, followed by
+/// the first call diverging.
+pub fn comparison(flavor: Flavor, arguments: &[u32], seed: u32, color: bool) -> String {
+    let outputs = outputs(flavor, arguments, seed);
+    let mut table = format!("{:>6} {:>12} {:>24}\n", "call", "output", "expected");
+    for (i, (slot, output)) in arguments.chunks(4).zip(&outputs).enumerate() {
+        let matches = (slot[0]..=slot[1]).contains(output);
+        let (verdict, paint) = match matches {
+            true => ("ok", GREEN),
+            false => ("differs", RED),
+        };
+        let line = format!(
+            "{:>6} {output:>12} {:>24}  {verdict}",
+            i + 1,
+            expected(slot)
+        );
+        match color && !is_any_value(slot) {
+            true => writeln!(table, "{paint}{line}{RESET}"),
+            false => writeln!(table, "{line}"),
+        }
+        .unwrap();
+    }
+    let calls = outputs.len();
+    match first_divergence(arguments, &outputs) {
+        None => writeln!(
+            table,
+            "seed {seed} matches all {calls} calls (PHP {})",
+            flavor.versions()
+        ),
+        Some(i) => writeln!(
+            table,
+            "seed {seed} first diverges at call {} of {calls} (PHP {})",
+            i + 1,
+            flavor.versions()
+        ),
+    }
+    .unwrap();
+    table
+}
+
+#[test]
+fn test_first_divergence() {
+    // mt_srand(4242) gives 697823703, 1736388855, 2019524934
+    let outputs = outputs(
+        Flavor::Php71,
+        &[0, 0x7fffffff, 0, 0x7fffffff].repeat(3),
+        4242,
+    );
+    assert_eq!(outputs, vec![697823703, 1736388855, 2019524934]);
+    let arguments = [
+        697823703, 697823703, 0, 0x7fffffff, 0, 0x7fffffff, 0, 0x7fffffff, 5, 5, 0, 0x7fffffff,
+    ];
+    assert_eq!(first_divergence(&arguments, &outputs), Some(2));
+    assert_eq!(first_divergence(&arguments[..8], &outputs), None);
+}
+
+#[test]
+fn test_comparison() {
+    let arguments = [697823703, 697823703, 0, 0x7fffffff, 5, 9, 0, 61];
+    let plain = comparison(Flavor::Php71, &arguments, 4242, false);
+    assert!(plain.contains("     1    697823703                697823703  ok\n"));
+    assert!(plain.contains(" 5-9@0-61  differs\n"));
+    assert!(plain.ends_with("seed 4242 first diverges at call 2 of 2 (PHP 7.1.0+)\n"));
+    assert!(!plain.contains('\x1b'));
+
+    let colored = comparison(Flavor::Php71, &arguments, 4242, true);
+    assert!(colored.contains(&format!("{GREEN}     1 ")));
+    assert!(colored.contains(&format!("  differs{RESET}\n")));
+}
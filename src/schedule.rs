@@ -0,0 +1,148 @@
+// `--step-order` and `--first`: which steps are searched first, so that the first hit comes
+// sooner for the seeds targets usually have.
+
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::engine::WORKGROUPS_PER_STEP;
+use crate::Options;
+
+/// The order the steps of a search go in, selected with `--step-order`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StepOrder {
+    /// Step after step, the seeds then come ordered.
+    #[default]
+    Sequential,
+    /// A different order on each run, so that no seed is always searched last.
+    Random,
+    /// The steps whose seeds are the closest to the current Unix time first, as seeds are often
+    /// derived from `time()`. Steps only cover ranges of seeds from 512 partitions on.
+    Time,
+}
+
+impl std::str::FromStr for StepOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(StepOrder::Sequential),
+            "random" => Ok(StepOrder::Random),
+            "time" => Ok(StepOrder::Time),
+            _ => Err(format!(
+                "unknown step order {s:?}, expected sequential, random or time"
+            )),
+        }
+    }
+}
+
+/// The steps of `options.partitions`, or of `options.step_range`, in the order of
+/// `options.step_order`.
+pub fn step_order(options: &Options) -> Vec<u32> {
+    let all = 0..options.partitions;
+    let mut steps: Vec<u32> = options.step_range.clone().unwrap_or(all).collect();
+    match options.step_order {
+        StepOrder::Sequential => {}
+        StepOrder::Random => shuffle(&mut steps, now().as_nanos() as u64),
+        StepOrder::Time => {
+            let now = now().as_secs();
+            steps.sort_by_key(|&step| distance(seed_range(options.partitions, step), now));
+        }
+    }
+    steps
+}
+
+/// The ranges of steps to search one after the other: all of them at once when they are searched
+/// in order, so that dispatches stay in flight from a step to the next, one by one otherwise, or
+/// when the search stops at the first step finding seeds with `--first`.
+pub fn runs(options: &Options) -> Vec<Range<u32>> {
+    if options.step_order == StepOrder::Sequential && !options.first {
+        let all = 0..options.partitions;
+        return vec![options.step_range.clone().unwrap_or(all)];
+    }
+    step_order(options)
+        .into_iter()
+        .map(|step| step..step + 1)
+        .collect()
+}
+
+fn now() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Fisher-Yates with splitmix64, random enough to pick an order.
+fn shuffle(steps: &mut [u32], mut state: u64) {
+    for i in (1..steps.len()).rev() {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        steps.swap(i, (z % (i as u64 + 1)) as usize);
+    }
+}
+
+/// The smallest and largest seeds of `step`, plus one: the workgroups of a kernel step go through
+/// the seeds in ascending order, 65536 at a time, so steps smaller than a kernel step only hold
+/// seeds of a range.
+fn seed_range(partitions: u32, step: u32) -> Range<u64> {
+    let per_step = u64::from(256 * WORKGROUPS_PER_STEP / partitions);
+    if per_step >= u64::from(WORKGROUPS_PER_STEP) {
+        return 0..1 << 32;
+    }
+    let first = u64::from(step) * per_step % u64::from(WORKGROUPS_PER_STEP);
+    first << 16..(first + per_step) << 16
+}
+
+fn distance(range: Range<u64>, seed: u64) -> u64 {
+    if seed < range.start {
+        range.start - seed
+    } else if seed >= range.end {
+        seed - range.end + 1
+    } else {
+        0
+    }
+}
+
+#[test]
+fn test_seed_range() {
+    assert_eq!(seed_range(256, 7), 0..1 << 32);
+    // The second half of the seeds whose lowest byte is 3
+    assert_eq!(seed_range(512, 7), 1 << 31..1 << 32);
+    assert_eq!(seed_range(65536, 256 + 1), 1 << 24..2 << 24);
+}
+
+#[test]
+fn test_step_order() {
+    let mut options = Options {
+        partitions: 1024,
+        step_order: StepOrder::Time,
+        ..Options::default()
+    };
+    let steps = step_order(&options);
+    // Seeds near the current time, 1.7e9 and more, are in the 2nd quarter of the seeds.
+    assert!(steps[..256].iter().all(|step| step % 4 == 1));
+    assert_eq!(&steps[..2], &[1, 5]);
+
+    options.step_order = StepOrder::Random;
+    let mut steps = step_order(&options);
+    steps.sort_unstable();
+    assert_eq!(steps, (0..1024).collect::<Vec<_>>());
+
+    options.step_order = StepOrder::Sequential;
+    let all = 0..1024;
+    assert_eq!(runs(&options), vec![all]);
+    options.first = true;
+    assert_eq!(runs(&options)[..2], [0..1, 1..2]);
+
+    // The steps of a work unit
+    options.step_range = Some(8..12);
+    assert_eq!(runs(&options), vec![8..9, 9..10, 10..11, 11..12]);
+    options.first = false;
+    assert_eq!(runs(&options), vec![8..12]);
+    options.step_order = StepOrder::Random;
+    let mut steps = step_order(&options);
+    steps.sort_unstable();
+    assert_eq!(steps, [8, 9, 10, 11]);
+}
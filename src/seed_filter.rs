@@ -0,0 +1,91 @@
+// `--exclude-seeds` and `--only-seeds`: seeds ruled out in previous sessions, or candidates
+// found by another tool.
+
+use std::collections::BTreeSet;
+
+/// Seeds to leave out of the results, or to restrict the results to.
+#[derive(Debug, Default)]
+pub struct SeedFilter {
+    excluded: BTreeSet<u32>,
+    only: Option<BTreeSet<u32>>,
+}
+
+impl SeedFilter {
+    pub fn exclude(&mut self, seeds: impl IntoIterator<Item = u32>) {
+        self.excluded.extend(seeds);
+    }
+
+    /// Restrict the results to `seeds`, along with the seeds of previous calls.
+    pub fn only(&mut self, seeds: impl IntoIterator<Item = u32>) {
+        self.only.get_or_insert_with(BTreeSet::new).extend(seeds);
+    }
+
+    /// Whether every seed is allowed.
+    pub fn is_empty(&self) -> bool {
+        self.excluded.is_empty() && self.only.is_none()
+    }
+
+    pub fn allows(&self, seed: u32) -> bool {
+        !self.excluded.contains(&seed) && self.only.as_ref().is_none_or(|only| only.contains(&seed))
+    }
+
+    /// The only seeds that can be found, in order, when the results are restricted.
+    pub fn candidates(&self) -> Option<Vec<u32>> {
+        let only = self.only.as_ref()?;
+        Some(only.difference(&self.excluded).copied().collect())
+    }
+}
+
+/// A seed in decimal, or in hexadecimal with `0x`.
+pub fn parse_seed(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// The seeds of a file: one per line, in decimal or in hexadecimal with `0x`. Result lines
+/// (`seed = 0x00000000 = 0 (PHP 7.1.0+)`) are accepted too, so that the output of previous runs
+/// can be used as is. Empty lines and lines starting with `#` are skipped.
+pub fn parse_seeds(text: &str) -> Result<Vec<u32>, String> {
+    let mut seeds = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let seed = match line.strip_prefix("seed = ") {
+            Some(result) => result.split_whitespace().next(),
+            None => Some(line),
+        };
+        match seed.and_then(parse_seed) {
+            Some(seed) => seeds.push(seed),
+            None => return Err(format!("cannot read a seed in {line:?}")),
+        }
+    }
+    Ok(seeds)
+}
+
+#[test]
+fn test_parse_seeds() {
+    let text = "# from the last run\n42\n0x10\n\nseed = 0x00067932 = 424242 (PHP 7.1.0+)\n";
+    assert_eq!(parse_seeds(text), Ok(vec![42, 16, 424242]));
+    assert!(parse_seeds("seed").is_err());
+}
+
+#[test]
+fn test_seed_filter() {
+    let mut filter = SeedFilter::default();
+    assert!(filter.allows(1));
+    assert_eq!(filter.candidates(), None);
+
+    filter.exclude([1, 2]);
+    assert!(!filter.allows(1));
+    assert!(filter.allows(3));
+
+    filter.only([3, 2, 4]);
+    assert!(filter.allows(3));
+    assert!(!filter.allows(2));
+    assert!(!filter.allows(5));
+    assert_eq!(filter.candidates(), Some(vec![3, 4]));
+}
@@ -0,0 +1,324 @@
+// `--seed-fn`: the seeding code of the target, such as `mt_srand($x * 1000 + $pid)`, written as
+// an expression over bounded variables, so that only the seeds it can reach are checked.
+
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+
+/// Seeds a function may reach before it is refused: past that, checking them takes about as long
+/// as searching every seed on the GPU.
+pub const MAX_SEEDS: u64 = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Shl,
+    Shr,
+    And,
+    Xor,
+    Or,
+}
+
+impl Operator {
+    /// The binding power of the operator, PHP's precedence: the higher, the tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Mul | Operator::Div | Operator::Rem => 5,
+            Operator::Add | Operator::Sub => 4,
+            Operator::Shl | Operator::Shr => 3,
+            Operator::And => 2,
+            Operator::Xor => 1,
+            Operator::Or => 0,
+        }
+    }
+
+    fn apply(self, a: i64, b: i64) -> Option<i64> {
+        Some(match self {
+            Operator::Add => a.wrapping_add(b),
+            Operator::Sub => a.wrapping_sub(b),
+            Operator::Mul => a.wrapping_mul(b),
+            Operator::Div => a.checked_div(b)?,
+            Operator::Rem => a.checked_rem(b)?,
+            Operator::Shl => a.wrapping_shl(b as u32),
+            Operator::Shr => a.wrapping_shr(b as u32),
+            Operator::And => a & b,
+            Operator::Xor => a ^ b,
+            Operator::Or => a | b,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Variable(String),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let operator = match c {
+            ' ' | '\t' => continue,
+            '(' => {
+                tokens.push(Token::Open);
+                continue;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                continue;
+            }
+            '+' => Operator::Add,
+            '-' => Operator::Sub,
+            '*' => Operator::Mul,
+            '/' => Operator::Div,
+            '%' => Operator::Rem,
+            '&' => Operator::And,
+            '^' => Operator::Xor,
+            '|' => Operator::Or,
+            '<' | '>' => {
+                if chars.next_if(|&(_, next)| next == c).is_none() {
+                    return Err(format!("unknown operator {c:?}, expected {c}{c}"));
+                }
+                match c {
+                    '<' => Operator::Shl,
+                    _ => Operator::Shr,
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '$' => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, next)) =
+                    chars.next_if(|&(_, n)| n.is_ascii_alphanumeric() || n == '_')
+                {
+                    end = j + next.len_utf8();
+                }
+                let word = &s[i..end];
+                if !c.is_ascii_digit() {
+                    tokens.push(Token::Variable(word.trim_start_matches('$').to_string()));
+                    continue;
+                }
+                let number = match word.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => word.parse(),
+                };
+                let number = number.map_err(|_| format!("cannot parse {word:?} as a number"))?;
+                tokens.push(Token::Number(number));
+                continue;
+            }
+            c => return Err(format!("unexpected {c:?}")),
+        };
+        tokens.push(Token::Operator(operator));
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    /// The index of the variable in the function.
+    Variable(usize),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, values: &[i64]) -> Option<i64> {
+        match self {
+            Expr::Number(n) => Some(*n),
+            Expr::Variable(i) => Some(values[*i]),
+            Expr::Negate(e) => Some(e.eval(values)?.wrapping_neg()),
+            Expr::Binary(op, a, b) => op.apply(a.eval(values)?, b.eval(values)?),
+        }
+    }
+}
+
+/// A precedence climbing parser, collecting the variables in the order they appear.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    variables: Vec<String>,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn operand(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Variable(name)) => {
+                let i = match self.variables.iter().position(|v| *v == name) {
+                    Some(i) => i,
+                    None => {
+                        self.variables.push(name);
+                        self.variables.len() - 1
+                    }
+                };
+                Ok(Expr::Variable(i))
+            }
+            Some(Token::Operator(Operator::Sub)) => Ok(Expr::Negate(Box::new(self.operand()?))),
+            Some(Token::Open) => {
+                let e = self.expr(0)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(e),
+                    _ => Err("missing )".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {token:?}")),
+            None => Err("the expression ends too early".to_string()),
+        }
+    }
+
+    fn expr(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut lhs = self.operand()?;
+        while let Some(Token::Operator(op)) = self.tokens.get(self.position).cloned() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.position += 1;
+            let rhs = self.expr(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+}
+
+/// The seeding code of the target: `seed = f(x, ...)`, each variable bounded by a range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedFunction {
+    expr: Expr,
+    variables: Vec<String>,
+}
+
+impl SeedFunction {
+    /// Parse an expression of integers (decimal, or hexadecimal with `0x`), variables (`x`, or
+    /// `$x` as in PHP), parentheses and the operators `+ - * / % << >> & ^ |`, with the
+    /// precedence of PHP.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+            variables: Vec::new(),
+        };
+        let expr = parser.expr(0)?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(format!("unexpected {token:?}"));
+        }
+        Ok(SeedFunction {
+            expr,
+            variables: parser.variables,
+        })
+    }
+
+    /// The variables of the function, in the order they first appear.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Every seed the function reaches when its variables go through `ranges`, in ascending
+    /// order, keeping the low 32 bits of the results like `mt_srand()` does.
+    pub fn seeds(&self, ranges: &[(String, RangeInclusive<u32>)]) -> Result<Vec<u32>, String> {
+        let bounds = self
+            .variables
+            .iter()
+            .map(|name| {
+                let (_, range) = ranges
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .ok_or_else(|| format!("the variable {name} has no range"))?;
+                Ok(range.clone())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let combinations = bounds
+            .iter()
+            .map(|range| u64::from(range.end() - range.start()) + 1)
+            .try_fold(1u64, |total, n| total.checked_mul(n))
+            .filter(|&total| total <= MAX_SEEDS)
+            .ok_or_else(|| {
+                format!("the ranges reach more than {MAX_SEEDS} seeds, search every seed instead")
+            })?;
+
+        let mut seeds = BTreeSet::new();
+        let mut values: Vec<i64> = bounds.iter().map(|r| i64::from(*r.start())).collect();
+        for _ in 0..combinations {
+            let seed = self
+                .expr
+                .eval(&values)
+                .ok_or_else(|| format!("division by zero for {values:?}"))?;
+            seeds.insert(seed as u32);
+            // The next combination, like an odometer.
+            for (value, range) in values.iter_mut().zip(&bounds) {
+                if *value < i64::from(*range.end()) {
+                    *value += 1;
+                    break;
+                }
+                *value = i64::from(*range.start());
+            }
+        }
+        Ok(seeds.into_iter().collect())
+    }
+}
+
+/// A variable of `--seed-var`: `NAME=MIN-MAX`, or `NAME=VALUE`.
+pub fn parse_variable(s: &str) -> Result<(String, RangeInclusive<u32>), String> {
+    let (name, range) = s
+        .split_once('=')
+        .ok_or_else(|| format!("{s:?} is not NAME=MIN-MAX"))?;
+    let (min, max) = crate::syntax::parse_range(range)?;
+    if min > max {
+        return Err(format!("the range of {name} ends before it starts"));
+    }
+    Ok((name.trim_start_matches('$').to_string(), min..=max))
+}
+
+#[test]
+fn test_parse() {
+    let f = SeedFunction::parse("$x * 1000 + pid").unwrap();
+    assert_eq!(f.variables(), ["x", "pid"]);
+    assert_eq!(f.expr.eval(&[3, 7]), Some(3007));
+    // PHP's precedence: shifts after additions, then &, ^ and |
+    let f = SeedFunction::parse("1 + 1 << 2 ^ 0x10 | (2 - -1) % 2").unwrap();
+    assert_eq!(f.expr.eval(&[]), Some((((1 + 1) << 2) ^ 0x10) | 1));
+    assert!(SeedFunction::parse("x +").is_err());
+    assert!(SeedFunction::parse("(x").is_err());
+    assert!(SeedFunction::parse("x < 2").is_err());
+    assert!(SeedFunction::parse("x y").is_err());
+}
+
+#[test]
+fn test_seeds() {
+    let f = SeedFunction::parse("x * 1000 + pid").unwrap();
+    let ranges = [
+        ("pid".to_string(), 1..=2),
+        ("x".to_string(), 0..=2),
+        ("unused".to_string(), 0..=9),
+    ];
+    assert_eq!(f.seeds(&ranges), Ok(vec![1, 2, 1001, 1002, 2001, 2002]));
+    // Only the low 32 bits are kept
+    let f = SeedFunction::parse("x - 1").unwrap();
+    assert_eq!(f.seeds(&[("x".to_string(), 0..=0)]), Ok(vec![u32::MAX]));
+    assert!(f.seeds(&[]).is_err());
+    assert!(f.seeds(&[("x".to_string(), 0..=u32::MAX)]).is_err());
+    let f = SeedFunction::parse("1 / x").unwrap();
+    assert!(f.seeds(&[("x".to_string(), 0..=1)]).is_err());
+}
+
+#[test]
+fn test_parse_variable() {
+    assert_eq!(
+        parse_variable("$x=0-86399"),
+        Ok(("x".to_string(), 0..=86399))
+    );
+    assert_eq!(parse_variable("pid=42"), Ok(("pid".to_string(), 42..=42)));
+    assert!(parse_variable("x").is_err());
+    assert!(parse_variable("x=5-4").is_err());
+}
@@ -0,0 +1,247 @@
+// The main entry point of the library: searches described call by call, rather than with the
+// raw quadruples of numbers the command line takes.
+
+use crate::context::GpuContext;
+use crate::flavor::Flavor;
+use crate::query::{Constraint, Query, SeedResult, StepResult};
+
+/// A search, built from what is known about each `mt_rand()` call since the seeding:
+///
+/// ```no_run
+/// use php_mt_seed::{context::GpuContext, flavor::Flavor, seed_search::SeedSearch};
+///
+/// let ctx = GpuContext::new(Default::default()).unwrap();
+/// let seeds = SeedSearch::new()
+///     .flavor(Flavor::Php71)
+///     .exact(1178568022)
+///     .range_match(7505, 1000, 10000)
+///     .skip(3)
+///     .run(&ctx)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SeedSearch {
+    constraints: Vec<Constraint>,
+    flavors: Vec<Flavor>,
+}
+
+impl SeedSearch {
+    pub fn new() -> Self {
+        SeedSearch::default()
+    }
+
+    /// Search this flavor, on top of the previous ones. PHP 7.1.0+ only when none is given.
+    pub fn flavor(mut self, flavor: Flavor) -> Self {
+        if !self.flavors.contains(&flavor) {
+            self.flavors.push(flavor);
+        }
+        self
+    }
+
+    /// Any constraint, in the order of the calls.
+    pub fn constraint(mut self, constraint: Constraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    /// `mt_rand()` returned `value`.
+    pub fn exact(self, value: u32) -> Self {
+        self.constraint(Constraint::exact(value))
+    }
+
+    /// `mt_rand(min, max)` returned `value`.
+    pub fn range_match(self, value: u32, min: u32, max: u32) -> Self {
+        self.constraint(Constraint {
+            match_min: value,
+            match_max: value,
+            range_min: min,
+            range_max: max,
+        })
+    }
+
+    /// `count` calls whose outputs are not known.
+    pub fn skip(mut self, count: usize) -> Self {
+        self.constraints
+            .extend(std::iter::repeat_n(Constraint::any(), count));
+        self
+    }
+
+    pub fn query(&self) -> Query {
+        let flavors = match self.flavors.is_empty() {
+            true => vec![Flavor::default()],
+            false => self.flavors.clone(),
+        };
+        Query {
+            constraints: self.constraints.clone(),
+            flavors,
+        }
+    }
+
+    /// Search every seed on `ctx`, flavor after flavor, and return the matching ones in a
+    /// deterministic order.
+    ///
+    /// When an identical search (same `Query::cache_key`) is already running on `ctx`, as when
+    /// a server gets the same job twice, its results are waited for instead. Fails on an invalid
+    /// query, and when the engine cannot search it.
+    pub fn run(&self, ctx: &GpuContext) -> Result<Vec<SeedResult>, String> {
+        let query = self.query();
+        query.validate()?;
+        let arguments = query.arguments();
+        ctx.deduplicate(&query.cache_key(), || {
+            let mut results = Vec::new();
+            for &flavor in &query.flavors {
+                let steps = 0..ctx.options().partitions;
+                ctx.search_steps(flavor, &arguments, steps, |seed| {
+                    results.push(SeedResult { seed, flavor })
+                })?;
+            }
+            Ok(results)
+        })
+    }
+
+    /// Run several searches together: each dispatch checks all of them, so that many small
+    /// searches cost little more than one. Returns the results of each search, like `run`.
+    pub fn run_batch(
+        searches: &[SeedSearch],
+        ctx: &GpuContext,
+    ) -> Result<Vec<Vec<SeedResult>>, String> {
+        let queries = searches
+            .iter()
+            .map(|search| {
+                let query = search.query();
+                query.validate()?;
+                Ok(query)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut results = vec![Vec::new(); queries.len()];
+        for flavor in Flavor::ALL {
+            let searched: Vec<usize> = (0..queries.len())
+                .filter(|&i| queries[i].flavors.contains(&flavor))
+                .collect();
+            if searched.is_empty() {
+                continue;
+            }
+            let arguments: Vec<Vec<u32>> =
+                searched.iter().map(|&i| queries[i].arguments()).collect();
+            let steps = 0..ctx.options().partitions;
+            ctx.search_queries(flavor, &arguments, steps, |query, seed| {
+                results[searched[query]].push(SeedResult { seed, flavor })
+            })?;
+        }
+        // Flavors in the order each search gave them, the seeds of a flavor staying in order
+        for (query, results) in queries.iter().zip(&mut results) {
+            results.sort_by_key(|result| query.flavors.iter().position(|&f| f == result.flavor));
+        }
+        Ok(results)
+    }
+
+    /// Search step by step (`partitions` of the context options per flavor), only when the next
+    /// step is asked for, so that early hits can be acted
+    /// upon before the whole search is over. Every step of a flavor comes before the next flavor.
+    /// A step the engine fails to search is an `Err`, the next ones may still be asked for.
+    pub fn steps<'a>(&self, ctx: &'a GpuContext) -> Result<Steps<'a>, String> {
+        let query = self.query();
+        query.validate()?;
+        Ok(Steps {
+            ctx,
+            arguments: query.arguments(),
+            flavors: query.flavors,
+            next: 0,
+        })
+    }
+}
+
+/// The lazy iterator of `SeedSearch::steps`.
+pub struct Steps<'a> {
+    ctx: &'a GpuContext,
+    arguments: Vec<u32>,
+    flavors: Vec<Flavor>,
+    /// Index of the next step among the steps of every flavor.
+    next: usize,
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Result<StepResult, String>;
+
+    fn next(&mut self) -> Option<Result<StepResult, String>> {
+        let partitions = self.ctx.options().partitions as usize;
+        let flavor = *self.flavors.get(self.next / partitions)?;
+        let step = (self.next % partitions) as u32;
+        self.next += 1;
+        let mut seeds = Vec::new();
+        let searched = self
+            .ctx
+            .search_steps(flavor, &self.arguments, step..step + 1, |seed| {
+                seeds.push(seed)
+            });
+        Some(searched.map(|()| StepResult {
+            flavor,
+            step,
+            seeds,
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let partitions = self.ctx.options().partitions as usize;
+        let remaining = self.flavors.len() * partitions - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl From<Query> for SeedSearch {
+    fn from(query: Query) -> Self {
+        SeedSearch {
+            constraints: query.constraints,
+            flavors: query.flavors,
+        }
+    }
+}
+
+#[test]
+fn test_seed_search_query() {
+    let query = SeedSearch::new()
+        .flavor(Flavor::Php52)
+        .exact(1178568022)
+        .range_match(7505, 1000, 10000)
+        .skip(2)
+        .query();
+    assert_eq!(query.flavors, vec![Flavor::Php52]);
+    assert_eq!(
+        query.arguments(),
+        vec![
+            1178568022, 1178568022, 0, 0x7fffffff, 7505, 7505, 1000, 10000, 0, 0x7fffffff, 0,
+            0x7fffffff, 0, 0x7fffffff, 0, 0x7fffffff,
+        ]
+    );
+    assert_eq!(query.validate(), Ok(()));
+
+    assert_eq!(
+        SeedSearch::new().exact(5).query().flavors,
+        vec![Flavor::Php71]
+    );
+    assert!(SeedSearch::new().query().validate().is_err());
+    assert!(SeedSearch::new()
+        .range_match(10, 0, 9)
+        .query()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn test_seed_search_steps() {
+    let ctx = GpuContext::new(crate::Options {
+        allow_software: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let search = SeedSearch::new().exact(963932192).flavor(Flavor::Php52);
+    let mut steps = search.steps(&ctx).unwrap();
+    assert_eq!(steps.size_hint(), (256, Some(256)));
+    // Seed 0 is in the first step of the first flavor
+    let first = steps.next().unwrap().unwrap();
+    assert_eq!(first.flavor, Flavor::Php52);
+    assert_eq!(first.step, 0);
+    assert!(first.seeds.contains(&0));
+    assert_eq!(steps.size_hint(), (255, Some(255)));
+    assert!(SeedSearch::new().steps(&ctx).is_err());
+}
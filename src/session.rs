@@ -0,0 +1,394 @@
+// `--session NAME` and `--restore`, like hashcat: the arguments, progress and results of a search
+// are kept in a session directory, so that an interrupted search can go on where it stopped.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::flavor::{format_seed, Flavor};
+use crate::potfile::fnv1a;
+use crate::seed_filter::parse_seeds;
+use crate::Options;
+
+/// The shortest time between two checkpoints of the progress, without `--checkpoint-interval`.
+pub const DEFAULT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The session `--restore` uses without `--session`, like hashcat's.
+pub const DEFAULT_SESSION: &str = "php_mt_seed_rs";
+
+/// Where the tool keeps its files: `$XDG_DATA_HOME/php_mt_seed_rs`, under `~/.local/share`
+/// without it, or `%APPDATA%` on Windows.
+pub fn data_dir() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_default()
+        .join("php_mt_seed_rs")
+}
+
+/// Where sessions are kept, in `data_dir()`.
+pub fn sessions_dir() -> PathBuf {
+    data_dir().join("sessions")
+}
+
+/// FNV-1a over what decides the seeds each step finds: the constraints once parsed, the flavors
+/// and the partitions. The arguments of a session may give other constraints when it is restored,
+/// such as when a file of `--from-php-dump` changed, and its progress would then not apply.
+pub fn query_hash(options: &Options, arguments: &[u32]) -> u64 {
+    let words = (options.flavors.iter().map(|flavor| flavor.kernel_id()))
+        .chain([u32::MAX, options.partitions])
+        .chain(arguments.iter().copied());
+    fnv1a(words.flat_map(u32::to_le_bytes))
+}
+
+/// The progress of a search, as checkpointed to the `progress` file of its session: the query
+/// hash, the runs of each flavor in the order they are searched, then the flavor and step of each
+/// completed step. `--replay` searches what it has left in the same order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Checkpoint {
+    query: Option<u64>,
+    orders: Vec<(Flavor, Vec<Range<u32>>)>,
+    completed: BTreeSet<(u32, u32)>,
+}
+
+impl Checkpoint {
+    pub fn parse(text: &str) -> Checkpoint {
+        let mut checkpoint = Checkpoint::default();
+        for line in text.lines() {
+            // Sessions of older versions have no query hash, and are restored without checking it.
+            if let Some(hash) = line.strip_prefix("query = ") {
+                checkpoint.query = u64::from_str_radix(hash, 16).ok();
+                continue;
+            }
+            if let Some((flavor, runs)) = line.strip_prefix("order ").and_then(parse_order) {
+                checkpoint.orders.push((flavor, runs));
+                continue;
+            }
+            let parsed = line.split_once(' ').and_then(|(flavor, step)| {
+                Some((flavor.parse::<Flavor>().ok()?, step.parse().ok()?))
+            });
+            match parsed {
+                Some((flavor, step)) => checkpoint.completed.insert((flavor.kernel_id(), step)),
+                // Progress appended by older versions may have been cut short by an interruption.
+                None => continue,
+            };
+        }
+        checkpoint
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = match self.query {
+            Some(query) => format!("query = {query:016x}\n"),
+            None => String::new(),
+        };
+        for (flavor, runs) in &self.orders {
+            let runs: Vec<String> = runs
+                .iter()
+                .map(|run| format!("{}..{}", run.start, run.end))
+                .collect();
+            text.push_str(&format!("order {} = {}\n", flavor.name(), runs.join(" ")));
+        }
+        for &(kernel_id, step) in &self.completed {
+            let flavor = Flavor::ALL
+                .into_iter()
+                .find(|flavor| flavor.kernel_id() == kernel_id)
+                .unwrap();
+            text.push_str(&format!("{} {step}\n", flavor.name()));
+        }
+        text
+    }
+
+    /// Check that the search of `query`, see `query_hash`, is the one the progress comes from.
+    pub fn check_query(&self, query: u64) -> Result<(), String> {
+        match self.query {
+            Some(previous) if previous != query => Err(format!(
+                "its constraints are not those its progress comes from (query {previous:016x}, \
+                 now {query:016x})"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The steps of `runs` not completed for `flavor`, as the ranges left of each run.
+    pub fn remaining(&self, flavor: Flavor, runs: Vec<Range<u32>>) -> Vec<Range<u32>> {
+        let done = |step: u32| self.completed.contains(&(flavor.kernel_id(), step));
+        let mut remaining = Vec::new();
+        for run in runs {
+            let mut start = run.start;
+            for step in run.clone() {
+                if done(step) {
+                    if start < step {
+                        remaining.push(start..step);
+                    }
+                    start = step + 1;
+                }
+            }
+            if start < run.end {
+                remaining.push(start..run.end);
+            }
+        }
+        remaining
+    }
+
+    /// What `--replay` searches for `flavor`: the steps left of the runs recorded, in their order,
+    /// or of `runs` when the search did not get to the flavor.
+    pub fn replay(&self, flavor: Flavor, runs: Vec<Range<u32>>) -> Vec<Range<u32>> {
+        let recorded = self.orders.iter().find(|(recorded, _)| *recorded == flavor);
+        let runs = recorded.map_or(runs, |(_, recorded)| recorded.clone());
+        self.remaining(flavor, runs)
+    }
+
+    fn record_order(&mut self, flavor: Flavor, runs: &[Range<u32>]) {
+        self.orders.retain(|(recorded, _)| *recorded != flavor);
+        self.orders.push((flavor, runs.to_vec()));
+    }
+}
+
+/// `7.1 = 3..4 0..1`, the runs of a flavor.
+fn parse_order(line: &str) -> Option<(Flavor, Vec<Range<u32>>)> {
+    let (flavor, runs) = line.split_once(" = ")?;
+    let runs = runs
+        .split_whitespace()
+        .map(|run| {
+            let (start, end) = run.split_once("..")?;
+            Some(start.parse().ok()?..end.parse().ok()?)
+        })
+        .collect::<Option<_>>()?;
+    Some((flavor.parse().ok()?, runs))
+}
+
+/// A session directory: `arguments` (one per line), `metadata`, `progress` (a `Checkpoint`) and
+/// `results` (result lines, which `--exclude-seeds` reads as well). Results are appended as the
+/// search goes, and the progress is checkpointed at most every `checkpoint_interval`, to a
+/// temporary file renamed over the previous checkpoint so that an interruption never leaves a
+/// partial one.
+pub struct Session {
+    dir: PathBuf,
+    arguments: Vec<String>,
+    checkpoint: Mutex<Checkpoint>,
+    results: Vec<(u32, Flavor)>,
+    results_file: File,
+    checkpoint_interval: Duration,
+    last_checkpoint: Mutex<Instant>,
+}
+
+impl Session {
+    /// Start the session `name` over, for a search with `arguments`.
+    pub fn create(name: &str, arguments: &[String]) -> Result<Session, String> {
+        Session::create_in(sessions_dir().join(name), arguments)
+    }
+
+    /// Reopen the session `name`, to go on with its search.
+    pub fn restore(name: &str) -> Result<Session, String> {
+        Session::restore_from(sessions_dir().join(name))
+    }
+
+    fn create_in(dir: PathBuf, arguments: &[String]) -> Result<Session, String> {
+        if arguments.iter().any(|argument| argument.contains('\n')) {
+            return Err("arguments with line breaks cannot be saved".to_string());
+        }
+        fs::create_dir_all(&dir).map_err(|e| format!("cannot create {dir:?}: {e}"))?;
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let metadata = format!(
+            "version = {}\ncreated = {created}\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        for (file, contents) in [
+            (
+                "arguments",
+                arguments.iter().map(|a| format!("{a}\n")).collect(),
+            ),
+            ("metadata", metadata),
+            ("progress", String::new()),
+            ("results", String::new()),
+        ] {
+            let path = dir.join(file);
+            fs::write(&path, contents).map_err(|e| format!("cannot write {path:?}: {e}"))?;
+        }
+        Session::restore_from(dir)
+    }
+
+    fn restore_from(dir: PathBuf) -> Result<Session, String> {
+        let read = |file: &str| {
+            let path = dir.join(file);
+            fs::read_to_string(&path).map_err(|e| format!("cannot read {path:?}: {e}"))
+        };
+        let arguments = read("arguments")?.lines().map(String::from).collect();
+        let checkpoint = Checkpoint::parse(&read("progress")?);
+
+        let mut results = Vec::new();
+        for line in read("results")?.lines() {
+            let flavor = Flavor::ALL
+                .into_iter()
+                .find(|flavor| line.ends_with(&format!("(PHP {})", flavor.versions())));
+            if let (Some(flavor), Ok([seed])) = (flavor, parse_seeds(line).as_deref()) {
+                results.push((*seed, flavor));
+            }
+        }
+
+        let path = dir.join("results");
+        let results_file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("cannot open {path:?}: {e}"))?;
+        Ok(Session {
+            dir,
+            arguments,
+            checkpoint: Mutex::new(checkpoint),
+            results,
+            results_file,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            last_checkpoint: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Check that the search of `query`, see `query_hash`, is the one the progress comes from,
+    /// and checkpoint its progress at most every `interval` from now on.
+    pub fn start(&mut self, query: u64, interval: Duration) -> Result<(), String> {
+        let checkpoint = self.checkpoint.get_mut().unwrap();
+        checkpoint
+            .check_query(query)
+            .map_err(|e| format!("{e}, start it over with --session"))?;
+        checkpoint.query = Some(query);
+        self.checkpoint_interval = interval;
+        self.checkpoint()
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The arguments the search was started with.
+    pub fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+
+    /// The seeds found before the session was restored.
+    pub fn results(&self) -> &[(u32, Flavor)] {
+        &self.results
+    }
+
+    /// The steps of `runs` the session has not completed for `flavor`, as the ranges left of
+    /// each run. The runs are recorded in the checkpoints, in their order, for `--replay`.
+    pub fn remaining(&self, flavor: Flavor, runs: Vec<Range<u32>>) -> Vec<Range<u32>> {
+        let mut checkpoint = self.checkpoint.lock().unwrap();
+        checkpoint.record_order(flavor, &runs);
+        checkpoint.remaining(flavor, runs)
+    }
+
+    pub fn complete_step(&self, flavor: Flavor, step: u32) {
+        let mut checkpoint = self.checkpoint.lock().unwrap();
+        checkpoint.completed.insert((flavor.kernel_id(), step));
+        drop(checkpoint);
+        if self.last_checkpoint.lock().unwrap().elapsed() >= self.checkpoint_interval {
+            self.checkpoint()
+                .unwrap_or_else(|e| panic!("Cannot checkpoint the session: {e}."));
+        }
+    }
+
+    /// Write the progress to `progress.tmp`, then rename it to `progress`.
+    pub fn checkpoint(&self) -> Result<(), String> {
+        let text = self.checkpoint.lock().unwrap().to_text();
+        let (temporary, path) = (self.dir.join("progress.tmp"), self.dir.join("progress"));
+        fs::write(&temporary, text).map_err(|e| format!("cannot write {temporary:?}: {e}"))?;
+        fs::rename(&temporary, &path).map_err(|e| format!("cannot replace {path:?}: {e}"))?;
+        *self.last_checkpoint.lock().unwrap() = Instant::now();
+        Ok(())
+    }
+
+    pub fn record_seed(&self, seed: u32, flavor: Flavor) {
+        append(
+            &self.results_file,
+            &format!("{}\n", format_seed(seed, flavor)),
+        );
+    }
+}
+
+impl Drop for Session {
+    /// The steps completed since the last checkpoint are kept when the search ends or panics.
+    fn drop(&mut self) {
+        if let Err(e) = self.checkpoint() {
+            log::error!("cannot checkpoint the session: {e}");
+        }
+    }
+}
+
+fn append(mut file: &File, line: &str) {
+    file.write_all(line.as_bytes())
+        .and_then(|()| file.flush())
+        .unwrap_or_else(|e| panic!("Cannot write to the session: {e}."));
+}
+
+#[test]
+fn test_session() {
+    let dir = std::env::temp_dir().join("php_mt_seed_rs_test_session");
+    let arguments = ["--session".to_string(), "test".to_string(), "1".to_string()];
+    let session = Session::create_in(dir.clone(), &arguments).unwrap();
+    session.complete_step(Flavor::Php71, 0);
+    session.complete_step(Flavor::Php71, 2);
+    session.complete_step(Flavor::Php52, 1);
+    session.record_seed(0x12345678, Flavor::Php52);
+    drop(session);
+
+    let session = Session::restore_from(dir.clone()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(session.arguments(), arguments);
+    assert_eq!(session.results(), [(0x12345678, Flavor::Php52)]);
+    let all = 0..4;
+    assert_eq!(
+        session.remaining(Flavor::Php71, vec![all]),
+        vec![1..2, 3..4]
+    );
+    assert_eq!(
+        session.remaining(Flavor::Php52, vec![3..4, 1..2, 0..1]),
+        vec![3..4, 0..1]
+    );
+}
+
+#[test]
+fn test_replay() {
+    let text = "query = 00000000000000ff\norder 7.1 = 3..4 0..1 2..3 1..2\n7.1 0\n7.1 3\n5.2.1 1\n";
+    let checkpoint = Checkpoint::parse(text);
+    assert_eq!(checkpoint.to_text(), text);
+    assert!(checkpoint.check_query(0xff).is_ok());
+    assert!(checkpoint.check_query(0xfe).is_err());
+    let all = 0..4;
+    assert_eq!(
+        checkpoint.replay(Flavor::Php71, vec![all.clone()]),
+        [2..3, 1..2]
+    );
+    assert_eq!(checkpoint.replay(Flavor::Php52, vec![all]), [0..1, 2..4]);
+}
+
+#[test]
+fn test_checkpoint() {
+    let dir = std::env::temp_dir().join("php_mt_seed_rs_test_checkpoint");
+    let mut session = Session::create_in(dir.clone(), &["1".to_string()]).unwrap();
+    session.start(0x1234, Duration::ZERO).unwrap();
+    session.complete_step(Flavor::Php71, 3);
+    let progress = fs::read_to_string(dir.join("progress")).unwrap();
+    assert_eq!(progress, "query = 0000000000001234\n7.1 3\n");
+    assert!(!dir.join("progress.tmp").exists());
+    drop(session);
+
+    let mut session = Session::restore_from(dir.clone()).unwrap();
+    assert!(session.start(0x4321, Duration::ZERO).is_err());
+    session.start(0x1234, Duration::from_secs(60)).unwrap();
+    session.complete_step(Flavor::Php71, 4);
+    let progress = fs::read_to_string(dir.join("progress")).unwrap();
+    assert_eq!(progress, "query = 0000000000001234\n7.1 3\n");
+    drop(session);
+    let session = Session::restore_from(dir.clone()).unwrap();
+    let all = 0..6;
+    assert_eq!(session.remaining(Flavor::Php71, vec![all]), [0..3, 5..6]);
+    drop(session);
+    fs::remove_dir_all(&dir).unwrap();
+}
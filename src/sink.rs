@@ -0,0 +1,156 @@
+// `--sink`: the seeds found sent to a socket as they are confirmed, one JSON message per line, so
+// that long searches can feed a pipeline instead of having their output scraped.
+
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Mutex;
+
+use crate::flavor::{format_seed, Flavor};
+
+/// Where the messages go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkAddress {
+    /// `tcp://HOST:PORT`.
+    Tcp(String),
+    /// `unix:PATH`, or `unix://PATH`.
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+impl std::str::FromStr for SinkAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(address) = s.strip_prefix("tcp://") {
+            if !address.contains(':') {
+                return Err(format!("{address:?} has no port, expected tcp://HOST:PORT"));
+            }
+            return Ok(SinkAddress::Tcp(address.to_string()));
+        }
+        if let Some(path) = s.strip_prefix("unix:") {
+            let path = path.strip_prefix("//").unwrap_or(path);
+            #[cfg(unix)]
+            return Ok(SinkAddress::Unix(path.into()));
+            #[cfg(not(unix))]
+            return Err(format!(
+                "unix sockets such as {path:?} are not supported here"
+            ));
+        }
+        Err(format!(
+            "unknown sink {s:?}, expected tcp://HOST:PORT or unix:PATH"
+        ))
+    }
+}
+
+/// The JSON message of a seed, on a single line: the seed in decimal and as openwall's
+/// php_mt_seed prints it, and its flavor as `--php` takes it.
+pub fn message(seed: u32, flavor: Flavor) -> String {
+    format!(
+        "{{\"seed\":{seed},\"php\":\"{}\",\"line\":\"{}\"}}\n",
+        flavor.name(),
+        format_seed(seed, flavor)
+    )
+}
+
+/// A connection to the address of `--sink`, made again when it breaks.
+pub struct Sink {
+    address: SinkAddress,
+    stream: Mutex<Option<Box<dyn Write + Send>>>,
+}
+
+impl std::fmt::Debug for Sink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sink")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Sink {
+    pub fn new(address: SinkAddress) -> Self {
+        Sink {
+            address,
+            stream: Mutex::new(None),
+        }
+    }
+
+    fn open(&self) -> std::io::Result<Box<dyn Write + Send>> {
+        Ok(match &self.address {
+            SinkAddress::Tcp(address) => Box::new(TcpStream::connect(address)?),
+            #[cfg(unix)]
+            SinkAddress::Unix(path) => Box::new(std::os::unix::net::UnixStream::connect(path)?),
+        })
+    }
+
+    /// Connect now, so that a wrong address is reported before searching rather than at the
+    /// first seed.
+    pub fn connect(&self) -> Result<(), String> {
+        let stream = self
+            .open()
+            .map_err(|e| format!("cannot connect to {:?}: {e}", self.address))?;
+        *self.stream.lock().unwrap() = Some(stream);
+        Ok(())
+    }
+
+    /// Send the message of `seed`. Failures are only logged, the search goes on, and the
+    /// connection is made again for the next seed.
+    pub fn send(&self, seed: u32, flavor: Flavor) {
+        let mut stream = self.stream.lock().unwrap();
+        if stream.is_none() {
+            *stream = self.open().ok();
+        }
+        let Some(connected) = stream.as_mut() else {
+            log::warn!("cannot connect to {:?}, seed {seed} not sent", self.address);
+            return;
+        };
+        if let Err(e) = connected.write_all(message(seed, flavor).as_bytes()) {
+            log::warn!("cannot send seed {seed} to {:?}: {e}", self.address);
+            *stream = None;
+        }
+    }
+}
+
+#[test]
+fn test_sink_address() {
+    assert_eq!(
+        "tcp://127.0.0.1:9000".parse(),
+        Ok(SinkAddress::Tcp("127.0.0.1:9000".to_string()))
+    );
+    assert!("tcp://localhost".parse::<SinkAddress>().is_err());
+    assert!("http://localhost:80".parse::<SinkAddress>().is_err());
+    #[cfg(unix)]
+    {
+        let path = std::path::PathBuf::from("/run/seeds.sock");
+        assert_eq!(
+            "unix:/run/seeds.sock".parse(),
+            Ok(SinkAddress::Unix(path.clone()))
+        );
+        assert_eq!(
+            "unix:///run/seeds.sock".parse(),
+            Ok(SinkAddress::Unix(path))
+        );
+    }
+}
+
+#[test]
+fn test_sink() {
+    use std::io::Read;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = SinkAddress::Tcp(listener.local_addr().unwrap().to_string());
+    let sink = Sink::new(address);
+    sink.connect().unwrap();
+    sink.send(4242, Flavor::Php71);
+    drop(sink);
+    let mut received = String::new();
+    listener
+        .accept()
+        .unwrap()
+        .0
+        .read_to_string(&mut received)
+        .unwrap();
+    assert_eq!(
+        received,
+        "{\"seed\":4242,\"php\":\"7.1\",\"line\":\"seed = 0x1092 = 4242 (PHP 7.1.0+)\"}\n"
+    );
+}
@@ -0,0 +1,59 @@
+// `--skip-range`: an unknown number of `mt_rand()` calls made before the first value observed,
+// such as the warm-up randomness of frameworks, searched for every count at once.
+
+use std::ops::RangeInclusive;
+
+/// Counts of `--skip-range` at most, one query each.
+pub const MAX_SKIPS: u32 = 4096;
+
+/// The counts of `--skip-range`: `MIN..MAX`, both included, or a single count.
+pub fn parse_skip_range(s: &str) -> Result<RangeInclusive<u32>, String> {
+    let count = |s: &str| {
+        s.parse::<u32>()
+            .map_err(|_| format!("cannot parse {s:?} as a number of calls"))
+    };
+    let (min, max) = match s.split_once("..") {
+        Some((min, max)) => (count(min)?, count(max.trim_start_matches('='))?),
+        None => (count(s)?, count(s)?),
+    };
+    if min > max {
+        return Err(format!("the range {s:?} ends before it starts"));
+    }
+    if max > MAX_SKIPS {
+        return Err(format!("at most {MAX_SKIPS} calls can be skipped"));
+    }
+    Ok(min..=max)
+}
+
+/// The query of each count of `skips`, with as many outputs skipped before the normalized
+/// `arguments`.
+pub fn queries(arguments: &[u32], skips: RangeInclusive<u32>) -> Vec<(u32, Vec<u32>)> {
+    skips
+        .map(|skipped| {
+            let mut query = [0, 0x7fffffff, 0, 0x7fffffff].repeat(skipped as usize);
+            query.extend_from_slice(arguments);
+            (skipped, query)
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_skip_range() {
+    assert_eq!(parse_skip_range("0..16"), Ok(0..=16));
+    assert_eq!(parse_skip_range("2..=3"), Ok(2..=3));
+    assert_eq!(parse_skip_range("5"), Ok(5..=5));
+    assert!(parse_skip_range("3..2").is_err());
+    assert!(parse_skip_range("0..100000").is_err());
+    assert!(parse_skip_range("..3").is_err());
+}
+
+#[test]
+fn test_queries() {
+    assert_eq!(
+        queries(&[5, 9, 0, 61], 0..=1),
+        vec![
+            (0, vec![5, 9, 0, 61]),
+            (1, vec![0, 0x7fffffff, 0, 0x7fffffff, 5, 9, 0, 61])
+        ]
+    );
+}
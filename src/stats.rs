@@ -0,0 +1,196 @@
+// `--stats`: how many candidates survive each constraint, step by step, to tell which observation
+// does the filtering, and past which one the others were never put to the test.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::explain::selectivity;
+use crate::flavor::Flavor;
+use crate::replay;
+
+/// Candidates a step may leave to the CPU under `--stats`, which counts how many of them pass each
+/// of the slots the kernels do not check.
+pub const CANDIDATES_PER_STEP: f64 = 4096.0;
+
+/// How many slots the kernels check under `--stats`: as few as possible, so that the survivors of
+/// each slot are counted on the CPU, without leaving it much more than `CANDIDATES_PER_STEP`
+/// candidates a step.
+pub fn kernel_slots(arguments: &[u32], seeds_per_step: f64) -> usize {
+    let mut candidates = seeds_per_step;
+    let mut slots = 0;
+    for slot in arguments.chunks(4) {
+        if candidates <= CANDIDATES_PER_STEP {
+            break;
+        }
+        candidates *= selectivity(slot);
+        slots += 1;
+    }
+    slots.max(1)
+}
+
+/// Add the `candidates` the kernels found checking the `kernel_slots` first slots of the
+/// normalized `arguments` to `survivors`: their count first, then how many of them pass each
+/// following slot, along with the ones before.
+pub fn count(
+    flavor: Flavor,
+    arguments: &[u32],
+    kernel_slots: usize,
+    candidates: &[u32],
+    survivors: &mut Vec<u64>,
+) {
+    let slots = arguments.len() / 4;
+    survivors.resize(1 + slots - kernel_slots, 0);
+    for &seed in candidates {
+        let outputs = replay::outputs(flavor, arguments, seed);
+        let passed = replay::first_divergence(arguments, &outputs).unwrap_or(slots);
+        for survivors in &mut survivors[..=passed - kernel_slots] {
+            *survivors += 1;
+        }
+    }
+}
+
+/// The survivors of a step of a search.
+#[derive(Debug, Clone, PartialEq)]
+struct StepSurvivors {
+    flavor: Flavor,
+    kernel_slots: usize,
+    survivors: Vec<u64>,
+}
+
+/// The survivors of every step searched, recorded by `search` with `--stats`.
+#[derive(Debug, Default)]
+pub struct MatchStats {
+    steps: Mutex<Vec<StepSurvivors>>,
+}
+
+impl MatchStats {
+    /// Record the `survivors` of a step, as counted by `count`.
+    pub fn record(&self, flavor: Flavor, kernel_slots: usize, survivors: Vec<u64>) {
+        self.steps.lock().unwrap().push(StepSurvivors {
+            flavor,
+            kernel_slots,
+            survivors,
+        });
+    }
+
+    /// A histogram of the survivors of each slot of the normalized `arguments`, per flavor: their
+    /// range over the steps, how many were expected per step by chance, and their total.
+    pub fn histogram(&self, arguments: &[u32], seeds_per_step: f64) -> String {
+        let steps = self.steps.lock().unwrap();
+        let slots: Vec<&[u32]> = arguments.chunks(4).collect();
+        let mut histogram = String::new();
+        for flavor in Flavor::ALL {
+            let flavor_steps: Vec<&StepSurvivors> =
+                steps.iter().filter(|s| s.flavor == flavor).collect();
+            let Some(first) = flavor_steps.first() else {
+                continue;
+            };
+            let kernel_slots = first.kernel_slots;
+            writeln!(
+                histogram,
+                "survivors per step, PHP {}, {} steps:",
+                flavor.versions(),
+                flavor_steps.len()
+            )
+            .unwrap();
+            writeln!(
+                histogram,
+                "  {:<24} {:>15} {:>12} {:>12}",
+                "constraint", "per step", "expected", "total"
+            )
+            .unwrap();
+            let mut expected: f64 = seeds_per_step
+                * slots[..kernel_slots]
+                    .iter()
+                    .map(|slot| selectivity(slot))
+                    .product::<f64>();
+            let mut emptied = None;
+            for (row, slot) in [None]
+                .into_iter()
+                .chain(slots[kernel_slots..].iter().map(Some))
+                .enumerate()
+            {
+                let counts: Vec<u64> = flavor_steps.iter().map(|s| s.survivors[row]).collect();
+                let total: u64 = counts.iter().sum();
+                let (min, max) = (counts.iter().min().unwrap(), counts.iter().max().unwrap());
+                let label = match slot {
+                    None if kernel_slots == 1 => "slot 1 on the GPU".to_string(),
+                    None => format!("slots 1 to {kernel_slots} on the GPU"),
+                    Some(slot) => {
+                        format!("slot {} ({})", kernel_slots + row, replay::expected(slot))
+                    }
+                };
+                if let Some(slot) = slot {
+                    expected *= selectivity(slot);
+                }
+                let bar = "#".repeat((total as f64 + 1.0).log2().ceil() as usize);
+                writeln!(
+                    histogram,
+                    "  {label:<24} {:>15} {expected:>12.3} {total:>12} {bar}",
+                    format!("{min}..{max}")
+                )
+                .unwrap();
+                // The expectation goes on from what actually survived.
+                expected = total as f64 / counts.len() as f64;
+                if total == 0 && emptied.is_none() {
+                    emptied = Some(kernel_slots + row);
+                }
+            }
+            if let Some(slot) = emptied.filter(|&slot| slot < slots.len()) {
+                writeln!(
+                    histogram,
+                    "no candidate survives slot {slot}: the slots after it were never put to the \
+                     test, and if the seed was expected, slot {slot} or an earlier one is likely \
+                     wrong"
+                )
+                .unwrap();
+            }
+        }
+        histogram
+    }
+}
+
+#[test]
+fn test_kernel_slots() {
+    let token = [5, 5, 0, 61];
+    // 2^24 / 62^2 is still above 4096 candidates, 2^24 / 62^3 is not.
+    assert_eq!(kernel_slots(&token.repeat(6), 16777216.0), 3);
+    assert_eq!(
+        kernel_slots(&[1178568022, 1178568022, 0, 0x7fffffff], 16777216.0),
+        1
+    );
+    assert_eq!(kernel_slots(&token, 1000.0), 1);
+}
+
+#[test]
+fn test_count() {
+    // mt_srand(4242) gives 697823703, then 44 for mt_rand(0, 61)
+    let arguments = [0, 0x7fffffff, 0, 0x7fffffff, 44, 44, 0, 61, 3, 3, 0, 61];
+    let mut survivors = Vec::new();
+    count(Flavor::Php71, &arguments, 1, &[4242, 0], &mut survivors);
+    assert_eq!(survivors, vec![2, 1, 0]);
+    count(Flavor::Php71, &arguments, 1, &[4242], &mut survivors);
+    assert_eq!(survivors, vec![3, 2, 0]);
+}
+
+#[test]
+fn test_histogram() {
+    let arguments = [
+        0, 0x7fffffff, 0, 0x7fffffff, 44, 44, 0, 61, 3, 3, 0, 61, 7, 7, 0, 61,
+    ];
+    let stats = MatchStats::default();
+    stats.record(Flavor::Php71, 1, vec![124, 2, 0, 0]);
+    stats.record(Flavor::Php71, 1, vec![124, 0, 0, 0]);
+    let histogram = stats.histogram(&arguments, 124.0);
+    assert!(histogram.contains("survivors per step, PHP 7.1.0+, 2 steps:"));
+    assert!(histogram.contains("slot 1 on the GPU"));
+    assert!(histogram.contains("slot 2 (44@0-61)"));
+    assert!(histogram.contains("no candidate survives slot 3"));
+    assert!(!histogram.contains("5.2.1"));
+    // Nothing to tell when the last slot is the first left without candidates
+    let stats = MatchStats::default();
+    stats.record(Flavor::Php71, 1, vec![124, 2, 1, 0]);
+    assert!(!stats
+        .histogram(&arguments, 124.0)
+        .contains("no candidate survives"));
+}
@@ -0,0 +1,70 @@
+// `--max-results` and `--first`: the search stopped once it has found enough, the dispatches not
+// submitted yet, the remaining steps and flavors all skipped, rather than going through the whole
+// range before the results are looked at.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Whether a search has found enough to stop, shared by the dispatch loop, the CPU stage and
+/// the loops over steps and flavors of the command line tool. Each search has its own, the
+/// options it shares with others only hold the limit.
+#[derive(Debug, Default)]
+pub struct Stop {
+    limit: Option<usize>,
+    found: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+impl Stop {
+    /// The state of a new search, which stops once `limit` seeds have been found, such as the
+    /// `max_results` of its options.
+    pub fn new(limit: Option<usize>) -> Stop {
+        Stop {
+            limit,
+            ..Stop::default()
+        }
+    }
+
+    /// How many seeds the search still needs, without a limit `None`.
+    pub fn remaining(&self) -> Option<usize> {
+        let found = self.found.load(Ordering::SeqCst);
+        self.limit.map(|limit| limit.saturating_sub(found))
+    }
+
+    /// Count a seed found, stopping the search if it is the last one the limit allows. Returns
+    /// false for seeds past the limit, which dispatches still in flight may find, and which are
+    /// dropped.
+    pub fn admit(&self) -> bool {
+        let found = self.found.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.limit.is_some_and(|limit| found >= limit) {
+            self.stop();
+        }
+        self.limit.is_none_or(|limit| found <= limit)
+    }
+
+    /// Stop the search: nothing more is dispatched, and the steps and flavors left are skipped.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn test_stop() {
+    let stop = Stop::new(Some(2));
+    assert!(stop.admit());
+    assert!(!stop.is_stopped());
+    assert_eq!(stop.remaining(), Some(1));
+    assert!(stop.admit());
+    assert!(stop.is_stopped());
+    assert!(!stop.admit());
+
+    let stop = Stop::default();
+    assert!((0..1000).all(|_| stop.admit()));
+    assert_eq!(stop.remaining(), None);
+    assert!(!stop.is_stopped());
+    stop.stop();
+    assert!(stop.is_stopped());
+}
@@ -0,0 +1,187 @@
+// Shorthands accepted among the constraints on the command line, besides plain numbers.
+
+use std::str::FromStr;
+
+fn parse_number(s: &str) -> Result<u32, String> {
+    u32::from_str(s).map_err(|_| format!("cannot parse {s:?} as an integer"))
+}
+
+/// `MIN-MAX`, or a single number.
+pub fn parse_range(s: &str) -> Result<(u32, u32), String> {
+    match s.split_once('-') {
+        Some((min, max)) => Ok((parse_number(min)?, parse_number(max)?)),
+        None => parse_number(s).map(|value| (value, value)),
+    }
+}
+
+/// `VALUE~DELTA`, the values from `VALUE - DELTA` to `VALUE + DELTA` that `range` holds.
+fn parse_tolerance(s: &str, range: (u32, u32)) -> Result<(u32, u32), String> {
+    let (value, delta) = s.split_once('~').unwrap();
+    let (value, delta) = (parse_number(value)?, parse_number(delta)?);
+    let match_min = value.saturating_sub(delta).max(range.0);
+    let match_max = value.saturating_add(delta).min(range.1);
+    Ok((match_min, match_max))
+}
+
+/// `MATCH_MIN-MATCH_MAX@RANGE_MIN-RANGE_MAX` is a whole slot, either side of the `@` may be a
+/// single number, and the range may be omitted: `7505@1000-10000`, `10-20`. The matches may also
+/// be a tolerance around a value: `7505~10@1000-10000`.
+fn push_shorthand(arguments: &mut Vec<u32>, shorthand: &str) -> Result<(), String> {
+    if !arguments.len().is_multiple_of(4) {
+        return Err("the previous constraint is not complete".to_string());
+    }
+    let (matches, range) = match shorthand.split_once('@') {
+        Some((matches, range)) => (matches, parse_range(range)?),
+        None => (shorthand, (0, 0x7fffffff)),
+    };
+    let (match_min, match_max) = match matches.contains('~') {
+        true => parse_tolerance(matches, range)?,
+        false => parse_range(matches)?,
+    };
+    arguments.extend_from_slice(&[match_min, match_max, range.0, range.1]);
+    Ok(())
+}
+
+/// A plain number, or a whole slot written as a shorthand.
+fn push_constraint(arguments: &mut Vec<u32>, constraint: &str) -> Result<(), String> {
+    if constraint.contains(['-', '@', '~']) {
+        push_shorthand(arguments, constraint)
+    } else {
+        arguments.push(parse_number(constraint)?);
+        Ok(())
+    }
+}
+
+/// `*N` repeats the last constraint, a whole slot of 4 numbers, so that it appears N times.
+///
+/// The star may also be glued to the last number or shorthand of the slot: `0 0 0 61*32`.
+fn push_repetition(arguments: &mut Vec<u32>, count: &str) -> Result<(), String> {
+    let count = parse_number(count)?;
+    if count == 0 {
+        return Err("a constraint cannot be repeated 0 times".to_string());
+    }
+    if arguments.is_empty() || !arguments.len().is_multiple_of(4) {
+        return Err(
+            "*N must follow a whole constraint: MATCH_MIN MATCH_MAX RANGE_MIN RANGE_MAX"
+                .to_string(),
+        );
+    }
+    let slot = arguments[arguments.len() - 4..].to_vec();
+    for _ in 1..count {
+        arguments.extend_from_slice(&slot);
+    }
+    Ok(())
+}
+
+/// Parse one command line token into the constraints.
+pub fn push_token(arguments: &mut Vec<u32>, token: &str) -> Result<(), String> {
+    let (constraint, count) = match token.split_once('*') {
+        Some((constraint, count)) => (constraint, Some(count)),
+        None => (token, None),
+    };
+    if !constraint.is_empty() {
+        push_constraint(arguments, constraint)?;
+    }
+    match count {
+        Some(count) => push_repetition(arguments, count),
+        None => Ok(()),
+    }
+}
+
+/// Parse whitespace-separated tokens into the constraints.
+pub fn parse_tokens(tokens: &str) -> Result<Vec<u32>, String> {
+    let mut arguments = Vec::new();
+    for token in tokens.split_whitespace() {
+        push_token(&mut arguments, token)?;
+    }
+    Ok(arguments)
+}
+
+/// The constraint sets of a `--batch` file, one per line written like on the command line, and
+/// normalized. Empty lines and `#` comments are skipped.
+pub fn parse_batch(text: &str) -> Result<Vec<Vec<u32>>, String> {
+    let mut queries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut arguments = parse_tokens(line).map_err(|e| format!("line {}: {e}", i + 1))?;
+        crate::normalize_arguments(&mut arguments);
+        if !crate::lint_arguments(&arguments) {
+            return Err(format!("line {}: invalid constraints", i + 1));
+        }
+        queries.push(arguments);
+    }
+    match queries.is_empty() {
+        true => Err("no constraints".to_string()),
+        false => Ok(queries),
+    }
+}
+
+#[test]
+fn test_repetition() {
+    assert_eq!(parse_tokens("0 0 0 61 *3"), Ok([0, 0, 0, 61].repeat(3)));
+    assert_eq!(parse_tokens("0 0 0 61*3"), parse_tokens("0 0 0 61 *3"));
+    assert_eq!(
+        parse_tokens("5 5 0 9 *2 7"),
+        Ok(vec![5, 5, 0, 9, 5, 5, 0, 9, 7])
+    );
+    assert_eq!(parse_tokens("0 0 0 61 *1"), Ok(vec![0, 0, 0, 61]));
+    assert!(parse_tokens("0 0 0 61 *0").is_err());
+    assert!(parse_tokens("0 0 *2").is_err());
+    assert!(parse_tokens("*2").is_err());
+    assert!(parse_tokens("0 0 0 61 *x").is_err());
+}
+
+#[test]
+fn test_shorthand() {
+    assert_eq!(
+        parse_tokens("7505@1000-10000"),
+        Ok(vec![7505, 7505, 1000, 10000])
+    );
+    assert_eq!(parse_tokens("10-20"), Ok(vec![10, 20, 0, 0x7fffffff]));
+    assert_eq!(parse_tokens("10-20@0-99"), Ok(vec![10, 20, 0, 99]));
+    assert_eq!(parse_tokens("0-61@0-61*2"), Ok([0, 61, 0, 61].repeat(2)));
+    assert_eq!(
+        parse_tokens("1 1 0 9 5@0-9 7"),
+        Ok(vec![1, 1, 0, 9, 5, 5, 0, 9, 7])
+    );
+    assert!(parse_tokens("1 5@0-9").is_err());
+    assert!(parse_tokens("5@").is_err());
+    assert!(parse_tokens("5-6-7").is_err());
+}
+
+#[test]
+fn test_tolerance() {
+    assert_eq!(parse_tokens("7505~10"), Ok(vec![7495, 7515, 0, 0x7fffffff]));
+    assert_eq!(
+        parse_tokens("1005~10@1000-10000"),
+        Ok(vec![1000, 1015, 1000, 10000])
+    );
+    assert_eq!(parse_tokens("3~5*2"), Ok([0, 8, 0, 0x7fffffff].repeat(2)));
+    assert_eq!(
+        parse_tokens("2147483647~1"),
+        Ok(vec![2147483646, 0x7fffffff, 0, 0x7fffffff])
+    );
+    assert!(parse_tokens("5~").is_err());
+    assert!(parse_tokens("5~1~2").is_err());
+}
+
+#[test]
+fn test_parse_batch() {
+    let batch = "# leaked tokens\n1178568022\n\n7505@1000-10000 0 0 0 61*2 # reset link\n";
+    assert_eq!(
+        parse_batch(batch),
+        Ok(vec![
+            vec![1178568022, 1178568022, 0, 0x7fffffff],
+            [vec![7505, 7505, 1000, 10000], [0, 0, 0, 61].repeat(2)].concat(),
+        ])
+    );
+    assert_eq!(
+        parse_batch("5\n9 5 0 61\n"),
+        Err("line 2: invalid constraints".to_string())
+    );
+    assert!(parse_batch("5\n*2\n").is_err());
+    assert!(parse_batch("# nothing\n").is_err());
+}
@@ -0,0 +1,195 @@
+// `--syslog`: the start, the seeds found and the end of a search sent to the local syslog daemon,
+// or to journald, with structured fields, for runs launched by cron or an orchestrator on shared
+// servers, whose stdout nobody reads.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+
+/// The native socket of journald, which keeps the fields as they are.
+#[cfg(unix)]
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// The sockets of syslog daemons: Linux, macOS and the BSDs.
+#[cfg(unix)]
+const SYSLOG_SOCKETS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+/// The `user` facility.
+const FACILITY: u8 = 1;
+
+/// The identifier of the program in the logs.
+const IDENTIFIER: &str = "php_mt_seed_rs";
+
+/// The ID of the structured data of RFC 5424 messages, under the enterprise number reserved for
+/// documentation (RFC 5612).
+const SD_ID: &str = "php_mt_seed@32473";
+
+/// The syslog severity of a log level.
+fn severity(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+/// An event as an RFC 5424 message, the fields in its structured data. The daemon adds the time
+/// and host.
+fn rfc5424(
+    level: log::Level,
+    event: &str,
+    fields: &[(&str, String)],
+    text: &str,
+    pid: u32,
+) -> String {
+    let priority = FACILITY * 8 + severity(level);
+    let mut data = String::new();
+    if !fields.is_empty() {
+        write!(data, "[{SD_ID}").unwrap();
+        for (name, value) in fields {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace(']', "\\]");
+            write!(data, " {name}=\"{value}\"").unwrap();
+        }
+        data.push(']');
+    } else {
+        data.push('-');
+    }
+    format!("<{priority}>1 - - {IDENTIFIER} {pid} {event} {data} {text}")
+}
+
+/// An event in the native protocol of journald, the fields prefixed with `PHP_MT_SEED_`.
+fn journald(level: log::Level, event: &str, fields: &[(&str, String)], text: &str) -> String {
+    let mut message = format!(
+        "MESSAGE={text}\nPRIORITY={}\nSYSLOG_FACILITY={FACILITY}\nSYSLOG_IDENTIFIER={IDENTIFIER}\n\
+         PHP_MT_SEED_EVENT={event}\n",
+        severity(level)
+    );
+    for (name, value) in fields {
+        writeln!(message, "PHP_MT_SEED_{}={value}", name.to_uppercase()).unwrap();
+    }
+    message
+}
+
+#[cfg(unix)]
+struct Connection {
+    socket: std::os::unix::net::UnixDatagram,
+    journald: bool,
+}
+
+#[cfg(not(unix))]
+enum Connection {}
+
+impl Connection {
+    fn open() -> Result<Connection, String> {
+        #[cfg(unix)]
+        {
+            let socket = std::os::unix::net::UnixDatagram::unbound().map_err(|e| e.to_string())?;
+            if socket.connect(JOURNALD_SOCKET).is_ok() {
+                return Ok(Connection {
+                    socket,
+                    journald: true,
+                });
+            }
+            for path in SYSLOG_SOCKETS {
+                if socket.connect(path).is_ok() {
+                    return Ok(Connection {
+                        socket,
+                        journald: false,
+                    });
+                }
+            }
+            Err(format!(
+                "neither journald nor a syslog daemon listens on {JOURNALD_SOCKET} or {}",
+                SYSLOG_SOCKETS.join(", ")
+            ))
+        }
+        #[cfg(not(unix))]
+        Err("syslog is only available on Unix".to_string())
+    }
+
+    fn send(
+        &self,
+        level: log::Level,
+        event: &str,
+        fields: &[(&str, String)],
+        text: &str,
+    ) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            let message = match self.journald {
+                true => journald(level, event, fields, text),
+                false => rfc5424(level, event, fields, text, std::process::id()),
+            };
+            self.socket.send(message.as_bytes()).map(|_| ())
+        }
+        #[cfg(not(unix))]
+        match *self {}
+    }
+}
+
+/// The connection to journald or the syslog daemon of `--syslog`, made again when it breaks.
+#[derive(Default)]
+pub struct Syslog {
+    connection: Mutex<Option<Connection>>,
+}
+
+impl std::fmt::Debug for Syslog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Syslog").finish_non_exhaustive()
+    }
+}
+
+impl Syslog {
+    /// Connect now, so that a missing daemon is reported before searching.
+    pub fn connect(&self) -> Result<(), String> {
+        *self.connection.lock().unwrap() = Some(Connection::open()?);
+        Ok(())
+    }
+
+    /// Send an `event` of the search, such as `start`, `seed` or `stop`, with its `fields` and
+    /// the `text` of its message. Failures are only logged, the search goes on.
+    pub fn event(&self, level: log::Level, event: &str, fields: &[(&str, String)], text: &str) {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Connection::open().ok();
+        }
+        let Some(connected) = connection.as_ref() else {
+            log::warn!("cannot connect to syslog, the {event} event is lost");
+            return;
+        };
+        if let Err(e) = connected.send(level, event, fields, text) {
+            log::warn!("cannot send the {event} event to syslog: {e}");
+            *connection = None;
+        }
+    }
+}
+
+#[test]
+fn test_rfc5424() {
+    let fields = [("seed", "4242".to_string()), ("php", "7.1".to_string())];
+    assert_eq!(
+        rfc5424(log::Level::Info, "seed", &fields, "seed = 4242", 99),
+        "<14>1 - - php_mt_seed_rs 99 seed [php_mt_seed@32473 seed=\"4242\" php=\"7.1\"] \
+         seed = 4242"
+    );
+    assert_eq!(
+        rfc5424(log::Level::Warn, "stop", &[], "done", 1),
+        "<12>1 - - php_mt_seed_rs 1 stop - done"
+    );
+    let quoted = [("constraints", "a\"b]c\\".to_string())];
+    assert!(rfc5424(log::Level::Info, "start", &quoted, "", 1)
+        .contains("constraints=\"a\\\"b\\]c\\\\\""));
+}
+
+#[test]
+fn test_journald() {
+    let fields = [("seed", "4242".to_string())];
+    assert_eq!(
+        journald(log::Level::Info, "seed", &fields, "seed = 4242"),
+        "MESSAGE=seed = 4242\nPRIORITY=6\nSYSLOG_FACILITY=1\nSYSLOG_IDENTIFIER=php_mt_seed_rs\n\
+         PHP_MT_SEED_EVENT=seed\nPHP_MT_SEED_SEED=4242\n"
+    );
+}
@@ -0,0 +1,106 @@
+// `--telemetry-port`: the progress of the running search served over HTTP, so that long cracks can
+// be monitored remotely without scraping their output.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the progress is served without `--telemetry-bind`: only to the machine itself.
+pub const DEFAULT_BIND: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// The progress served on the port of `--telemetry-port`: the last event of `--progress-json`, or
+/// `{}` until the first step is done.
+#[derive(Debug)]
+pub struct Telemetry {
+    port: u16,
+    latest: Arc<Mutex<Option<String>>>,
+}
+
+impl Telemetry {
+    pub fn new(port: u16) -> Self {
+        Telemetry {
+            port,
+            latest: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Listen on the port of `address`, and answer every request on another thread. Returns the
+    /// address listened on, whose port is picked by the system if it was 0.
+    pub fn start(&self, address: IpAddr) -> Result<SocketAddr, String> {
+        let listener = TcpListener::bind((address, self.port))
+            .map_err(|e| format!("cannot listen on {address} port {}: {e}", self.port))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let latest = self.latest.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let body = latest.lock().unwrap().clone();
+                if let Err(e) = respond(stream, body.as_deref().unwrap_or("{}")) {
+                    log::debug!("telemetry request failed: {e}");
+                }
+            }
+        });
+        Ok(address)
+    }
+
+    /// Serve `line`, a progress event of `progress::json_line`, from now on.
+    pub fn update(&self, line: String) {
+        *self.latest.lock().unwrap() = Some(line);
+    }
+}
+
+/// Answer the request of `stream` with `body`, whatever its path: there is nothing else to get.
+fn respond(stream: TcpStream, body: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not needed, but are read so that the client does not get a reset.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let response = match request_line.split(' ').next() {
+        Some("GET") => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        ),
+        _ => "HTTP/1.1 405 Method Not Allowed\r\nAllow: GET\r\nContent-Length: 0\r\n\
+              Connection: close\r\n\r\n"
+            .to_string(),
+    };
+    (&stream).write_all(response.as_bytes())
+}
+
+#[cfg(test)]
+fn get(address: SocketAddr, request: &str) -> String {
+    use std::io::Read;
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_telemetry() {
+    let telemetry = Telemetry::new(0);
+    let address = telemetry.start(DEFAULT_BIND).unwrap();
+    assert!(address.ip().is_loopback());
+    let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    assert!(get(address, request).ends_with("\r\n\r\n{}"));
+
+    let line = "{\"php\":\"7.1\",\"step\":64,\"steps\":256}".to_string();
+    telemetry.update(line.clone());
+    let response = get(address, request);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(&format!("\r\n\r\n{line}")));
+
+    let response = get(address, "POST / HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 405"));
+}
@@ -0,0 +1,212 @@
+// `--max-temp`: pause between dispatches while the GPU is too hot, so that hours-long searches do
+// not cook laptops. The temperature comes from NVML with the `nvml` feature, or from the hwmon
+// sensors of the DRM cards on Linux.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// How far below the limit the GPU has to cool down before the search goes on, so that it does
+/// not stop and start again at every dispatch.
+pub const HYSTERESIS: u32 = 5;
+
+/// How often the temperature is read while searching, and while waiting for the GPU to cool down.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the temperature of the first GPU is read.
+enum Sensor {
+    #[cfg(feature = "nvml")]
+    Nvml(Box<nvml_wrapper::Nvml>),
+    /// The hwmon directory of a DRM card, with `temp1_input` in millidegrees Celsius and maybe
+    /// `power1_average` in microwatts.
+    Hwmon(PathBuf),
+}
+
+/// The first hwmon directory of the DRM cards reporting a temperature, as amdgpu and nouveau do.
+fn find_hwmon(drm: &Path) -> Option<PathBuf> {
+    let mut cards: Vec<PathBuf> = fs::read_dir(drm)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join("device/hwmon"))
+        .collect();
+    cards.sort();
+    cards.iter().find_map(|hwmon| {
+        let mut sensors: Vec<PathBuf> = fs::read_dir(hwmon)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|sensor| sensor.join("temp1_input").exists())
+            .collect();
+        sensors.sort();
+        sensors.into_iter().next()
+    })
+}
+
+/// A number of thousandths or millionths in a sysfs file.
+fn read_sysfs(path: &Path, divisor: f64) -> Option<f64> {
+    let value: f64 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    Some(value / divisor)
+}
+
+impl Sensor {
+    fn open() -> Option<Sensor> {
+        #[cfg(feature = "nvml")]
+        if let Ok(nvml) = nvml_wrapper::Nvml::init() {
+            if nvml.device_by_index(0).is_ok() {
+                return Some(Sensor::Nvml(Box::new(nvml)));
+            }
+        }
+        find_hwmon(Path::new("/sys/class/drm")).map(Sensor::Hwmon)
+    }
+
+    /// Degrees Celsius.
+    fn temperature(&self) -> Option<f64> {
+        match self {
+            #[cfg(feature = "nvml")]
+            Sensor::Nvml(nvml) => {
+                use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+                let device = nvml.device_by_index(0).ok()?;
+                device
+                    .temperature(TemperatureSensor::Gpu)
+                    .ok()
+                    .map(f64::from)
+            }
+            Sensor::Hwmon(hwmon) => read_sysfs(&hwmon.join("temp1_input"), 1e3),
+        }
+    }
+
+    /// Watts, when the sensor reports them.
+    fn power(&self) -> Option<f64> {
+        match self {
+            #[cfg(feature = "nvml")]
+            Sensor::Nvml(nvml) => {
+                let device = nvml.device_by_index(0).ok()?;
+                device.power_usage().ok().map(|mw| f64::from(mw) / 1e3)
+            }
+            Sensor::Hwmon(hwmon) => read_sysfs(&hwmon.join("power1_average"), 1e6),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            #[cfg(feature = "nvml")]
+            Sensor::Nvml(_) => "NVML".to_string(),
+            Sensor::Hwmon(hwmon) => hwmon.display().to_string(),
+        }
+    }
+}
+
+/// The sensor of the process, looked for once: searches are run step by step with some options.
+fn sensor() -> Option<&'static Sensor> {
+    static SENSOR: OnceLock<Option<Sensor>> = OnceLock::new();
+    SENSOR
+        .get_or_init(|| {
+            let sensor = Sensor::open();
+            match &sensor {
+                Some(sensor) => log::info!("reading the GPU temperature from {}", sensor.name()),
+                None => eprintln!(
+                    "\rWARNING: cannot read the temperature of the GPU, --max-temp is ignored{}",
+                    if cfg!(feature = "nvml") {
+                        ""
+                    } else {
+                        " (NVIDIA cards need the `nvml` feature)"
+                    }
+                ),
+            }
+            sensor
+        })
+        .as_ref()
+}
+
+/// Keeps the GPU under a temperature limit by pausing the search.
+pub struct Throttle {
+    limit: u32,
+    sensor: Option<&'static Sensor>,
+    last_check: Option<Instant>,
+}
+
+impl Throttle {
+    /// A throttle keeping the first GPU under `max_temp` degrees Celsius, or doing nothing without
+    /// a limit.
+    pub fn new(max_temp: Option<u32>) -> Throttle {
+        Throttle {
+            limit: max_temp.unwrap_or(u32::MAX),
+            sensor: max_temp.and_then(|_| sensor()),
+            last_check: None,
+        }
+    }
+
+    /// Called between dispatches: once in a while, read the temperature and, if it is over the
+    /// limit, wait for the GPU to cool down `HYSTERESIS` degrees below it.
+    pub fn wait(&mut self) {
+        let Some(sensor) = self.sensor else {
+            return;
+        };
+        if self
+            .last_check
+            .is_some_and(|last| last.elapsed() < POLL_INTERVAL)
+        {
+            return;
+        }
+        self.last_check = Some(Instant::now());
+        let Some(temperature) = sensor.temperature() else {
+            return;
+        };
+        if !must_pause(temperature, self.limit) {
+            return;
+        }
+        let power = match sensor.power() {
+            Some(power) => format!(", {power:.0} W"),
+            None => String::new(),
+        };
+        let resume = self.limit.saturating_sub(HYSTERESIS);
+        let message =
+            format!("GPU at {temperature:.0}°C{power}, pausing until it cools down to {resume}°C");
+        log::warn!("{message}");
+        eprintln!("\r{message}");
+        let start_time = Instant::now();
+        while sensor
+            .temperature()
+            .is_some_and(|temperature| !may_resume(temperature, self.limit))
+        {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        log::info!("resuming after {:.1?}", start_time.elapsed());
+    }
+}
+
+fn must_pause(temperature: f64, limit: u32) -> bool {
+    temperature > f64::from(limit)
+}
+
+fn may_resume(temperature: f64, limit: u32) -> bool {
+    temperature <= f64::from(limit.saturating_sub(HYSTERESIS))
+}
+
+#[test]
+fn test_throttle_thresholds() {
+    assert!(!must_pause(80.0, 80));
+    assert!(must_pause(80.5, 80));
+    assert!(!may_resume(76.0, 80));
+    assert!(may_resume(75.0, 80));
+
+    // Without a limit, there is nothing to read nor wait for.
+    let mut throttle = Throttle::new(None);
+    assert!(throttle.sensor.is_none());
+    throttle.wait();
+}
+
+#[test]
+fn test_find_hwmon() {
+    let drm = std::env::temp_dir().join("php_mt_seed_rs_test_drm");
+    let _ = fs::remove_dir_all(&drm);
+    let hwmon = drm.join("card1/device/hwmon/hwmon3");
+    fs::create_dir_all(&hwmon).unwrap();
+    fs::create_dir_all(drm.join("card0/device")).unwrap();
+    assert_eq!(find_hwmon(&drm), None);
+    fs::write(hwmon.join("temp1_input"), "67000\n").unwrap();
+    assert_eq!(find_hwmon(&drm), Some(hwmon.clone()));
+    assert_eq!(read_sysfs(&hwmon.join("temp1_input"), 1e3), Some(67.0));
+    fs::remove_dir_all(&drm).unwrap();
+}
@@ -0,0 +1,488 @@
+// Tokens drawn character by character from a charset, like `$charset[mt_rand(0, $len - 1)]` in
+// a loop, and their import from captured HTTP traffic.
+
+use std::{collections::BTreeMap, fmt};
+
+use regex::Regex;
+
+/// Charsets commonly found in PHP token generators, in the order they are usually written.
+/// Only the order of the characters matters, tokens alone do not tell it.
+const COMMON_CHARSETS: &[&str] = &[
+    "0123456789",
+    "0123456789abcdef",
+    "0123456789ABCDEF",
+    "abcdefghijklmnopqrstuvwxyz",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "abcdefghijklmnopqrstuvwxyz0123456789",
+    "0123456789abcdefghijklmnopqrstuvwxyz",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
+    "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+    "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789",
+    "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+    "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+];
+
+/// A charset is ruled out when the odds of seeing as few distinct characters as the corpus
+/// has were below this.
+const UNLIKELY: f64 = 0.01;
+
+/// The odds of seeing at most `seen` distinct characters in `draws` uniform draws from `size`.
+fn at_most_distinct(size: usize, draws: usize, seen: usize) -> f64 {
+    // distinct[d]: odds of having seen exactly d distinct characters so far
+    let mut distinct = vec![0.0; size + 1];
+    distinct[0] = 1.0;
+    // Past a few thousand draws every character of a common charset shows up, the odds would
+    // only keep shrinking.
+    for _ in 0..draws.min(10_000) {
+        for d in (1..=size).rev() {
+            let new = distinct[d - 1] * (size - d + 1) as f64 / size as f64;
+            distinct[d] = distinct[d] * d as f64 / size as f64 + new;
+        }
+        distinct[0] = 0.0;
+    }
+    distinct[..=seen.min(size)].iter().sum()
+}
+
+/// The constraint of a symbol drawn from `charset`, a character or a byte: an exact match of its
+/// index. A symbol appearing several times in the charset, such as the lead byte of the
+/// characters of a non-ASCII alphabet, matches the indices from its first to its last
+/// occurrence, which may let a few more seeds through but never misses one. Unless the charset
+/// has one, `unknown` masks a symbol which is not known, such as a character redacted from a
+/// capture: any index matches. `None` if the symbol is not in the charset.
+fn symbol_constraint<T: PartialEq>(symbol: T, charset: &[T], unknown: T) -> Option<[u32; 4]> {
+    let range_max = charset.len() as u32 - 1;
+    if symbol == unknown && !charset.contains(&unknown) {
+        return Some([0, range_max, 0, range_max]);
+    }
+    let first = charset.iter().position(|d| *d == symbol)? as u32;
+    let last = charset.iter().rposition(|d| *d == symbol)? as u32;
+    Some([first, last, 0, range_max])
+}
+
+fn character_constraint(c: char, charset: &[char]) -> Result<[u32; 4], String> {
+    symbol_constraint(c, charset, '?').ok_or_else(|| format!("{c:?} is not in the charset"))
+}
+
+/// A character in lowercase, or as it is when its lowercase is several characters.
+fn fold_case(c: char) -> char {
+    let mut lowercase = c.to_lowercase();
+    match (lowercase.next(), lowercase.next()) {
+        (Some(lower), None) => lower,
+        _ => c,
+    }
+}
+
+/// The constraints of a token: one exact match per character, in the range of the charset. Its
+/// characters are code points, like `mb_substr($chars, mt_rand(0, mb_strlen($chars) - 1), 1)`
+/// draws them.
+///
+/// With `ignore_case`, for tokens seen after they were lowercased or normalized, a character
+/// matches both its cases: the charset is folded to lowercase, so that a letter matches the
+/// indices from its first case to its second, like the symbols appearing several times.
+pub fn token_constraints(
+    token: &str,
+    charset: &str,
+    ignore_case: bool,
+) -> Result<Vec<u32>, String> {
+    let fold = |c| match ignore_case {
+        true => fold_case(c),
+        false => c,
+    };
+    let charset: Vec<char> = charset.chars().map(fold).collect();
+    if charset.is_empty() {
+        return Err("the charset is empty".to_string());
+    }
+    let mut arguments = Vec::new();
+    for c in token.chars() {
+        arguments.extend(character_constraint(fold(c), &charset)?);
+    }
+    Ok(arguments)
+}
+
+/// The bytes of a token, `\xNN` being a byte which is not valid UTF-8 on its own, and `\\` a
+/// backslash.
+fn unescape_bytes(token: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut rest = token.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match rest {
+            [b'\\', tail @ ..] => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            [b'x', high, low, tail @ ..] => {
+                let hex = std::str::from_utf8(&[*high, *low])
+                    .unwrap_or_default()
+                    .to_string();
+                let byte = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| format!("cannot read \\x{hex} as a byte"))?;
+                bytes.push(byte);
+                rest = tail;
+            }
+            _ => return Err("a backslash must start \\xNN or \\\\".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+/// The constraints of a token drawn byte by byte from the bytes of the charset, like
+/// `$chars[mt_rand(0, strlen($chars) - 1)]` draws them when the charset has multi-byte characters.
+/// The token may then not be valid UTF-8: see `unescape_bytes`.
+pub fn byte_constraints(token: &str, charset: &str) -> Result<Vec<u32>, String> {
+    let charset = charset.as_bytes();
+    if charset.is_empty() {
+        return Err("the charset is empty".to_string());
+    }
+    let mut arguments = Vec::new();
+    for byte in unescape_bytes(token)? {
+        let constraint = symbol_constraint(byte, charset, b'?')
+            .ok_or_else(|| format!("the byte \\x{byte:02x} is not in the charset"))?;
+        arguments.extend(constraint);
+    }
+    Ok(arguments)
+}
+
+/// The characters of hashcat's `?s`, in the order of ASCII.
+const SPECIAL: &str = " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// A charset of hashcat masks, by its letter.
+fn mask_charset(letter: char) -> Option<String> {
+    let lower = "abcdefghijklmnopqrstuvwxyz";
+    let upper = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let digits = "0123456789";
+    Some(match letter {
+        'l' => lower.to_string(),
+        'u' => upper.to_string(),
+        'd' => digits.to_string(),
+        'h' => "0123456789abcdef".to_string(),
+        'H' => "0123456789ABCDEF".to_string(),
+        's' => SPECIAL.to_string(),
+        'a' => [lower, upper, digits, SPECIAL].concat(),
+        _ => return None,
+    })
+}
+
+/// A position of a mask.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaskPosition {
+    /// A character drawn with `$charset[mt_rand(0, strlen($charset) - 1)]`.
+    Drawn(Vec<char>),
+    /// A character written as is, without drawing it.
+    Literal(char),
+}
+
+/// A hashcat mask, such as `?d?d?d?d?l?l`: each `?` and its letter is the charset of a position,
+/// `?l` (a-z), `?u` (A-Z), `?d` (0-9), `?h` (0-9a-f), `?H` (0-9A-F), `?s` (punctuation and
+/// space) or `?a` (all of them), `??` is a literal `?`, and other characters are literals.
+pub fn parse_mask(mask: &str) -> Result<Vec<MaskPosition>, String> {
+    let mut positions = Vec::new();
+    let mut chars = mask.chars();
+    while let Some(c) = chars.next() {
+        if c != '?' {
+            positions.push(MaskPosition::Literal(c));
+            continue;
+        }
+        match chars.next() {
+            Some('?') => positions.push(MaskPosition::Literal('?')),
+            Some(letter) => {
+                let charset =
+                    mask_charset(letter).ok_or_else(|| format!("unknown charset ?{letter}"))?;
+                positions.push(MaskPosition::Drawn(charset.chars().collect()));
+            }
+            None => return Err("the mask ends with a lone ?".to_string()),
+        }
+    }
+    if !positions
+        .iter()
+        .any(|p| matches!(p, MaskPosition::Drawn(_)))
+    {
+        return Err("the mask draws no character".to_string());
+    }
+    Ok(positions)
+}
+
+/// The constraints of a token generated position by position from the charsets of `mask`: one
+/// exact match per drawn character, in the range of its charset. Literals must be as in the mask,
+/// and do not take any call.
+pub fn mask_constraints(token: &str, mask: &[MaskPosition]) -> Result<Vec<u32>, String> {
+    let length = token.chars().count();
+    if length != mask.len() {
+        return Err(format!(
+            "the token has {length} characters, the mask {}",
+            mask.len()
+        ));
+    }
+    let mut arguments = Vec::new();
+    for (i, (c, position)) in token.chars().zip(mask).enumerate() {
+        match position {
+            MaskPosition::Drawn(charset) => arguments.extend(
+                character_constraint(c, charset)
+                    .map_err(|e| format!("character {}: {e}", i + 1))?,
+            ),
+            MaskPosition::Literal(literal) if c == *literal || c == '?' => {}
+            MaskPosition::Literal(literal) => {
+                return Err(format!("character {} is {c:?}, not {literal:?}", i + 1))
+            }
+        }
+    }
+    Ok(arguments)
+}
+
+/// The tokens of a capture, in order.
+///
+/// Without a regex, `text` is a token list, like Burp Sequencer saves them: one token per line.
+/// With one, every match is a token, or its first group when the regex has groups, so that
+/// tokens can be picked out of whole HTTP responses.
+pub fn extract_tokens(text: &str, regex: Option<&Regex>) -> Vec<String> {
+    match regex {
+        None => text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+        Some(regex) => regex
+            .captures_iter(text)
+            .filter_map(|captures| captures.get(1).or_else(|| captures.get(0)))
+            .map(|token| token.as_str().to_string())
+            .collect(),
+    }
+}
+
+#[test]
+fn test_token_constraints() {
+    assert_eq!(
+        token_constraints("ba", "abc", false),
+        Ok(vec![1, 1, 0, 2, 0, 0, 0, 2])
+    );
+    assert!(token_constraints("d", "abc", false).is_err());
+    assert_eq!(
+        token_constraints("b?", "abc", false),
+        Ok(vec![1, 1, 0, 2, 0, 2, 0, 2])
+    );
+    assert_eq!(token_constraints("?", "?!", false), Ok(vec![0, 0, 0, 1]));
+    assert!(token_constraints("a", "", false).is_err());
+}
+
+#[test]
+fn test_ignore_case() {
+    let charset = "abcABC0";
+    // "b" is at 1 and "B" at 4: both match, as do the indices between them.
+    assert_eq!(
+        token_constraints("bB0", charset, true),
+        Ok(vec![1, 4, 0, 6, 1, 4, 0, 6, 6, 6, 0, 6])
+    );
+    assert_eq!(token_constraints("B", charset, false), Ok(vec![4, 4, 0, 6]));
+    // Letters whose case cannot be folded to a single character still match themselves.
+    assert_eq!(fold_case('Σ'), 'σ');
+    assert_eq!(fold_case('İ'), 'İ');
+    assert_eq!(token_constraints("İ", "aİ", true), Ok(vec![1, 1, 0, 1]));
+    assert!(token_constraints("d", charset, true).is_err());
+}
+
+#[test]
+fn test_multibyte_charsets() {
+    // 3 characters, 6 bytes
+    let charset = "αβγ";
+    assert_eq!(
+        token_constraints("γα", charset, false),
+        Ok(vec![2, 2, 0, 2, 0, 0, 0, 2])
+    );
+    // "γ" is the bytes 0xce 0xb3, 0xce being the lead byte of every character of the charset.
+    assert_eq!(
+        byte_constraints("γ", charset),
+        Ok(vec![0, 4, 0, 5, 5, 5, 0, 5])
+    );
+    assert_eq!(
+        byte_constraints("\\xb1\\xce?", charset),
+        Ok(vec![1, 1, 0, 5, 0, 4, 0, 5, 0, 5, 0, 5])
+    );
+    assert_eq!(unescape_bytes("a\\\\b"), Ok(b"a\\b".to_vec()));
+    assert!(byte_constraints("\\xzz", charset).is_err());
+    assert!(byte_constraints("\\n", charset).is_err());
+    assert!(byte_constraints("a", charset).is_err());
+}
+
+#[test]
+fn test_parse_mask() {
+    let mask = parse_mask("?d-?l??").unwrap();
+    assert_eq!(mask.len(), 4);
+    assert_eq!(mask[0], MaskPosition::Drawn("0123456789".chars().collect()));
+    assert_eq!(mask[1], MaskPosition::Literal('-'));
+    assert_eq!(mask[3], MaskPosition::Literal('?'));
+    assert_eq!(SPECIAL.len(), 33);
+    match &parse_mask("?a").unwrap()[0] {
+        MaskPosition::Drawn(charset) => assert_eq!(charset.len(), 95),
+        literal => panic!("{literal:?}"),
+    }
+    assert!(parse_mask("?x").is_err());
+    assert!(parse_mask("?d?").is_err());
+    assert!(parse_mask("abc").is_err());
+}
+
+#[test]
+fn test_mask_constraints() {
+    let mask = parse_mask("?d?d-?l").unwrap();
+    assert_eq!(
+        mask_constraints("42-b", &mask),
+        Ok(vec![4, 4, 0, 9, 2, 2, 0, 9, 1, 1, 0, 25])
+    );
+    assert_eq!(
+        mask_constraints("4?-?", &mask),
+        Ok(vec![4, 4, 0, 9, 0, 9, 0, 9, 0, 25, 0, 25])
+    );
+    assert!(mask_constraints("42b", &mask).is_err());
+    assert!(mask_constraints("42+b", &mask).is_err());
+    assert!(mask_constraints("4a-b", &mask).is_err());
+}
+
+#[test]
+fn test_extract_tokens() {
+    let burp = "\r\nZx81Ka\r\n  Pq02Lm \r\n";
+    assert_eq!(extract_tokens(burp, None), vec!["Zx81Ka", "Pq02Lm"]);
+
+    let responses = "HTTP/1.1 302 Found\nLocation: /reset?token=Zx81Ka\n\n\
+                     HTTP/1.1 302 Found\nLocation: /reset?token=Pq02Lm\n";
+    let regex = Regex::new("token=([A-Za-z0-9]+)").unwrap();
+    assert_eq!(
+        extract_tokens(responses, Some(&regex)),
+        vec!["Zx81Ka", "Pq02Lm"]
+    );
+    let regex = Regex::new("[0-9]{2}").unwrap();
+    assert_eq!(
+        extract_tokens(responses, Some(&regex)),
+        vec!["30", "81", "30", "02"]
+    );
+}
+
+/// What a corpus of tokens tells about how they were generated.
+pub struct TokenAnalysis {
+    pub tokens: usize,
+    /// How many tokens have each length.
+    pub lengths: BTreeMap<usize, usize>,
+    /// Every character seen, sorted.
+    pub alphabet: Vec<char>,
+    /// Positions holding the same character in every token, so not drawn randomly.
+    pub constant_positions: Vec<(usize, char)>,
+    /// The charsets consistent with the corpus, most likely first.
+    pub charsets: Vec<String>,
+}
+
+/// Infer the charset of the tokens, like Burp Sequencer's character-level analysis does.
+///
+/// Candidates are the common charsets holding every character seen, smallest first. Those with
+/// characters that never showed up in a large enough corpus are unlikely and skipped. When no
+/// common charset fits, the characters seen are proposed, sorted, but their order is a guess.
+pub fn analyze_tokens(tokens: &[String]) -> TokenAnalysis {
+    let mut lengths = BTreeMap::new();
+    let mut alphabet = Vec::new();
+    let mut draws = 0;
+    for token in tokens {
+        let chars: Vec<char> = token.chars().collect();
+        *lengths.entry(chars.len()).or_insert(0) += 1;
+        draws += chars.len();
+        alphabet.extend(chars);
+    }
+    alphabet.sort_unstable();
+    alphabet.dedup();
+
+    let shortest = tokens.iter().map(|token| token.chars().count()).min();
+    let constant_positions = match (tokens.first(), tokens.len() > 1) {
+        (Some(first), true) => (0..shortest.unwrap_or(0))
+            .filter_map(|position| {
+                let c = first.chars().nth(position)?;
+                tokens
+                    .iter()
+                    .all(|token| token.chars().nth(position) == Some(c))
+                    .then_some((position, c))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut charsets: Vec<String> = COMMON_CHARSETS
+        .iter()
+        .filter(|charset| alphabet.iter().all(|&c| charset.contains(c)))
+        .filter(|charset| {
+            at_most_distinct(charset.chars().count(), draws, alphabet.len()) >= UNLIKELY
+        })
+        .map(|charset| charset.to_string())
+        .collect();
+    charsets.sort_by_key(|charset| charset.len());
+    if charsets.is_empty() && !alphabet.is_empty() {
+        charsets.push(alphabet.iter().collect());
+    }
+
+    TokenAnalysis {
+        tokens: tokens.len(),
+        lengths,
+        alphabet,
+        constant_positions,
+        charsets,
+    }
+}
+
+impl fmt::Display for TokenAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "tokens analyzed: {}", self.tokens)?;
+        let lengths: Vec<_> = self
+            .lengths
+            .iter()
+            .map(|(length, count)| format!("{length} ({count})"))
+            .collect();
+        writeln!(f, "lengths: {}", lengths.join(", "))?;
+        let alphabet: String = self.alphabet.iter().collect();
+        writeln!(f, "characters seen ({}): {alphabet}", self.alphabet.len())?;
+        for (position, c) in &self.constant_positions {
+            writeln!(f, "position {position} is always {c:?}, it is not random")?;
+        }
+        for (i, charset) in self.charsets.iter().enumerate() {
+            let likely = if i == 0 { "proposed" } else { "also possible" };
+            writeln!(
+                f,
+                "{likely} charset ({}): {charset}",
+                charset.chars().count()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_at_most_distinct() {
+    assert_eq!(at_most_distinct(2, 1, 1), 1.0);
+    assert!((at_most_distinct(2, 2, 1) - 0.5).abs() < 1e-12);
+    assert!(at_most_distinct(62, 512, 16) < 1e-100);
+}
+
+#[test]
+fn test_analyze_tokens() {
+    // Hexadecimal tokens, with every digit showing up
+    let tokens: Vec<String> = (0..64u32)
+        .map(|i| format!("{:08x}", i.wrapping_mul(0x9e3779b9)))
+        .collect();
+    let analysis = analyze_tokens(&tokens);
+    assert_eq!(analysis.lengths, BTreeMap::from([(8, 64)]));
+    assert_eq!(analysis.charsets[0], "0123456789abcdef");
+    // Larger charsets would have shown their other characters in 512 draws
+    assert_eq!(analysis.charsets.len(), 1);
+
+    // Two tokens are not enough to rule larger charsets out, and "x" is not random
+    let tokens = ["x1a", "x2b"].map(String::from);
+    let analysis = analyze_tokens(&tokens);
+    assert_eq!(analysis.constant_positions, vec![(0, 'x')]);
+    assert_eq!(analysis.charsets[0], "abcdefghijklmnopqrstuvwxyz0123456789");
+    assert!(analysis.charsets.len() > 1);
+
+    // Nothing common fits
+    let tokens = ["a-b", "b-a"].map(String::from);
+    assert_eq!(analyze_tokens(&tokens).charsets, vec!["-ab"]);
+}
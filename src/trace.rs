@@ -0,0 +1,22 @@
+// `--trace-output FILE`: the spans of the search (preparing the engine, each batch of dispatches,
+// reading results back and verifying candidates on the CPU) written in the Chrome trace format, to
+// open in chrome://tracing or https://ui.perfetto.dev instead of adding prints to time them.
+
+use std::fs::File;
+use std::path::Path;
+
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Record the spans of the process to `path`, until the guard returned is dropped, which
+/// completes the file.
+pub fn start(path: &Path) -> Result<FlushGuard, String> {
+    let file = File::create(path).map_err(|e| format!("cannot create {path:?}: {e}"))?;
+    let (layer, guard) = ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(true)
+        .build();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+        .map_err(|e| e.to_string())?;
+    Ok(guard)
+}
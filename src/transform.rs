@@ -0,0 +1,106 @@
+// `--seed-offset` and `--crc32-wordlist`: the secrets behind the seeds found, when the application
+// seeds with `mt_srand($base + $offset)` or `mt_srand(crc32($secret))`.
+
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+/// How the application derives the seed from its secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedTransform {
+    /// `mt_srand($base + OFFSET)`.
+    Offset(i64),
+    /// `mt_srand(crc32($secret))`, the secret being one of the lines of the wordlist.
+    Crc32(PathBuf),
+}
+
+/// The CRC-32 of PHP's `crc32()`, the one of zlib.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// The base of `mt_srand($base + offset)` for `seed`. `mt_srand()` only keeps the low 32 bits of
+/// its argument, so the base is only known modulo 2^32: the smallest non-negative one is given.
+pub fn base(seed: u32, offset: i64) -> i64 {
+    i64::from(seed).wrapping_sub(offset).rem_euclid(1 << 32)
+}
+
+/// The lines of `wordlist` whose CRC-32 is one of `seeds`, along with it, in the order of the
+/// wordlist. Lines do not have to be valid UTF-8, they are given lossily.
+pub fn crc32_preimages(
+    wordlist: impl BufRead,
+    seeds: &[u32],
+) -> Result<Vec<(u32, String)>, String> {
+    let seeds: HashSet<u32> = seeds.iter().copied().collect();
+    let mut preimages = Vec::new();
+    for line in wordlist.split(b'\n') {
+        let mut line = line.map_err(|e| e.to_string())?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let crc = crc32(&line);
+        if seeds.contains(&crc) {
+            preimages.push((crc, String::from_utf8_lossy(&line).into_owned()));
+        }
+    }
+    Ok(preimages)
+}
+
+/// The secret candidates behind `seeds` for `transform`, one line each.
+pub fn secrets(transform: &SeedTransform, seeds: &[u32]) -> Result<Vec<String>, String> {
+    match transform {
+        SeedTransform::Offset(offset) => Ok(seeds
+            .iter()
+            .map(|&seed| {
+                let base = base(seed, *offset);
+                format!("seed {seed}: $base = {base} (mt_srand($base + {offset}))")
+            })
+            .collect()),
+        SeedTransform::Crc32(path) => {
+            let file = std::fs::File::open(path)
+                .map_err(|e| format!("cannot read the wordlist {path:?}: {e}"))?;
+            let preimages = crc32_preimages(std::io::BufReader::new(file), seeds)?;
+            Ok(preimages
+                .into_iter()
+                .map(|(seed, secret)| format!("seed {seed}: $secret = {secret:?} (crc32)"))
+                .collect())
+        }
+    }
+}
+
+#[test]
+fn test_crc32() {
+    // The example of PHP's documentation of crc32()
+    assert_eq!(
+        crc32(b"The quick brown fox jumped over the lazy dog."),
+        2191738434
+    );
+    assert_eq!(crc32(b""), 0);
+}
+
+#[test]
+fn test_base() {
+    assert_eq!(base(1000, 234), 766);
+    assert_eq!(base(5, 10), (1 << 32) - 5);
+    assert_eq!(base(5, -10), 15);
+}
+
+#[test]
+fn test_crc32_preimages() {
+    let wordlist = b"hunter2\r\nswordfish\n\xffpass\n".as_slice();
+    let seeds = [crc32(b"swordfish"), crc32(b"\xffpass"), 42];
+    assert_eq!(
+        crc32_preimages(wordlist, &seeds),
+        Ok(vec![
+            (seeds[0], "swordfish".to_string()),
+            (seeds[1], "\u{fffd}pass".to_string())
+        ])
+    );
+}
@@ -0,0 +1,64 @@
+// `--unknown-max`: values drawn with `mt_rand(0, N)` for an unknown N, such as the characters of a
+// token whose alphabet is not known, searched for every plausible N at once.
+
+/// The maximums of the common alphabets, `--unknown-max alphabets`: digits, hexadecimal, letters
+/// of one case, digits and letters of one case, both cases, and base64.
+pub const ALPHABET_MAXES: [u32; 6] = [9, 15, 25, 35, 61, 63];
+
+/// The maximums of `--unknown-max`: `alphabets`, or numbers separated by commas.
+pub fn parse_maxes(s: &str) -> Result<Vec<u32>, String> {
+    if s == "alphabets" {
+        return Ok(ALPHABET_MAXES.to_vec());
+    }
+    s.split(',')
+        .map(|max| {
+            max.trim()
+                .parse()
+                .map_err(|_| format!("cannot parse {max:?} as an integer"))
+        })
+        .collect()
+}
+
+/// The query of each maximum of `maxes`, with the normalized `arguments` whose values came from
+/// `mt_rand()` without a range drawn from `mt_rand(0, max)` instead. Maximums that some of the
+/// values exceed cannot explain them, and are left out.
+pub fn queries(arguments: &[u32], maxes: &[u32]) -> Vec<(u32, Vec<u32>)> {
+    maxes
+        .iter()
+        .filter_map(|&max| {
+            let mut query = arguments.to_vec();
+            for slot in query.chunks_mut(4) {
+                if slot[2..] != [0, 0x7fffffff] {
+                    continue;
+                }
+                if slot[0] > max {
+                    return None;
+                }
+                slot[1] = slot[1].min(max);
+                slot[3] = max;
+            }
+            Some((max, query))
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_maxes() {
+    assert_eq!(parse_maxes("alphabets"), Ok(ALPHABET_MAXES.to_vec()));
+    assert_eq!(parse_maxes("9, 99"), Ok(vec![9, 99]));
+    assert!(parse_maxes("9,x").is_err());
+}
+
+#[test]
+fn test_queries() {
+    let arguments = [3, 3, 0, 0x7fffffff, 12, 12, 0, 0x7fffffff, 5, 5, 0, 100];
+    assert_eq!(
+        queries(&arguments, &[9, 15]),
+        vec![(15, vec![3, 3, 0, 15, 12, 12, 0, 15, 5, 5, 0, 100])]
+    );
+    // Skipped outputs stay skipped
+    assert_eq!(
+        queries(&[0, 0x7fffffff, 0, 0x7fffffff], &[61]),
+        vec![(61, vec![0, 61, 0, 61])]
+    );
+}
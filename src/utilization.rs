@@ -0,0 +1,123 @@
+// How busy the GPU is kept, logged along with the steps in verbose mode: the share of the time the
+// host waits on dispatches, and of the time the kernels run when the engine can measure it, to
+// tell whether the host loop (submit, wait, map) or the kernels limit the search.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::engine::Engine;
+
+/// Below this share of its waits, the kernels are not what the host waits on.
+const KERNEL_BOUND: f64 = 0.8;
+
+/// The time spent in a step, or in a whole search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utilization {
+    pub elapsed: Duration,
+    /// Spent submitting dispatches and waiting for their results.
+    pub waited: Duration,
+    /// Spent in the kernels, when the engine measures it.
+    pub kernel: Option<Duration>,
+}
+
+impl Utilization {
+    /// The share of the time the GPU had work to do.
+    pub fn busy(&self) -> f64 {
+        self.waited.as_secs_f64() / self.elapsed.as_secs_f64()
+    }
+
+    /// What limits the search: the kernels when the GPU is kept busy with them, the host loop
+    /// otherwise.
+    pub fn bottleneck(&self) -> &'static str {
+        let kernel_share = match self.kernel {
+            Some(kernel) => kernel.as_secs_f64() / self.waited.as_secs_f64(),
+            None => 1.0,
+        };
+        if self.busy() < KERNEL_BOUND {
+            "the host loop between dispatches"
+        } else if kernel_share < KERNEL_BOUND {
+            "submitting dispatches and reading their results back"
+        } else {
+            "the kernels"
+        }
+    }
+}
+
+impl fmt::Display for Utilization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GPU busy {:.0}%", 100.0 * self.busy())?;
+        if let Some(kernel) = self.kernel {
+            let kernel = kernel.as_secs_f64() / self.elapsed.as_secs_f64();
+            write!(f, ", kernels {:.0}%", 100.0 * kernel)?;
+        }
+        Ok(())
+    }
+}
+
+/// Measures the utilization of the steps of a search one after the other.
+pub struct StepTimer {
+    start: Instant,
+    waited: Duration,
+    kernel_start: Option<Duration>,
+}
+
+impl StepTimer {
+    pub fn start(engine: &dyn Engine) -> Self {
+        StepTimer {
+            start: Instant::now(),
+            waited: Duration::ZERO,
+            kernel_start: engine.kernel_time(),
+        }
+    }
+
+    /// Count the time spent executing a dispatch.
+    pub fn record(&mut self, waited: Duration) {
+        self.waited += waited;
+    }
+
+    /// The utilization since the start, or the last step finished, and start timing the next step.
+    pub fn finish(&mut self, engine: &dyn Engine) -> Utilization {
+        let kernel_time = engine.kernel_time();
+        let utilization = Utilization {
+            elapsed: self.start.elapsed(),
+            waited: self.waited,
+            // The engine starts over from zero when it is prepared again after a device loss.
+            kernel: kernel_time
+                .zip(self.kernel_start)
+                .map(|(now, start)| now.saturating_sub(start)),
+        };
+        *self = StepTimer {
+            start: Instant::now(),
+            waited: Duration::ZERO,
+            kernel_start: kernel_time,
+        };
+        utilization
+    }
+}
+
+#[test]
+fn test_utilization() {
+    let utilization = Utilization {
+        elapsed: Duration::from_secs(4),
+        waited: Duration::from_secs(3),
+        kernel: Some(Duration::from_secs(2)),
+    };
+    assert_eq!(utilization.to_string(), "GPU busy 75%, kernels 50%");
+    assert_eq!(utilization.bottleneck(), "the host loop between dispatches");
+
+    let busy = Utilization {
+        waited: Duration::from_secs(4),
+        kernel: None,
+        ..utilization
+    };
+    assert_eq!(busy.to_string(), "GPU busy 100%");
+    assert_eq!(busy.bottleneck(), "the kernels");
+    let mapping = Utilization {
+        kernel: Some(Duration::from_secs(1)),
+        ..busy
+    };
+    assert_eq!(
+        mapping.bottleneck(),
+        "submitting dispatches and reading their results back"
+    );
+}
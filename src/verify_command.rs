@@ -0,0 +1,76 @@
+// `--verify-cmd`: application-specific checks the GPU cannot do, such as replaying the target's
+// own code, run on each seed found before it is reported.
+
+use std::process::{Command, Stdio};
+
+use crate::flavor::Flavor;
+
+/// A shell command accepting or rejecting a seed by its exit status. `{seed}` is replaced by the
+/// seed in decimal, and `{php}` by its flavor as `--php` takes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyCommand {
+    template: String,
+}
+
+impl VerifyCommand {
+    pub fn new(template: &str) -> Result<Self, String> {
+        if !template.contains("{seed}") {
+            return Err("the command must contain {seed}".to_string());
+        }
+        Ok(VerifyCommand {
+            template: template.to_string(),
+        })
+    }
+
+    /// The command line run for `seed`.
+    fn command_line(&self, seed: u32, flavor: Flavor) -> String {
+        self.template
+            .replace("{seed}", &seed.to_string())
+            .replace("{php}", flavor.name())
+    }
+
+    /// Whether the command exits successfully for `seed`. Its output is discarded, but not its
+    /// errors.
+    pub fn accepts(&self, seed: u32, flavor: Flavor) -> bool {
+        let command_line = self.command_line(seed, flavor);
+        let status = shell(&command_line)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status()
+            .unwrap_or_else(|e| panic!("Cannot run {command_line:?}: {e}."));
+        status.success()
+    }
+}
+
+/// `command_line` run by the shell of the platform.
+pub(crate) fn shell(command_line: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(command_line);
+    command
+}
+
+#[test]
+fn test_verify_command_line() {
+    let command = VerifyCommand::new("php check.php {seed} --php={php}").unwrap();
+    assert_eq!(
+        command.command_line(42, Flavor::Php52),
+        "php check.php 42 --php=5.2.1"
+    );
+    assert!(VerifyCommand::new("php check.php").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_verify_command_accepts() {
+    let command = VerifyCommand::new("test {seed} -eq 42 && echo accepted").unwrap();
+    assert!(command.accepts(42, Flavor::Php71));
+    assert!(!command.accepts(7, Flavor::Php71));
+}
@@ -0,0 +1,284 @@
+// `--webhook URL`: a JSON summary of the search and of the seeds found POSTed over HTTP when it ends,
+// or as soon as the first seed is found with `--webhook-on first-hit`, so that unattended runs
+// notify the automation of their operator. Posts go through curl, which has the TLS this tool
+// lacks, for `--notify` too.
+
+use std::io::Write;
+use std::net::ToSocketAddrs;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::flavor::{format_seed, Flavor};
+
+/// How long curl may take to post, from connecting to the answer of the receiver.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// When the payload is posted, with `--webhook-on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WebhookEvent {
+    /// Once, when the search ends.
+    #[default]
+    Finish,
+    /// When the first seed is found, and again when the search ends.
+    FirstHit,
+}
+
+impl std::str::FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "finish" => Ok(WebhookEvent::Finish),
+            "first-hit" => Ok(WebhookEvent::FirstHit),
+            _ => Err(format!("unknown event {s:?}, expected finish or first-hit")),
+        }
+    }
+}
+
+/// `http://HOST[:PORT][/PATH]`, or `https://`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookUrl {
+    https: bool,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl std::str::FromStr for WebhookUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (https, rest) = match (s.strip_prefix("https://"), s.strip_prefix("http://")) {
+            (Some(rest), _) => (true, rest),
+            (None, Some(rest)) => (false, rest),
+            (None, None) => return Err(format!("{s:?} is not an http:// or https:// URL")),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("cannot parse the port {port:?}"))?,
+            ),
+            _ if https => (authority, 443),
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("{s:?} has no host"));
+        }
+        Ok(WebhookUrl {
+            https,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for WebhookUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scheme = if self.https { "https" } else { "http" };
+        write!(f, "{scheme}://{}:{}{}", self.host, self.port, self.path)
+    }
+}
+
+/// The receiver of `--webhook`, and the search it reports on once started.
+#[derive(Debug)]
+pub struct Webhook {
+    url: WebhookUrl,
+    /// The JSON fields of the query, and when the search started.
+    query: Mutex<Option<(String, Instant)>>,
+    hit: AtomicBool,
+}
+
+impl Webhook {
+    pub fn new(url: WebhookUrl) -> Self {
+        Webhook {
+            url,
+            query: Mutex::new(None),
+            hit: AtomicBool::new(false),
+        }
+    }
+
+    /// Resolve the host now, so that a wrong URL is reported before searching rather than at the
+    /// end, and keep the summary of the query: its `flavors`, the constraints of `arguments` and
+    /// the `partitions` of its steps.
+    pub fn start(
+        &self,
+        flavors: &[Flavor],
+        arguments: &[u32],
+        partitions: u32,
+    ) -> Result<(), String> {
+        (self.url.host.as_str(), self.url.port)
+            .to_socket_addrs()
+            .map_err(|e| format!("cannot resolve {}: {e}", self.url.host))?;
+        let flavors: Vec<String> = flavors
+            .iter()
+            .map(|f| format!("\"{}\"", f.name()))
+            .collect();
+        let constraints: Vec<String> = arguments.iter().map(u32::to_string).collect();
+        let query = format!(
+            "\"php\":[{}],\"constraints\":[{}],\"partitions\":{partitions}",
+            flavors.join(","),
+            constraints.join(",")
+        );
+        *self.query.lock().unwrap() = Some((query, Instant::now()));
+        Ok(())
+    }
+
+    /// Post the `first-hit` payload, with `seed` alone, unless a seed was already posted.
+    pub fn first_hit(&self, seed: u32, flavor: Flavor) {
+        if !self.hit.swap(true, Ordering::SeqCst) {
+            self.post("first-hit", &[(flavor, vec![seed])]);
+        }
+    }
+
+    /// Post the `finish` payload, with every seed `found`.
+    pub fn finish(&self, found: &[(Flavor, Vec<u32>)]) {
+        self.post("finish", found);
+    }
+
+    fn post(&self, event: &str, found: &[(Flavor, Vec<u32>)]) {
+        let Some(body) = self.payload(event, found) else {
+            return;
+        };
+        match post_json(&self.url.to_string(), &body) {
+            Ok(200..=299) => log::info!("posted the {event} webhook to {}", self.url),
+            Ok(status) => log::warn!("the {event} webhook to {} got {status}", self.url),
+            Err(e) => log::warn!("cannot post the {event} webhook to {}: {e}", self.url),
+        }
+    }
+
+    /// The JSON payload of `event` on a single line, or `None` before `start`.
+    fn payload(&self, event: &str, found: &[(Flavor, Vec<u32>)]) -> Option<String> {
+        let query = self.query.lock().unwrap();
+        let (query, started) = query.as_ref()?;
+        let seeds: Vec<String> = found
+            .iter()
+            .flat_map(|(flavor, seeds)| seeds.iter().map(move |&seed| (seed, *flavor)))
+            .map(|(seed, flavor)| {
+                format!(
+                    "{{\"seed\":{seed},\"php\":\"{}\",\"line\":\"{}\"}}",
+                    flavor.name(),
+                    format_seed(seed, flavor)
+                )
+            })
+            .collect();
+        Some(format!(
+            "{{\"event\":\"{event}\",{query},\"seconds\":{:.1},\"found\":{},\"seeds\":[{}]}}",
+            started.elapsed().as_secs_f64(),
+            seeds.len(),
+            seeds.join(",")
+        ))
+    }
+}
+
+/// POST the JSON `body` to `url` with curl, and return the HTTP status of the answer.
+///
+/// The URL, which holds the token of chat webhooks, is given to curl in a config on its standard
+/// input rather than as an argument, which other users of the machine could list.
+pub(crate) fn post_json(url: &str, body: &str) -> Result<u16, String> {
+    // Strings of curl configs are quoted, with C escapes.
+    let quote = |s: &str| {
+        let escaped = s
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+        format!("\"{escaped}\"")
+    };
+    let config = format!(
+        "url = {}\nheader = \"Content-Type: application/json\"\ndata-binary = {}\n",
+        quote(url),
+        quote(body)
+    );
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--max-time"])
+        .arg(TIMEOUT.as_secs().to_string())
+        // The answer is printed, the status on the last line.
+        .args(["--write-out", "\\n%{http_code}", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run curl: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(config.as_bytes());
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(match error.trim() {
+            "" => format!("curl {}", output.status),
+            error => error.to_string(),
+        });
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let status = stdout.rsplit('\n').next().unwrap_or_default();
+    status
+        .parse()
+        .map_err(|_| format!("cannot read the status {status:?}"))
+}
+
+#[test]
+fn test_webhook_url() {
+    let url: WebhookUrl = "http://hooks.local:8080/search/done".parse().unwrap();
+    assert_eq!(url.to_string(), "http://hooks.local:8080/search/done");
+    let url: WebhookUrl = "http://127.0.0.1".parse().unwrap();
+    assert_eq!(url.to_string(), "http://127.0.0.1:80/");
+    let url: WebhookUrl = "https://hooks.local/done".parse().unwrap();
+    assert_eq!(url.to_string(), "https://hooks.local:443/done");
+    assert!("tcp://127.0.0.1:9000".parse::<WebhookUrl>().is_err());
+    assert!("http://hooks.local:http/".parse::<WebhookUrl>().is_err());
+    assert!("http:///path".parse::<WebhookUrl>().is_err());
+    assert_eq!("first-hit".parse(), Ok(WebhookEvent::FirstHit));
+}
+
+#[test]
+fn test_webhook_post() {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let receiver = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for stream in listener.incoming().take(2) {
+            let mut stream = stream.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            let mut request = String::new();
+            stream.read_to_string(&mut request).unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    let webhook = Webhook::new(format!("http://127.0.0.1:{port}/hook").parse().unwrap());
+    // Nothing is posted before the search starts.
+    assert_eq!(webhook.payload("finish", &[]), None);
+    webhook.start(&[Flavor::Php71], &[1178568022], 256).unwrap();
+    webhook.first_hit(0x12345678, Flavor::Php71);
+    webhook.first_hit(7, Flavor::Php71);
+    webhook.finish(&[(Flavor::Php71, vec![0x12345678, 7])]);
+
+    let requests = receiver.join().unwrap();
+    assert!(requests[0].starts_with("POST /hook HTTP/1.1\r\n"));
+    let (_, first) = requests[0].split_once("\r\n\r\n").unwrap();
+    assert!(first.starts_with(
+        "{\"event\":\"first-hit\",\"php\":[\"7.1\"],\"constraints\":[1178568022],\
+         \"partitions\":256,\"seconds\":"
+    ));
+    assert!(first.ends_with(&format!(
+        "\"found\":1,\"seeds\":[{{\"seed\":305419896,\"php\":\"7.1\",\"line\":\"{}\"}}]}}",
+        format_seed(0x12345678, Flavor::Php71)
+    )));
+    let (_, last) = requests[1].split_once("\r\n\r\n").unwrap();
+    assert!(last.starts_with("{\"event\":\"finish\","));
+    assert!(last.contains("\"found\":2,"));
+}
@@ -0,0 +1,264 @@
+// `export-work`, `work` and `import-results`: a search split into work units, files holding its
+// arguments and a range of its steps, searched on machines that never talk to each other (air-gapped
+// ones, volunteers) and merged back from the results files they write.
+
+use std::ops::Range;
+
+use crate::flavor::{format_seed, Flavor};
+use crate::potfile::fnv1a;
+use crate::seed_filter::parse_seeds;
+
+/// What a work unit file starts with.
+const HEADER: &str = "# php_mt_seed_rs work unit";
+
+/// Steps `first..end` of a search split in `partitions` steps, with the arguments of the search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkUnit {
+    /// The position of the unit among the `count` of its search, from 1.
+    pub index: u32,
+    pub count: u32,
+    /// Identifies the search, see `query_hash`.
+    pub query: u64,
+    pub partitions: u32,
+    pub steps: Range<u32>,
+    pub arguments: Vec<String>,
+}
+
+/// FNV-1a over the arguments of a search, so that results of different searches are not merged.
+pub fn query_hash(arguments: &[String]) -> u64 {
+    fnv1a(arguments.iter().flat_map(|a| a.bytes().chain([b'\n'])))
+}
+
+/// Split the `partitions` steps of the search of `arguments` in `count` units of contiguous steps.
+pub fn split(arguments: &[String], partitions: u32, count: u32) -> Result<Vec<WorkUnit>, String> {
+    if count == 0 || count > partitions {
+        return Err(format!(
+            "cannot split {partitions} steps in {count} units, raise --partitions"
+        ));
+    }
+    if arguments.iter().any(|argument| argument.contains('\n')) {
+        return Err("arguments with line breaks cannot be saved".to_string());
+    }
+    let query = query_hash(arguments);
+    Ok((0..count)
+        .map(|i| WorkUnit {
+            index: i + 1,
+            count,
+            query,
+            partitions,
+            // The first units take the remainder, a step more than the others.
+            steps: step_at(partitions, count, i)..step_at(partitions, count, i + 1),
+            arguments: arguments.to_vec(),
+        })
+        .collect())
+}
+
+fn step_at(partitions: u32, count: u32, unit: u32) -> u32 {
+    (u64::from(partitions) * u64::from(unit)).div_ceil(u64::from(count)) as u32
+}
+
+impl WorkUnit {
+    /// `unit-03-of-16.work`, zero-padded so that the files list in order.
+    pub fn file_name(&self) -> String {
+        let width = self.count.to_string().len();
+        format!("unit-{:0width$}-of-{}.work", self.index, self.count)
+    }
+
+    /// The unit file: `key = value` lines, then an `argument = ...` line per argument.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{HEADER}, search it with `php_mt_seed_rs work FILE`\n\
+             version = {}\n\
+             unit = {}/{}\n\
+             query = {:016x}\n\
+             partitions = {}\n\
+             steps = {}..{}\n",
+            env!("CARGO_PKG_VERSION"),
+            self.index,
+            self.count,
+            self.query,
+            self.partitions,
+            self.steps.start,
+            self.steps.end
+        );
+        for argument in &self.arguments {
+            text.push_str(&format!("argument = {argument}\n"));
+        }
+        text
+    }
+
+    /// Read a unit file, or the beginning of a results file.
+    pub fn parse(text: &str) -> Result<WorkUnit, String> {
+        if !text.starts_with(HEADER) {
+            return Err("this is not a work unit".to_string());
+        }
+        let mut fields = Vec::new();
+        let mut arguments = Vec::new();
+        for line in text.lines().skip(1) {
+            let Some((key, value)) = line.split_once(" = ") else {
+                continue;
+            };
+            match key {
+                "argument" => arguments.push(value.to_string()),
+                _ => fields.push((key, value)),
+            }
+        }
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .ok_or(format!("the unit has no {name}"))
+        };
+        let parse = |name: &str, value: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("cannot parse the {name} {value:?}"))
+        };
+        let (index, count) = field("unit")?
+            .split_once('/')
+            .ok_or("cannot parse the unit".to_string())?;
+        let (first, end) = field("steps")?
+            .split_once("..")
+            .ok_or("cannot parse the steps".to_string())?;
+        let query = u64::from_str_radix(field("query")?, 16)
+            .map_err(|_| "cannot parse the query".to_string())?;
+        Ok(WorkUnit {
+            index: parse("unit", index)?,
+            count: parse("unit", count)?,
+            query,
+            partitions: parse("partitions", field("partitions")?)?,
+            steps: parse("steps", first)?..parse("steps", end)?,
+            arguments,
+        })
+    }
+
+    /// The results file of the searched unit: the unit, then the result lines of the seeds found.
+    pub fn results_text(&self, found: &[(Flavor, Vec<u32>)]) -> String {
+        let mut text = self.to_text();
+        text.push_str("# results\n");
+        for (flavor, seeds) in found {
+            for &seed in seeds {
+                text.push_str(&format!("{}\n", format_seed(seed, *flavor)));
+            }
+        }
+        text
+    }
+}
+
+/// Read a results file: its unit and the seeds found in it.
+pub fn parse_results(text: &str) -> Result<(WorkUnit, Vec<(u32, Flavor)>), String> {
+    let unit = WorkUnit::parse(text)?;
+    let (_, results) = text
+        .split_once("\n# results\n")
+        .ok_or("the unit has not been searched".to_string())?;
+    let mut seeds = Vec::new();
+    for line in results.lines() {
+        let flavor = Flavor::ALL
+            .into_iter()
+            .find(|flavor| line.ends_with(&format!("(PHP {})", flavor.versions())))
+            .ok_or(format!("cannot read a result in {line:?}"))?;
+        seeds.extend(parse_seeds(line)?.into_iter().map(|seed| (seed, flavor)));
+    }
+    Ok((unit, seeds))
+}
+
+/// The results of the units of a search, merged.
+#[derive(Debug, PartialEq)]
+pub struct Merged {
+    /// The seeds found, each once, in the order of their steps.
+    pub seeds: Vec<(u32, Flavor)>,
+    /// The units imported, by index.
+    pub units: Vec<u32>,
+    pub count: u32,
+    /// The steps no unit imported covers.
+    pub missing: Vec<Range<u32>>,
+}
+
+/// Merge the results of units, refusing the units of another search.
+pub fn merge(mut results: Vec<(WorkUnit, Vec<(u32, Flavor)>)>) -> Result<Merged, String> {
+    results.sort_by_key(|(unit, _)| unit.index);
+    let Some((first, _)) = results.first() else {
+        return Err("there are no results to import".to_string());
+    };
+    let (query, count, partitions) = (first.query, first.count, first.partitions);
+    let first = first.index;
+    let mut merged = Merged {
+        seeds: Vec::new(),
+        units: Vec::new(),
+        count,
+        missing: Vec::new(),
+    };
+    let mut covered = 0;
+    for (unit, seeds) in results {
+        if (unit.query, unit.count, unit.partitions) != (query, count, partitions) {
+            return Err(format!(
+                "unit {}/{} comes from another search than unit {}/{count}",
+                unit.index, unit.count, first
+            ));
+        }
+        if merged.units.last() == Some(&unit.index) {
+            continue;
+        }
+        if covered < unit.steps.start {
+            merged.missing.push(covered..unit.steps.start);
+        }
+        covered = unit.steps.end;
+        merged.units.push(unit.index);
+        // Seeds checked on the CPU, such as those of `--quick-epoch-pass`, are found by every unit.
+        for seed in seeds {
+            if !merged.seeds.contains(&seed) {
+                merged.seeds.push(seed);
+            }
+        }
+    }
+    if covered < partitions {
+        merged.missing.push(covered..partitions);
+    }
+    Ok(merged)
+}
+
+#[test]
+fn test_split() {
+    let arguments = ["--php".to_string(), "5.2.1".to_string(), "42".to_string()];
+    let units = split(&arguments, 256, 3).unwrap();
+    let steps: Vec<Range<u32>> = units.iter().map(|unit| unit.steps.clone()).collect();
+    assert_eq!(steps, [0..86, 86..171, 171..256]);
+    assert_eq!(units[1].file_name(), "unit-2-of-3.work");
+    assert_eq!(WorkUnit::parse(&units[1].to_text()), Ok(units[1].clone()));
+    assert!(split(&arguments, 256, 512).is_err());
+    assert!(split(&arguments, 256, 0).is_err());
+    let units = split(&arguments, 65536, 16).unwrap();
+    assert_eq!(units[15].file_name(), "unit-16-of-16.work");
+    assert_eq!(units[15].steps, 61440..65536);
+}
+
+#[test]
+fn test_import_results() {
+    let arguments = ["1178568022".to_string()];
+    let units = split(&arguments, 256, 4).unwrap();
+    let found = [(Flavor::Php71, vec![0x12345678]), (Flavor::Php52, vec![7])];
+    let text = units[1].results_text(&found);
+    let (unit, seeds) = parse_results(&text).unwrap();
+    assert_eq!(unit, units[1]);
+    assert_eq!(seeds, [(0x12345678, Flavor::Php71), (7, Flavor::Php52)]);
+    assert!(parse_results(&units[1].to_text()).is_err());
+
+    let empty = units[3].results_text(&[]);
+    let merged = merge(vec![
+        parse_results(&empty).unwrap(),
+        parse_results(&text).unwrap(),
+        parse_results(&text).unwrap(),
+    ])
+    .unwrap();
+    assert_eq!(merged.units, [2, 4]);
+    assert_eq!(merged.seeds, seeds);
+    assert_eq!(merged.missing, [0..64, 128..192]);
+
+    let other = split(&["42".to_string()], 256, 4).unwrap();
+    let other = parse_results(&other[0].results_text(&[])).unwrap();
+    assert_eq!(
+        merge(vec![parse_results(&text).unwrap(), other]),
+        Err("unit 2/4 comes from another search than unit 1/4".to_string())
+    );
+}